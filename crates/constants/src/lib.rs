@@ -1,5 +1,8 @@
 //! configurable constants for hotshot
 
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
 /// the ID of the genesis block proposer
 pub const GENESIS_PROPOSER_ID: [u8; 2] = [4, 2];
 
@@ -17,3 +20,80 @@ pub const COMBINED_NETWORK_MIN_PRIMARY_FAILURES: u64 = 10;
 
 /// the number of messages to send over the secondary network before re-attempting the (presumed down) primary network
 pub const COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL: u64 = 10;
+
+/// A protocol version, `major.minor`.
+///
+/// Nodes speaking the same major version can interoperate; a differing minor version only means
+/// one side may not understand optional features of the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Version {
+    /// the major version number
+    pub major: u16,
+    /// the minor version number
+    pub minor: u16,
+}
+
+impl Version {
+    /// Whether a peer speaking `other` can exchange messages with a node speaking `self`
+    #[must_use]
+    pub fn is_compatible_with(&self, other: &Version) -> bool {
+        self.major == other.major
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once('.').ok_or_else(|| {
+            format!("Couldn't parse version {s}. Must be of the form major.minor")
+        })?;
+        Ok(Version {
+            major: major
+                .parse()
+                .map_err(|e| format!("invalid major version: {e}"))?,
+            minor: minor
+                .parse()
+                .map_err(|e| format!("invalid minor version: {e}"))?,
+        })
+    }
+}
+
+/// the version of the wire protocol spoken by this build
+pub const PROTOCOL_VERSION: Version = Version { major: 0, minor: 1 };
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_from_str() {
+        assert_eq!(
+            "1.12".parse::<Version>(),
+            Ok(Version {
+                major: 1,
+                minor: 12
+            })
+        );
+        assert_eq!(PROTOCOL_VERSION.to_string().parse(), Ok(PROTOCOL_VERSION));
+        for invalid in ["", "1", "1.", ".1", "a.1", "1.b", "1.2.3", "70000.0"] {
+            assert!(invalid.parse::<Version>().is_err(), "parsed {invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_version_compatibility() {
+        let version = |major, minor| Version { major, minor };
+        // only the major version has to match
+        assert!(version(1, 2).is_compatible_with(&version(1, 5)));
+        assert!(version(1, 5).is_compatible_with(&version(1, 2)));
+        assert!(!version(1, 2).is_compatible_with(&version(2, 2)));
+        assert!(!version(0, 1).is_compatible_with(&version(1, 1)));
+    }
+}
@@ -1,15 +1,16 @@
-use async_compatibility_layer::art::async_sleep;
+use async_compatibility_layer::art::{async_sleep, async_spawn};
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use clap::Parser;
+use commit::Committable;
 use futures::StreamExt;
 use hotshot::traits::implementations::{CombinedCommChannel, CombinedNetworks};
 use hotshot::{
     traits::{
         implementations::{
             Libp2pCommChannel, Libp2pNetwork, MemoryStorage, NetworkingMetricsValue,
-            WebCommChannel, WebServerNetwork,
+            PrometheusMetrics, WebCommChannel, WebServerNetwork,
         },
         NodeImplementation,
     },
@@ -20,11 +21,12 @@ use hotshot_orchestrator::{
     self,
     client::{OrchestratorClient, ValidatorArgs},
     config::{NetworkConfig, NetworkConfigFile, WebServerConfig},
+    results::{LatencyStats, RunResults},
 };
 use hotshot_task::task::FilterEvent;
 use hotshot_types::block_impl::VIDBlockHeader;
 use hotshot_types::message::Message;
-use hotshot_types::traits::network::ConnectedNetwork;
+use hotshot_types::traits::{metrics::Metrics, network::ConnectedNetwork};
 use hotshot_types::ValidatorConfig;
 use hotshot_types::{
     block_impl::{VIDBlockPayload, VIDTransaction},
@@ -36,6 +38,7 @@ use hotshot_types::{
         network::CommunicationChannel,
         node_implementation::NodeType,
         state::{ConsensusTime, TestableBlock, TestableState},
+        BlockPayload,
     },
     HotShotConfig,
 };
@@ -51,7 +54,10 @@ use rand::rngs::StdRng;
 use rand::SeedableRng;
 use std::marker::PhantomData;
 use std::time::Duration;
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 use std::{num::NonZeroUsize, str::FromStr};
 
 use libp2p_identity::PeerId;
@@ -60,7 +66,7 @@ use std::fmt::Debug;
 use std::{
     //collections::{BTreeSet, VecDeque},
     fs,
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     //num::NonZeroUsize,
     //str::FromStr,
     //sync::Arc,
@@ -109,6 +115,9 @@ pub fn load_config_from_file<TYPES: NodeType>(
 
     let mut config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType> =
         config_toml.into();
+    if let Err(e) = config.validate() {
+        panic!("Invalid config file {config_file}: {e}");
+    }
 
     // Generate network's public keys
     let known_nodes: Vec<_> = (0..config.config.total_nodes.get())
@@ -145,11 +154,14 @@ pub async fn run_orchestrator<
 ) {
     error!("Starting orchestrator",);
     let run_config = load_config_from_file::<TYPES>(config_file);
-    let _result = hotshot_orchestrator::run_orchestrator::<
+    if let Err(e) = hotshot_orchestrator::run_orchestrator::<
         TYPES::SignatureKey,
         TYPES::ElectionConfigType,
     >(run_config, host, port)
-    .await;
+    .await
+    {
+        panic!("Orchestrator failed: {e}");
+    }
 }
 
 /// Helper function to calculate the nuymber of transactions to send per node per round
@@ -163,29 +175,61 @@ fn calculate_num_tx_per_round(
             as usize
 }
 
+/// The registry this node's metrics are kept in, served for Prometheus on `metrics_port` plus
+/// the node index, if the config sets a metrics port. It's served once for every run of the
+/// experiment, so call this once per process
+fn metrics_from_config<TYPES: NodeType>(
+    config: &NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+) -> Option<PrometheusMetrics> {
+    let metrics_port = config.metrics_port?;
+    // nodes sharing a host each need their own port
+    let Some(port) = u16::try_from(config.node_index)
+        .ok()
+        .and_then(|index| metrics_port.checked_add(index))
+    else {
+        error!("No metrics port for node {}", config.node_index);
+        return None;
+    };
+    let metrics = PrometheusMetrics::default();
+    let served = metrics.clone();
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+    error!("Serving metrics on {addr}");
+    async_spawn(async move {
+        if let Err(e) = served.serve(addr).await {
+            error!("Failed to serve metrics on {addr}: {e}");
+        }
+    });
+    Some(metrics)
+}
+
 async fn webserver_network_from_config<TYPES: NodeType>(
     config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
     pub_key: TYPES::SignatureKey,
 ) -> WebServerNetwork<TYPES> {
     // Get the configuration for the web server
     let WebServerConfig {
-        host,
-        port,
+        urls,
         wait_between_polls,
     }: WebServerConfig = config.clone().web_server_config.unwrap();
 
     WebServerNetwork::create(
-        &host.to_string(),
-        port,
+        urls,
         wait_between_polls,
         pub_key.clone(),
+        config.config.my_own_validator_config.private_key.clone(),
         false,
     )
 }
 
+/// How many times to try starting a node's libp2p network before giving up
+const LIBP2P_BIND_ATTEMPTS: u32 = 10;
+/// How long to wait between attempts to start a node's libp2p network
+const LIBP2P_BIND_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 async fn libp2p_network_from_config<TYPES: NodeType>(
     config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
     pub_key: TYPES::SignatureKey,
+    metrics: Option<&PrometheusMetrics>,
 ) -> Libp2pNetwork<Message<TYPES>, TYPES::SignatureKey> {
     let mut config = config;
     let libp2p_config = config
@@ -260,7 +304,7 @@ match node_type {
         mesh_outbound_min: libp2p_config.mesh_outbound_min,
         mesh_n: libp2p_config.mesh_n,
     },
-    NetworkNodeType::Conductor => unreachable!(),
+    NetworkNodeType::Conductor | NetworkNodeType::Relay => unreachable!(),
 };
     config_builder.mesh_params(Some(mesh_params));
 
@@ -276,26 +320,44 @@ match node_type {
     }
     let node_config = config_builder.build().unwrap();
 
-    Libp2pNetwork::new(
-        NetworkingMetricsValue::new(),
-        node_config,
-        pub_key.clone(),
-        Arc::new(RwLock::new(
-            bootstrap_nodes
-                .iter()
-                .map(|(peer_id, addr)| (Some(*peer_id), addr.clone()))
-                .collect(),
-        )),
-        bs_len,
-        config.node_index as usize,
-        // NOTE: this introduces an invariant that the keys are assigned using this indexed
-        // function
-        all_keys,
-        da_keys.clone(),
-        da_keys.contains(&pub_key),
-    )
-    .await
-    .unwrap()
+    let networking_metrics = metrics.map_or_else(NetworkingMetricsValue::new, |metrics| {
+        NetworkingMetricsValue::with_metrics(&*metrics.subgroup("networking".to_string()))
+    });
+
+    // Each run of an experiment binds the same port, which the previous run's network may not
+    // have released yet
+    let mut attempt = 1;
+    loop {
+        let network = Libp2pNetwork::new(
+            networking_metrics.clone(),
+            node_config.clone(),
+            pub_key.clone(),
+            config.config.my_own_validator_config.private_key.clone(),
+            Arc::new(RwLock::new(
+                bootstrap_nodes
+                    .iter()
+                    .map(|(peer_id, addr)| (Some(*peer_id), addr.clone()))
+                    .collect(),
+            )),
+            bs_len,
+            config.node_index as usize,
+            // NOTE: this introduces an invariant that the keys are assigned using this indexed
+            // function
+            all_keys.clone(),
+            da_keys.clone(),
+            da_keys.contains(&pub_key),
+        )
+        .await;
+        match network {
+            Ok(network) => return network,
+            Err(e) if attempt < LIBP2P_BIND_ATTEMPTS => {
+                warn!("Could not start libp2p on {bound_addr}, retrying: {e:?}");
+                async_sleep(LIBP2P_BIND_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => panic!("Could not start libp2p on {bound_addr}: {e:?}"),
+        }
+    }
 }
 
 /// Defines the behavior of a "run" of the network with a given configuration
@@ -320,16 +382,20 @@ pub trait RunDA<
     Self: Sync,
     SystemContext<TYPES, NODE>: HotShotType<TYPES, NODE>,
 {
-    /// Initializes networking, returns self
+    /// Initializes networking, keeping its metrics in `metrics` if given, returns self
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+        metrics: Option<&PrometheusMetrics>,
     ) -> Self;
 
     /// Initializes the genesis state and HotShot instance; does not start HotShot consensus
     /// # Panics if it cannot generate a genesis block, fails to initialize HotShot, or cannot
     /// get the anchored view
     /// Note: sequencing leaf does not have state, so does not return state
-    async fn initialize_state_and_hotshot(&self) -> SystemContextHandle<TYPES, NODE> {
+    async fn initialize_state_and_hotshot(
+        &self,
+        metrics: Option<&PrometheusMetrics>,
+    ) -> SystemContextHandle<TYPES, NODE> {
         let initializer = hotshot::HotShotInitializer::<TYPES>::from_genesis()
             .expect("Couldn't generate genesis block");
 
@@ -376,6 +442,10 @@ pub trait RunDA<
             ),
         };
 
+        let consensus_metrics = metrics.map_or_else(ConsensusMetricsValue::new, |metrics| {
+            ConsensusMetricsValue::with_metrics(&*metrics.subgroup("consensus".to_string()))
+        });
+
         SystemContext::init(
             pk,
             sk,
@@ -385,29 +455,34 @@ pub trait RunDA<
             memberships,
             networks_bundle,
             initializer,
-            ConsensusMetricsValue::new(),
+            consensus_metrics,
         )
         .await
         .expect("Could not init hotshot")
         .0
     }
 
-    /// Starts HotShot consensus, returns when consensus has finished
+    /// Starts HotShot consensus, returns this node's results when consensus has finished
     async fn run_hotshot(
         &self,
         mut context: SystemContextHandle<TYPES, NODE>,
         transactions: &mut Vec<VIDTransaction>,
         transactions_to_send_per_round: u64,
-    ) {
+    ) -> RunResults {
         let NetworkConfig {
             rounds,
             node_index,
+            run_index,
             start_delay_seconds,
             ..
         } = self.get_config();
 
         let mut total_transactions_committed = 0;
         let mut total_transactions_sent = 0;
+        // when each of our transactions not yet decided was sent, to measure its latency
+        let mut sent_at = HashMap::new();
+        let mut latencies = Vec::new();
+        let mut failed_views = BTreeSet::new();
 
         error!("Sleeping for {start_delay_seconds} seconds before starting hotshot!");
         async_sleep(Duration::from_secs(start_delay_seconds)).await;
@@ -437,6 +512,17 @@ pub trait RunDA<
                             qc: _,
                             block_size,
                         } => {
+                            for leaf in leaf_chain.iter() {
+                                let Some(payload) = &leaf.block_payload else {
+                                    continue;
+                                };
+                                for commitment in payload.transaction_commitments() {
+                                    if let Some(sent) = sent_at.remove(&commitment) {
+                                        latencies.push(Instant::now().duration_since(sent));
+                                    }
+                                }
+                            }
+
                             // this might be a obob
                             if let Some(leaf) = leaf_chain.get(0) {
                                 info!("Decide event for leaf: {}", *leaf.view_number);
@@ -450,6 +536,7 @@ pub trait RunDA<
                                 for _ in 0..transactions_to_send_per_round {
                                     let tx = transactions.remove(0);
 
+                                    sent_at.insert(tx.commit(), Instant::now());
                                     _ = context.submit_transaction(tx).await.unwrap();
                                     total_transactions_sent += 1;
                                 }
@@ -471,9 +558,11 @@ pub trait RunDA<
                         }
                         EventType::ReplicaViewTimeout { view_number } => {
                             warn!("Timed out as a replicas in view {:?}", view_number);
+                            failed_views.insert(view_number);
                         }
                         EventType::NextLeaderViewTimeout { view_number } => {
                             warn!("Timed out as the next leader in view {:?}", view_number);
+                            failed_views.insert(view_number);
                         }
                         EventType::ViewFinished { view_number: _ } => {}
                         _ => unimplemented!(),
@@ -485,6 +574,25 @@ pub trait RunDA<
         // Output run results
         let total_time_elapsed = start.elapsed();
         error!("[{node_index}]: {rounds} rounds completed in {total_time_elapsed:?} - Total transactions sent: {total_transactions_sent} - Total transactions committed: {total_transactions_committed} - Total commitments: {num_successful_commits}");
+
+        // Stop consensus and the networks, so the next run of the experiment starts afresh
+        context.shut_down().await;
+        // Channels may share a network, so shut them down one at a time
+        self.get_quorum_channel().shut_down().await;
+        self.get_da_channel().shut_down().await;
+        self.get_view_sync_channel().shut_down().await;
+        self.get_vid_channel().shut_down().await;
+
+        RunResults {
+            node_index,
+            run_index,
+            rounds_completed: num_successful_commits,
+            failed_views: failed_views.len(),
+            transactions_sent: total_transactions_sent,
+            transactions_committed: total_transactions_committed,
+            elapsed: total_time_elapsed,
+            latency: LatencyStats::from_samples(latencies),
+        }
     }
 
     /// Returns the da network for this run
@@ -544,14 +652,15 @@ where
 {
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+        _metrics: Option<&PrometheusMetrics>,
     ) -> WebServerDARun<TYPES> {
         // Get our own key
         let pub_key = config.config.my_own_validator_config.public_key.clone();
+        let private_key = config.config.my_own_validator_config.private_key.clone();
 
         // extract values from config (for DA network)
         let WebServerConfig {
-            host,
-            port,
+            urls,
             wait_between_polls,
         }: WebServerConfig = config.clone().da_web_server_config.unwrap();
 
@@ -570,18 +679,17 @@ where
 
         let da_channel: WebCommChannel<TYPES> = WebCommChannel::new(
             WebServerNetwork::create(
-                &host.to_string(),
-                port,
+                urls.clone(),
                 wait_between_polls,
                 pub_key.clone(),
+                private_key.clone(),
                 true,
             )
             .into(),
         );
 
         let vid_channel: WebCommChannel<TYPES> = WebCommChannel::new(
-            WebServerNetwork::create(&host.to_string(), port, wait_between_polls, pub_key, true)
-                .into(),
+            WebServerNetwork::create(urls, wait_between_polls, pub_key, private_key, true).into(),
         );
 
         WebServerDARun {
@@ -655,12 +763,13 @@ where
 {
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+        metrics: Option<&PrometheusMetrics>,
     ) -> Libp2pDARun<TYPES> {
         let pub_key = config.config.my_own_validator_config.public_key.clone();
 
         // create and wait for underlying network
         let underlying_quorum_network =
-            libp2p_network_from_config::<TYPES>(config.clone(), pub_key).await;
+            libp2p_network_from_config::<TYPES>(config.clone(), pub_key, metrics).await;
 
         underlying_quorum_network.wait_for_ready().await;
 
@@ -748,9 +857,10 @@ where
 {
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+        metrics: Option<&PrometheusMetrics>,
     ) -> CombinedDARun<TYPES> {
         // generate our own key
-        let (pub_key, _privkey) =
+        let (pub_key, privkey) =
             <<TYPES as NodeType>::SignatureKey as SignatureKey>::generated_from_seed_indexed(
                 config.seed,
                 config.node_index,
@@ -758,14 +868,13 @@ where
 
         // create and wait for libp2p network
         let libp2p_underlying_quorum_network =
-            libp2p_network_from_config::<TYPES>(config.clone(), pub_key.clone()).await;
+            libp2p_network_from_config::<TYPES>(config.clone(), pub_key.clone(), metrics).await;
 
         libp2p_underlying_quorum_network.wait_for_ready().await;
 
         // extract values from config (for webserver DA network)
         let WebServerConfig {
-            host,
-            port,
+            urls,
             wait_between_polls,
         }: WebServerConfig = config.clone().da_web_server_config.unwrap();
 
@@ -774,7 +883,7 @@ where
             webserver_network_from_config::<TYPES>(config.clone(), pub_key.clone()).await;
 
         let webserver_underlying_da_network =
-            WebServerNetwork::create(&host.to_string(), port, wait_between_polls, pub_key, true);
+            WebServerNetwork::create(urls, wait_between_polls, pub_key, privkey, true);
 
         webserver_underlying_quorum_network.wait_for_ready().await;
 
@@ -882,23 +991,96 @@ pub async fn main_entry_point<
         .get_config_from_orchestrator::<TYPES>(node_index)
         .await;
 
-    run_config.node_index = node_index.into();
-
     let (public_key, private_key) =
         <<TYPES as NodeType>::SignatureKey as SignatureKey>::generated_from_seed_indexed(
             run_config.seed,
             node_index.into(),
         );
-    run_config.config.my_own_validator_config = ValidatorConfig {
-        public_key,
-        private_key,
-        stake_value: 1,
-    };
-    //run_config.libp2p_config.as_mut().unwrap().public_ip = args.public_ip.unwrap();
+    orchestrator_client.start_heartbeats(public_key.clone());
+
+    run_config.node_index = node_index.into();
+    let metrics = metrics_from_config::<TYPES>(&run_config);
+
+    // Each run of the experiment starts consensus from genesis, with new networks
+    loop {
+        run_config.node_index = node_index.into();
+        run_config.config.my_own_validator_config = ValidatorConfig {
+            public_key: public_key.clone(),
+            private_key: private_key.clone(),
+            stake_value: 1,
+        };
+        let run_index = run_config.run_index;
+        //run_config.libp2p_config.as_mut().unwrap().public_ip = args.public_ip.unwrap();
+
+        error!("Starting run {run_index}");
+        let results = run_experiment::<
+            TYPES,
+            DACHANNEL,
+            QUORUMCHANNEL,
+            VIEWSYNCCHANNEL,
+            VIDCHANNEL,
+            NODE,
+            RUNDA,
+        >(
+            &orchestrator_client,
+            run_config,
+            &public_key,
+            metrics.as_ref(),
+        )
+        .await;
+
+        error!("Posting run results to orchestrator");
+        orchestrator_client.post_run_results(results).await;
+
+        error!("Waiting for the next run from orchestrator");
+        match orchestrator_client
+            .get_next_run_config::<TYPES>(node_index, run_index + 1)
+            .await
+        {
+            Some(next_run_config) => run_config = next_run_config,
+            None => break,
+        }
+    }
+    error!("Every run of the experiment has finished");
+}
 
+/// Runs a single run of the experiment with `run_config`, once every node is ready, keeping its
+/// metrics in `metrics` if given
+async fn run_experiment<
+    TYPES: NodeType<
+        Transaction = VIDTransaction,
+        BlockPayload = VIDBlockPayload,
+        BlockHeader = VIDBlockHeader,
+    >,
+    DACHANNEL: CommunicationChannel<TYPES> + Debug,
+    QUORUMCHANNEL: CommunicationChannel<TYPES> + Debug,
+    VIEWSYNCCHANNEL: CommunicationChannel<TYPES> + Debug,
+    VIDCHANNEL: CommunicationChannel<TYPES> + Debug,
+    NODE: NodeImplementation<
+        TYPES,
+        QuorumNetwork = QUORUMCHANNEL,
+        CommitteeNetwork = DACHANNEL,
+        Storage = MemoryStorage<TYPES>,
+    >,
+    RUNDA: RunDA<TYPES, DACHANNEL, QUORUMCHANNEL, VIEWSYNCCHANNEL, VIDCHANNEL, NODE>,
+>(
+    orchestrator_client: &OrchestratorClient,
+    run_config: NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>,
+    public_key: &TYPES::SignatureKey,
+    metrics: Option<&PrometheusMetrics>,
+) -> RunResults
+where
+    <TYPES as NodeType>::StateType: TestableState,
+    <TYPES as NodeType>::BlockPayload: TestableBlock,
+    Leaf<TYPES>: TestableLeaf,
+{
+    // the networks and HotShot instance of the last run registered their metrics already
+    if let Some(metrics) = metrics {
+        metrics.reset();
+    }
     error!("Initializing networking");
-    let run = RUNDA::initialize_networking(run_config.clone()).await;
-    let hotshot = run.initialize_state_and_hotshot().await;
+    let run = RUNDA::initialize_networking(run_config.clone(), metrics).await;
+    let hotshot = run.initialize_state_and_hotshot(metrics).await;
 
     // pre-generate transactions
     let NetworkConfig {
@@ -933,16 +1115,17 @@ pub async fn main_entry_point<
 
     error!("Waiting for start command from orchestrator");
     orchestrator_client
-        .wait_for_all_nodes_ready(run_config.clone().node_index)
+        .wait_for_all_nodes_ready(public_key)
         .await;
 
     error!("All nodes are ready!  Starting HotShot");
+    orchestrator_client.post_running(public_key).await;
     run.run_hotshot(
         hotshot,
         &mut transactions,
         transactions_to_send_per_round as u64,
     )
-    .await;
+    .await
 }
 
 pub fn libp2p_generate_indexed_identity(seed: [u8; 32], index: u64) -> Keypair {
@@ -18,7 +18,7 @@ use clap::Parser;
 use hotshot::demo::DemoTypes;
 use hotshot_orchestrator::client::ValidatorArgs;
 use hotshot_orchestrator::config::NetworkConfig;
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::traits::{node_implementation::NodeType, signature_key::SignatureKey};
 use tracing::error;
 use types::VIDNetwork;
 
@@ -28,25 +28,45 @@ async fn main() {
     // use configfile args
     let args = ConfigArgs::parse();
 
+    let config: NetworkConfig<
+        <DemoTypes as NodeType>::SignatureKey,
+        <DemoTypes as NodeType>::ElectionConfigType,
+    > = load_config_from_file::<DemoTypes>(args.config_file.clone());
+
+    // the web servers only accept posts from the nodes in the run
+    let stake_table: Vec<_> = config
+        .config
+        .known_nodes_with_stake
+        .iter()
+        .map(<DemoTypes as NodeType>::SignatureKey::get_public_key)
+        .collect();
+
     // spawn web servers
     let (server_shutdown_sender_cdn, server_shutdown_cdn) = oneshot();
     let (server_shutdown_sender_da, server_shutdown_da) = oneshot();
     let _sender = Arc::new(server_shutdown_sender_cdn);
     let _sender = Arc::new(server_shutdown_sender_da);
 
+    let cdn_stake_table = stake_table.clone();
     async_spawn(async move {
-        if let Err(e) = hotshot_web_server::run_web_server::<
-            <DemoTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey,
-        >(Some(server_shutdown_cdn), 9000)
+        if let Err(e) = hotshot_web_server::run_web_server::<DemoTypes>(
+            Some(server_shutdown_cdn),
+            9000,
+            cdn_stake_table,
+            hotshot_web_server::Options::default(),
+        )
         .await
         {
             error!("Problem starting cdn web server: {:?}", e);
         }
     });
     async_spawn(async move {
-        if let Err(e) = hotshot_web_server::run_web_server::<
-            <DemoTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey,
-        >(Some(server_shutdown_da), 9001)
+        if let Err(e) = hotshot_web_server::run_web_server::<DemoTypes>(
+            Some(server_shutdown_da),
+            9001,
+            stake_table,
+            hotshot_web_server::Options::default(),
+        )
         .await
         {
             error!("Problem starting da web server: {:?}", e);
@@ -68,10 +88,6 @@ async fn main() {
     }));
 
     // multi validator run
-    let config: NetworkConfig<
-        <DemoTypes as NodeType>::SignatureKey,
-        <DemoTypes as NodeType>::ElectionConfigType,
-    > = load_config_from_file::<DemoTypes>(args.config_file);
     let mut nodes = Vec::new();
     for _ in 0..(config.config.total_nodes.get()) {
         let node = async_spawn(async move {
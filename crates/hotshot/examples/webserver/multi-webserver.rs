@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use crate::infra::load_config_from_file;
 use async_compatibility_layer::{
     art::async_spawn,
     channel::oneshot,
@@ -7,13 +8,21 @@ use async_compatibility_layer::{
 };
 use clap::Parser;
 use hotshot::demo::DemoTypes;
+use hotshot_orchestrator::config::NetworkConfig;
+use hotshot_types::traits::{node_implementation::NodeType, signature_key::SignatureKey};
 use tracing::error;
 
+#[path = "../infra/mod.rs"]
+pub mod infra;
+
 #[derive(Parser, Debug)]
 struct MultiWebServerArgs {
     cdn_port: u16,
     da_port: u16,
     view_sync_port: u16,
+    /// The config file of the run. Only the keys of its nodes, generated from its seed,
+    /// may post to the web servers
+    config_file: String,
 }
 
 #[cfg_attr(async_executor_impl = "tokio", tokio::main)]
@@ -28,19 +37,38 @@ async fn main() {
     let _sender = Arc::new(server_shutdown_sender_cdn);
     let _sender = Arc::new(server_shutdown_sender_da);
 
+    // only the nodes in the run, with keys generated from its seed, may post
+    let config: NetworkConfig<
+        <DemoTypes as NodeType>::SignatureKey,
+        <DemoTypes as NodeType>::ElectionConfigType,
+    > = load_config_from_file::<DemoTypes>(args.config_file);
+    let stake_table: Vec<_> = config
+        .config
+        .known_nodes_with_stake
+        .iter()
+        .map(<DemoTypes as NodeType>::SignatureKey::get_public_key)
+        .collect();
+    let cdn_stake_table = stake_table.clone();
+
     let cdn_server = async_spawn(async move {
-        if let Err(e) = hotshot_web_server::run_web_server::<
-            <DemoTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey,
-        >(Some(server_shutdown_cdn), args.cdn_port)
+        if let Err(e) = hotshot_web_server::run_web_server::<DemoTypes>(
+            Some(server_shutdown_cdn),
+            args.cdn_port,
+            cdn_stake_table,
+            hotshot_web_server::Options::default(),
+        )
         .await
         {
             error!("Problem starting cdn web server: {:?}", e);
         }
     });
     let da_server = async_spawn(async move {
-        if let Err(e) = hotshot_web_server::run_web_server::<
-            <DemoTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey,
-        >(Some(server_shutdown_da), args.da_port)
+        if let Err(e) = hotshot_web_server::run_web_server::<DemoTypes>(
+            Some(server_shutdown_da),
+            args.da_port,
+            stake_table,
+            hotshot_web_server::Options::default(),
+        )
         .await
         {
             error!("Problem starting da web server: {:?}", e);
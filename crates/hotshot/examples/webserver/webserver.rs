@@ -1,4 +1,6 @@
+use crate::infra::load_config_from_file;
 use hotshot::demo::DemoTypes;
+use hotshot_orchestrator::config::NetworkConfig;
 use std::sync::Arc;
 
 use async_compatibility_layer::{
@@ -6,10 +8,19 @@ use async_compatibility_layer::{
     logging::{setup_backtrace, setup_logging},
 };
 use clap::Parser;
+use hotshot_types::traits::{node_implementation::NodeType, signature_key::SignatureKey};
+
+#[path = "../infra/mod.rs"]
+pub mod infra;
 
 #[derive(Parser, Debug)]
 struct WebServerArgs {
     port: u16,
+    /// The config file of the run. Only the keys of its nodes, generated from its seed,
+    /// may post to the web server
+    config_file: String,
+    #[command(flatten)]
+    options: hotshot_web_server::Options,
 }
 
 #[cfg_attr(async_executor_impl = "tokio", tokio::main)]
@@ -20,8 +31,22 @@ async fn main() {
     let args = WebServerArgs::parse();
     let (server_shutdown_sender, server_shutdown) = oneshot();
     let _sender = Arc::new(server_shutdown_sender);
-    let _result = hotshot_web_server::run_web_server::<
-        <DemoTypes as hotshot_types::traits::node_implementation::NodeType>::SignatureKey,
-    >(Some(server_shutdown), args.port)
+    // only the nodes in the run, with keys generated from its seed, may post
+    let config: NetworkConfig<
+        <DemoTypes as NodeType>::SignatureKey,
+        <DemoTypes as NodeType>::ElectionConfigType,
+    > = load_config_from_file::<DemoTypes>(args.config_file);
+    let stake_table = config
+        .config
+        .known_nodes_with_stake
+        .iter()
+        .map(<DemoTypes as NodeType>::SignatureKey::get_public_key)
+        .collect();
+    let _result = hotshot_web_server::run_web_server::<DemoTypes>(
+        Some(server_shutdown),
+        args.port,
+        stake_table,
+        args.options,
+    )
     .await;
 }
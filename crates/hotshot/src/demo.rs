@@ -105,12 +105,15 @@ impl State for DemoState {
 impl TestableState for DemoState {
     fn create_random_transaction(
         _state: Option<&Self>,
-        _rng: &mut dyn rand::RngCore,
+        rng: &mut dyn rand::RngCore,
         padding: u64,
     ) -> <Self::BlockPayload as BlockPayload>::Transaction {
         /// clippy appeasement for `RANDOM_TX_BASE_SIZE`
         const RANDOM_TX_BASE_SIZE: usize = 8;
-        VIDTransaction(vec![0; RANDOM_TX_BASE_SIZE + (padding as usize)])
+        // random contents, so that transactions of the same size are told apart
+        let mut txn = vec![0; RANDOM_TX_BASE_SIZE + (padding as usize)];
+        rng.fill_bytes(&mut txn);
+        VIDTransaction(txn)
     }
 }
 /// Implementation of [`NodeType`] for [`VDemoNode`]
@@ -151,7 +151,7 @@ pub struct SystemContextInner<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     event_sender: RwLock<Option<BroadcastSender<Event<TYPES>>>>,
 
     /// the metrics that the implementor is using.
-    _metrics: Arc<ConsensusMetricsValue>,
+    metrics: Arc<ConsensusMetricsValue>,
 
     /// The hotstuff implementation
     consensus: Arc<RwLock<Consensus<TYPES>>>,
@@ -251,7 +251,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             networks: Arc::new(networks),
             memberships: Arc::new(memberships),
             event_sender: RwLock::default(),
-            _metrics: consensus_metrics.clone(),
+            metrics: consensus_metrics.clone(),
             internal_event_stream: ChannelStream::new(),
             output_event_stream: ChannelStream::new(),
         });
@@ -516,6 +516,123 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
     ) -> ViewQueue<TYPES> {
         channel_map.channel_map.entry(view_num).or_default().clone()
     }
+
+    /// Spawn all tasks like [`HotShotType::run_tasks`], except that the networking tasks send the
+    /// events published to `outgoing_event_stream` rather than those the other tasks publish.
+    ///
+    /// This lets a test rewrite what this node sends, to make it misbehave.
+    #[cfg(feature = "hotshot-testing")]
+    pub async fn run_tasks_with_outgoing_stream(
+        self,
+        outgoing_event_stream: ChannelStream<HotShotEvent<TYPES>>,
+    ) -> SystemContextHandle<TYPES, I> {
+        self.launch_tasks(outgoing_event_stream).await
+    }
+
+    /// Spawn all tasks, with the networking tasks sending the events published to
+    /// `outgoing_event_stream`
+    #[allow(clippy::too_many_lines)]
+    async fn launch_tasks(
+        self,
+        outgoing_event_stream: ChannelStream<HotShotEvent<TYPES>>,
+    ) -> SystemContextHandle<TYPES, I> {
+        // ED Need to set first first number to 1, or properly trigger the change upon start
+        let task_runner = TaskRunner::new();
+        let registry = task_runner.registry.clone();
+
+        let output_event_stream = self.inner.output_event_stream.clone();
+        let internal_event_stream = self.inner.internal_event_stream.clone();
+
+        let quorum_network = self.inner.networks.quorum_network.clone();
+        let da_network = self.inner.networks.da_network.clone();
+        let quorum_membership = self.inner.memberships.quorum_membership.clone();
+        let da_membership = self.inner.memberships.da_membership.clone();
+        let vid_membership = self.inner.memberships.vid_membership.clone();
+        let view_sync_membership = self.inner.memberships.view_sync_membership.clone();
+        let metrics = self.inner.metrics.group.clone();
+
+        let handle = SystemContextHandle {
+            registry,
+            output_event_stream: output_event_stream.clone(),
+            internal_event_stream: internal_event_stream.clone(),
+            hotshot: self.clone(),
+            storage: self.inner.storage.clone(),
+        };
+
+        let task_runner = add_network_message_task(
+            task_runner,
+            internal_event_stream.clone(),
+            quorum_network.clone(),
+            NetworkTaskKind::Quorum,
+            &*metrics,
+        )
+        .await;
+        let task_runner = add_network_message_task(
+            task_runner,
+            internal_event_stream.clone(),
+            da_network.clone(),
+            NetworkTaskKind::Committee,
+            &*metrics,
+        )
+        .await;
+
+        let task_runner = add_network_event_task(
+            task_runner,
+            outgoing_event_stream.clone(),
+            quorum_network.clone(),
+            quorum_membership,
+            NetworkTaskKind::Quorum,
+            &*metrics,
+        )
+        .await;
+        let task_runner = add_network_event_task(
+            task_runner,
+            outgoing_event_stream.clone(),
+            da_network.clone(),
+            da_membership,
+            NetworkTaskKind::Committee,
+            &*metrics,
+        )
+        .await;
+        let task_runner = add_network_event_task(
+            task_runner,
+            outgoing_event_stream.clone(),
+            quorum_network.clone(),
+            view_sync_membership,
+            NetworkTaskKind::ViewSync,
+            &*metrics,
+        )
+        .await;
+        let task_runner = add_network_event_task(
+            task_runner,
+            outgoing_event_stream.clone(),
+            quorum_network.clone(),
+            vid_membership,
+            NetworkTaskKind::VID,
+            &*metrics,
+        )
+        .await;
+        let task_runner = add_consensus_task(
+            task_runner,
+            internal_event_stream.clone(),
+            output_event_stream.clone(),
+            handle.clone(),
+        )
+        .await;
+        let task_runner =
+            add_da_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
+        let task_runner =
+            add_vid_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
+        let task_runner =
+            add_transaction_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
+        let task_runner =
+            add_view_sync_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
+        async_spawn(async move {
+            task_runner.launch().await;
+            info!("Task runner exited!");
+        });
+        handle
+    }
 }
 
 /// [`HotShot`] implementations that depend on [`TYPES::ConsensusType`].
@@ -626,95 +743,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> HotShotType<TYPES, I>
         &self.inner.consensus
     }
 
-    #[allow(clippy::too_many_lines)]
     async fn run_tasks(self) -> SystemContextHandle<TYPES, I> {
-        // ED Need to set first first number to 1, or properly trigger the change upon start
-        let task_runner = TaskRunner::new();
-        let registry = task_runner.registry.clone();
-
-        let output_event_stream = self.inner.output_event_stream.clone();
         let internal_event_stream = self.inner.internal_event_stream.clone();
-
-        let quorum_network = self.inner.networks.quorum_network.clone();
-        let da_network = self.inner.networks.da_network.clone();
-        let quorum_membership = self.inner.memberships.quorum_membership.clone();
-        let da_membership = self.inner.memberships.da_membership.clone();
-        let vid_membership = self.inner.memberships.vid_membership.clone();
-        let view_sync_membership = self.inner.memberships.view_sync_membership.clone();
-
-        let handle = SystemContextHandle {
-            registry,
-            output_event_stream: output_event_stream.clone(),
-            internal_event_stream: internal_event_stream.clone(),
-            hotshot: self.clone(),
-            storage: self.inner.storage.clone(),
-        };
-
-        let task_runner = add_network_message_task(
-            task_runner,
-            internal_event_stream.clone(),
-            quorum_network.clone(),
-        )
-        .await;
-        let task_runner = add_network_message_task(
-            task_runner,
-            internal_event_stream.clone(),
-            da_network.clone(),
-        )
-        .await;
-
-        let task_runner = add_network_event_task(
-            task_runner,
-            internal_event_stream.clone(),
-            quorum_network.clone(),
-            quorum_membership,
-            NetworkTaskKind::Quorum,
-        )
-        .await;
-        let task_runner = add_network_event_task(
-            task_runner,
-            internal_event_stream.clone(),
-            da_network.clone(),
-            da_membership,
-            NetworkTaskKind::Committee,
-        )
-        .await;
-        let task_runner = add_network_event_task(
-            task_runner,
-            internal_event_stream.clone(),
-            quorum_network.clone(),
-            view_sync_membership,
-            NetworkTaskKind::ViewSync,
-        )
-        .await;
-        let task_runner = add_network_event_task(
-            task_runner,
-            internal_event_stream.clone(),
-            quorum_network.clone(),
-            vid_membership,
-            NetworkTaskKind::VID,
-        )
-        .await;
-        let task_runner = add_consensus_task(
-            task_runner,
-            internal_event_stream.clone(),
-            output_event_stream.clone(),
-            handle.clone(),
-        )
-        .await;
-        let task_runner =
-            add_da_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
-        let task_runner =
-            add_vid_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
-        let task_runner =
-            add_transaction_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
-        let task_runner =
-            add_view_sync_task(task_runner, internal_event_stream.clone(), handle.clone()).await;
-        async_spawn(async move {
-            task_runner.launch().await;
-            info!("Task runner exited!");
-        });
-        handle
+        self.launch_tasks(internal_event_stream).await
     }
 }
 
@@ -16,6 +16,10 @@ use hotshot_task_impls::{
     consensus::{consensus_event_filter, ConsensusTaskState, ConsensusTaskTypes},
     da::{DATaskState, DATaskTypes},
     events::HotShotEvent,
+    metrics::{
+        ConsensusTaskMetrics, DAMetrics, NetworkTaskMetrics, TransactionMetrics, VIDMetrics,
+        ViewSyncMetrics,
+    },
     network::{
         NetworkEventTaskState, NetworkEventTaskTypes, NetworkMessageTaskState,
         NetworkMessageTaskTypes, NetworkTaskKind,
@@ -29,6 +33,7 @@ use hotshot_types::{
     message::Messages,
     traits::{
         consensus_api::ConsensusSharedApi,
+        metrics::Metrics,
         network::{CommunicationChannel, ConsensusIntentEvent, TransmitType},
         node_implementation::{NodeImplementation, NodeType},
         state::ConsensusTime,
@@ -39,7 +44,7 @@ use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 /// event for global event stream
@@ -51,6 +56,22 @@ pub enum GlobalEvent {
     Dummy,
 }
 
+/// The metrics of a network task of `task_kind`, for the messages it handles in `direction`, in
+/// their own subgroup of `metrics`
+fn network_task_metrics(
+    metrics: &dyn Metrics,
+    task_kind: NetworkTaskKind,
+    direction: &str,
+) -> NetworkTaskMetrics {
+    let network = match task_kind {
+        NetworkTaskKind::Quorum => "quorum",
+        NetworkTaskKind::Committee => "da",
+        NetworkTaskKind::ViewSync => "view_sync",
+        NetworkTaskKind::VID => "vid",
+    };
+    NetworkTaskMetrics::new(&*metrics.subgroup(format!("{network}_network_{direction}")))
+}
+
 /// Add the network task to handle messages and publish events.
 /// # Panics
 /// Is unable to panic. This section here is just to satisfy clippy
@@ -58,6 +79,8 @@ pub async fn add_network_message_task<TYPES: NodeType, NET: CommunicationChannel
     task_runner: TaskRunner,
     event_stream: ChannelStream<HotShotEvent<TYPES>>,
     channel: NET,
+    task_kind: NetworkTaskKind,
+    metrics: &dyn Metrics,
 ) -> TaskRunner {
     let net = channel.clone();
     let broadcast_stream = GeneratedStream::<Messages<TYPES>>::new(Arc::new(move || {
@@ -102,6 +125,7 @@ pub async fn add_network_message_task<TYPES: NodeType, NET: CommunicationChannel
     let message_stream = Merge::new(broadcast_stream, direct_stream);
     let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
         event_stream: event_stream.clone(),
+        metrics: network_task_metrics(metrics, task_kind, "received"),
     };
     let registry = task_runner.registry.clone();
     let network_message_handler = HandleMessage(Arc::new(
@@ -148,12 +172,14 @@ pub async fn add_network_event_task<TYPES: NodeType, NET: CommunicationChannel<T
     channel: NET,
     membership: TYPES::Membership,
     task_kind: NetworkTaskKind,
+    metrics: &dyn Metrics,
 ) -> TaskRunner {
     let filter = NetworkEventTaskState::<TYPES, NET>::filter(task_kind);
     let network_state: NetworkEventTaskState<_, _> = NetworkEventTaskState {
         channel,
         event_stream: event_stream.clone(),
         view: TYPES::Time::genesis(),
+        metrics: network_task_metrics(metrics, task_kind, "sent"),
     };
     let registry = task_runner.registry.clone();
     let network_event_handler = HandleEvent(Arc::new(
@@ -229,6 +255,15 @@ pub async fn add_consensus_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
         timeout_membership: c_api.inner.memberships.quorum_membership.clone().into(),
         quorum_membership: c_api.inner.memberships.quorum_membership.clone().into(),
         committee_membership: c_api.inner.memberships.da_membership.clone().into(),
+        votes_awaited: None,
+        metrics: ConsensusTaskMetrics::new(
+            &*handle
+                .hotshot
+                .inner
+                .metrics
+                .group
+                .subgroup("quorum".to_string()),
+        ),
     };
     consensus_state
         .quorum_network
@@ -299,6 +334,15 @@ pub async fn add_vid_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
         private_key: c_api.private_key().clone(),
         event_stream: event_stream.clone(),
         id: handle.hotshot.inner.id,
+        votes_awaited: None,
+        metrics: VIDMetrics::new(
+            &*handle
+                .hotshot
+                .inner
+                .metrics
+                .group
+                .subgroup("vid".to_string()),
+        ),
     };
     let vid_event_handler = HandleEvent(Arc::new(
         move |event, mut state: VIDTaskState<TYPES, I, HotShotConsensusApi<TYPES, I>>| {
@@ -356,6 +400,16 @@ pub async fn add_da_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
         public_key: c_api.public_key().clone(),
         private_key: c_api.private_key().clone(),
         id: handle.hotshot.inner.id,
+        view_start: Instant::now(),
+        votes_awaited: None,
+        metrics: DAMetrics::new(
+            &*handle
+                .hotshot
+                .inner
+                .metrics
+                .group
+                .subgroup("da".to_string()),
+        ),
     };
     let da_event_handler = HandleEvent(Arc::new(
         move |event, mut state: DATaskState<TYPES, I, HotShotConsensusApi<TYPES, I>>| {
@@ -413,6 +467,14 @@ pub async fn add_transaction_task<TYPES: NodeType, I: NodeImplementation<TYPES>>
         private_key: c_api.private_key().clone(),
         event_stream: event_stream.clone(),
         id: handle.hotshot.inner.id,
+        metrics: TransactionMetrics::new(
+            &*handle
+                .hotshot
+                .inner
+                .metrics
+                .group
+                .subgroup("transactions".to_string()),
+        ),
     };
     let transactions_event_handler = HandleEvent(Arc::new(
         move |event, mut state: TransactionTaskState<TYPES, I, HotShotConsensusApi<TYPES, I>>| {
@@ -471,6 +533,14 @@ pub async fn add_view_sync_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
         view_sync_timeout: Duration::new(10, 0),
         id: handle.hotshot.inner.id,
         last_garbage_collected_view: TYPES::Time::new(0),
+        metrics: ViewSyncMetrics::new(
+            &*handle
+                .hotshot
+                .inner
+                .metrics
+                .group
+                .subgroup("view_sync".to_string()),
+        ),
     };
     let registry = task_runner.registry.clone();
     let view_sync_event_handler = HandleEvent(Arc::new(
@@ -1,5 +1,6 @@
 /// Sortition trait
 pub mod election;
+mod metrics;
 mod networking;
 mod node_implementation;
 mod storage;
@@ -12,6 +13,7 @@ pub use storage::{Result as StorageResult, Storage};
 /// Module for publicly usable implementations of the traits
 pub mod implementations {
     pub use super::{
+        metrics::PrometheusMetrics,
         networking::{
             combined_network::{CombinedCommChannel, CombinedNetworks},
             libp2p_network::{Libp2pCommChannel, Libp2pNetwork, PeerInfoVec},
@@ -0,0 +1,247 @@
+//! A [`Metrics`] implementation backed by a Prometheus registry, and an HTTP exporter for it.
+//!
+//! Metric names are the labels they were created with, prefixed by the names of the subgroups
+//! they were created in and joined with `_`, so `metrics.subgroup("consensus").create_gauge("current_view")`
+//! is exported as `consensus_current_view`.
+
+use hotshot_types::traits::metrics::{Counter, Gauge, Histogram, Label, Metrics};
+use prometheus_client::{
+    encoding::text::encode,
+    metrics::{
+        counter::Counter as PromCounter, family::Family, gauge::Gauge as PromGauge,
+        histogram::Histogram as PromHistogram,
+    },
+    registry::{Registry, Unit},
+};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tracing::error;
+
+/// The buckets histograms are created with unless others are given, spanning from a
+/// millisecond to about a thousand seconds (or from 0.001 to about 1000 of whatever is measured)
+fn default_buckets() -> Vec<f64> {
+    prometheus_client::metrics::histogram::exponential_buckets(0.001, 2.0, 21).collect()
+}
+
+/// Metrics kept in a Prometheus registry. Clones share the registry, so metrics created through
+/// any clone or subgroup are exported together.
+#[derive(Clone, Debug)]
+pub struct PrometheusMetrics {
+    /// the registry every metric is registered in
+    registry: Arc<Mutex<Registry>>,
+    /// prefix of the subgroup this is, empty for the root
+    prefix: String,
+    /// the buckets histograms are created with
+    buckets: Arc<Vec<f64>>,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self {
+            registry: Arc::default(),
+            prefix: String::new(),
+            buckets: Arc::new(default_buckets()),
+        }
+    }
+}
+
+impl PrometheusMetrics {
+    /// Create histograms with these bucket bounds rather than the default ones
+    #[must_use]
+    pub fn with_histogram_buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.buckets = Arc::new(buckets);
+        self
+    }
+
+    /// The full name of the metric or subgroup called `name` in this subgroup. Characters
+    /// Prometheus doesn't allow in names are replaced with `_`.
+    fn name(&self, name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}_{name}", self.prefix)
+        }
+    }
+
+    /// Register `metric` as `label` in this subgroup
+    fn register(
+        &self,
+        label: &str,
+        unit_label: Option<String>,
+        metric: impl prometheus_client::registry::Metric,
+    ) {
+        let name = self.name(label);
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        match unit_label {
+            Some(unit) => {
+                registry.register_with_unit(name, label, Unit::Other(unit), metric);
+            }
+            None => registry.register(name, label, metric),
+        }
+    }
+
+    /// Drop every metric from the registry, so metrics can be created again under the same names.
+    /// Metrics created before keep counting, but are no longer exported
+    pub fn reset(&self) {
+        *self.registry.lock().unwrap_or_else(|e| e.into_inner()) = Registry::default();
+    }
+
+    /// Every metric in the registry, in the Prometheus text exposition format
+    #[must_use]
+    pub fn export(&self) -> String {
+        let mut exported = String::new();
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = encode(&mut exported, &registry) {
+            error!("Failed to encode metrics: {e}");
+        }
+        exported
+    }
+
+    /// Serve the metrics at `/metrics` on `addr` until the server fails
+    ///
+    /// # Errors
+    /// If the server can't listen on `addr`
+    pub async fn serve(self, addr: SocketAddr) -> io::Result<()> {
+        let mut app = tide::with_state(self);
+        app.at("/metrics")
+            .get(|req: tide::Request<Self>| async move {
+                Ok(tide::Response::builder(200)
+                    .content_type("text/plain; version=0.0.4")
+                    .body(req.state().export())
+                    .build())
+            });
+        app.listen(addr).await
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn create_counter(&self, label: String, unit_label: Option<String>) -> Box<dyn Counter> {
+        let counter = PromCounter::<u64>::default();
+        self.register(&label, unit_label, counter.clone());
+        Box::new(PrometheusCounter(counter))
+    }
+
+    fn create_gauge(&self, label: String, unit_label: Option<String>) -> Box<dyn Gauge> {
+        let gauge = PromGauge::<i64>::default();
+        self.register(&label, unit_label, gauge.clone());
+        Box::new(PrometheusGauge(gauge))
+    }
+
+    fn create_histogram(&self, label: String, unit_label: Option<String>) -> Box<dyn Histogram> {
+        let histogram = PromHistogram::new(self.buckets.iter().copied());
+        self.register(&label, unit_label, histogram.clone());
+        Box::new(PrometheusHistogram(histogram))
+    }
+
+    fn create_label(&self, label: String) -> Box<dyn Label> {
+        let family = Family::<Vec<(String, String)>, PromGauge>::default();
+        self.register(&label, None, family.clone());
+        Box::new(PrometheusLabel(family))
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(Self {
+            registry: Arc::clone(&self.registry),
+            prefix: self.name(&subgroup_name),
+            buckets: Arc::clone(&self.buckets),
+        })
+    }
+}
+
+/// A [`Counter`] in a [`PrometheusMetrics`] registry
+#[derive(Clone, Debug)]
+struct PrometheusCounter(PromCounter<u64>);
+
+impl Counter for PrometheusCounter {
+    fn add(&self, amount: usize) {
+        self.0.inc_by(amount as u64);
+    }
+}
+
+/// A [`Gauge`] in a [`PrometheusMetrics`] registry
+#[derive(Clone, Debug)]
+struct PrometheusGauge(PromGauge<i64>);
+
+impl Gauge for PrometheusGauge {
+    fn set(&self, amount: usize) {
+        self.0.set(i64::try_from(amount).unwrap_or(i64::MAX));
+    }
+
+    fn update(&self, delta: i64) {
+        self.0.inc_by(delta);
+    }
+}
+
+/// A [`Histogram`] in a [`PrometheusMetrics`] registry
+#[derive(Clone, Debug)]
+struct PrometheusHistogram(PromHistogram);
+
+impl Histogram for PrometheusHistogram {
+    fn add_point(&self, point: f64) {
+        self.0.observe(point);
+    }
+}
+
+/// A [`Label`] in a [`PrometheusMetrics`] registry, exported as a gauge of 1 whose `value`
+/// label is the current value
+#[derive(Clone, Debug)]
+struct PrometheusLabel(Family<Vec<(String, String)>, PromGauge>);
+
+impl Label for PrometheusLabel {
+    fn set(&self, value: String) {
+        self.0.clear();
+        self.0
+            .get_or_create(&vec![("value".to_string(), value)])
+            .set(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_metrics() {
+        let metrics = PrometheusMetrics::default();
+        let consensus = metrics.subgroup("consensus".to_string());
+        consensus
+            .create_gauge("current_view".to_string(), None)
+            .set(7);
+        let timeouts = consensus.create_counter("number_of_timeouts".to_string(), None);
+        timeouts.add(2);
+        timeouts.add(3);
+        consensus
+            .subgroup("da".to_string())
+            .create_label("version".to_string())
+            .set("0.1".to_string());
+
+        let exported = metrics.export();
+        assert!(exported.contains("consensus_current_view 7"));
+        assert!(exported.contains("consensus_number_of_timeouts_total 5"));
+        assert!(exported.contains("consensus_da_version{value=\"0.1\"} 1"));
+    }
+
+    #[test]
+    fn test_prometheus_metrics_reset() {
+        let metrics = PrometheusMetrics::default();
+        let served = metrics.clone();
+        let old = metrics.create_counter("rounds".to_string(), None);
+        old.add(3);
+
+        metrics.reset();
+        assert!(!served.export().contains("rounds_total"));
+
+        // the name is free to register again, and the old counter doesn't count towards it
+        let new = metrics.create_counter("rounds".to_string(), None);
+        new.add(1);
+        old.add(5);
+        assert!(served.export().contains("rounds_total 1"));
+    }
+}
@@ -37,8 +37,14 @@ pub struct NetworkingMetricsValue {
     pub outgoing_direct_message_count: Box<dyn Counter>,
     /// A [`Counter`] which tracks how many messages have been send by broadcast
     pub outgoing_broadcast_message_count: Box<dyn Counter>,
+    /// A [`Counter`] which tracks how many bytes of messages have been received
+    pub incoming_bytes: Box<dyn Counter>,
+    /// A [`Counter`] which tracks how many bytes of messages have been sent
+    pub outgoing_bytes: Box<dyn Counter>,
     /// A [`Counter`] which tracks how many messages failed to send
     pub message_failed_to_send: Box<dyn Counter>,
+    /// A [`Histogram`] of how many seconds looking up a peer in the DHT took
+    pub dht_lookup_duration: Box<dyn Histogram>,
     // A [`Gauge`] which tracks how many connected entries there are in the gossipsub mesh
     // pub gossipsub_mesh_connected: Box<dyn Gauge>,
     // A [`Gauge`] which tracks how many kademlia entries there are
@@ -165,12 +171,22 @@ impl NetworkingMetricsValue {
     #[must_use]
     pub fn new() -> Self {
         let values = Arc::default();
-        let metrics: Box<dyn Metrics> = Box::new(NetworkingMetrics {
+        let metrics = NetworkingMetrics {
             prefix: String::new(),
             values: Arc::clone(&values),
-        });
+        };
         Self {
             values,
+            ..Self::with_metrics(&metrics)
+        }
+    }
+
+    /// Create the networking metrics in `metrics`, e.g. to export them. `values` stays empty, since
+    /// the values are kept by `metrics` instead.
+    #[must_use]
+    pub fn with_metrics(metrics: &dyn Metrics) -> Self {
+        Self {
+            values: Arc::default(),
             connected_peers: metrics.create_gauge(String::from("connected_peers"), None),
             incoming_direct_message_count: metrics
                 .create_counter(String::from("incoming_direct_message_count"), None),
@@ -180,8 +196,14 @@ impl NetworkingMetricsValue {
                 .create_counter(String::from("outgoing_direct_message_count"), None),
             outgoing_broadcast_message_count: metrics
                 .create_counter(String::from("outgoing_broadcast_message_count"), None),
+            incoming_bytes: metrics.create_counter(String::from("incoming_bytes"), None),
+            outgoing_bytes: metrics.create_counter(String::from("outgoing_bytes"), None),
             message_failed_to_send: metrics
                 .create_counter(String::from("message_failed_to_send"), None),
+            dht_lookup_duration: metrics.create_histogram(
+                String::from("dht_lookup_duration"),
+                Some(String::from("seconds")),
+            ),
         }
     }
 }
@@ -14,32 +14,32 @@ use hotshot_constants::LOOK_AHEAD;
 use hotshot_task::{boxed_sync, BoxSyncFuture};
 use hotshot_types::{
     data::ViewNumber,
-    message::{Message, MessageKind},
+    message::{Message, MessageKind, VersionedMessage},
     traits::{
         election::Membership,
         network::{
-            CommunicationChannel, ConnectedNetwork, ConsensusIntentEvent, FailedToSerializeSnafu,
-            NetworkError, NetworkMsg, TestableChannelImplementation,
+            CommunicationChannel, ConnectedNetwork, ConsensusIntentEvent, FailedToDeserializeSnafu,
+            FailedToSerializeSnafu, NetworkError, NetworkMsg, TestableChannelImplementation,
             TestableNetworkingImplementation, TransmitType, ViewMessage,
         },
         node_implementation::NodeType,
-        signature_key::SignatureKey,
+        signature_key::{EncodedSignature, SignatureKey},
         state::ConsensusTime,
     },
 };
-use hotshot_utils::bincode::bincode_opts;
 use libp2p_identity::PeerId;
 use libp2p_networking::{
     network::{
         MeshParams,
-        NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg},
+        NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg, StreamMsg},
         NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeHandle, NetworkNodeHandleError,
-        NetworkNodeType,
+        NetworkNodeType, PeerRecord,
     },
     reexport::Multiaddr,
 };
 
-use serde::Serialize;
+use hotshot_utils::bincode::bincode_opts;
+use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 use std::{
     collections::{BTreeSet, HashSet},
@@ -51,7 +51,7 @@ use std::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tracing::{error, info, instrument, warn};
 
@@ -71,6 +71,123 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Debug for Libp2pNetwork<M, K> {
     }
 }
 
+/// Unwrap a message received from the network, dropping it if the sender speaks an
+/// incompatible protocol version
+fn open_versioned<M: NetworkMsg>(msg: &[u8]) -> Result<M, NetworkError> {
+    VersionedMessage::from_bytes(msg)
+        .context(FailedToDeserializeSnafu)?
+        .open()
+}
+
+/// How long a published [`PeerIdBinding`] is valid for. Bindings are issued at fixed
+/// boundaries half this apart and republished every quarter of it, so a node that goes away
+/// stops being routable within this window.
+pub const PEER_ID_BINDING_VALIDITY: Duration = Duration::from_secs(60 * 60);
+
+/// DHT record proving that the holder of a `SignatureKey` controls a `PeerId`:
+/// the key signs the peer id and the addresses it is reachable on, along with an expiry.
+/// Without it any node could publish its own peer id under another validator's key
+/// and receive that validator's direct messages.
+///
+/// The peer id and addresses are stored as bytes so the record doesn't depend on their serde impls.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeerIdBinding {
+    /// the bound peer id
+    peer_id: Vec<u8>,
+    /// the addresses the peer is reachable on
+    addrs: Vec<Vec<u8>>,
+    /// seconds since the unix epoch after which the binding is no longer valid
+    expiry: u64,
+    /// signature by the key over all of the above
+    signature: EncodedSignature,
+}
+
+/// seconds since the unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+impl PeerIdBinding {
+    /// Bind `peer_id` and `addrs` to the public key of `private_key`, valid for `validity` from
+    /// the last multiple of half of `validity` since the unix epoch.
+    ///
+    /// Every binding made between two such boundaries has the same expiry (see
+    /// [`PeerIdBinding::is_current`]), so a node can keep republishing the binding it already
+    /// signed and replicas holding an earlier copy still agree with those holding a newer one.
+    ///
+    /// # Errors
+    /// If the binding could not be serialized for signing
+    pub fn new<K: SignatureKey>(
+        private_key: &K::PrivateKey,
+        peer_id: PeerId,
+        addrs: &[Multiaddr],
+        validity: Duration,
+    ) -> Result<Self, Box<bincode::ErrorKind>> {
+        let peer_id = peer_id.to_bytes();
+        let addrs: Vec<_> = addrs.iter().map(Multiaddr::to_vec).collect();
+        let expiry = Self::expiry(validity);
+        let signed = Self::signed_bytes(&K::from_private(private_key), &peer_id, &addrs, expiry)?;
+        Ok(Self {
+            peer_id,
+            addrs,
+            expiry,
+            signature: K::sign(private_key, &signed),
+        })
+    }
+
+    /// Whether a binding made now for `validity` would have the same expiry as this one
+    #[must_use]
+    pub fn is_current(&self, validity: Duration) -> bool {
+        self.expiry == Self::expiry(validity)
+    }
+
+    /// Expiry of a binding made now for `validity`
+    fn expiry(validity: Duration) -> u64 {
+        let rollover = validity.as_secs() / 2;
+        let now = unix_now();
+        let issued = if rollover == 0 {
+            now
+        } else {
+            now - now % rollover
+        };
+        issued.saturating_add(validity.as_secs())
+    }
+
+    /// The bytes covered by the signature. The key is included so a binding
+    /// can't be replayed under any other key.
+    fn signed_bytes<K: SignatureKey>(
+        key: &K,
+        peer_id: &[u8],
+        addrs: &[Vec<u8>],
+        expiry: u64,
+    ) -> Result<Vec<u8>, Box<bincode::ErrorKind>> {
+        bincode_opts().serialize(&(key.to_bytes().0, peer_id, addrs, expiry))
+    }
+}
+
+impl<K: SignatureKey> PeerRecord<K> for PeerIdBinding {
+    fn verify(self, key: &K) -> Option<(PeerId, Vec<Multiaddr>)> {
+        if self.expiry <= unix_now() {
+            warn!("Peer id binding for {:?} has expired", key);
+            return None;
+        }
+        let signed = Self::signed_bytes(key, &self.peer_id, &self.addrs, self.expiry).ok()?;
+        if !key.validate(&self.signature, &signed) {
+            warn!("Peer id binding for {:?} has an invalid signature", key);
+            return None;
+        }
+        let peer_id = PeerId::from_bytes(&self.peer_id).ok()?;
+        let addrs = self
+            .addrs
+            .into_iter()
+            .filter_map(|addr| Multiaddr::try_from(addr).ok())
+            .collect();
+        Some((peer_id, addrs))
+    }
+}
+
 /// Type alias for a shared collection of peerid, multiaddrs
 pub type PeerInfoVec = Arc<RwLock<Vec<(Option<PeerId>, Multiaddr)>>>;
 
@@ -79,6 +196,8 @@ pub type PeerInfoVec = Arc<RwLock<Vec<(Option<PeerId>, Multiaddr)>>>;
 struct Libp2pNetworkInner<M: NetworkMsg, K: SignatureKey + 'static> {
     /// this node's public key
     pk: K,
+    /// this node's private key, used to sign our [`PeerIdBinding`]
+    sk: K::PrivateKey,
     /// handle to control the network
     handle: Arc<NetworkNodeHandle<()>>,
     /// map of known replica peer ids to public keys
@@ -102,6 +221,8 @@ struct Libp2pNetworkInner<M: NetworkMsg, K: SignatureKey + 'static> {
     dht_timeout: Duration,
     /// whether or not we've bootstrapped into the DHT yet
     is_bootstrapped: Arc<AtomicBool>,
+    /// whether or not the network was told to shut down, by any of the channels sharing it
+    is_shut_down: AtomicBool,
     /// The networking metrics we're keeping track of
     metrics: NetworkingMetricsValue,
     /// topic map
@@ -231,6 +352,7 @@ where
                         NetworkingMetricsValue::new(),
                         config,
                         pubkey.clone(),
+                        privkey,
                         bootstrap_addrs_ref,
                         num_bootstrap,
                         node_id as usize,
@@ -271,6 +393,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
     /// One must call `connect` in order to connect.
     /// * `config`: the configuration of the node
     /// * `pk`: public key associated with the node
+    /// * `sk`: private key associated with the node, used to prove we own `pk`
     /// * `bootstrap_addrs`: rwlock containing the bootstrap addrs
     /// # Errors
     /// Returns error in the event that the underlying libp2p network
@@ -284,6 +407,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
         metrics: NetworkingMetricsValue,
         config: NetworkNodeConfig,
         pk: K,
+        sk: K::PrivateKey,
         bootstrap_addrs: Arc<RwLock<Vec<(Option<PeerId>, Multiaddr)>>>,
         bootstrap_addrs_len: usize,
         id: usize,
@@ -333,12 +457,14 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
                 direct_send: direct_send.clone(),
                 direct_recv,
                 pk,
+                sk,
                 broadcast_send: broadcast_send.clone(),
                 bootstrap_addrs_len,
                 bootstrap_addrs,
                 is_ready: Arc::new(AtomicBool::new(false)),
                 dht_timeout: Duration::from_secs(30),
                 is_bootstrapped: Arc::new(AtomicBool::new(false)),
+                is_shut_down: AtomicBool::new(false),
                 metrics,
                 topic_map,
                 node_lookup_send,
@@ -363,6 +489,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
         let handle = self.inner.handle.clone();
         let dht_timeout = self.inner.dht_timeout;
         let latest_seen_view = self.inner.latest_seen_view.clone();
+        let lookup_duration = self.inner.metrics.dht_lookup_duration.clone();
 
         // deals with handling lookup queue. should be infallible
         async_spawn(async move {
@@ -376,9 +503,14 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
                 // only run if we are not too close to the next view number
                 if latest_seen_view.load(Ordering::Relaxed) + THRESHOLD <= *view_number {
                     // look up
-                    if let Err(err) = handle.lookup_node::<K>(pk.clone(), dht_timeout).await {
+                    let start = Instant::now();
+                    if let Err(err) = handle
+                        .lookup_node::<K, PeerIdBinding>(pk.clone(), dht_timeout)
+                        .await
+                    {
                         error!("Failed to perform lookup for key {:?}: {}", pk, err);
                     };
+                    lookup_duration.add_point(start.elapsed().as_secs_f64());
                 }
             }
         });
@@ -387,6 +519,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
     /// Initiates connection to the outside world
     fn spawn_connect(&mut self, id: usize) {
         let pk = self.inner.pk.clone();
+        let sk = self.inner.sk.clone();
         let bootstrap_ref = self.inner.bootstrap_addrs.clone();
         let num_bootstrap = self.inner.bootstrap_addrs_len;
         let handle = self.inner.handle.clone();
@@ -451,7 +584,11 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
 
                 // we want our records published before
                 // we begin participating in consensus
-                while handle.put_record(&pk, &handle.peer_id()).await.is_err() {
+                let mut binding = None;
+                while Self::publish_binding(&handle, &pk, &sk, &mut binding)
+                    .await
+                    .is_err()
+                {
                     async_sleep(Duration::from_secs(1)).await;
                 }
 
@@ -461,10 +598,6 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
                     node_type
                 );
 
-                while handle.put_record(&handle.peer_id(), &pk).await.is_err() {
-                    async_sleep(Duration::from_secs(1)).await;
-                }
-
                 info!(
                     "node {:?} is barring bootstrap, type: {:?}",
                     handle.peer_id(),
@@ -473,11 +606,53 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
 
                 is_ready.store(true, Ordering::Relaxed);
                 info!("STARTING CONSENSUS ON {:?}", handle.peer_id());
+
+                // keep our binding fresh for as long as we're running
+                while !handle.is_killed() {
+                    async_sleep(PEER_ID_BINDING_VALIDITY / 4).await;
+                    while !handle.is_killed()
+                        && Self::publish_binding(&handle, &pk, &sk, &mut binding)
+                            .await
+                            .is_err()
+                    {
+                        async_sleep(Duration::from_secs(1)).await;
+                    }
+                }
                 Ok::<(), NetworkError>(())
             }
         });
     }
 
+    /// Publish a [`PeerIdBinding`] of our peer id to `pk` under `pk`.
+    ///
+    /// `binding` is only re-signed once its expiry rolls over: signatures may be randomized,
+    /// and the DHT only trusts a record once enough replicas hold byte-identical copies.
+    async fn publish_binding(
+        handle: &NetworkNodeHandle<()>,
+        pk: &K,
+        sk: &K::PrivateKey,
+        binding: &mut Option<PeerIdBinding>,
+    ) -> Result<(), NetworkError> {
+        let binding = match binding {
+            Some(binding) if binding.is_current(PEER_ID_BINDING_VALIDITY) => binding.clone(),
+            _ => binding
+                .insert(
+                    PeerIdBinding::new::<K>(
+                        sk,
+                        handle.peer_id(),
+                        &[handle.listen_addr()],
+                        PEER_ID_BINDING_VALIDITY,
+                    )
+                    .context(FailedToSerializeSnafu)?,
+                )
+                .clone(),
+        };
+        handle
+            .put_record(pk, &binding)
+            .await
+            .map_err(Into::<NetworkError>::into)
+    }
+
     /// make network aware of known peers
     async fn _add_known_peers(
         &self,
@@ -501,25 +676,32 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
         let is_bootstrapped = self.inner.is_bootstrapped.clone();
         async_spawn(async move {
             while let Ok(msg) = handle.inner.handle.receiver().recv().await {
+                if let GossipMsg(msg, _) | DirectRequest(msg, _, _) | StreamMsg(msg, _) = &msg {
+                    handle.inner.metrics.incoming_bytes.add(msg.len());
+                }
                 match msg {
-                    GossipMsg(msg, _topic) => {
-                        let result: Result<M, _> = bincode_opts().deserialize(&msg);
-                        if let Ok(result) = result {
+                    GossipMsg(msg, _topic) => match open_versioned::<M>(&msg) {
+                        Ok(result) => {
                             broadcast_send
                                 .send(result)
                                 .await
                                 .map_err(|_| NetworkError::ChannelSend)?;
                         }
-                    }
+                        Err(e) => {
+                            warn!("dropping broadcast message: {}", e);
+                        }
+                    },
                     DirectRequest(msg, _pid, chan) => {
-                        let result: Result<M, _> = bincode_opts()
-                            .deserialize(&msg)
-                            .context(FailedToSerializeSnafu);
-                        if let Ok(result) = result {
-                            direct_send
-                                .send(result)
-                                .await
-                                .map_err(|_| NetworkError::ChannelSend)?;
+                        match open_versioned::<M>(&msg) {
+                            Ok(result) => {
+                                direct_send
+                                    .send(result)
+                                    .await
+                                    .map_err(|_| NetworkError::ChannelSend)?;
+                            }
+                            Err(e) => {
+                                warn!("dropping direct message: {}", e);
+                            }
                         }
                         if handle
                             .inner
@@ -531,11 +713,18 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> Libp2pNetwork<M, K> {
                             error!("failed to ack!");
                         };
                     }
-                    DirectResponse(msg, _) => {
-                        let _result: Result<M, _> = bincode_opts()
-                            .deserialize(&msg)
-                            .context(FailedToSerializeSnafu);
-                    }
+                    DirectResponse(_msg, _) => {}
+                    StreamMsg(msg, _pid) => match open_versioned::<M>(&msg) {
+                        Ok(result) => {
+                            direct_send
+                                .send(result)
+                                .await
+                                .map_err(|_| NetworkError::ChannelSend)?;
+                        }
+                        Err(e) => {
+                            warn!("dropping streamed message: {}", e);
+                        }
+                    },
                     NetworkEvent::IsBootstrapped => {
                         is_bootstrapped.store(true, Ordering::Relaxed);
                     }
@@ -566,10 +755,12 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Libp2p
         Self: 'b,
     {
         let closure = async move {
-            self.inner.node_lookup_send.send(None).await.unwrap();
-            if self.inner.handle.is_killed() {
+            if self.inner.is_shut_down.swap(true, Ordering::Relaxed)
+                || self.inner.handle.is_killed()
+            {
                 error!("Called shut down when already shut down! Noop.");
             } else {
+                self.inner.node_lookup_send.send(None).await.unwrap();
                 self.inner.handle.shutdown().await.unwrap();
             }
         };
@@ -612,9 +803,14 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Libp2p
                 .map_err(|_| NetworkError::ShutDown)?;
         }
 
-        match self.inner.handle.gossip(topic, &message).await {
+        let versioned = VersionedMessage::new(&message).context(FailedToSerializeSnafu)?;
+        match self.inner.handle.gossip(topic, &versioned).await {
             Ok(()) => {
                 self.inner.metrics.outgoing_broadcast_message_count.add(1);
+                self.inner
+                    .metrics
+                    .outgoing_bytes
+                    .add(versioned.payload.len());
                 Ok(())
             }
             Err(e) => {
@@ -643,12 +839,17 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Libp2p
 
         self.wait_for_ready().await;
 
-        let pid = match self
+        let lookup_start = Instant::now();
+        let lookup = self
             .inner
             .handle
-            .lookup_node::<K>(recipient.clone(), self.inner.dht_timeout)
-            .await
-        {
+            .lookup_node::<K, PeerIdBinding>(recipient.clone(), self.inner.dht_timeout)
+            .await;
+        self.inner
+            .metrics
+            .dht_lookup_duration
+            .add_point(lookup_start.elapsed().as_secs_f64());
+        let pid = match lookup {
             Ok(pid) => pid,
             Err(err) => {
                 self.inner.metrics.message_failed_to_send.add(1);
@@ -660,9 +861,21 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Libp2p
             }
         };
 
-        match self.inner.handle.direct_request(pid, &message).await {
+        let versioned = VersionedMessage::new(&message).context(FailedToSerializeSnafu)?;
+        // anything that doesn't fit in a single chunk is streamed rather than sent in one go
+        let result =
+            if versioned.payload.len() > self.inner.handle.config().stream_config.chunk_size {
+                self.inner.handle.stream_request(pid, &versioned).await
+            } else {
+                self.inner.handle.direct_request(pid, &versioned).await
+            };
+        match result {
             Ok(()) => {
                 self.inner.metrics.outgoing_direct_message_count.add(1);
+                self.inner
+                    .metrics
+                    .outgoing_bytes
+                    .add(versioned.payload.len());
                 Ok(())
             }
             Err(e) => {
@@ -890,3 +1103,62 @@ impl<TYPES: NodeType> TestableChannelImplementation<TYPES> for Libp2pCommChannel
         Box::new(move |network| Libp2pCommChannel::new(network))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bn254::BLSPubKey;
+
+    /// a binding only verifies against the key that signed it, and only until it expires
+    #[test]
+    fn test_peer_id_binding() {
+        let (pk, sk) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+        let (other_pk, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1);
+        let peer_id = PeerId::random();
+        let addr = Multiaddr::from_str("/ip4/127.0.0.1/udp/9000/quic-v1").unwrap();
+
+        let binding = PeerIdBinding::new::<BLSPubKey>(
+            &sk,
+            peer_id,
+            &[addr.clone()],
+            PEER_ID_BINDING_VALIDITY,
+        )
+        .unwrap();
+        assert_eq!(
+            PeerRecord::verify(binding.clone(), &pk),
+            Some((peer_id, vec![addr]))
+        );
+
+        // someone else's key
+        assert_eq!(PeerRecord::verify(binding.clone(), &other_pk), None);
+
+        // tampered peer id
+        let mut forged = binding.clone();
+        forged.peer_id = PeerId::random().to_bytes();
+        assert_eq!(PeerRecord::verify(forged, &pk), None);
+
+        // expired
+        let expired =
+            PeerIdBinding::new::<BLSPubKey>(&sk, peer_id, &[], Duration::from_secs(0)).unwrap();
+        assert_eq!(PeerRecord::verify(expired, &pk), None);
+    }
+
+    /// bindings made between two rollovers share an expiry, so can be republished as is
+    #[test]
+    fn test_peer_id_binding_rollover() {
+        let (_, sk) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+        let peer_id = PeerId::random();
+        let binding =
+            PeerIdBinding::new::<BLSPubKey>(&sk, peer_id, &[], PEER_ID_BINDING_VALIDITY).unwrap();
+        assert_eq!(binding.expiry % (PEER_ID_BINDING_VALIDITY.as_secs() / 2), 0);
+        // there's always at least half the validity left
+        assert!(binding.expiry > unix_now() + PEER_ID_BINDING_VALIDITY.as_secs() / 2);
+        assert!(binding.is_current(PEER_ID_BINDING_VALIDITY));
+
+        let stale = PeerIdBinding {
+            expiry: binding.expiry - PEER_ID_BINDING_VALIDITY.as_secs() / 2,
+            ..binding
+        };
+        assert!(!stale.is_current(PEER_ID_BINDING_VALIDITY));
+    }
+}
@@ -5,7 +5,7 @@
 
 use super::{FailedToSerializeSnafu, NetworkError, NetworkReliability, NetworkingMetricsValue};
 use async_compatibility_layer::{
-    art::async_spawn,
+    art::{async_sleep, async_spawn},
     channel::{bounded, Receiver, SendError, Sender},
 };
 use async_lock::{Mutex, RwLock};
@@ -16,11 +16,13 @@ use futures::StreamExt;
 use hotshot_task::{boxed_sync, BoxSyncFuture};
 use hotshot_types::{
     message::{Message, MessageKind},
+    simulation::SimulationRng,
     traits::{
         election::Membership,
         network::{
-            CommunicationChannel, ConnectedNetwork, NetworkMsg, TestableChannelImplementation,
-            TestableNetworkingImplementation, TransmitType, ViewMessage,
+            CommunicationChannel, ConnectedNetwork, LinkFaults, NetworkMsg,
+            TestableChannelImplementation, TestableNetworkingImplementation, TransmitType,
+            ViewMessage,
         },
         node_implementation::NodeType,
         signature_key::SignatureKey,
@@ -36,6 +38,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 use tracing::{debug, error, info, info_span, instrument, trace, warn, Instrument};
 
@@ -50,6 +53,8 @@ pub struct MasterMap<M: NetworkMsg, K: SignatureKey> {
     map: DashMap<K, MemoryNetwork<M, K>>,
     /// The id of this `MemoryNetwork` cluster
     id: u64,
+    /// The faults of the links between the `MemoryNetwork`s
+    link_faults: std::sync::RwLock<LinkFaults<K>>,
 }
 
 impl<M: NetworkMsg, K: SignatureKey> MasterMap<M, K> {
@@ -58,9 +63,19 @@ impl<M: NetworkMsg, K: SignatureKey> MasterMap<M, K> {
     pub fn new() -> Arc<MasterMap<M, K>> {
         Arc::new(MasterMap {
             map: DashMap::new(),
-            id: rand::thread_rng().gen(),
+            id: SimulationRng.gen(),
+            link_faults: std::sync::RwLock::new(LinkFaults::default()),
         })
     }
+
+    /// Replace the faults of the links between the `MemoryNetwork`s with `faults`. Messages
+    /// already on their way are still delivered.
+    ///
+    /// # Panics
+    /// Panics if a thread panicked while holding the faults
+    pub fn set_link_faults(&self, faults: LinkFaults<K>) {
+        *self.link_faults.write().unwrap() = faults;
+    }
 }
 
 /// Internal enum for combining streams
@@ -74,6 +89,8 @@ enum Combo<T> {
 /// Internal state for a `MemoryNetwork` instance
 #[derive(Debug)]
 struct MemoryNetworkInner<M: NetworkMsg, K: SignatureKey> {
+    /// The public key of this node
+    pub_key: K,
     /// Input for broadcast messages
     broadcast_input: RwLock<Option<Sender<Vec<u8>>>>,
     /// Input for direct messages
@@ -131,6 +148,7 @@ impl<M: NetworkMsg, K: SignatureKey> MemoryNetwork<M, K> {
         let (broadcast_task_send, broadcast_output) = bounded(128);
         let (direct_task_send, direct_output) = bounded(128);
         let in_flight_message_count = AtomicUsize::new(0);
+        let incoming_bytes = metrics.incoming_bytes.clone();
         trace!("Channels open, spawning background task");
 
         async_spawn(
@@ -149,6 +167,7 @@ impl<M: NetworkMsg, K: SignatureKey> MemoryNetwork<M, K> {
                     match message {
                         Combo::Direct(vec) => {
                             trace!(?vec, "Incoming direct message");
+                            incoming_bytes.add(vec.len());
                             // Attempt to decode message
                             let x = bincode_opts().deserialize(&vec);
                             match x {
@@ -168,6 +187,7 @@ impl<M: NetworkMsg, K: SignatureKey> MemoryNetwork<M, K> {
                         }
                         Combo::Broadcast(vec) => {
                             trace!(?vec, "Incoming broadcast message");
+                            incoming_bytes.add(vec.len());
                             // Attempt to decode message
                             let x = bincode_opts().deserialize(&vec);
                             match x {
@@ -195,6 +215,7 @@ impl<M: NetworkMsg, K: SignatureKey> MemoryNetwork<M, K> {
         trace!("Task spawned, creating MemoryNetwork");
         let mn = MemoryNetwork {
             inner: Arc::new(MemoryNetworkInner {
+                pub_key: pub_key.clone(),
                 broadcast_input: RwLock::new(Some(broadcast_input)),
                 direct_input: RwLock::new(Some(direct_input)),
                 broadcast_output: Mutex::new(broadcast_output),
@@ -238,6 +259,22 @@ impl<M: NetworkMsg, K: SignatureKey> MemoryNetwork<M, K> {
             Err(SendError(message))
         }
     }
+
+    /// How long a message from this node takes to reach `recipient`, or `None` if the link
+    /// between them is down
+    fn link_latency(&self, recipient: &K) -> Option<Duration> {
+        self.inner
+            .master_map
+            .link_faults
+            .read()
+            .unwrap()
+            .link_latency(&self.inner.pub_key, recipient)
+    }
+
+    /// Replace the faults of the links between the nodes of this network with `faults`
+    pub fn set_link_faults(&self, faults: LinkFaults<K>) {
+        self.inner.master_map.set_link_faults(faults);
+    }
 }
 
 impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
@@ -300,13 +337,24 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
             .serialize(&message)
             .context(FailedToSerializeSnafu)?;
         trace!("Message bincoded, sending");
-        for node in &self.inner.master_map.map {
-            // TODO delay/drop etc here
-            let (key, node) = node.pair();
-            if !recipients.contains(key) {
+        // go through the recipients in order rather than the map, so that a run's messages are
+        // delivered in the same order every time
+        for key in &recipients {
+            let Some(node) = self
+                .inner
+                .master_map
+                .map
+                .get(key)
+                .map(|node| node.value().clone())
+            else {
                 continue;
-            }
+            };
+            let Some(latency) = self.link_latency(key) else {
+                trace!(?key, "Link to node is down, dropping message");
+                continue;
+            };
             trace!(?key, "Sending message to node");
+            self.inner.metrics.outgoing_bytes.add(vec.len());
             if let Some(r) = &self.inner.reliability_config {
                 let config = r.read().await;
                 {
@@ -316,6 +364,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
                         Arc::new(move |msg: Vec<u8>| {
                             let node3 = (node2).clone();
                             boxed_sync(async move {
+                                async_sleep(latency).await;
                                 let _res = node3.broadcast_input(msg).await;
                                 // NOTE we're dropping metrics here but this is only for testing
                                 // purposes. I think that should be okay
@@ -324,6 +373,12 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
                     );
                     async_spawn(fut);
                 }
+            } else if !latency.is_zero() {
+                let msg = vec.clone();
+                async_spawn(async move {
+                    async_sleep(latency).await;
+                    let _res = node.broadcast_input(msg).await;
+                });
             } else {
                 let res = node.broadcast_input(vec.clone()).await;
                 match res {
@@ -351,6 +406,11 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
         trace!("Message bincoded, finding recipient");
         if let Some(node) = self.inner.master_map.map.get(&recipient) {
             let node = node.value().clone();
+            let Some(latency) = self.link_latency(&recipient) else {
+                trace!(?recipient, "Link to node is down, dropping message");
+                return Ok(());
+            };
+            self.inner.metrics.outgoing_bytes.add(vec.len());
             if let Some(r) = &self.inner.reliability_config {
                 let config = r.read().await;
                 {
@@ -359,6 +419,7 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
                         Arc::new(move |msg: Vec<u8>| {
                             let node2 = node.clone();
                             boxed_sync(async move {
+                                async_sleep(latency).await;
                                 let _res = node2.broadcast_input(msg).await;
                                 // NOTE we're dropping metrics here but this is only for testing
                                 // purposes. I think that should be okay
@@ -368,6 +429,12 @@ impl<M: NetworkMsg, K: SignatureKey + 'static> ConnectedNetwork<M, K> for Memory
                     async_spawn(fut);
                 }
                 Ok(())
+            } else if !latency.is_zero() {
+                async_spawn(async move {
+                    async_sleep(latency).await;
+                    let _res = node.direct_input(vec).await;
+                });
+                Ok(())
             } else {
                 let res = node.direct_input(vec).await;
                 match res {
@@ -554,4 +621,9 @@ impl<TYPES: NodeType> TestableChannelImplementation<TYPES> for MemoryCommChannel
     {
         Box::new(move |network| MemoryCommChannel::new(network))
     }
+
+    fn set_link_faults(&self, faults: LinkFaults<TYPES::SignatureKey>) -> bool {
+        self.0.set_link_faults(faults);
+        true
+    }
 }
@@ -11,34 +11,87 @@ use async_compatibility_layer::{
 };
 use async_lock::RwLock;
 use async_trait::async_trait;
+use commit::Committable;
+use hotshot_constants::{Version, PROTOCOL_VERSION};
 use hotshot_task::{boxed_sync, BoxSyncFuture};
 use hotshot_types::{
-    message::{Message, MessagePurpose},
+    message::{DataMessage, Message, MessageKind, MessagePurpose, VersionedMessage},
     traits::{
         network::{
-            CommunicationChannel, ConnectedNetwork, ConsensusIntentEvent, NetworkError, NetworkMsg,
-            TestableChannelImplementation, TestableNetworkingImplementation, TransmitType,
-            WebServerNetworkError,
+            CommunicationChannel, ConnectedNetwork, ConsensusIntentEvent, FailedToSerializeSnafu,
+            NetworkError, NetworkMsg, TestableChannelImplementation,
+            TestableNetworkingImplementation, TransmitType, WebServerNetworkError,
         },
         node_implementation::NodeType,
         signature_key::SignatureKey,
     },
 };
-use hotshot_web_server::{self, config};
+use hotshot_web_server::{self, config, SignedRequest};
 use rand::random;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use snafu::ResultExt;
 
 use hotshot_types::traits::network::ViewMessage;
 use std::{
-    collections::{hash_map::Entry, BTreeSet, HashMap},
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet, VecDeque},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
-use surf_disco::error::ClientError;
-use tracing::{debug, error, info};
+use surf_disco::{error::ClientError, Url};
+use tracing::{debug, error, info, warn};
+
+/// How often a failing poll may check whether the web server it polls is still reachable
+const FAILOVER_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a failed post waits before it is retried against the next web server of the
+/// cluster, multiplied by how many it has tried already
+const FAILOVER_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How many delivered messages a poll remembers, so those delivered again after failing over
+/// are dropped
+const MAX_DELIVERED: usize = 10_000;
+
+/// The most recent messages a poll has delivered, by digest. The instances of a cluster
+/// number what they hold independently, so a poll that fails over starts again from the new
+/// instance's first index and relies on this to drop what it has already delivered.
+#[derive(Debug, Default)]
+struct Delivered {
+    /// digests of the delivered messages
+    digests: HashSet<MessageDigest>,
+    /// the same digests, oldest first
+    order: VecDeque<MessageDigest>,
+}
+
+impl Delivered {
+    /// Record the message with `digest` as delivered, returning whether it wasn't already
+    fn insert(&mut self, digest: MessageDigest) -> bool {
+        if !self.digests.insert(digest) {
+            return false;
+        }
+        self.order.push_back(digest);
+        if self.order.len() > MAX_DELIVERED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.digests.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// SHA-256 digest of a message, so no one can craft a message that is dropped as a duplicate
+/// of another
+type MessageDigest = [u8; 32];
+
+/// Digest of a message as the web server holds it. Every instance of a cluster holds the
+/// same bytes, since posts are replicated exactly as they were signed.
+fn digest(message: &[u8]) -> MessageDigest {
+    Sha256::digest(message).into()
+}
+
 /// Represents the communication channel abstraction for the web server
 #[derive(Clone, Debug)]
 pub struct WebCommChannel<TYPES: NodeType>(Arc<WebServerNetwork<TYPES>>);
@@ -66,16 +119,50 @@ impl<TYPES: NodeType> WebServerNetwork<TYPES> {
         &self,
         message: SendMsg<Message<TYPES>>,
     ) -> Result<(), NetworkError> {
-        let result: Result<(), ClientError> = self
-            .inner
-            .client
-            .post(&message.get_endpoint())
-            .body_binary(&message.get_message())
-            .unwrap()
-            .send()
-            .await;
-        // error!("POST message error for endpoint {} is {:?}", &message.get_endpoint(), result.clone());
-        result.map_err(|_e| NetworkError::WebServer {
+        let versioned =
+            VersionedMessage::new(&message.get_message()).context(FailedToSerializeSnafu)?;
+        self.post_to_web_server(message.get_endpoint(), &versioned)
+            .await
+    }
+
+    /// Post a versioned message to `endpoint` of the web server, failing over between the
+    /// instances of the cluster if need be
+    async fn post_to_web_server(
+        &self,
+        endpoint: String,
+        versioned: &VersionedMessage,
+    ) -> Result<(), NetworkError> {
+        self.inner.incompatibility()?;
+        // the server only accepts posts signed by a member of its stake table
+        let request = SignedRequest::new(
+            &self.inner.own_key,
+            &self.inner.private_key,
+            &endpoint,
+            versioned.to_bytes().context(FailedToSerializeSnafu)?,
+        );
+        // try each web server of the cluster at most once
+        for attempt in 0..self.inner.clients.len() {
+            if attempt > 0 {
+                async_sleep(FAILOVER_BACKOFF * u32::try_from(attempt).unwrap_or(u32::MAX)).await;
+            }
+            let result: Result<(), ClientError> = self
+                .inner
+                .client()
+                .post(&endpoint)
+                .body_binary(&request)
+                .unwrap()
+                .send()
+                .await;
+            // error!("POST message error for endpoint {} is {:?}", &message.get_endpoint(), result.clone());
+            match result {
+                Ok(()) => return Ok(()),
+                // the post was refused rather than lost, or there is nowhere else to send it
+                Err(_e) if !self.inner.fail_over().await => break,
+                // retry against the next web server in the cluster
+                Err(_e) => self.inner.incompatibility()?,
+            }
+        }
+        Err(NetworkError::WebServer {
             source: WebServerNetworkError::ClientError,
         })
     }
@@ -85,7 +172,9 @@ impl<TYPES: NodeType> WebServerNetwork<TYPES> {
 #[derive(Debug)]
 struct Inner<TYPES: NodeType> {
     /// Our own key
-    _own_key: TYPES::SignatureKey,
+    own_key: TYPES::SignatureKey,
+    /// Our own private key, which we sign our posts to the web server with
+    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
     /// Queue for broadcasted messages
     broadcast_poll_queue: Arc<RwLock<Vec<RecvMsg<Message<TYPES>>>>>,
     /// Queue for direct messages
@@ -94,15 +183,29 @@ struct Inner<TYPES: NodeType> {
     running: AtomicBool,
     /// The web server connection is ready
     connected: AtomicBool,
-    /// The connectioni to the web server
-    client: surf_disco::Client<ClientError>,
+    /// The protocol version of the web server, if the handshake found it incompatible with ours
+    incompatible_version: Mutex<Option<Version>>,
+    /// The web server holds requests to its long-poll routes open until there is data,
+    /// so we don't need to wait between polls
+    push_available: AtomicBool,
+    /// The connections to the instances of the web server cluster, in the order we fail over between them
+    clients: Vec<surf_disco::Client<ClientError>>,
+    /// Index in `clients` of the instance we are currently talking to
+    active_client: AtomicUsize,
+    /// How many times we have failed over, so polls know to start over against the new instance
+    failovers: AtomicU64,
+    /// The most recent transactions delivered, across transaction polls
+    delivered_txns: Mutex<Delivered>,
+    /// When a failing poll last checked whether the active instance is reachable
+    last_failover_check: Mutex<Option<Instant>>,
     /// The duration to wait between poll attempts
     wait_between_polls: Duration,
     /// Whether we are connecting to a DA server
     is_da: bool,
 
-    /// The last tx_index we saw from the web server
-    tx_index: Arc<RwLock<u64>>,
+    /// The last tx_index we saw from the web server, and how many times we had failed over
+    /// when we saw it
+    tx_index: Arc<RwLock<(u64, u64)>>,
 
     /// Task map for quorum proposals.
     proposal_task_map:
@@ -135,6 +238,61 @@ struct Inner<TYPES: NodeType> {
 
 impl<TYPES: NodeType> Inner<TYPES> {
     #![allow(clippy::too_many_lines)]
+    /// The connection to the web server instance we are currently talking to
+    fn client(&self) -> &surf_disco::Client<ClientError> {
+        &self.clients[self.active_client.load(Ordering::Relaxed)]
+    }
+
+    /// Called when a request fails. If the web server instance we are talking to is unreachable,
+    /// moves on to the next one in the cluster and returns `true`; returns `false` if the request
+    /// failed for any other reason, or there is no other instance to move to.
+    async fn fail_over(&self) -> bool {
+        if self.clients.len() < 2 {
+            return false;
+        }
+        let active = self.active_client.load(Ordering::Relaxed);
+        let reachable: Result<Version, ClientError> = self.clients[active]
+            .get(&config::get_version_route())
+            .send()
+            .await;
+        if reachable.is_ok() {
+            return false;
+        }
+        let next = (active + 1) % self.clients.len();
+        // another request may have failed over already
+        if self
+            .active_client
+            .compare_exchange(active, next, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            warn!("Web server {active} is unreachable, failing over to web server {next}");
+            self.failovers.fetch_add(1, Ordering::Relaxed);
+            // the new instance may be running another version, as during a rolling upgrade
+            let version: Result<Version, ClientError> = self.clients[next]
+                .get(&config::get_version_route())
+                .send()
+                .await;
+            if let Ok(version) = version {
+                self.accept_version(version);
+            }
+            self.detect_push().await;
+        }
+        true
+    }
+
+    /// `fail_over`, but only checking once per `FAILOVER_CHECK_INTERVAL`, since polls that find
+    /// nothing also fail
+    async fn fail_over_throttled(&self) {
+        {
+            let mut last_check = self.last_failover_check.lock().unwrap();
+            if last_check.is_some_and(|last_check| last_check.elapsed() < FAILOVER_CHECK_INTERVAL) {
+                return;
+            }
+            *last_check = Some(Instant::now());
+        }
+        self.fail_over().await;
+    }
+
     /// Pull a web server.
     async fn poll_web_server(
         &self,
@@ -144,13 +302,29 @@ impl<TYPES: NodeType> Inner<TYPES> {
     ) -> Result<(), NetworkError> {
         let mut vote_index = 0;
         let mut tx_index = 0;
+        let mut failovers = self.failovers.load(Ordering::Relaxed);
+        let mut delivered = Delivered::default();
 
         if message_purpose == MessagePurpose::Data {
-            tx_index = *self.tx_index.read().await;
+            let (saved_failovers, saved_index) = *self.tx_index.read().await;
+            // an index from before failing over means nothing to the instance we use now
+            if saved_failovers == failovers {
+                tx_index = saved_index;
+            }
             debug!("Previous tx index was {}", tx_index);
         };
 
         while self.running.load(Ordering::Relaxed) {
+            // there's no point polling a web server we can't understand
+            self.incompatibility()?;
+            // the instance we failed over to numbers what it holds independently, so start over
+            // from its first index and drop what we delivered already
+            let now_failovers = self.failovers.load(Ordering::Relaxed);
+            if now_failovers != failovers {
+                failovers = now_failovers;
+                vote_index = 0;
+                tx_index = 0;
+            }
             let endpoint = match message_purpose {
                 MessagePurpose::Proposal => config::get_proposal_route(view_number),
                 MessagePurpose::CurrentProposal => config::get_recent_proposal_route(),
@@ -168,6 +342,14 @@ impl<TYPES: NodeType> Inner<TYPES> {
                 MessagePurpose::VidVote => config::get_vid_vote_route(view_number, vote_index), // like `Vote`
                 MessagePurpose::VidCert => config::get_vid_certificate_route(view_number), // like `DAC`
             };
+            // there is no long-poll variant for the most recent proposal, it's always there
+            let push = self.push_available.load(Ordering::Relaxed)
+                && message_purpose != MessagePurpose::CurrentProposal;
+            let endpoint = if push {
+                config::get_wait_route(&endpoint)
+            } else {
+                endpoint
+            };
 
             if message_purpose == MessagePurpose::Data {
                 let possible_message = self.get_txs_from_web_server(endpoint).await;
@@ -178,16 +360,22 @@ impl<TYPES: NodeType> Inner<TYPES> {
                             debug!("missed txns from {} to {}", tx_index + 1, index - 1);
                             tx_index = index - 1;
                         }
-                        for tx in &deserialized_messages {
+                        let mut delivered_txns = self.delivered_txns.lock().unwrap();
+                        for (digest, tx) in deserialized_messages {
                             tx_index += 1;
-                            broadcast_poll_queue.push(tx.clone());
+                            if delivered_txns.insert(digest) {
+                                broadcast_poll_queue.push(tx);
+                            }
                         }
                         debug!("tx index is {}", tx_index);
                     }
+                    // the server already held the request, ask again straight away
+                    Ok(None) if push => {}
                     Ok(None) => {
                         async_sleep(self.wait_between_polls).await;
                     }
                     Err(_e) => {
+                        self.fail_over_throttled().await;
                         async_sleep(self.wait_between_polls).await;
                     }
                 }
@@ -205,7 +393,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 self.broadcast_poll_queue
                                     .write()
                                     .await
-                                    .push(deserialized_messages[0].clone());
+                                    .push(deserialized_messages[0].1.clone());
 
                                 return Ok(());
                                 // Wait for the view to change before polling for proposals again
@@ -222,7 +410,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 self.broadcast_poll_queue
                                     .write()
                                     .await
-                                    .push(deserialized_messages[0].clone());
+                                    .push(deserialized_messages[0].1.clone());
 
                                 return Ok(());
                             }
@@ -234,17 +422,21 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 //     self.is_da
                                 // );
                                 let mut direct_poll_queue = self.direct_poll_queue.write().await;
-                                for vote in &deserialized_messages {
+                                for (digest, vote) in &deserialized_messages {
                                     vote_index += 1;
-                                    direct_poll_queue.push(vote.clone());
+                                    if delivered.insert(*digest) {
+                                        direct_poll_queue.push(vote.clone());
+                                    }
                                 }
                             }
                             MessagePurpose::VidVote => {
                                 // TODO copy-pasted from `MessagePurpose::Vote` https://github.com/EspressoSystems/HotShot/issues/1690
                                 let mut direct_poll_queue = self.direct_poll_queue.write().await;
-                                for vote in &deserialized_messages {
+                                for (digest, vote) in &deserialized_messages {
                                     vote_index += 1;
-                                    direct_poll_queue.push(vote.clone());
+                                    if delivered.insert(*digest) {
+                                        direct_poll_queue.push(vote.clone());
+                                    }
                                 }
                             }
                             MessagePurpose::DAC => {
@@ -256,7 +448,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 self.broadcast_poll_queue
                                     .write()
                                     .await
-                                    .push(deserialized_messages[0].clone());
+                                    .push(deserialized_messages[0].1.clone());
 
                                 // return if we found a DAC, since there will only be 1 per view
                                 // In future we should check to make sure DAC is valid
@@ -272,7 +464,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 self.broadcast_poll_queue
                                     .write()
                                     .await
-                                    .push(deserialized_messages[0].clone());
+                                    .push(deserialized_messages[0].1.clone());
 
                                 // return if we found a VID cert, since there will only be 1 per view
                                 // In future we should check to make sure VID cert is valid
@@ -285,7 +477,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 self.broadcast_poll_queue
                                     .write()
                                     .await
-                                    .push(deserialized_messages[0].clone());
+                                    .push(deserialized_messages[0].1.clone());
 
                                 return Ok(());
                                 // Wait for the view to change before polling for proposals again
@@ -305,9 +497,11 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                 //     self.is_da
                                 // );
                                 let mut direct_poll_queue = self.direct_poll_queue.write().await;
-                                for vote in &deserialized_messages {
+                                for (digest, vote) in &deserialized_messages {
                                     vote_index += 1;
-                                    direct_poll_queue.push(vote.clone());
+                                    if delivered.insert(*digest) {
+                                        direct_poll_queue.push(vote.clone());
+                                    }
                                 }
                             }
                             MessagePurpose::ViewSyncProposal => {
@@ -321,9 +515,11 @@ impl<TYPES: NodeType> Inner<TYPES> {
                                     self.broadcast_poll_queue.write().await;
                                 // TODO ED Special case this for view sync
                                 // TODO ED Need to add vote indexing to web server for view sync certs
-                                for cert in &deserialized_messages {
+                                for (digest, cert) in &deserialized_messages {
                                     vote_index += 1;
-                                    broadcast_poll_queue.push(cert.clone());
+                                    if delivered.insert(*digest) {
+                                        broadcast_poll_queue.push(cert.clone());
+                                    }
                                 }
                             }
 
@@ -332,11 +528,14 @@ impl<TYPES: NodeType> Inner<TYPES> {
                             }
                         }
                     }
+                    // the server already held the request, ask again straight away
+                    Ok(None) if push => {}
                     Ok(None) => {
                         async_sleep(self.wait_between_polls).await;
                     }
                     Err(_e) => {
                         // error!("error is {:?}", _e);
+                        self.fail_over_throttled().await;
                         async_sleep(self.wait_between_polls).await;
                     }
                 }
@@ -362,7 +561,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
                             // Write the most recent tx index so we can pick up where we left off later
 
                             let mut lock = self.tx_index.write().await;
-                            *lock = tx_index;
+                            *lock = (failovers, tx_index);
 
                             if view_number == event_view {
                                 debug!("Shutting down polling task for view {}", event_view);
@@ -384,13 +583,95 @@ impl<TYPES: NodeType> Inner<TYPES> {
         Err(NetworkError::ShutDown)
     }
 
+    /// The error to fail requests with if the handshake found the web server speaks an
+    /// incompatible protocol version
+    fn incompatibility(&self) -> Result<(), NetworkError> {
+        match *self.incompatible_version.lock().unwrap() {
+            Some(remote) => Err(NetworkError::IncompatibleVersion {
+                local: PROTOCOL_VERSION,
+                remote,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Unwraps a message posted to the web server, refusing it if it was sent under an
+    /// incompatible protocol version
+    fn open_message(message: &[u8]) -> Result<RecvMsg<Message<TYPES>>, NetworkError> {
+        let versioned = VersionedMessage::from_bytes(message)
+            .map_err(|source| NetworkError::FailedToDeserialize { source })?;
+        versioned.open()
+    }
+
+    /// Performs the version handshake with the web server, retrying until the server is reachable.
+    /// Marks the connection as ready if the server speaks a compatible protocol version; an
+    /// incompatible server is refused and the connection is never marked ready.
+    async fn handshake(&self) {
+        while self.running.load(Ordering::Relaxed) {
+            let result: Result<Version, ClientError> =
+                self.client().get(&config::get_version_route()).send().await;
+            match result {
+                Ok(version) => {
+                    if self.accept_version(version) {
+                        self.connected.store(true, Ordering::Relaxed);
+                        self.detect_push().await;
+                    }
+                    return;
+                }
+                Err(e) => {
+                    debug!("Web server not ready for handshake: {e:?}");
+                    // try the next instance of the cluster, if there is one
+                    let next =
+                        (self.active_client.load(Ordering::Relaxed) + 1) % self.clients.len();
+                    self.active_client.store(next, Ordering::Relaxed);
+                    async_sleep(self.wait_between_polls).await;
+                }
+            }
+        }
+    }
+
+    /// Whether we can talk to a web server speaking protocol `version`. A compatible server is
+    /// sent messages in the current layout whatever its minor version; an incompatible server is
+    /// refused, failing every request from then on.
+    fn accept_version(&self, version: Version) -> bool {
+        if !PROTOCOL_VERSION.is_compatible_with(&version) {
+            error!(
+                "Refusing web server with incompatible protocol version {version}, we speak {PROTOCOL_VERSION}"
+            );
+            *self.incompatible_version.lock().unwrap() = Some(version);
+            return false;
+        }
+        if version != PROTOCOL_VERSION {
+            warn!("Web server speaks compatible protocol version {version}, we speak {PROTOCOL_VERSION}");
+        }
+        true
+    }
+
+    /// Checks whether the web server offers long-polling, falling back to polling on an interval if not
+    async fn detect_push(&self) {
+        let result: Result<u64, ClientError> =
+            self.client().get(&config::get_push_route()).send().await;
+        match result {
+            Ok(timeout_ms) => {
+                info!(
+                    "Web server holds long-poll requests for {timeout_ms}ms, using push delivery"
+                );
+                self.push_available.store(true, Ordering::Relaxed);
+            }
+            Err(e) => {
+                info!("Web server does not support push delivery, polling instead: {e:?}");
+                self.push_available.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
     /// Fetches transactions from web server
     async fn get_txs_from_web_server(
         &self,
         endpoint: String,
-    ) -> Result<Option<(u64, Vec<RecvMsg<Message<TYPES>>>)>, NetworkError> {
+    ) -> Result<Option<(u64, Vec<(MessageDigest, RecvMsg<Message<TYPES>>)>)>, NetworkError> {
         let result: Result<Option<(u64, Vec<Vec<u8>>)>, ClientError> =
-            self.client.get(&endpoint).send().await;
+            self.client().get(&endpoint).send().await;
         match result {
             Err(_error) => Err(NetworkError::WebServer {
                 source: WebServerNetworkError::ClientError,
@@ -398,11 +679,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
             Ok(Some((index, messages))) => {
                 let mut deserialized_messages = Vec::new();
                 for message in &messages {
-                    let deserialized_message = bincode::deserialize(message);
-                    if let Err(e) = deserialized_message {
-                        return Err(NetworkError::FailedToDeserialize { source: e });
-                    }
-                    deserialized_messages.push(deserialized_message.unwrap());
+                    deserialized_messages.push((digest(message), Self::open_message(message)?));
                 }
                 Ok(Some((index, deserialized_messages)))
             }
@@ -411,13 +688,13 @@ impl<TYPES: NodeType> Inner<TYPES> {
     }
 
     /// Sends a GET request to the webserver for some specified endpoint
-    /// Returns a vec of deserialized, received messages or an error
+    /// Returns a vec of deserialized, received messages, each with its digest, or an error
     async fn get_message_from_web_server(
         &self,
         endpoint: String,
-    ) -> Result<Option<Vec<RecvMsg<Message<TYPES>>>>, NetworkError> {
+    ) -> Result<Option<Vec<(MessageDigest, RecvMsg<Message<TYPES>>)>>, NetworkError> {
         let result: Result<Option<Vec<Vec<u8>>>, ClientError> =
-            self.client.get(&endpoint).send().await;
+            self.client().get(&endpoint).send().await;
         match result {
             Err(_error) => Err(NetworkError::WebServer {
                 source: WebServerNetworkError::ClientError,
@@ -425,11 +702,7 @@ impl<TYPES: NodeType> Inner<TYPES> {
             Ok(Some(messages)) => {
                 let mut deserialized_messages = Vec::new();
                 for message in &messages {
-                    let deserialized_message = bincode::deserialize(message);
-                    if let Err(e) = deserialized_message {
-                        return Err(NetworkError::FailedToDeserialize { source: e });
-                    }
-                    deserialized_messages.push(deserialized_message.unwrap());
+                    deserialized_messages.push((digest(message), Self::open_message(message)?));
                 }
                 Ok(Some(deserialized_messages))
             }
@@ -490,35 +763,41 @@ impl<M: NetworkMsg> NetworkMsg for SendMsg<M> {}
 impl<M: NetworkMsg> NetworkMsg for RecvMsg<M> {}
 
 impl<TYPES: NodeType + 'static> WebServerNetwork<TYPES> {
-    /// Creates a new instance of the `WebServerNetwork`
+    /// Creates a new instance of the `WebServerNetwork`, talking to the first of `urls` and failing
+    /// over to the next whenever the one in use becomes unreachable. All of `urls` should be
+    /// instances of one replicated web server cluster.
     /// # Panics
-    /// if the web server url is malformed
+    /// if `urls` is empty
     pub fn create(
-        host: &str,
-        port: u16,
+        urls: Vec<Url>,
         wait_between_polls: Duration,
         key: TYPES::SignatureKey,
+        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
         is_da_server: bool,
     ) -> Self {
-        let base_url_string = format!("http://{host}:{port}");
-        info!("Connecting to web server at {base_url_string:?} is da: {is_da_server}");
-
-        let base_url = base_url_string.parse();
-        if base_url.is_err() {
-            error!("Web server url {:?} is malformed", base_url_string);
-        }
+        assert!(!urls.is_empty(), "No web server urls given");
+        info!("Connecting to web servers at {urls:?} is da: {is_da_server}");
 
-        // TODO ED Wait for healthcheck
-        let client = surf_disco::Client::<ClientError>::new(base_url.unwrap());
+        let clients = urls
+            .into_iter()
+            .map(surf_disco::Client::<ClientError>::new)
+            .collect();
 
         let inner = Arc::new(Inner {
             broadcast_poll_queue: Arc::default(),
             direct_poll_queue: Arc::default(),
             running: AtomicBool::new(true),
             connected: AtomicBool::new(false),
-            client,
+            incompatible_version: Mutex::new(None),
+            push_available: AtomicBool::new(false),
+            clients,
+            active_client: AtomicUsize::new(0),
+            failovers: AtomicU64::new(0),
+            delivered_txns: Mutex::default(),
+            last_failover_check: Mutex::new(None),
             wait_between_polls,
-            _own_key: key,
+            own_key: key,
+            private_key,
             is_da: is_da_server,
             tx_index: Arc::default(),
             proposal_task_map: Arc::default(),
@@ -532,7 +811,10 @@ impl<TYPES: NodeType + 'static> WebServerNetwork<TYPES> {
             txn_task_map: Arc::default(),
         });
 
-        inner.connected.store(true, Ordering::Relaxed);
+        async_spawn({
+            let inner = inner.clone();
+            async move { inner.handshake().await }
+        });
 
         Self {
             inner,
@@ -550,7 +832,14 @@ impl<TYPES: NodeType + 'static> WebServerNetwork<TYPES> {
         let endpoint = match &message.purpose() {
             MessagePurpose::Proposal => config::post_proposal_route(*view_number),
             MessagePurpose::Vote => config::post_vote_route(*view_number),
-            MessagePurpose::Data => config::post_transactions_route(),
+            MessagePurpose::Data => match &message.kind {
+                MessageKind::Data(DataMessage::SubmitTransaction(txn, _)) => {
+                    config::post_transactions_route(&config::commitment_param(
+                        txn.commit().as_ref(),
+                    ))
+                }
+                MessageKind::Consensus(_) => return Err(WebServerNetworkError::EndpointError),
+            },
             MessagePurpose::Internal | MessagePurpose::CurrentProposal => {
                 return Err(WebServerNetworkError::EndpointError)
             }
@@ -668,9 +957,14 @@ impl<TYPES: NodeType> CommunicationChannel<TYPES> for WebCommChannel<TYPES> {
 impl<TYPES: NodeType + 'static> ConnectedNetwork<Message<TYPES>, TYPES::SignatureKey>
     for WebServerNetwork<TYPES>
 {
-    /// Blocks until the network is successfully initialized
+    /// Blocks until the network is successfully initialized, or the web server turns out to
+    /// speak an incompatible protocol version, which the network can never recover from
     async fn wait_for_ready(&self) {
         while !self.inner.connected.load(Ordering::Relaxed) {
+            if let Err(e) = self.inner.incompatibility() {
+                error!("Web server network will never be ready: {e}");
+                return;
+            }
             async_sleep(Duration::from_secs(1)).await;
         }
     }
@@ -1179,6 +1473,32 @@ impl<TYPES: NodeType + 'static> ConnectedNetwork<Message<TYPES>, TYPES::Signatur
                     info!("Task map entry should have existed");
                 };
             }
+            ConsensusIntentEvent::TransactionsDecided(view_number, commitments) => {
+                // the server drops decided transactions, so nobody includes them again
+                let commitments: Vec<String> = commitments
+                    .iter()
+                    .map(|commitment| config::commitment_param(commitment))
+                    .collect();
+                match VersionedMessage::new(&commitments) {
+                    Ok(versioned) => {
+                        let network = self.clone();
+                        async_spawn(async move {
+                            if let Err(e) = network
+                                .post_to_web_server(
+                                    config::post_decided_transactions_route(view_number),
+                                    &versioned,
+                                )
+                                .await
+                            {
+                                warn!(
+                                    "Failed to report transactions decided in view {view_number}: {e:?}"
+                                );
+                            }
+                        });
+                    }
+                    Err(e) => error!("Failed to serialize decided transactions: {e:?}"),
+                }
+            }
 
             _ => {}
         }
@@ -1198,29 +1518,28 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES> for WebServerNetwo
         // TODO ED Restrict this to be an open port using portpicker
         let port = random::<u16>();
         info!("Launching web server on port {port}");
-        // Start web server
-        async_spawn(hotshot_web_server::run_web_server::<TYPES::SignatureKey>(
-            Some(server_shutdown),
-            port,
-        ));
 
         // We assign known_nodes' public key and stake value rather than read from config file since it's a test
         let known_nodes = (0..expected_node_count as u64)
-            .map(|id| {
-                TYPES::SignatureKey::from_private(
-                    &TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], id).1,
-                )
-            })
+            .map(|id| TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], id))
             .collect::<Vec<_>>();
 
+        // Start web server
+        async_spawn(hotshot_web_server::run_web_server::<TYPES>(
+            Some(server_shutdown),
+            port,
+            known_nodes.iter().map(|(key, _)| key.clone()).collect(),
+            hotshot_web_server::Options::default(),
+        ));
+
         // Start each node's web server client
         Box::new(move |id| {
             let sender = Arc::clone(&sender);
             let mut network = WebServerNetwork::create(
-                "0.0.0.0",
-                port,
+                vec![format!("http://0.0.0.0:{port}").parse().unwrap()],
                 Duration::from_millis(100),
-                known_nodes[id as usize].clone(),
+                known_nodes[id as usize].0.clone(),
+                known_nodes[id as usize].1.clone(),
                 is_da,
             );
             network.server_shutdown_signal = Some(sender);
@@ -1261,3 +1580,26 @@ impl<TYPES: NodeType> TestableChannelImplementation<TYPES> for WebCommChannel<TY
         Box::new(move |network| WebCommChannel::new(network))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// messages are only delivered once, remembering up to `MAX_DELIVERED` of them
+    #[test]
+    fn test_delivered() {
+        let mut delivered = Delivered::default();
+        assert!(delivered.insert(digest(b"vote")));
+        assert!(!delivered.insert(digest(b"vote")));
+        assert!(delivered.insert(digest(b"other vote")));
+
+        for i in 0..MAX_DELIVERED {
+            delivered.insert(digest(&i.to_le_bytes()));
+        }
+        assert_eq!(delivered.order.len(), MAX_DELIVERED);
+        assert_eq!(delivered.digests.len(), MAX_DELIVERED);
+        // the oldest were forgotten, the newest weren't
+        assert!(delivered.insert(digest(b"vote")));
+        assert!(!delivered.insert(digest(&(MAX_DELIVERED - 1).to_le_bytes())));
+    }
+}
@@ -1,10 +1,10 @@
 //! Provides an event-streaming handle for a [`HotShot`] running in the background
 
 use crate::{traits::NodeImplementation, types::Event, SystemContext};
-use async_compatibility_layer::channel::UnboundedStream;
+use async_compatibility_layer::{art::async_spawn, channel::UnboundedStream};
 use async_lock::RwLock;
 use commit::Committable;
-use futures::Stream;
+use futures::{stream, Stream, StreamExt};
 use hotshot_task::{
     boxed_sync,
     event_stream::{ChannelStream, EventStream, StreamId},
@@ -12,7 +12,10 @@ use hotshot_task::{
     task::FilterEvent,
     BoxSyncFuture,
 };
-use hotshot_task_impls::events::HotShotEvent;
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    journal::{JournalError, JournalEvent, JournalWriter},
+};
 use hotshot_types::simple_vote::QuorumData;
 use hotshot_types::{
     consensus::Consensus,
@@ -20,11 +23,13 @@ use hotshot_types::{
     event::EventType,
     message::{MessageKind, SequencingMessage},
     traits::{
-        election::Membership, node_implementation::NodeType, state::ConsensusTime, storage::Storage,
+        election::Membership, node_implementation::NodeType, state::ConsensusTime,
+        storage::Storage, BlockPayload,
     },
 };
 use hotshot_types::{data::Leaf, simple_certificate::QuorumCertificate};
-use std::sync::Arc;
+use serde::Serialize;
+use std::{path::Path, sync::Arc};
 use tracing::error;
 
 /// Event streaming handle for a [`SystemContext`] instance running in the background
@@ -96,6 +101,47 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
         self.internal_event_stream.subscribe(filter).await
     }
 
+    /// Record every internal and external event from now on in a journal at `path`, which
+    /// [`read_journal`](hotshot_task_impls::journal::read_journal) reads back. Recording ends when
+    /// the node shuts down.
+    ///
+    /// # Errors
+    /// If the journal file can't be created
+    pub async fn record_journal(&self, path: impl AsRef<Path>) -> Result<(), JournalError>
+    where
+        <TYPES::BlockPayload as BlockPayload>::Metadata: Serialize,
+    {
+        let mut journal = JournalWriter::create(path)?;
+        let (internal, _) = self
+            .internal_event_stream
+            .subscribe(FilterEvent::default())
+            .await;
+        let (external, _) = self
+            .output_event_stream
+            .subscribe(FilterEvent::default())
+            .await;
+        let mut events = Box::pin(stream::select(
+            internal.map(JournalEvent::from),
+            external.map(JournalEvent::from),
+        ));
+        async_spawn(async move {
+            while let Some(event) = events.next().await {
+                let shutdown = matches!(event, JournalEvent::Internal(HotShotEvent::Shutdown));
+                if let Err(e) = journal.record(event) {
+                    error!("Failed to record an event in the journal: {e}");
+                    return;
+                }
+                if shutdown {
+                    break;
+                }
+            }
+            if let Err(e) = journal.flush() {
+                error!("Failed to flush the journal: {e}");
+            }
+        });
+        Ok(())
+    }
+
     /// Gets the current committed state of the [`HotShot`] instance
     ///
     /// # Errors
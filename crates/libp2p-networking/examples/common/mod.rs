@@ -359,7 +359,7 @@ pub async fn regular_handle_network_event(
     use NetworkEvent::*;
     match event {
         IsBootstrapped => {}
-        GossipMsg(m, _) | DirectResponse(m, _) => {
+        GossipMsg(m, _) | DirectResponse(m, _) | StreamMsg(m, _) => {
             if let Ok(msg) = deserialize_msg::<Message>(&m) {
                 info!("regular msg recved: {:?}", msg.clone());
                 match msg {
@@ -623,7 +623,7 @@ pub async fn start_main(opts: CliOpt) -> Result<(), CounterError> {
             }
         }
         // regular and bootstrap nodes
-        NetworkNodeType::Regular | NetworkNodeType::Bootstrap => {
+        NetworkNodeType::Regular | NetworkNodeType::Bootstrap | NetworkNodeType::Relay => {
             let config = NetworkNodeConfigBuilder::default()
                 .bound_addr(Some(opts.bound_addr))
                 .node_type(opts.node_type)
@@ -793,7 +793,7 @@ pub async fn conductor_handle_network_event(
                 error!("failed to deserialize msg");
             }
         }
-        DirectResponse(_m, _peer_id) => { /* nothing to do here */ }
+        DirectResponse(_m, _peer_id) | StreamMsg(_m, _peer_id) => { /* nothing to do here */ }
     }
     Ok(())
 }
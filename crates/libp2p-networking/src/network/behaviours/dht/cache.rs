@@ -184,6 +184,14 @@ impl Cache {
         self.save_if_necessary().await;
     }
 
+    pub async fn remove(&self, key: &Vec<u8>) {
+        // the stale expiry is cleaned up on the next prune
+        if self.cache.remove(key).is_some() {
+            self.disk_parity_delta.fetch_add(1, Ordering::Relaxed);
+            self.save_if_necessary().await;
+        }
+    }
+
     async fn save_if_necessary(&self) {
         let cur_disk_parity_delta = self.disk_parity_delta.load(Ordering::Relaxed);
         if cur_disk_parity_delta >= self.config.max_disk_parity_delta {
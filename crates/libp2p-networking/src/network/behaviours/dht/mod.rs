@@ -6,6 +6,8 @@ use std::{
 };
 
 mod cache;
+/// disk backed store of known peers
+mod peer_store;
 
 use async_compatibility_layer::art::async_block_on;
 use futures::channel::oneshot::Sender;
@@ -26,7 +28,8 @@ use tracing::{error, info, warn};
 pub(crate) const NUM_REPLICATED_TO_TRUST: usize = 2;
 const MAX_DHT_QUERY_SIZE: usize = 5;
 
-use self::cache::Cache;
+pub use self::peer_store::DEFAULT_PEER_STORE_MAX_AGE;
+use self::{cache::Cache, peer_store::PeerStore};
 
 use super::exponential_backoff::ExponentialBackoff;
 
@@ -65,6 +68,8 @@ pub struct DHTBehaviour {
     pub replication_factor: NonZeroUsize,
     /// kademlia cache
     cache: Cache,
+    /// known peers and their addresses, persisted across restarts
+    peer_store: PeerStore,
 }
 
 /// State of bootstrapping
@@ -113,13 +118,17 @@ impl DHTBehaviour {
         self.kadem.get_closest_peers(random_peer);
     }
 
-    /// Create a new DHT behaviour
+    /// Create a new DHT behaviour.
+    /// Peers remembered in the peer store at `peer_store_location` are added back
+    /// to the routing table, unless they haven't been seen in `peer_store_max_age`
     #[must_use]
     pub async fn new(
         mut kadem: KademliaBehaviour<MemoryStore>,
         pid: PeerId,
         replication_factor: NonZeroUsize,
         cache_location: Option<String>,
+        peer_store_location: Option<String>,
+        peer_store_max_age: Option<Duration>,
     ) -> Self {
         // needed because otherwise we stay in client mode when testing locally
         // and don't publish keys stuff
@@ -127,6 +136,30 @@ impl DHTBehaviour {
         // we won't have a local network
         // <https://github.com/libp2p/rust-libp2p/issues/4194>
         kadem.set_mode(Some(Mode::Server));
+
+        let peer_store = PeerStore::new(
+            peer_store::ConfigBuilder::default()
+                .filename(peer_store_location)
+                .max_age(peer_store_max_age.unwrap_or(DEFAULT_PEER_STORE_MAX_AGE))
+                .build()
+                .unwrap_or_default(),
+        );
+        let mut num_restored = 0;
+        for (peer, entry) in peer_store.peers() {
+            if *peer != pid {
+                for addr in &entry.addrs {
+                    kadem.add_address(peer, addr.clone());
+                }
+                num_restored += 1;
+            }
+        }
+        if num_restored > 0 {
+            info!(
+                "peer {:?} restored {} peers from the peer store",
+                pid, num_restored
+            );
+        }
+
         Self {
             begin_bootstrap: false,
             bootstrap_nodes: HashMap::default(),
@@ -155,6 +188,7 @@ impl DHTBehaviour {
                     .unwrap_or_default(),
             )
             .await,
+            peer_store,
         }
     }
 
@@ -164,6 +198,11 @@ impl DHTBehaviour {
         self.in_progress_get_closest_peers.insert(qid, chan);
     }
 
+    /// drop a record from the local cache so the next lookup goes to the DHT
+    pub fn evict_cached_record(&mut self, key: &Vec<u8>) {
+        async_block_on(self.cache.remove(key));
+    }
+
     /// print out the routing table to stderr
     pub fn print_routing_table(&mut self) {
         let mut err = format!("KBUCKETS: PID: {:?}, ", self.peer_id);
@@ -184,9 +223,18 @@ impl DHTBehaviour {
     /// Associate address with kademlia peer
     pub fn add_address(&mut self, peer_id: &PeerId, addr: Multiaddr) {
         // add address to kademlia
+        self.peer_store.record(*peer_id, [addr.clone()]);
         self.kadem.add_address(peer_id, addr);
     }
 
+    /// Passthru to kademlia
+    /// Forget about a peer entirely, e.g. because it speaks an incompatible protocol
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.kadem.remove_peer(peer_id);
+        self.bootstrap_nodes.remove(peer_id);
+        self.peer_store.remove(peer_id);
+    }
+
     /// Save in case kademlia forgets about bootstrap nodes
     pub fn add_bootstrap_nodes(&mut self, nodes: HashMap<PeerId, HashSet<Multiaddr>>) {
         for (k, v) in nodes {
@@ -482,12 +530,15 @@ impl DHTBehaviour {
             }
             KademliaEvent::InboundRequest { request: _r } => {}
             KademliaEvent::RoutingUpdated {
-                peer: _,
+                peer,
                 is_new_peer: _,
-                addresses: _,
+                addresses,
                 bucket_range: _,
                 old_peer: _,
-            } => {}
+            } => {
+                // remember the routing table across restarts
+                self.peer_store.record(peer, addresses.iter().cloned());
+            }
             e @ KademliaEvent::OutboundQueryProgressed { .. } => {
                 info!("Not handling dht event {:?}", e);
             }
@@ -0,0 +1,256 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime},
+};
+
+use bincode::Options;
+use hotshot_utils::bincode::bincode_opts;
+use libp2p::Multiaddr;
+use libp2p_identity::PeerId;
+use snafu::ResultExt;
+
+use super::cache::{CacheError, DeserializationSnafu, DiskSnafu, SerializationSnafu};
+
+/// how long a peer may go unseen before we forget about it, one day by default
+pub const DEFAULT_PEER_STORE_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+
+#[derive(Clone, derive_builder::Builder, custom_debug::Debug, Default)]
+pub struct Config {
+    #[builder(default = "None")]
+    pub filename: Option<String>,
+    #[builder(default = "DEFAULT_PEER_STORE_MAX_AGE")]
+    pub max_age: Duration,
+    #[builder(default = "32")]
+    pub max_disk_parity_delta: u32,
+}
+
+/// What we know about a peer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerEntry {
+    /// the addresses the peer was reachable on
+    pub addrs: HashSet<Multiaddr>,
+    /// the last time we heard from the peer
+    pub last_seen: SystemTime,
+}
+
+/// on disk format: (peer id, addresses, last seen).
+/// peer ids and addresses are stored as bytes so the format doesn't depend on their serde impls
+type StoredPeers = Vec<(Vec<u8>, Vec<Vec<u8>>, SystemTime)>;
+
+/// Disk backed store of the peers we know about (including our routing table)
+/// so that a restarted node can reconnect without bootstrapping from scratch.
+/// Peers we haven't seen in `max_age` are aged out.
+pub struct PeerStore {
+    /// the store's config
+    config: Config,
+
+    /// known peers
+    peers: HashMap<PeerId, PeerEntry>,
+
+    /// number of changes since the last save
+    disk_parity_delta: u32,
+}
+
+impl PeerStore {
+    pub fn new(config: Config) -> Self {
+        let mut store = Self {
+            config,
+            peers: HashMap::new(),
+            disk_parity_delta: 0,
+        };
+
+        // try loading from file
+        if let Err(err) = store.load() {
+            tracing::warn!("failed to load peer store from file: {}", err);
+        };
+
+        store
+    }
+
+    pub fn load(&mut self) -> Result<(), CacheError> {
+        if let Some(filename) = &self.config.filename {
+            let encoded = std::fs::read(filename).context(DiskSnafu)?;
+
+            let peers: StoredPeers = bincode_opts()
+                .deserialize(&encoded)
+                .context(DeserializationSnafu)?;
+
+            for (peer, addrs, last_seen) in peers {
+                let Ok(peer) = PeerId::from_bytes(&peer) else {
+                    tracing::warn!("skipping malformed peer id in peer store");
+                    continue;
+                };
+                let addrs = addrs
+                    .into_iter()
+                    .filter_map(|addr| Multiaddr::try_from(addr).ok())
+                    .collect();
+                self.peers.insert(peer, PeerEntry { addrs, last_seen });
+            }
+            self.prune();
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> Result<(), CacheError> {
+        if let Some(filename) = &self.config.filename {
+            // prune first
+            self.prune();
+
+            let peers: StoredPeers = self
+                .peers
+                .iter()
+                .map(|(peer, entry)| {
+                    (
+                        peer.to_bytes(),
+                        entry.addrs.iter().map(Multiaddr::to_vec).collect(),
+                        entry.last_seen,
+                    )
+                })
+                .collect();
+
+            let encoded = bincode_opts()
+                .serialize(&peers)
+                .context(SerializationSnafu)?;
+
+            std::fs::write(filename, encoded).context(DiskSnafu)?;
+            self.disk_parity_delta = 0;
+        }
+
+        Ok(())
+    }
+
+    /// drop peers we haven't seen in `max_age`
+    fn prune(&mut self) {
+        let now = SystemTime::now();
+        let max_age = self.config.max_age;
+        let before = self.peers.len();
+        self.peers.retain(|_, entry| {
+            // a last seen time in the future means the clock moved, keep it
+            now.duration_since(entry.last_seen)
+                .map_or(true, |age| age < max_age)
+        });
+        let removed = before - self.peers.len();
+        if removed > 0 {
+            tracing::info!("aged out {} peers from the peer store", removed);
+            self.disk_parity_delta += u32::try_from(removed).unwrap_or(u32::MAX);
+        }
+    }
+
+    /// the peers we know about
+    pub fn peers(&self) -> impl Iterator<Item = (&PeerId, &PeerEntry)> {
+        self.peers.iter()
+    }
+
+    /// note that we have just seen `peer` reachable on `addrs`
+    pub fn record(&mut self, peer: PeerId, addrs: impl IntoIterator<Item = Multiaddr>) {
+        let entry = self.peers.entry(peer).or_insert_with(|| PeerEntry {
+            addrs: HashSet::new(),
+            last_seen: SystemTime::now(),
+        });
+        entry.addrs.extend(addrs);
+        entry.last_seen = SystemTime::now();
+
+        self.disk_parity_delta += 1;
+        self.save_if_necessary();
+    }
+
+    /// forget about `peer`
+    pub fn remove(&mut self, peer: &PeerId) {
+        if self.peers.remove(peer).is_some() {
+            self.disk_parity_delta += 1;
+            self.save_if_necessary();
+        }
+    }
+
+    fn save_if_necessary(&mut self) {
+        if self.disk_parity_delta >= self.config.max_disk_parity_delta {
+            if let Err(err) = self.save() {
+                tracing::error!("failed to save peer store to file: {}", err);
+            };
+        }
+    }
+}
+
+impl Drop for PeerStore {
+    fn drop(&mut self) {
+        // flush whatever we learned since the last save
+        if self.disk_parity_delta > 0 {
+            if let Err(err) = self.save() {
+                tracing::error!("failed to save peer store to file: {}", err);
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::str::FromStr;
+
+    /// peer store save and reload test
+    #[test]
+    fn test_peer_store_save_load() {
+        let _ = std::fs::remove_file("test_save_load.peers");
+
+        let config = Config {
+            filename: Some("test_save_load.peers".to_string()),
+            max_age: Duration::from_secs(600),
+            max_disk_parity_delta: 4,
+        };
+        let mut store = PeerStore::new(config.clone());
+
+        let addr = Multiaddr::from_str("/ip4/127.0.0.1/udp/9000/quic-v1").unwrap();
+        let peers = (0..10).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for peer in &peers {
+            store.record(*peer, [addr.clone()]);
+        }
+        store.save().unwrap();
+        drop(store);
+
+        // reload and check every peer made it
+        let store = PeerStore::new(config);
+        assert_eq!(store.peers().count(), peers.len());
+        for peer in &peers {
+            let entry = store.peers.get(peer).unwrap();
+            assert!(entry.addrs.contains(&addr));
+        }
+        drop(store);
+
+        let _ = std::fs::remove_file("test_save_load.peers");
+    }
+
+    /// stale peers are aged out on reload
+    #[test]
+    fn test_peer_store_age_out() {
+        let _ = std::fs::remove_file("test_age_out.peers");
+
+        let mut config = Config {
+            filename: Some("test_age_out.peers".to_string()),
+            max_age: Duration::from_secs(600),
+            max_disk_parity_delta: 4,
+        };
+        let mut store = PeerStore::new(config.clone());
+
+        let (fresh, stale) = (PeerId::random(), PeerId::random());
+        store.record(fresh, []);
+        store.record(stale, []);
+        store.peers.get_mut(&stale).unwrap().last_seen =
+            SystemTime::now() - Duration::from_secs(1200);
+        store.save().unwrap();
+
+        // saving prunes too
+        assert!(!store.peers.contains_key(&stale));
+        drop(store);
+
+        // and so does loading, once the peer gets old enough
+        config.max_age = Duration::from_millis(1);
+        std::thread::sleep(Duration::from_millis(10));
+        let store = PeerStore::new(config);
+        assert_eq!(store.peers().count(), 0);
+        drop(store);
+
+        let _ = std::fs::remove_file("test_age_out.peers");
+    }
+}
@@ -20,8 +20,9 @@ pub struct DirectMessageRequest(pub Vec<u8>);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DirectMessageResponse(pub Vec<u8>);
 
-/// Maximum size of a direct message
-pub const MAX_MSG_SIZE_DM: usize = 100_000_000;
+/// Maximum size of a direct message.
+/// Anything larger than a stream chunk is streamed, so this only needs to fit one chunk
+pub const MAX_MSG_SIZE_DM: usize = 2 * 1024 * 1024;
 
 impl AsRef<str> for DirectMessageProtocol {
     fn as_ref(&self) -> &str {
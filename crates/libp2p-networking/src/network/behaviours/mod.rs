@@ -11,5 +11,13 @@ pub mod exponential_backoff;
 /// for `RequestResponse`
 pub mod direct_message_codec;
 
+/// Chunked streaming of large direct messages
+/// on top of `RequestResponse`
+pub mod stream;
+
+/// Implementation of a codec for sending chunks
+/// of streamed messages
+pub mod stream_codec;
+
 /// Wrapper around Kademlia
 pub mod dht;
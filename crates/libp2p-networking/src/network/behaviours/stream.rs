@@ -0,0 +1,1104 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use libp2p::{
+    request_response::{Behaviour, Event, Message, RequestId, ResponseChannel},
+    swarm::{NetworkBehaviour, THandlerInEvent, THandlerOutEvent, ToSwarm},
+    Multiaddr,
+};
+use libp2p_identity::PeerId;
+use tracing::{error, info, warn};
+
+use super::{
+    direct_message_codec::MAX_MSG_SIZE_DM,
+    exponential_backoff::ExponentialBackoff,
+    stream_codec::{StreamAck, StreamChunk, StreamCodec},
+};
+
+/// Maximum size of a streamed message
+pub const MAX_MSG_SIZE_STREAM: usize = 100_000_000;
+
+/// Largest chunk any node may send. Messages that fit in a chunk are sent as direct messages
+/// instead, so a chunk must fit in one with room to spare for its envelope. Every node reads
+/// chunks up to this size whatever its own [`StreamConfig::chunk_size`], so nodes configured with
+/// different chunk sizes can stream to each other
+pub const MAX_CHUNK_SIZE: usize = MAX_MSG_SIZE_DM / 2;
+
+/// Parameters of the streaming protocol
+#[derive(Clone, Debug)]
+pub struct StreamConfig {
+    /// the size of each chunk we put on the wire, at most [`MAX_CHUNK_SIZE`]
+    pub chunk_size: usize,
+    /// the largest message we will send or accept
+    pub max_message_size: usize,
+    /// the number of unacknowledged chunks a single transfer may have in flight
+    pub window: usize,
+    /// the number of concurrent transfers to (and from) a single peer,
+    /// further outbound transfers are queued and further inbound transfers are told to back off
+    pub max_transfers_per_peer: usize,
+    /// the number of consecutive failed chunks before a transfer is abandoned
+    pub retry_count: u8,
+    /// how long a partially received transfer is kept around for the sender to resume it
+    pub inbound_timeout: Duration,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1 << 20,
+            max_message_size: MAX_MSG_SIZE_STREAM,
+            window: 4,
+            max_transfers_per_peer: 4,
+            retry_count: 5,
+            inbound_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl StreamConfig {
+    /// Check the parameters make sense together
+    /// # Errors
+    /// If a chunk is empty or too large to send as a direct message, or nothing may be sent at
+    /// all
+    pub fn validate(&self) -> Result<(), String> {
+        if self.chunk_size == 0 {
+            return Err("stream chunk size must be at least one byte".to_string());
+        }
+        if self.chunk_size > MAX_CHUNK_SIZE {
+            return Err(format!(
+                "stream chunk size of {} bytes exceeds the maximum of {MAX_CHUNK_SIZE}",
+                self.chunk_size,
+            ));
+        }
+        if self.window == 0 || self.max_transfers_per_peer == 0 {
+            return Err(
+                "stream window and transfers per peer must both be at least one".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A message being streamed to a peer
+struct OutboundTransfer {
+    /// the recv-ers peer id
+    peer_id: PeerId,
+    /// the whole message
+    data: Vec<u8>,
+    /// every byte before this offset has been acknowledged by the recv-er
+    acked: u64,
+    /// the offset of the next chunk to send
+    next: u64,
+    /// whether the first chunk has been sent since the transfer was (re)started
+    started: bool,
+    /// whether the recv-er has acknowledged any chunk
+    acked_once: bool,
+    /// number of chunks sent but not yet acknowledged
+    in_flight: usize,
+    /// backoff since the last failed chunk
+    backoff: ExponentialBackoff,
+    /// the number of remaining retries before giving up
+    retries_left: u8,
+}
+
+impl OutboundTransfer {
+    /// the total length of the message
+    fn total_len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// whether the recv-er has acknowledged the whole message
+    fn is_complete(&self) -> bool {
+        self.acked_once && self.acked >= self.total_len()
+    }
+}
+
+/// A message being streamed to us
+struct InboundTransfer {
+    /// the length of the whole message
+    total_len: u64,
+    /// the contiguous prefix of the message received so far
+    data: Vec<u8>,
+    /// chunks received out of order, keyed by offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    /// bytes held in `pending`
+    pending_bytes: u64,
+    /// last time we heard from the sender about this transfer
+    last_activity: Instant,
+}
+
+/// List of stream output events
+#[derive(Debug)]
+pub enum StreamEvent {
+    /// We received a whole streamed message
+    StreamMsg(Vec<u8>, PeerId),
+}
+
+/// Chunked, flow controlled, resumable direct messaging built on libp2p's request response.
+/// Every chunk is acknowledged with the offset the recv-er has reached, so a failed chunk
+/// only causes the transfer to be resumed from the last acknowledged offset
+/// rather than being retried from scratch.
+pub struct StreamBehaviour {
+    /// The wrapped behaviour
+    request_response: Behaviour<StreamCodec>,
+    /// parameters of the protocol
+    config: StreamConfig,
+    /// the id to give the next outbound transfer
+    next_transfer_id: u64,
+    /// outbound transfers that are actively being sent
+    outbound: HashMap<u64, OutboundTransfer>,
+    /// outbound transfers waiting on a free slot for their peer
+    queued: HashMap<PeerId, VecDeque<(u64, OutboundTransfer)>>,
+    /// chunks in flight, and the transfer they belong to
+    in_progress: HashMap<RequestId, u64>,
+    /// inbound transfers, keyed by sender and the sender's transfer id
+    inbound: HashMap<(PeerId, u64), InboundTransfer>,
+    /// recently completed inbound transfers and their length,
+    /// so retransmitted chunks are acknowledged instead of starting the transfer over
+    completed: HashMap<(PeerId, u64), (u64, Instant)>,
+    /// list of out events for parent behaviour
+    out_event_queue: VecDeque<StreamEvent>,
+}
+
+impl StreamBehaviour {
+    /// Create new behaviour based on request response
+    #[must_use]
+    pub fn new(request_response: Behaviour<StreamCodec>, config: StreamConfig) -> Self {
+        Self {
+            request_response,
+            config,
+            next_transfer_id: 0,
+            outbound: HashMap::default(),
+            queued: HashMap::default(),
+            in_progress: HashMap::default(),
+            inbound: HashMap::default(),
+            completed: HashMap::default(),
+            out_event_queue: VecDeque::default(),
+        }
+    }
+
+    /// Add address to request response behaviour
+    pub fn add_address(&mut self, peer_id: &PeerId, address: Multiaddr) {
+        self.request_response.add_address(peer_id, address);
+    }
+
+    /// Remove address from request response behaviour
+    pub fn remove_address(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+        self.request_response.remove_address(peer_id, address);
+    }
+
+    /// Stream `data` to `peer_id`.
+    /// The transfer starts immediately if we have fewer than
+    /// [`StreamConfig::max_transfers_per_peer`] transfers to the peer, and is queued otherwise.
+    pub fn add_stream_request(&mut self, peer_id: PeerId, data: Vec<u8>) {
+        if data.len() > self.config.max_message_size {
+            error!(
+                "refusing to stream message of {} bytes to {:?}, maximum is {}",
+                data.len(),
+                peer_id,
+                self.config.max_message_size
+            );
+            return;
+        }
+        let transfer_id = self.next_transfer_id;
+        self.next_transfer_id += 1;
+        let transfer = OutboundTransfer {
+            peer_id,
+            data,
+            acked: 0,
+            next: 0,
+            started: false,
+            acked_once: false,
+            in_flight: 0,
+            backoff: ExponentialBackoff::default(),
+            retries_left: self.config.retry_count,
+        };
+        if self.num_active(&peer_id) < self.config.max_transfers_per_peer {
+            self.outbound.insert(transfer_id, transfer);
+            self.send_chunks(transfer_id);
+        } else {
+            info!("queueing stream transfer {} to {:?}", transfer_id, peer_id);
+            self.queued
+                .entry(peer_id)
+                .or_default()
+                .push_back((transfer_id, transfer));
+        }
+    }
+
+    /// number of outbound transfers actively being sent to `peer_id`
+    fn num_active(&self, peer_id: &PeerId) -> usize {
+        self.outbound
+            .values()
+            .filter(|t| t.peer_id == *peer_id)
+            .count()
+    }
+
+    /// fill the window of `transfer_id` with chunks
+    fn send_chunks(&mut self, transfer_id: u64) {
+        let Some(transfer) = self.outbound.get_mut(&transfer_id) else {
+            return;
+        };
+        // don't resend anything while we are backing off from a failure
+        if !transfer.backoff.is_expired() {
+            return;
+        }
+        while transfer.in_flight < self.config.window
+            && (transfer.next < transfer.total_len() || !transfer.started)
+        {
+            // we never hold more than `max_message_size` bytes, so these fit in a `usize`
+            #[allow(clippy::cast_possible_truncation)]
+            let start = transfer.next as usize;
+            let end = (start + self.config.chunk_size).min(transfer.data.len());
+            let chunk = StreamChunk {
+                transfer_id,
+                offset: transfer.next,
+                total_len: transfer.total_len(),
+                data: transfer.data[start..end].to_vec(),
+            };
+            let request_id = self.request_response.send_request(&transfer.peer_id, chunk);
+            self.in_progress.insert(request_id, transfer_id);
+            transfer.in_flight += 1;
+            transfer.next = end as u64;
+            transfer.started = true;
+        }
+    }
+
+    /// start queued transfers to `peer_id` while it has free slots
+    fn start_queued(&mut self, peer_id: PeerId) {
+        while self.num_active(&peer_id) < self.config.max_transfers_per_peer {
+            let Some((transfer_id, transfer)) =
+                self.queued.get_mut(&peer_id).and_then(VecDeque::pop_front)
+            else {
+                self.queued.remove(&peer_id);
+                return;
+            };
+            self.outbound.insert(transfer_id, transfer);
+            self.send_chunks(transfer_id);
+        }
+    }
+
+    /// abandon an outbound transfer
+    fn abandon(&mut self, transfer_id: u64) {
+        if let Some(transfer) = self.outbound.remove(&transfer_id) {
+            self.in_progress.retain(|_, id| *id != transfer_id);
+            self.start_queued(transfer.peer_id);
+        }
+    }
+
+    /// a chunk of `transfer_id` failed to go through, resume from the last acknowledged offset
+    fn chunk_failed(&mut self, transfer_id: u64) {
+        let Some(transfer) = self.outbound.get_mut(&transfer_id) else {
+            return;
+        };
+        transfer.in_flight = transfer.in_flight.saturating_sub(1);
+        if transfer.retries_left == 0 {
+            error!(
+                "giving up on stream transfer {} to {:?} after {} of {} bytes",
+                transfer_id,
+                transfer.peer_id,
+                transfer.acked,
+                transfer.total_len()
+            );
+            self.abandon(transfer_id);
+            return;
+        }
+        transfer.retries_left -= 1;
+        transfer.backoff.start_next(false);
+        transfer.next = transfer.acked;
+        transfer.started = false;
+    }
+
+    /// the recv-er acknowledged every byte of `transfer_id` before `next_offset`
+    fn chunk_acked(&mut self, transfer_id: u64, next_offset: u64) {
+        let Some(transfer) = self.outbound.get_mut(&transfer_id) else {
+            return;
+        };
+        transfer.in_flight = transfer.in_flight.saturating_sub(1);
+        transfer.acked_once = true;
+        transfer.acked = transfer.acked.max(next_offset);
+        transfer.next = transfer.next.max(transfer.acked);
+        if transfer.in_flight == 0 && transfer.acked < transfer.next {
+            // every chunk sent was answered, yet the recv-er is missing some: it dropped them,
+            // so go back and resend from what it has
+            transfer.next = transfer.acked;
+        }
+        transfer.retries_left = self.config.retry_count;
+        transfer.backoff.reset();
+        if transfer.is_complete() {
+            info!(
+                "finished streaming {} bytes to {:?}",
+                transfer.total_len(),
+                transfer.peer_id
+            );
+            let peer_id = transfer.peer_id;
+            self.outbound.remove(&transfer_id);
+            self.start_queued(peer_id);
+        } else {
+            self.send_chunks(transfer_id);
+        }
+    }
+
+    /// handle a chunk from `peer`, returning our acknowledgement
+    fn handle_chunk(&mut self, peer: PeerId, chunk: StreamChunk) -> StreamAck {
+        let StreamChunk {
+            transfer_id,
+            offset,
+            total_len,
+            data,
+        } = chunk;
+        if total_len > self.config.max_message_size as u64 {
+            warn!(
+                "refusing stream of {} bytes from {:?}, maximum is {}",
+                total_len, peer, self.config.max_message_size
+            );
+            return StreamAck::Rejected {
+                transfer_id,
+                reason: format!(
+                    "message of {total_len} bytes exceeds maximum of {}",
+                    self.config.max_message_size
+                ),
+            };
+        }
+        let key = (peer, transfer_id);
+        if let Some((len, _)) = self.completed.get(&key) {
+            return StreamAck::Ack {
+                transfer_id,
+                next_offset: *len,
+            };
+        }
+        if !self.inbound.contains_key(&key) {
+            if offset != 0 {
+                // we have no record of the earlier chunks (e.g. they expired), start over
+                return StreamAck::Restart { transfer_id };
+            }
+            let active = self.inbound.keys().filter(|(p, _)| *p == peer).count();
+            if active >= self.config.max_transfers_per_peer {
+                return StreamAck::Busy { transfer_id };
+            }
+            self.inbound.insert(
+                key,
+                InboundTransfer {
+                    total_len,
+                    data: Vec::new(),
+                    pending: BTreeMap::new(),
+                    pending_bytes: 0,
+                    last_activity: Instant::now(),
+                },
+            );
+        }
+        let transfer = self.inbound.get_mut(&key).unwrap();
+        if transfer.total_len != total_len || offset.saturating_add(data.len() as u64) > total_len {
+            self.inbound.remove(&key);
+            return StreamAck::Rejected {
+                transfer_id,
+                reason: "chunk does not match transfer".to_string(),
+            };
+        }
+        transfer.last_activity = Instant::now();
+
+        let received = transfer.data.len() as u64;
+        // the window is the sender's to keep to, so don't trust it: drop chunks further ahead
+        // than our own window would send, and the sender resends them once it has caught up
+        let ahead = (self.config.window * self.config.chunk_size) as u64;
+        if offset > received.saturating_add(ahead) {
+            warn!(
+                "dropping chunk at {} of stream transfer {} from {:?}, {} bytes ahead of what we have",
+                offset,
+                transfer_id,
+                peer,
+                offset - received
+            );
+            return StreamAck::Ack {
+                transfer_id,
+                next_offset: received,
+            };
+        }
+        if offset > received {
+            // out of order, hold on to it until the gap is filled. The chunks held never add up
+            // to more than the rest of the message, however they overlap
+            let replaced = transfer
+                .pending
+                .get(&offset)
+                .map_or(0, |data| data.len() as u64);
+            let pending_bytes = transfer.pending_bytes - replaced + data.len() as u64;
+            if pending_bytes > total_len - received {
+                self.inbound.remove(&key);
+                return StreamAck::Rejected {
+                    transfer_id,
+                    reason: "out of order chunks overlap".to_string(),
+                };
+            }
+            transfer.pending_bytes = pending_bytes;
+            transfer.pending.insert(offset, data);
+        } else if offset + data.len() as u64 > received {
+            // we may already have a prefix of this chunk from an earlier attempt
+            #[allow(clippy::cast_possible_truncation)]
+            transfer
+                .data
+                .extend_from_slice(&data[(received - offset) as usize..]);
+        }
+        // drain any pending chunks that are now contiguous
+        while let Some(entry) = transfer.pending.first_entry() {
+            let received = transfer.data.len() as u64;
+            if *entry.key() > received {
+                break;
+            }
+            let (offset, data) = entry.remove_entry();
+            transfer.pending_bytes -= data.len() as u64;
+            if offset + data.len() as u64 > received {
+                #[allow(clippy::cast_possible_truncation)]
+                transfer
+                    .data
+                    .extend_from_slice(&data[(received - offset) as usize..]);
+            }
+        }
+
+        let next_offset = transfer.data.len() as u64;
+        if next_offset == total_len {
+            let transfer = self.inbound.remove(&key).unwrap();
+            self.completed.insert(key, (total_len, Instant::now()));
+            info!("recv-ed STREAM of {} bytes from {:?}", total_len, peer);
+            self.out_event_queue
+                .push_back(StreamEvent::StreamMsg(transfer.data, peer));
+        }
+        StreamAck::Ack {
+            transfer_id,
+            next_offset,
+        }
+    }
+
+    /// reply to a chunk over `channel`
+    fn respond(&mut self, channel: ResponseChannel<StreamAck>, ack: StreamAck) {
+        if let Err(e) = self.request_response.send_response(channel, ack) {
+            error!("Error acknowledging stream chunk. {:?}", e);
+        }
+    }
+
+    /// drop inbound transfers the sender has not resumed in time
+    fn expire_inbound(&mut self) {
+        let timeout = self.config.inbound_timeout;
+        self.completed
+            .retain(|_, (_, completed_at)| completed_at.elapsed() < timeout);
+        self.inbound.retain(|(peer, transfer_id), transfer| {
+            let keep = transfer.last_activity.elapsed() < timeout;
+            if !keep {
+                warn!(
+                    "dropping stalled stream transfer {} from {:?} after {} of {} bytes",
+                    transfer_id,
+                    peer,
+                    transfer.data.len(),
+                    transfer.total_len
+                );
+            }
+            keep
+        });
+    }
+
+    /// handle an event from the wrapped request response behaviour
+    fn handle_stream_event(&mut self, event: Event<StreamChunk, StreamAck>) {
+        match event {
+            Event::InboundFailure { peer, error, .. } => {
+                error!(
+                    "inbound failure to receive stream chunk from {:?} with error {:?}",
+                    peer, error
+                );
+            }
+            Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                error!(
+                    "outbound failure to stream chunk to {:?} with error {:?}",
+                    peer, error
+                );
+                if let Some(transfer_id) = self.in_progress.remove(&request_id) {
+                    self.chunk_failed(transfer_id);
+                }
+            }
+            Event::Message { message, peer, .. } => match message {
+                Message::Request {
+                    request, channel, ..
+                } => {
+                    let ack = self.handle_chunk(peer, request);
+                    self.respond(channel, ack);
+                }
+                Message::Response {
+                    request_id,
+                    response,
+                } => {
+                    if self.in_progress.remove(&request_id).is_none() {
+                        // the transfer was abandoned while this chunk was in flight
+                        return;
+                    }
+                    match response {
+                        StreamAck::Ack {
+                            transfer_id,
+                            next_offset,
+                        } => self.chunk_acked(transfer_id, next_offset),
+                        StreamAck::Restart { transfer_id } => {
+                            warn!(
+                                "{:?} lost track of transfer {}, restarting it",
+                                peer, transfer_id
+                            );
+                            if let Some(transfer) = self.outbound.get_mut(&transfer_id) {
+                                transfer.acked = 0;
+                            }
+                            self.chunk_failed(transfer_id);
+                        }
+                        StreamAck::Busy { transfer_id } => {
+                            info!("{:?} is busy, backing off transfer {}", peer, transfer_id);
+                            self.chunk_failed(transfer_id);
+                        }
+                        StreamAck::Rejected {
+                            transfer_id,
+                            reason,
+                        } => {
+                            error!(
+                                "{:?} rejected stream transfer {}: {}",
+                                peer, transfer_id, reason
+                            );
+                            self.abandon(transfer_id);
+                        }
+                    }
+                }
+            },
+            Event::ResponseSent { .. } => {}
+        }
+    }
+}
+
+impl NetworkBehaviour for StreamBehaviour {
+    type ConnectionHandler = <Behaviour<StreamCodec> as NetworkBehaviour>::ConnectionHandler;
+
+    type ToSwarm = StreamEvent;
+
+    fn on_swarm_event(
+        &mut self,
+        event: libp2p::swarm::derive_prelude::FromSwarm<'_, Self::ConnectionHandler>,
+    ) {
+        self.request_response.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: libp2p::swarm::derive_prelude::ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.request_response
+            .on_connection_handler_event(peer_id, connection_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        params: &mut impl libp2p::swarm::PollParameters,
+    ) -> Poll<ToSwarm<StreamEvent, THandlerInEvent<Self>>> {
+        // resume transfers whose backoff has expired
+        let stalled = self
+            .outbound
+            .iter()
+            .filter(|(_, t)| !t.started && t.backoff.is_expired())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for transfer_id in stalled {
+            self.send_chunks(transfer_id);
+        }
+        self.expire_inbound();
+
+        while let Poll::Ready(ready) =
+            NetworkBehaviour::poll(&mut self.request_response, cx, params)
+        {
+            match ready {
+                ToSwarm::GenerateEvent(e) => {
+                    self.handle_stream_event(e);
+                }
+                ToSwarm::Dial { opts } => {
+                    return Poll::Ready(ToSwarm::Dial { opts });
+                }
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    handler,
+                    event,
+                } => {
+                    return Poll::Ready(ToSwarm::NotifyHandler {
+                        peer_id,
+                        handler,
+                        event,
+                    });
+                }
+                ToSwarm::CloseConnection {
+                    peer_id,
+                    connection,
+                } => {
+                    return Poll::Ready(ToSwarm::CloseConnection {
+                        peer_id,
+                        connection,
+                    });
+                }
+                ToSwarm::ListenOn { opts } => {
+                    return Poll::Ready(ToSwarm::ListenOn { opts });
+                }
+                ToSwarm::RemoveListener { id } => {
+                    return Poll::Ready(ToSwarm::RemoveListener { id });
+                }
+                ToSwarm::NewExternalAddrCandidate(c) => {
+                    return Poll::Ready(ToSwarm::NewExternalAddrCandidate(c));
+                }
+                ToSwarm::ExternalAddrConfirmed(c) => {
+                    return Poll::Ready(ToSwarm::ExternalAddrConfirmed(c));
+                }
+                ToSwarm::ExternalAddrExpired(c) => {
+                    return Poll::Ready(ToSwarm::ExternalAddrExpired(c));
+                }
+            }
+        }
+        if let Some(event) = self.out_event_queue.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
+        Poll::Pending
+    }
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: libp2p::swarm::ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), libp2p::swarm::ConnectionDenied> {
+        self.request_response.handle_pending_inbound_connection(
+            connection_id,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: libp2p::swarm::ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        self.request_response.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: libp2p::swarm::ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: libp2p::core::Endpoint,
+    ) -> Result<Vec<Multiaddr>, libp2p::swarm::ConnectionDenied> {
+        self.request_response.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: libp2p::swarm::ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: libp2p::core::Endpoint,
+    ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        self.request_response
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::behaviours::stream_codec::StreamProtocol;
+    use libp2p::request_response::{Config, OutboundFailure, ProtocolSupport};
+
+    /// a behaviour streaming 4 byte chunks, two at a time
+    fn behaviour(config: StreamConfig) -> StreamBehaviour {
+        let request_response = Behaviour::with_codec(
+            StreamCodec::new(config.chunk_size),
+            [(StreamProtocol(), ProtocolSupport::Full)].into_iter(),
+            Config::default(),
+        );
+        StreamBehaviour::new(request_response, config)
+    }
+
+    fn config() -> StreamConfig {
+        StreamConfig {
+            chunk_size: 4,
+            max_message_size: 64,
+            window: 2,
+            max_transfers_per_peer: 1,
+            ..StreamConfig::default()
+        }
+    }
+
+    fn chunk(transfer_id: u64, offset: u64, total_len: u64, data: &[u8]) -> StreamChunk {
+        StreamChunk {
+            transfer_id,
+            offset,
+            total_len,
+            data: data.to_vec(),
+        }
+    }
+
+    fn ack(transfer_id: u64, next_offset: u64) -> StreamAck {
+        StreamAck::Ack {
+            transfer_id,
+            next_offset,
+        }
+    }
+
+    /// the chunks of transfers in flight
+    fn in_flight(behaviour: &StreamBehaviour) -> Vec<RequestId> {
+        behaviour.in_progress.keys().copied().collect()
+    }
+
+    /// answer the chunk sent as `request_id` with `response`
+    fn respond(
+        behaviour: &mut StreamBehaviour,
+        peer: PeerId,
+        request_id: RequestId,
+        response: StreamAck,
+    ) {
+        behaviour.handle_stream_event(Event::Message {
+            peer,
+            message: Message::Response {
+                request_id,
+                response,
+            },
+        });
+    }
+
+    /// the chunk sent as `request_id` was lost, e.g. because the connection went down
+    fn fail(behaviour: &mut StreamBehaviour, peer: PeerId, request_id: RequestId) {
+        behaviour.handle_stream_event(Event::OutboundFailure {
+            peer,
+            request_id,
+            error: OutboundFailure::ConnectionClosed,
+        });
+    }
+
+    #[test]
+    fn test_stream_config_validate() {
+        assert!(StreamConfig::default().validate().is_ok());
+        assert!(config().validate().is_ok());
+        for invalid in [
+            StreamConfig {
+                chunk_size: 0,
+                ..config()
+            },
+            StreamConfig {
+                chunk_size: MAX_CHUNK_SIZE + 1,
+                ..config()
+            },
+            StreamConfig {
+                window: 0,
+                ..config()
+            },
+            StreamConfig {
+                max_transfers_per_peer: 0,
+                ..config()
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "accepted {invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_handle_chunk_reassembles_out_of_order() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        let message = b"0123456789ab";
+
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 0, 12, &message[0..4])),
+            ack(0, 4)
+        );
+        // the last chunk overtook the middle one, it's held until the gap is filled
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 8, 12, &message[8..12])),
+            ack(0, 4)
+        );
+        assert!(behaviour.out_event_queue.is_empty());
+        // a retransmission overlapping what we have only adds the new bytes
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 2, 12, &message[2..8])),
+            ack(0, 12)
+        );
+        let Some(StreamEvent::StreamMsg(data, from)) = behaviour.out_event_queue.pop_front() else {
+            panic!("expected the whole message");
+        };
+        assert_eq!((data.as_slice(), from), (&message[..], peer));
+        assert!(behaviour.inbound.is_empty());
+
+        // a chunk retransmitted after we finished is acknowledged, not delivered twice
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 8, 12, &message[8..12])),
+            ack(0, 12)
+        );
+        assert!(behaviour.out_event_queue.is_empty());
+    }
+
+    #[test]
+    fn test_handle_chunk_restart() {
+        let mut behaviour = behaviour(config());
+        // we never saw the start of this transfer, e.g. because it expired
+        assert_eq!(
+            behaviour.handle_chunk(PeerId::random(), chunk(3, 4, 12, b"4567")),
+            StreamAck::Restart { transfer_id: 3 }
+        );
+        assert!(behaviour.inbound.is_empty());
+    }
+
+    #[test]
+    fn test_handle_chunk_busy() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 0, 8, b"0123")),
+            ack(0, 4)
+        );
+        // the peer already has as many transfers to us as it may
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(1, 0, 8, b"0123")),
+            StreamAck::Busy { transfer_id: 1 }
+        );
+        // which doesn't hold up other peers
+        assert_eq!(
+            behaviour.handle_chunk(PeerId::random(), chunk(1, 0, 8, b"0123")),
+            ack(1, 4)
+        );
+        // nor the peer, once its transfer is done
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 4, 8, b"4567")),
+            ack(0, 8)
+        );
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(1, 0, 8, b"0123")),
+            ack(1, 4)
+        );
+    }
+
+    #[test]
+    fn test_handle_chunk_rejected() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        assert!(matches!(
+            behaviour.handle_chunk(peer, chunk(0, 0, 65, b"0123")),
+            StreamAck::Rejected { transfer_id: 0, .. }
+        ));
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(1, 0, 12, b"0123")),
+            ack(1, 4)
+        );
+        // the length of the transfer can't change half way through
+        assert!(matches!(
+            behaviour.handle_chunk(peer, chunk(1, 4, 16, b"4567")),
+            StreamAck::Rejected { transfer_id: 1, .. }
+        ));
+        assert!(behaviour.inbound.is_empty());
+        // nor may a chunk run past its end
+        assert!(matches!(
+            behaviour.handle_chunk(peer, chunk(2, 0, 2, b"0123")),
+            StreamAck::Rejected { transfer_id: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_handle_chunk_too_far_ahead() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 0, 20, b"0123")),
+            ack(0, 4)
+        );
+        // our window holds two chunks past what we have, this one is beyond it
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 16, 20, b"ghij")),
+            ack(0, 4)
+        );
+        assert!(behaviour.inbound[&(peer, 0)].pending.is_empty());
+        // a chunk within the window is held
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 12, 20, b"cdef")),
+            ack(0, 4)
+        );
+        assert_eq!(behaviour.inbound[&(peer, 0)].pending_bytes, 4);
+    }
+
+    #[test]
+    fn test_handle_chunk_overlapping_pending() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        assert_eq!(
+            behaviour.handle_chunk(peer, chunk(0, 0, 16, b"0123")),
+            ack(0, 4)
+        );
+        // resending a chunk replaces it rather than counting it twice
+        for _ in 0..3 {
+            assert_eq!(
+                behaviour.handle_chunk(peer, chunk(0, 8, 16, b"89ab")),
+                ack(0, 4)
+            );
+        }
+        assert_eq!(behaviour.inbound[&(peer, 0)].pending_bytes, 4);
+        // chunks overlapping each other could otherwise hold many times the message
+        for offset in [9, 10] {
+            assert_eq!(
+                behaviour.handle_chunk(peer, chunk(0, offset, 16, b"xxxx")),
+                ack(0, 4)
+            );
+        }
+        assert!(matches!(
+            behaviour.handle_chunk(peer, chunk(0, 11, 16, b"xxxx")),
+            StreamAck::Rejected { transfer_id: 0, .. }
+        ));
+        assert!(behaviour.inbound.is_empty());
+    }
+
+    #[test]
+    fn test_resend_dropped_chunks() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        behaviour.add_stream_request(peer, b"0123456789ab".to_vec());
+        let sent = in_flight(&behaviour);
+        // the recv-er dropped the second chunk, which the first ack doesn't say yet
+        respond(&mut behaviour, peer, sent[0], ack(0, 4));
+        let third = *in_flight(&behaviour)
+            .iter()
+            .find(|request_id| !sent.contains(request_id))
+            .unwrap();
+        respond(&mut behaviour, peer, sent[1], ack(0, 4));
+        assert_eq!(behaviour.outbound[&0].next, 12);
+        // once every chunk is answered without the gap filled, it goes back for it
+        respond(&mut behaviour, peer, third, ack(0, 4));
+        let resent = in_flight(&behaviour);
+        assert_eq!(resent.len(), 2);
+        assert_eq!(behaviour.outbound[&0].next, 12);
+        respond(&mut behaviour, peer, resent[0], ack(0, 8));
+        respond(&mut behaviour, peer, resent[1], ack(0, 12));
+        assert!(behaviour.outbound.is_empty());
+    }
+
+    #[test]
+    fn test_resume_after_disconnect() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        behaviour.add_stream_request(peer, b"0123456789ab".to_vec());
+        let sent = in_flight(&behaviour);
+        assert_eq!(sent.len(), 2);
+
+        // the first chunk makes it, which frees up the window for the last one
+        respond(&mut behaviour, peer, sent[0], ack(0, 4));
+        assert_eq!(behaviour.outbound[&0].next, 12);
+        // then the connection drops
+        for request_id in in_flight(&behaviour) {
+            fail(&mut behaviour, peer, request_id);
+        }
+        let transfer = &behaviour.outbound[&0];
+        assert_eq!(
+            (transfer.acked, transfer.next, transfer.in_flight),
+            (4, 4, 0)
+        );
+        // nothing is resent until the backoff expires
+        assert!(!transfer.backoff.is_expired());
+        behaviour.send_chunks(0);
+        assert!(in_flight(&behaviour).is_empty());
+
+        // once it has, the transfer picks up from the last acknowledged offset
+        behaviour.outbound.get_mut(&0).unwrap().backoff = ExponentialBackoff::default();
+        behaviour.send_chunks(0);
+        let resent = in_flight(&behaviour);
+        assert_eq!(resent.len(), 2);
+        assert_eq!(behaviour.outbound[&0].next, 12);
+        respond(&mut behaviour, peer, resent[0], ack(0, 8));
+        respond(&mut behaviour, peer, resent[1], ack(0, 12));
+        assert!(behaviour.outbound.is_empty());
+        assert!(behaviour.in_progress.is_empty());
+    }
+
+    #[test]
+    fn test_restart_response() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        behaviour.add_stream_request(peer, b"0123456789ab".to_vec());
+        let sent = in_flight(&behaviour);
+        respond(&mut behaviour, peer, sent[0], ack(0, 4));
+        // the recv-er lost track of the transfer, so everything has to be sent again
+        respond(
+            &mut behaviour,
+            peer,
+            sent[1],
+            StreamAck::Restart { transfer_id: 0 },
+        );
+        let transfer = &behaviour.outbound[&0];
+        assert_eq!((transfer.acked, transfer.next), (0, 0));
+        assert!(!transfer.started);
+    }
+
+    #[test]
+    fn test_busy_response() {
+        let mut behaviour = behaviour(StreamConfig {
+            retry_count: 1,
+            ..config()
+        });
+        let peer = PeerId::random();
+        behaviour.add_stream_request(peer, b"01234567".to_vec());
+        let sent = in_flight(&behaviour);
+        // a busy recv-er gets the transfer again after backing off
+        respond(
+            &mut behaviour,
+            peer,
+            sent[0],
+            StreamAck::Busy { transfer_id: 0 },
+        );
+        let transfer = &behaviour.outbound[&0];
+        assert_eq!((transfer.next, transfer.retries_left), (0, 0));
+        assert!(!transfer.backoff.is_expired());
+        // and is given up on once it runs out of retries
+        respond(
+            &mut behaviour,
+            peer,
+            sent[1],
+            StreamAck::Busy { transfer_id: 0 },
+        );
+        assert!(behaviour.outbound.is_empty());
+    }
+
+    #[test]
+    fn test_rejected_response() {
+        let mut behaviour = behaviour(config());
+        let peer = PeerId::random();
+        behaviour.add_stream_request(peer, b"01234567".to_vec());
+        // only one transfer to the peer at a time, so this one waits
+        behaviour.add_stream_request(peer, b"abcdefgh".to_vec());
+        assert_eq!(behaviour.queued[&peer].len(), 1);
+        let sent = in_flight(&behaviour);
+        respond(
+            &mut behaviour,
+            peer,
+            sent[0],
+            StreamAck::Rejected {
+                transfer_id: 0,
+                reason: "too large".to_string(),
+            },
+        );
+        // the rejected transfer is dropped along with its other chunks, and the next one starts
+        assert!(!behaviour.outbound.contains_key(&0));
+        assert!(behaviour.outbound.contains_key(&1));
+        assert!(behaviour.queued.get(&peer).map_or(true, VecDeque::is_empty));
+        assert!(behaviour.in_progress.values().all(|id| *id == 1));
+    }
+}
@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use bincode::Options;
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use hotshot_utils::bincode::bincode_opts;
+use libp2p::{
+    core::upgrade::{read_length_prefixed, write_length_prefixed},
+    request_response::Codec,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io;
+
+/// Protocol for streamed direct messages
+#[derive(Debug, Clone)]
+pub struct StreamProtocol();
+
+/// Codec for streamed direct messages.
+/// Frames larger than `max_frame_size` are refused before being read into memory.
+#[derive(Clone, Debug)]
+pub struct StreamCodec {
+    /// the largest frame we are willing to read off the wire
+    max_frame_size: usize,
+}
+
+/// A single chunk of a streamed message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamChunk {
+    /// id of the transfer, unique per sender
+    pub transfer_id: u64,
+    /// offset of `data` within the whole message
+    pub offset: u64,
+    /// length of the whole message
+    pub total_len: u64,
+    /// the bytes of the message starting at `offset`
+    pub data: Vec<u8>,
+}
+
+/// The receiver's answer to a [`StreamChunk`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamAck {
+    /// the receiver has every byte of the transfer before `next_offset`
+    Ack {
+        /// id of the transfer
+        transfer_id: u64,
+        /// the next byte the receiver is waiting for
+        next_offset: u64,
+    },
+    /// the receiver has no record of the transfer, the sender should start over from the beginning
+    Restart {
+        /// id of the transfer
+        transfer_id: u64,
+    },
+    /// the receiver has too many transfers from us in flight, try again later
+    Busy {
+        /// id of the transfer
+        transfer_id: u64,
+    },
+    /// the receiver refuses the transfer outright (e.g. it is too large)
+    Rejected {
+        /// id of the transfer
+        transfer_id: u64,
+        /// why the transfer was refused
+        reason: String,
+    },
+}
+
+/// Overhead we allow for the [`StreamChunk`] header on top of the chunk size
+const FRAME_OVERHEAD: usize = 64;
+
+impl AsRef<str> for StreamProtocol {
+    fn as_ref(&self) -> &str {
+        "/HotShot/stream/1.0"
+    }
+}
+
+impl StreamCodec {
+    /// Create a codec for chunks of at most `chunk_size` bytes
+    #[must_use]
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            max_frame_size: chunk_size + FRAME_OVERHEAD,
+        }
+    }
+
+    /// read a single length prefixed, bincode serialized frame
+    async fn read_frame<T, M>(&self, io: &mut T) -> io::Result<M>
+    where
+        T: AsyncRead + Unpin + Send,
+        M: DeserializeOwned,
+    {
+        let frame = read_length_prefixed(io, self.max_frame_size).await?;
+        bincode_opts()
+            .deserialize(&frame)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// write a single length prefixed, bincode serialized frame and close the stream
+    async fn write_frame<T, M>(io: &mut T, msg: &M) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+        M: Serialize,
+    {
+        let frame = bincode_opts()
+            .serialize(msg)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_length_prefixed(io, frame).await?;
+        io.close().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Codec for StreamCodec {
+    type Protocol = StreamProtocol;
+
+    type Request = StreamChunk;
+
+    type Response = StreamAck;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<StreamChunk>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        self.read_frame(io).await
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<StreamAck>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        self.read_frame(io).await
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        chunk: StreamChunk,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        Self::write_frame(io, &chunk).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        ack: StreamAck,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        Self::write_frame(io, &ack).await
+    }
+}
@@ -1,8 +1,15 @@
 use futures::channel::oneshot::Sender;
 use libp2p::{
+    autonat::{Behaviour as AutonatBehaviour, Event as AutonatEvent},
+    dcutr::{Behaviour as DcutrBehaviour, Event as DcutrEvent},
     gossipsub::IdentTopic as Topic,
     identify::{Behaviour as IdentifyBehaviour, Event as IdentifyEvent},
+    relay::{
+        client::{Behaviour as RelayClientBehaviour, Event as RelayClientEvent},
+        Behaviour as RelayBehaviour, Event as RelayEvent,
+    },
     request_response::ResponseChannel,
+    swarm::behaviour::toggle::Toggle,
     Multiaddr,
 };
 use libp2p_identity::PeerId;
@@ -16,6 +23,7 @@ use super::{
         direct_message_codec::DirectMessageResponse,
         exponential_backoff::ExponentialBackoff,
         gossip::{GossipBehaviour, GossipEvent},
+        stream::{StreamBehaviour, StreamEvent},
     },
     NetworkEventInternal,
 };
@@ -29,6 +37,7 @@ pub(crate) const NUM_REPLICATED_TO_TRUST: usize = 2;
 /// - direct messaging
 /// - p2p broadcast
 /// - connection management
+/// - NAT traversal
 #[derive(NetworkBehaviour, custom_debug::Debug)]
 #[behaviour(to_swarm = "NetworkEventInternal")]
 pub struct NetworkDef {
@@ -51,22 +60,54 @@ pub struct NetworkDef {
     /// purpose: directly messaging peer
     #[debug(skip)]
     pub request_response: DMBehaviour,
+
+    /// purpose: streaming large messages directly to a peer
+    #[debug(skip)]
+    pub stream: StreamBehaviour,
+
+    /// purpose: detecting whether we are reachable from outside a NAT
+    #[debug(skip)]
+    pub autonat: Toggle<AutonatBehaviour>,
+
+    /// purpose: relaying connections to peers behind a NAT.
+    /// only enabled for relay nodes
+    #[debug(skip)]
+    relay_server: Toggle<RelayBehaviour>,
+
+    /// purpose: reaching (and being reached by) peers through a relay
+    #[debug(skip)]
+    relay_client: Toggle<RelayClientBehaviour>,
+
+    /// purpose: upgrading relayed connections to direct ones by hole punching
+    #[debug(skip)]
+    dcutr: Toggle<DcutrBehaviour>,
 }
 
 impl NetworkDef {
     /// Create a new instance of a `NetworkDef`
+    #[allow(clippy::too_many_arguments)]
     #[must_use]
     pub fn new(
         gossipsub: GossipBehaviour,
         dht: DHTBehaviour,
         identify: IdentifyBehaviour,
         request_response: DMBehaviour,
+        stream: StreamBehaviour,
+        autonat: Option<AutonatBehaviour>,
+        relay_server: Option<RelayBehaviour>,
+        relay_client: Option<RelayClientBehaviour>,
+        dcutr: Option<DcutrBehaviour>,
     ) -> NetworkDef {
         Self {
             gossipsub,
             dht,
             identify,
             request_response,
+            stream,
+            autonat: autonat.into(),
+            relay_server: relay_server.into(),
+            relay_client: relay_client.into(),
+            dcutr: dcutr.into(),
         }
     }
 }
@@ -156,12 +197,26 @@ impl NetworkDef {
     }
 }
 
+/// Stream functions
+impl NetworkDef {
+    /// Stream `data` to a given peer in chunks
+    pub fn add_stream_request(&mut self, peer_id: PeerId, data: Vec<u8>) {
+        self.stream.add_stream_request(peer_id, data);
+    }
+}
+
 impl From<DMEvent> for NetworkEventInternal {
     fn from(event: DMEvent) -> Self {
         Self::DMEvent(event)
     }
 }
 
+impl From<StreamEvent> for NetworkEventInternal {
+    fn from(event: StreamEvent) -> Self {
+        Self::StreamEvent(event)
+    }
+}
+
 impl From<GossipEvent> for NetworkEventInternal {
     fn from(event: GossipEvent) -> Self {
         Self::GossipEvent(event)
@@ -174,6 +229,30 @@ impl From<DHTEvent> for NetworkEventInternal {
     }
 }
 
+impl From<AutonatEvent> for NetworkEventInternal {
+    fn from(event: AutonatEvent) -> Self {
+        Self::AutonatEvent(event)
+    }
+}
+
+impl From<RelayEvent> for NetworkEventInternal {
+    fn from(event: RelayEvent) -> Self {
+        Self::RelayEvent(event)
+    }
+}
+
+impl From<RelayClientEvent> for NetworkEventInternal {
+    fn from(event: RelayClientEvent) -> Self {
+        Self::RelayClientEvent(event)
+    }
+}
+
+impl From<DcutrEvent> for NetworkEventInternal {
+    fn from(event: DcutrEvent) -> Self {
+        Self::DcutrEvent(event)
+    }
+}
+
 impl From<IdentifyEvent> for NetworkEventInternal {
     fn from(event: IdentifyEvent) -> Self {
         Self::IdentifyEvent(Box::new(event))
@@ -29,6 +29,11 @@ pub enum NetworkError {
         /// The underlying source of the error
         source: std::io::Error,
     },
+    /// Error building the noise handshake for relayed connections
+    NoiseConfig {
+        /// The underlying source of the error
+        source: libp2p::noise::Error,
+    },
     /// Error building the gossipsub configuration
     #[snafu(display("Error building the gossipsub configuration: {message}"))]
     GossipsubConfig {
@@ -50,6 +55,12 @@ pub enum NetworkError {
     },
     /// Error when there are no known peers to bootstrap off
     NoKnownPeers,
+    /// Error in the parameters of the streaming protocol
+    #[snafu(display("Invalid stream configuration: {message}"))]
+    StreamConfig {
+        /// what is wrong with the configuration
+        message: String,
+    },
 }
 
 /// Error enum for querying store
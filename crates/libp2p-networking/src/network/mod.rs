@@ -10,26 +10,31 @@ pub use self::{
     node::{
         network_node_handle_error, MeshParams, NetworkNode, NetworkNodeConfig,
         NetworkNodeConfigBuilder, NetworkNodeConfigBuilderError, NetworkNodeHandle,
-        NetworkNodeHandleError,
+        NetworkNodeHandleError, PeerRecord,
     },
 };
 
 use self::behaviours::{
     dht::DHTEvent, direct_message::DMEvent, direct_message_codec::DirectMessageResponse,
-    gossip::GossipEvent,
+    gossip::GossipEvent, stream::StreamEvent,
 };
 use bincode::Options;
-use futures::channel::oneshot::Sender;
+use futures::{channel::oneshot::Sender, future::Either};
 use hotshot_utils::bincode::bincode_opts;
 use libp2p::{
+    autonat::{Event as AutonatEvent, NatStatus},
     build_multiaddr,
-    core::{muxing::StreamMuxerBox, transport::Boxed},
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade},
+    dcutr::Event as DcutrEvent,
     gossipsub::TopicHash,
     identify::Event as IdentifyEvent,
     identity::Keypair,
-    quic,
+    noise, quic,
+    relay::{
+        client::Event as RelayClientEvent, client::Transport as RelayTransport, Event as RelayEvent,
+    },
     request_response::ResponseChannel,
-    Multiaddr, Transport,
+    yamux, Multiaddr, Transport,
 };
 use libp2p_identity::PeerId;
 use rand::seq::IteratorRandom;
@@ -59,6 +64,9 @@ pub enum NetworkNodeType {
     Regular,
     /// conductor node is never pruned
     Conductor,
+    /// publicly reachable node that accepts all connections
+    /// and relays connections to nodes behind a NAT (circuit relay v2)
+    Relay,
 }
 
 impl FromStr for NetworkNodeType {
@@ -69,8 +77,9 @@ impl FromStr for NetworkNodeType {
             "Conductor" => Ok(NetworkNodeType::Conductor),
             "Regular" => Ok(NetworkNodeType::Regular),
             "Bootstrap" => Ok(NetworkNodeType::Bootstrap),
+            "Relay" => Ok(NetworkNodeType::Relay),
             _ => Err(
-                "Couldn't parse node type. Must be one of Conductor, Bootstrap, Regular"
+                "Couldn't parse node type. Must be one of Conductor, Bootstrap, Regular, Relay"
                     .to_string(),
             ),
         }
@@ -123,6 +132,13 @@ pub enum ClientRequest {
     },
     /// client request to send a direct reply to a message
     DirectResponse(ResponseChannel<DirectMessageResponse>, Vec<u8>),
+    /// client request to stream a large serialized message directly to a peer
+    StreamRequest {
+        /// peer id
+        pid: PeerId,
+        /// msg contents
+        contents: Vec<u8>,
+    },
     /// prune a peer
     Prune(PeerId),
     /// add vec of known peers or addresses
@@ -153,10 +169,16 @@ pub enum ClientRequest {
     GetConnectedPeerNum(Sender<usize>),
     /// Request the set of connected peers
     GetConnectedPeers(Sender<HashSet<PeerId>>),
+    /// Request the address of each connection to a peer
+    GetConnectionAddrs(PeerId, Sender<Vec<Multiaddr>>),
+    /// Request what AutoNAT found out about our reachability
+    GetNatStatus(Sender<NatStatus>),
     /// Print the routing  table to stderr, debugging only
     GetRoutingTable(Sender<()>),
     /// Get address of peer
     LookupPeer(PeerId, Sender<()>),
+    /// Drop a record from the local DHT cache, e.g. because it failed validation
+    EvictCachedDHT(Vec<u8>),
 }
 
 /// events generated by the swarm that we wish
@@ -169,6 +191,8 @@ pub enum NetworkEvent {
     DirectRequest(Vec<u8>, PeerId, ResponseChannel<DirectMessageResponse>),
     /// Recv-ed a direct response from a node (that hopefully was initiated by this node)
     DirectResponse(Vec<u8>, PeerId),
+    /// Recv-ed a streamed message from a node
+    StreamMsg(Vec<u8>, PeerId),
     /// Report that kademlia has successfully bootstrapped into the network
     IsBootstrapped,
 }
@@ -186,6 +210,16 @@ pub enum NetworkEventInternal {
     GossipEvent(GossipEvent),
     /// a direct message event
     DMEvent(DMEvent),
+    /// a stream event
+    StreamEvent(StreamEvent),
+    /// an AutoNAT event
+    AutonatEvent(AutonatEvent),
+    /// a circuit relay server event
+    RelayEvent(RelayEvent),
+    /// a circuit relay client event
+    RelayClientEvent(RelayClientEvent),
+    /// a hole punching event
+    DcutrEvent(DcutrEvent),
 }
 
 /// Bind all interfaces on port `port`
@@ -195,12 +229,15 @@ pub fn gen_multiaddr(port: u16) -> Multiaddr {
     build_multiaddr!(Ip4([0, 0, 0, 0]), Udp(port), QuicV1)
 }
 
-/// Generate authenticated transport
+/// Generate authenticated transport.
+/// If `relay_transport` is passed, connections through circuit relays are
+/// supported alongside direct ones.
 /// # Errors
 /// could not sign the quic key with `identity`
-#[instrument(skip(identity))]
+#[instrument(skip(identity, relay_transport))]
 pub async fn gen_transport(
     identity: Keypair,
+    relay_transport: Option<RelayTransport>,
 ) -> Result<Boxed<(PeerId, StreamMuxerBox)>, NetworkError> {
     let quic_transport = {
         let mut config = quic::Config::new(&identity);
@@ -221,8 +258,28 @@ pub async fn gen_transport(
     }
     .map_err(|e| NetworkError::TransportLaunch { source: e })?;
 
-    Ok(dns_quic
+    let transport = dns_quic
         .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+        .boxed();
+
+    let Some(relay_transport) = relay_transport else {
+        return Ok(transport);
+    };
+
+    // relayed connections are plain streams, so authenticate and multiplex them ourselves
+    let relay_transport = relay_transport
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(
+            noise::Config::new(&identity).map_err(|e| NetworkError::NoiseConfig { source: e })?,
+        )
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)));
+
+    Ok(transport
+        .or_transport(relay_transport)
+        .map(|either, _| match either {
+            Either::Left(connection) | Either::Right(connection) => connection,
+        })
         .boxed())
 }
 
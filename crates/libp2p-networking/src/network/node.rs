@@ -7,6 +7,7 @@ pub use self::{
     },
     handle::{
         network_node_handle_error, NetworkNodeHandle, NetworkNodeHandleError, NetworkNodeReceiver,
+        PeerRecord,
     },
 };
 
@@ -19,9 +20,11 @@ use crate::network::{
     behaviours::{
         dht::{DHTBehaviour, DHTEvent, DHTProgress, KadPutQuery},
         direct_message::{DMBehaviour, DMEvent},
-        direct_message_codec::{DirectMessageProtocol, MAX_MSG_SIZE_DM},
+        direct_message_codec::DirectMessageProtocol,
         exponential_backoff::ExponentialBackoff,
         gossip::GossipEvent,
+        stream::{StreamBehaviour, StreamEvent, MAX_CHUNK_SIZE},
+        stream_codec::{StreamCodec, StreamProtocol},
     },
     def::NUM_REPLICATED_TO_TRUST,
 };
@@ -29,11 +32,14 @@ use async_compatibility_layer::{
     art::async_spawn,
     channel::{unbounded, UnboundedReceiver, UnboundedRecvError, UnboundedSender},
 };
-use either::Either;
 use futures::{select, FutureExt, StreamExt};
-use hotshot_constants::KAD_DEFAULT_REPUB_INTERVAL_SEC;
+use hotshot_constants::{Version, KAD_DEFAULT_REPUB_INTERVAL_SEC, PROTOCOL_VERSION};
 use libp2p::{
-    core::{muxing::StreamMuxerBox, transport::Boxed},
+    autonat::{
+        Behaviour as AutonatBehaviour, Config as AutonatConfig, Event as AutonatEvent, NatStatus,
+    },
+    core::{muxing::StreamMuxerBox, transport::Boxed, ConnectedPoint},
+    dcutr::Behaviour as DcutrBehaviour,
     gossipsub::{
         Behaviour as Gossipsub, ConfigBuilder as GossipsubConfigBuilder,
         Message as GossipsubMessage, MessageAuthenticity, MessageId, Topic, ValidationMode,
@@ -44,10 +50,12 @@ use libp2p::{
     },
     identity::Keypair,
     kad::{store::MemoryStore, Behaviour, Config},
+    multiaddr::Protocol,
+    relay::{self, Behaviour as RelayBehaviour, Config as RelayConfig},
     request_response::{
         Behaviour as RequestResponse, Config as RequestResponseConfig, ProtocolSupport,
     },
-    swarm::SwarmEvent,
+    swarm::{ConnectionId, SwarmEvent, THandlerErr},
     Multiaddr, Swarm, SwarmBuilder,
 };
 use libp2p_identity::PeerId;
@@ -55,7 +63,6 @@ use rand::{prelude::SliceRandom, thread_rng};
 use snafu::ResultExt;
 use std::{
     collections::{HashMap, HashSet},
-    io::Error,
     iter,
     num::{NonZeroU32, NonZeroUsize},
     time::Duration,
@@ -68,6 +75,30 @@ pub const ESTABLISHED_LIMIT: NonZeroU32 =
 /// Number of connections to a single peer before logging an error
 pub const ESTABLISHED_LIMIT_UNWR: u32 = 10;
 
+/// Maximum size of a gossiped message. Broadcasts aren't streamed, so this is much larger than
+/// [`super::behaviours::direct_message_codec::MAX_MSG_SIZE_DM`]
+pub const MAX_GOSSIP_MSG_SIZE: usize = 200_000_000;
+
+/// Most messages we hold for a peer while waiting for it to identify, past which we drop them
+pub const MAX_UNIDENTIFIED_MESSAGES: usize = 64;
+
+/// Prefix of the protocol version string we advertise over identify
+const IDENTIFY_PROTOCOL_PREFIX: &str = "HotShot/";
+
+/// The protocol version string we advertise over identify, e.g. `HotShot/0.1`
+fn identify_protocol_version() -> String {
+    format!("{IDENTIFY_PROTOCOL_PREFIX}{PROTOCOL_VERSION}")
+}
+
+/// Parse the [`Version`] out of a peer's identify protocol version string.
+/// Returns `None` if the peer is not speaking the `HotShot` protocol at all.
+fn parse_identify_protocol_version(protocol_version: &str) -> Option<Version> {
+    protocol_version
+        .strip_prefix(IDENTIFY_PROTOCOL_PREFIX)?
+        .parse()
+        .ok()
+}
+
 /// Network definition
 #[derive(custom_debug::Debug)]
 pub struct NetworkNode {
@@ -80,6 +111,17 @@ pub struct NetworkNode {
     swarm: Swarm<NetworkDef>,
     /// the configuration parameters of the netework
     config: NetworkNodeConfig,
+    /// whether we have asked the relays in `config.relay_addrs` to listen on our behalf
+    listening_via_relays: bool,
+    /// connected peers that identified with a compatible protocol version
+    identified_peers: HashSet<PeerId>,
+    /// messages sent to us directly by connected peers that haven't identified yet, delivered
+    /// once they identify with a compatible protocol version and dropped otherwise
+    #[debug(skip)]
+    unidentified_messages: HashMap<PeerId, Vec<NetworkEvent>>,
+    /// the address each connection to a peer was made on: the address we dialed, or the local
+    /// address it came in on
+    connection_addrs: HashMap<PeerId, HashMap<ConnectionId, Multiaddr>>,
 }
 
 impl NetworkNode {
@@ -93,6 +135,23 @@ impl NetworkNode {
         self.swarm.connected_peers().copied().collect()
     }
 
+    /// the address of each connection to `peer_id`
+    pub fn connection_addrs(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.connection_addrs
+            .get(peer_id)
+            .map(|connections| connections.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// whether AutoNAT found us to be publicly reachable, `Unknown` if it's disabled
+    pub fn nat_status(&self) -> NatStatus {
+        self.swarm
+            .behaviour()
+            .autonat
+            .as_ref()
+            .map_or(NatStatus::Unknown, AutonatBehaviour::nat_status)
+    }
+
     /// starts the swarm listening on `listen_addr`
     /// and optionally dials into peer `known_peer`
     /// returns the address the swarm is listening upon
@@ -108,9 +167,36 @@ impl NetworkNode {
             }
         };
         info!("peerid {:?} started on addr: {:?}", self.peer_id, addr);
+        // without AutoNAT we can't tell whether we need the relays, so always use them.
+        // otherwise, wait for AutoNAT to tell us we are private
+        if !self.config.autonat {
+            self.listen_via_relays();
+        }
         Ok(addr)
     }
 
+    /// reserve a slot with each relay in `relay_addrs`, so that
+    /// peers can reach us through `<relay addr>/p2p/<relay id>/p2p-circuit`
+    fn listen_via_relays(&mut self) {
+        if self.listening_via_relays || !self.config.relay_client {
+            return;
+        }
+        self.listening_via_relays = true;
+        for (relay_id, relay_addr) in &self.config.relay_addrs {
+            let circuit_addr = relay_addr
+                .clone()
+                .with(Protocol::P2p(*relay_id))
+                .with(Protocol::P2pCircuit);
+            info!(
+                "peerid {:?} listening via relay on {:?}",
+                self.peer_id, circuit_addr
+            );
+            if let Err(e) = self.swarm.listen_on(circuit_addr) {
+                error!("failed to listen via relay {:?}: {:?}", relay_id, e);
+            }
+        }
+    }
+
     /// initialize the DHT with known peers
     /// add the peers to kademlia and then
     /// the `spawn_listeners` function
@@ -151,6 +237,10 @@ impl NetworkNode {
     ///   * Creates a swarm to manage peers and events
     #[instrument]
     pub async fn new(config: NetworkNodeConfig) -> Result<Self, NetworkError> {
+        config
+            .stream_config
+            .validate()
+            .map_err(|message| NetworkError::StreamConfig { message })?;
         // Generate a random PeerId
         let identity = if let Some(ref kp) = config.identity {
             kp.clone()
@@ -159,7 +249,14 @@ impl NetworkNode {
         };
         let peer_id = PeerId::from(identity.public());
         debug!(?peer_id);
-        let transport: Boxed<(PeerId, StreamMuxerBox)> = gen_transport(identity.clone()).await?;
+        let (relay_transport, relay_client) = if config.relay_client {
+            let (transport, behaviour) = relay::client::new(peer_id);
+            (Some(transport), Some(behaviour))
+        } else {
+            (None, None)
+        };
+        let transport: Boxed<(PeerId, StreamMuxerBox)> =
+            gen_transport(identity.clone(), relay_transport).await?;
         trace!("Launched network transport");
         // Generate the swarm
         let mut swarm: Swarm<NetworkDef> = {
@@ -176,7 +273,7 @@ impl NetworkNode {
                 // NOTE this should most likely be a builder pattern
                 // at some point in the future.
                 match config.node_type {
-                    NetworkNodeType::Bootstrap => MeshParams {
+                    NetworkNodeType::Bootstrap | NetworkNodeType::Relay => MeshParams {
                         mesh_n_high: 1000, // make this super high in case we end up scaling to 1k
                         // nodes
                         mesh_n_low: 10,
@@ -210,7 +307,7 @@ impl NetworkNode {
                 .mesh_outbound_min(params.mesh_outbound_min)
                 .mesh_n(params.mesh_n)
                 .history_length(500)
-                .max_transmit_size(2 * MAX_MSG_SIZE_DM)
+                .max_transmit_size(MAX_GOSSIP_MSG_SIZE)
                 // Use the (blake3) hash of a message as its ID
                 .message_id_fn(message_id_fn)
                 .build()
@@ -231,8 +328,9 @@ impl NetworkNode {
             //   node connection information
             //   E.g. this will answer the question: how are other nodes
             //   seeing the peer from behind a NAT
-            let identify_cfg =
-                IdentifyConfig::new("HotShot/identify/1.0".to_string(), identity.public());
+            //   The protocol version doubles as our compatibility handshake: peers
+            //   advertising an incompatible version are disconnected on receipt
+            let identify_cfg = IdentifyConfig::new(identify_protocol_version(), identity.public());
             let identify = IdentifyBehaviour::new(identify_cfg);
 
             // - Build DHT needed for peer discovery
@@ -258,9 +356,36 @@ impl NetworkNode {
 
             let request_response = RequestResponse::new(
                 [(DirectMessageProtocol(), ProtocolSupport::Full)].into_iter(),
+                rrconfig.clone(),
+            );
+
+            // read chunks of any size a peer may be configured with, not just our own
+            let stream = RequestResponse::with_codec(
+                StreamCodec::new(MAX_CHUNK_SIZE),
+                [(StreamProtocol(), ProtocolSupport::Full)].into_iter(),
                 rrconfig,
             );
 
+            // relays serve AutoNAT probes for the nodes they relay for
+            let autonat =
+                (config.autonat || config.node_type == NetworkNodeType::Relay).then(|| {
+                    let mut autonat = AutonatBehaviour::new(peer_id, AutonatConfig::default());
+                    let servers = config
+                        .to_connect_addrs
+                        .iter()
+                        .filter_map(|(peer, addr)| peer.map(|peer| (peer, addr.clone())))
+                        .chain(config.relay_addrs.iter().cloned());
+                    for (peer, addr) in servers {
+                        autonat.add_server(peer, Some(addr));
+                    }
+                    autonat
+                });
+
+            let relay_server = (config.node_type == NetworkNodeType::Relay)
+                .then(|| RelayBehaviour::new(peer_id, RelayConfig::default()));
+
+            let dcutr = (config.relay_client && config.dcutr).then(|| DcutrBehaviour::new(peer_id));
+
             let network = NetworkDef::new(
                 GossipBehaviour::new(gossipsub),
                 DHTBehaviour::new(
@@ -270,10 +395,17 @@ impl NetworkNode {
                         .replication_factor
                         .unwrap_or_else(|| NonZeroUsize::new(4).unwrap()),
                     config.dht_cache_location.clone(),
+                    config.peer_store_location.clone(),
+                    config.peer_store_max_age,
                 )
                 .await,
                 identify,
                 DMBehaviour::new(request_response),
+                StreamBehaviour::new(stream, config.stream_config.clone()),
+                autonat,
+                relay_server,
+                relay_client,
+                dcutr,
             );
 
             // build swarm
@@ -303,6 +435,10 @@ impl NetworkNode {
             peer_id,
             swarm,
             config,
+            listening_via_relays: false,
+            connection_addrs: HashMap::new(),
+            identified_peers: HashSet::new(),
+            unidentified_messages: HashMap::new(),
         })
     }
 
@@ -329,6 +465,9 @@ impl NetworkNode {
                     ClientRequest::LookupPeer(pid, chan) => {
                         self.swarm.behaviour_mut().dht.lookup_peer(pid, chan);
                     }
+                    ClientRequest::EvictCachedDHT(key) => {
+                        self.swarm.behaviour_mut().dht.evict_cached_record(&key);
+                    }
                     ClientRequest::GetRoutingTable(chan) => {
                         self.swarm.behaviour_mut().dht.print_routing_table();
                         if chan.send(()).is_err() {
@@ -355,6 +494,16 @@ impl NetworkNode {
                             error!("error sending peer set to client");
                         }
                     }
+                    ClientRequest::GetConnectionAddrs(peer_id, s) => {
+                        if s.send(self.connection_addrs(&peer_id)).is_err() {
+                            error!("error sending connection addresses to client");
+                        }
+                    }
+                    ClientRequest::GetNatStatus(s) => {
+                        if s.send(self.nat_status()).is_err() {
+                            error!("error sending NAT status to client");
+                        }
+                    }
                     ClientRequest::GetDHT {
                         key,
                         notify,
@@ -404,6 +553,10 @@ impl NetworkNode {
                     ClientRequest::DirectResponse(chan, msg) => {
                         behaviour.add_direct_response(chan, msg);
                     }
+                    ClientRequest::StreamRequest { pid, contents } => {
+                        info!("pid {:?} adding stream request", self.peer_id);
+                        behaviour.add_stream_request(pid, contents);
+                    }
                     ClientRequest::AddKnownPeers(peers) => {
                         self.add_known_peers(&peers);
                     }
@@ -429,10 +582,7 @@ impl NetworkNode {
     #[instrument(skip(self))]
     async fn handle_swarm_events(
         &mut self,
-        event: SwarmEvent<
-            NetworkEventInternal,
-            Either<Either<Either<void::Void, Error>, Error>, void::Void>,
-        >,
+        event: SwarmEvent<NetworkEventInternal, THandlerErr<NetworkDef>>,
         send_to_client: &UnboundedSender<NetworkEvent>,
     ) -> Result<(), NetworkError> {
         // Make the match cleaner
@@ -441,7 +591,7 @@ impl NetworkNode {
         #[allow(deprecated)]
         match event {
             SwarmEvent::ConnectionEstablished {
-                connection_id: _,
+                connection_id,
                 peer_id,
                 endpoint,
                 num_established,
@@ -456,9 +606,17 @@ impl NetworkNode {
                 } else {
                     info!("peerid {:?} connection is established to {:?} with endpoint {:?} with concurrent dial errors {:?}. {:?} connections left", self.peer_id, peer_id, endpoint, concurrent_dial_errors, num_established);
                 }
+                let addr = match endpoint {
+                    ConnectedPoint::Dialer { address, .. } => address,
+                    ConnectedPoint::Listener { local_addr, .. } => local_addr,
+                };
+                self.connection_addrs
+                    .entry(peer_id)
+                    .or_default()
+                    .insert(connection_id, addr);
             }
             SwarmEvent::ConnectionClosed {
-                connection_id: _,
+                connection_id,
                 peer_id,
                 endpoint,
                 num_established,
@@ -472,6 +630,17 @@ impl NetworkNode {
                 } else {
                     info!("peerid {:?} connection is closed to {:?} with endpoint {:?}. {:?} connections left. Cause: {:?}", self.peer_id, peer_id, endpoint, num_established, cause);
                 }
+                if let Some(connections) = self.connection_addrs.get_mut(&peer_id) {
+                    connections.remove(&connection_id);
+                    if connections.is_empty() {
+                        self.connection_addrs.remove(&peer_id);
+                    }
+                }
+                // the peer identifies again if it reconnects
+                if num_established == 0 {
+                    self.identified_peers.remove(&peer_id);
+                    self.unidentified_messages.remove(&peer_id);
+                }
             }
             SwarmEvent::Dialing {
                 peer_id,
@@ -511,12 +680,25 @@ impl NetworkNode {
                                     listen_addrs,
                                     protocols: _,
                                     public_key: _,
-                                    protocol_version: _,
+                                    protocol_version,
                                     agent_version: _,
                                     observed_addr,
                                 },
                         } = *e
                         {
+                            // refuse peers that speak an incompatible wire protocol before they
+                            // make it into our routing table
+                            let held = self.unidentified_messages.remove(&peer_id);
+                            if !self.check_peer_version(peer_id, &protocol_version) {
+                                return Ok(());
+                            }
+                            self.identified_peers.insert(peer_id);
+                            for event in held.into_iter().flatten() {
+                                send_to_client
+                                    .send(event)
+                                    .await
+                                    .map_err(|_e| NetworkError::StreamClosed)?;
+                            }
                             let behaviour = self.swarm.behaviour_mut();
                             // NOTE in practice, we will want to NOT include this. E.g. only DNS/non localhost IPs
                             // NOTE I manually checked and peer_id corresponds to listen_addrs.
@@ -546,8 +728,36 @@ impl NetworkNode {
                             NetworkEvent::DirectResponse(data, pid)
                         }
                     }),
+                    NetworkEventInternal::StreamEvent(e) => Some(match e {
+                        StreamEvent::StreamMsg(data, pid) => NetworkEvent::StreamMsg(data, pid),
+                    }),
+                    NetworkEventInternal::AutonatEvent(e) => {
+                        if let AutonatEvent::StatusChanged { old, new } = e {
+                            info!(
+                                "peerid {:?} NAT status changed from {:?} to {:?}",
+                                self.peer_id, old, new
+                            );
+                            if let NatStatus::Private = new {
+                                self.listen_via_relays();
+                            }
+                        }
+                        None
+                    }
+                    NetworkEventInternal::RelayEvent(e) => {
+                        info!("peerid {:?} relay event {:?}", self.peer_id, e);
+                        None
+                    }
+                    NetworkEventInternal::RelayClientEvent(e) => {
+                        info!("peerid {:?} relay client event {:?}", self.peer_id, e);
+                        None
+                    }
+                    NetworkEventInternal::DcutrEvent(e) => {
+                        info!("peerid {:?} hole punching event {:?}", self.peer_id, e);
+                        None
+                    }
                 };
 
+                let maybe_event = maybe_event.and_then(|event| self.hold_unidentified(event));
                 if let Some(event) = maybe_event {
                     // forward messages directly to Client
                     send_to_client
@@ -629,4 +839,62 @@ impl NetworkNode {
     pub fn peer_id(&self) -> PeerId {
         self.peer_id
     }
+
+    /// Holds on to `event` if it's a message sent to us directly by a peer that hasn't
+    /// identified yet, since we can't tell whether it speaks our protocol version until it does.
+    /// Returns the event if it can be delivered now.
+    /// Gossip is relayed by peers other than its sender, so it is left to the version of the
+    /// envelope it comes in.
+    fn hold_unidentified(&mut self, event: NetworkEvent) -> Option<NetworkEvent> {
+        let peer_id = match &event {
+            NetworkEvent::DirectRequest(_, peer_id, _) | NetworkEvent::StreamMsg(_, peer_id) => {
+                *peer_id
+            }
+            _ => return Some(event),
+        };
+        if self.identified_peers.contains(&peer_id) {
+            return Some(event);
+        }
+        let held = self.unidentified_messages.entry(peer_id).or_default();
+        if held.len() < MAX_UNIDENTIFIED_MESSAGES {
+            held.push(event);
+        } else {
+            warn!(
+                "peer {:?} hasn't identified yet, dropping a message from it",
+                peer_id
+            );
+        }
+        None
+    }
+
+    /// Compatibility handshake, run once a peer has identified itself.
+    /// Peers with the same major version are kept, and messages are exchanged with them in the
+    /// current layout whatever their minor version. Every other peer is evicted from the DHT and
+    /// disconnected. Returns whether the peer was kept.
+    fn check_peer_version(&mut self, peer_id: PeerId, protocol_version: &str) -> bool {
+        match parse_identify_protocol_version(protocol_version)
+            .filter(|remote| PROTOCOL_VERSION.is_compatible_with(remote))
+        {
+            Some(remote) => {
+                if remote != PROTOCOL_VERSION {
+                    warn!(
+                        "peer {:?} speaks compatible protocol version {}, we speak {}",
+                        peer_id, remote, PROTOCOL_VERSION
+                    );
+                }
+                true
+            }
+            None => {
+                error!(
+                    "peer {:?} speaks incompatible protocol version {:?} (we speak {}), disconnecting",
+                    peer_id, protocol_version, PROTOCOL_VERSION
+                );
+                self.swarm.behaviour_mut().dht.remove_peer(&peer_id);
+                if self.swarm.disconnect_peer_id(peer_id).is_err() {
+                    warn!("peer {:?} was already disconnected", peer_id);
+                }
+                false
+            }
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::network::NetworkNodeType;
+use crate::network::{behaviours::stream::StreamConfig, NetworkNodeType};
 use libp2p::{identity::Keypair, Multiaddr};
 use libp2p_identity::PeerId;
 use std::{collections::HashSet, num::NonZeroUsize, time::Duration};
@@ -8,6 +8,7 @@ pub const DEFAULT_REPLICATION_FACTOR: Option<NonZeroUsize> = NonZeroUsize::new(2
 
 /// describe the configuration of the network
 #[derive(Clone, Default, derive_builder::Builder, custom_debug::Debug)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct NetworkNodeConfig {
     #[builder(default)]
     /// The type of node (bootstrap etc)
@@ -28,6 +29,15 @@ pub struct NetworkNodeConfig {
     #[builder(default = "None")]
     pub dht_cache_location: Option<String>,
 
+    /// location of the store of known peers, default is None
+    #[builder(default = "None")]
+    pub peer_store_location: Option<String>,
+
+    /// how long a peer may go unseen before it is aged out of the peer store,
+    /// default is [`crate::network::behaviours::dht::DEFAULT_PEER_STORE_MAX_AGE`]
+    #[builder(default)]
+    pub peer_store_max_age: Option<Duration>,
+
     #[builder(default)]
     /// parameters for gossipsub mesh network
     pub mesh_params: Option<MeshParams>,
@@ -40,6 +50,36 @@ pub struct NetworkNodeConfig {
     /// expiratiry for records in DHT
     #[builder(default)]
     pub ttl: Option<Duration>,
+
+    /// chunk size, window and limits for streamed direct messages
+    #[builder(default)]
+    pub stream_config: StreamConfig,
+
+    /// probe whether we are publicly reachable with AutoNAT.
+    /// If set, relayed listening through `relay_addrs` is deferred until AutoNAT
+    /// reports us as private
+    #[builder(default)]
+    pub autonat: bool,
+    /// act as a circuit relay v2 client, allowing us to dial peers through relays
+    /// and to be reached through the relays in `relay_addrs`
+    #[builder(default)]
+    pub relay_client: bool,
+    /// relays to reserve a slot with so peers can reach us, requires `relay_client`
+    #[builder(default)]
+    pub relay_addrs: HashSet<(PeerId, Multiaddr)>,
+    /// upgrade relayed connections to direct ones with DCUtR hole punching, requires `relay_client`
+    #[builder(default)]
+    pub dcutr: bool,
+}
+
+impl NetworkNodeConfigBuilder {
+    /// Reject configurations the node can't run with
+    fn validate(&self) -> Result<(), String> {
+        if let Some(stream_config) = &self.stream_config {
+            stream_config.validate()?;
+        }
+        Ok(())
+    }
 }
 
 /// NOTE: `mesh_outbound_min <= mesh_n_low <= mesh_n <= mesh_n_high`
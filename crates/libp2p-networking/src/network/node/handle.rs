@@ -15,7 +15,7 @@ use async_lock::Mutex;
 use bincode::Options;
 use futures::{stream::FuturesOrdered, Future, FutureExt};
 use hotshot_utils::bincode::bincode_opts;
-use libp2p::{request_response::ResponseChannel, Multiaddr};
+use libp2p::{autonat::NatStatus, request_response::ResponseChannel, Multiaddr};
 use libp2p_identity::PeerId;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
@@ -30,6 +30,13 @@ use std::{
 };
 use tracing::{debug, info, instrument};
 
+/// A DHT record mapping some key to the `PeerId` of the node behind it
+pub trait PeerRecord<K>: for<'a> Deserialize<'a> {
+    /// Check the record is a valid binding for `key`, returning the peer id
+    /// and the addresses it is reachable on if so
+    fn verify(self, key: &K) -> Option<(PeerId, Vec<Multiaddr>)>;
+}
+
 /// A handle containing:
 /// - A reference to the state
 /// - Controls for the swarm
@@ -285,16 +292,34 @@ impl<S> NetworkNodeHandle<S> {
         r.await.map_err(|_| NetworkNodeHandleError::RecvError)
     }
 
-    /// Looks up a node's `PeerId` and attempts to validate routing
+    /// Looks up a node's `PeerId` and attempts to validate routing.
+    /// The record stored under `key` is only trusted once it verifies against `key`.
     /// # Errors
-    /// if the peer was unable to be looked up (did not provide a response, DNE)
-    pub async fn lookup_node<V: for<'a> Deserialize<'a> + Serialize>(
+    /// - if the peer was unable to be looked up (did not provide a response, DNE)
+    /// - Will return [`NetworkNodeHandleError::InvalidPeerRecord`] when the record fails verification
+    pub async fn lookup_node<V: for<'a> Deserialize<'a> + Serialize, R: PeerRecord<V>>(
         &self,
         key: V,
         dht_timeout: Duration,
     ) -> Result<PeerId, NetworkNodeHandleError> {
         // get record (from DHT)
-        let pid = self.get_record_timeout::<PeerId>(&key, dht_timeout).await?;
+        let record = self.get_record_timeout::<R>(&key, dht_timeout).await?;
+        let (pid, addrs) = if let Some(verified) = record.verify(&key) {
+            verified
+        } else {
+            // our cached copy may have gone stale, try once more with a fresh one
+            self.evict_cached_record(&key).await?;
+            self.get_record_timeout::<R>(&key, dht_timeout)
+                .await?
+                .verify(&key)
+                .ok_or(NetworkNodeHandleError::InvalidPeerRecord)?
+        };
+
+        // the addresses are signed along with the peer id, so they are safe to dial
+        if !addrs.is_empty() {
+            self.add_known_peers(addrs.into_iter().map(|addr| (Some(pid), addr)).collect())
+                .await?;
+        }
 
         // pid lookup for routing
         self.lookup_pid(pid).await?;
@@ -327,6 +352,20 @@ impl<S> NetworkNodeHandle<S> {
         r.await.context(CancelledRequestSnafu).context(DHTSnafu)
     }
 
+    /// Drop a record from the local DHT cache, so the next lookup of `key` queries the DHT
+    /// # Errors
+    /// - Will return [`NetworkNodeHandleError::SerializationError`] when unable to serialize the key
+    /// - Will return [`NetworkNodeHandleError::SendError`] when underlying `NetworkNode` has been killed
+    pub async fn evict_cached_record(
+        &self,
+        key: &impl Serialize,
+    ) -> Result<(), NetworkNodeHandleError> {
+        let req = ClientRequest::EvictCachedDHT(
+            bincode_opts().serialize(key).context(SerializationSnafu)?,
+        );
+        self.send_request(req).await
+    }
+
     /// Receive a record from the kademlia DHT if it exists.
     /// Must be replicated on at least 2 nodes
     /// # Errors
@@ -474,6 +513,32 @@ impl<S> NetworkNodeHandle<S> {
         self.send_request(req).await
     }
 
+    /// Stream `msg` to `peer_id` in flow controlled chunks.
+    /// Meant for payloads too large to comfortably send with [`Self::direct_request`].
+    /// # Errors
+    /// - Will return [`NetworkNodeHandleError::SendError`] when underlying `NetworkNode` has been killed
+    /// - Will return [`NetworkNodeHandleError::SerializationError`] when unable to serialize `msg`
+    /// - Will return [`NetworkNodeHandleError::MessageTooLarge`] when `msg` exceeds the configured maximum stream size
+    pub async fn stream_request(
+        &self,
+        pid: PeerId,
+        msg: &impl Serialize,
+    ) -> Result<(), NetworkNodeHandleError> {
+        let serialized_msg = bincode_opts().serialize(msg).context(SerializationSnafu)?;
+        let max = self.network_config.stream_config.max_message_size;
+        if serialized_msg.len() > max {
+            return Err(NetworkNodeHandleError::MessageTooLarge {
+                size: serialized_msg.len(),
+                max,
+            });
+        }
+        let req = ClientRequest::StreamRequest {
+            pid,
+            contents: serialized_msg,
+        };
+        self.send_request(req).await
+    }
+
     /// Forcefully disconnet from a peer
     /// # Errors
     /// If the channel is closed somehow
@@ -553,6 +618,34 @@ impl<S> NetworkNodeHandle<S> {
         Ok(r.await.unwrap())
     }
 
+    /// return the address of each connection to `peer_id`; a relayed connection's address
+    /// contains `/p2p-circuit`
+    /// # Errors
+    /// If the channel is closed somehow
+    /// # Panics
+    /// If channel errors out
+    pub async fn connection_addrs(
+        &self,
+        peer_id: PeerId,
+    ) -> Result<Vec<Multiaddr>, NetworkNodeHandleError> {
+        let (s, r) = futures::channel::oneshot::channel();
+        let req = ClientRequest::GetConnectionAddrs(peer_id, s);
+        self.send_request(req).await?;
+        Ok(r.await.unwrap())
+    }
+
+    /// return whether AutoNAT found this node to be publicly reachable
+    /// # Errors
+    /// If the channel is closed somehow
+    /// # Panics
+    /// If channel errors out
+    pub async fn nat_status(&self) -> Result<NatStatus, NetworkNodeHandleError> {
+        let (s, r) = futures::channel::oneshot::channel();
+        let req = ClientRequest::GetNatStatus(s);
+        self.send_request(req).await?;
+        Ok(r.await.unwrap())
+    }
+
     /// Get a reference to the network node handle's id.
     pub fn id(&self) -> usize {
         self.id
@@ -691,6 +784,15 @@ pub enum NetworkNodeHandleError {
     },
     /// no known topic matches the hashset of keys
     NoSuchTopic,
+    /// The record found in the DHT does not bind the key to a peer
+    InvalidPeerRecord,
+    /// The message is larger than the peer is willing to accept
+    MessageTooLarge {
+        /// size of the serialized message
+        size: usize,
+        /// the maximum size allowed
+        max: usize,
+    },
 }
 
 /// Re-exports of the snafu errors that [`NetworkNodeHandleError`] can throw
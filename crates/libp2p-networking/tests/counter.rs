@@ -35,6 +35,9 @@ const TIMEOUT_STRESS: Duration = Duration::from_secs(60);
 
 const DHT_KV_PADDING: usize = 1024;
 
+/// large enough to be split over several stream chunks
+const STREAM_PADDING: usize = 5 * 1024 * 1024 + 17;
+
 /// Message types. We can either
 /// - increment the Counter
 /// - request a counter value
@@ -47,6 +50,8 @@ pub enum CounterMessage {
     },
     AskForCounter,
     MyCounterIs(CounterState),
+    /// a counter value padded out to a large message
+    MyPaddedCounterIs(CounterState, Vec<u8>),
     Noop,
 }
 
@@ -62,11 +67,11 @@ pub async fn counter_handle_network_event(
     use NetworkEvent::*;
     match event {
         IsBootstrapped => {}
-        GossipMsg(m, _) | DirectResponse(m, _) => {
+        GossipMsg(m, _) | DirectResponse(m, _) | StreamMsg(m, _) => {
             if let Ok(msg) = bincode_opts().deserialize::<CounterMessage>(&m) {
                 match msg {
                     // direct message only
-                    MyCounterIs(c) => {
+                    MyCounterIs(c) | MyPaddedCounterIs(c, _) => {
                         handle.modify_state(|s| *s = c).await;
                     }
                     // gossip message only
@@ -105,7 +110,7 @@ pub async fn counter_handle_network_event(
                         let response = MyCounterIs(handle.state().await);
                         handle.direct_response(chan, &response).await?;
                     }
-                    MyCounterIs(_) => {
+                    MyCounterIs(_) | MyPaddedCounterIs(..) => {
                         handle.direct_response(chan, &CounterMessage::Noop).await?;
                     }
                     Noop => {
@@ -288,6 +293,45 @@ pub async fn run_request_response_one_round(
     }
 }
 
+/// streams a large message carrying an incremented counter from a random handle
+/// to every other handle, then asserts that all nodes match the new state
+async fn run_stream_one_round(
+    handles: Vec<Arc<NetworkNodeHandle<CounterState>>>,
+    timeout: Duration,
+) {
+    let mut rng = rand::thread_rng();
+    let sender_handle = get_random_handle(&handles, &mut rng);
+    sender_handle.modify_state(|s| *s += 1).await;
+    let new_state = sender_handle.state().await;
+    let msg = CounterMessage::MyPaddedCounterIs(new_state, vec![0xAB; STREAM_PADDING]);
+
+    for h in &handles {
+        if h.peer_id() == sender_handle.peer_id() {
+            continue;
+        }
+        if sender_handle.lookup_pid(h.peer_id()).await.is_err() {
+            error!("ERROR LOOKING UP STREAM RECIPIENT ADDRS");
+        }
+        sender_handle
+            .stream_request(h.peer_id(), &msg)
+            .await
+            .unwrap();
+    }
+
+    for h in &handles {
+        if h.state_wait_timeout_until(timeout, |state| *state == new_state)
+            .await
+            .is_err()
+        {
+            print_connections(&handles).await;
+            panic!(
+                "timed out waiting for handle {} to receive streamed state {new_state}",
+                h.id()
+            );
+        }
+    }
+}
+
 pub async fn run_gossip_many_rounds(
     handles: Vec<Arc<NetworkNodeHandle<CounterState>>>,
     timeout: Duration,
@@ -440,6 +484,24 @@ async fn test_coverage_request_response_one_round() {
     .await
 }
 
+/// large direct message split over several stream chunks
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn test_coverage_stream_one_round() {
+    test_bed(
+        run_stream_one_round,
+        counter_handle_network_event,
+        TOTAL_NUM_PEERS_COVERAGE,
+        NUM_OF_BOOTSTRAP_COVERAGE,
+        TIMEOUT_COVERAGE,
+    )
+    .await
+}
+
 /// stress test of direct messsage
 #[cfg_attr(
     async_executor_impl = "tokio",
@@ -0,0 +1,225 @@
+use async_compatibility_layer::{
+    art::async_sleep,
+    logging::{setup_backtrace, setup_logging},
+};
+use bincode::Options;
+use hotshot_utils::bincode::bincode_opts;
+use libp2p::{autonat::NatStatus, multiaddr::Protocol, Multiaddr};
+use libp2p_networking::network::{
+    NetworkEvent, NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeHandle,
+    NetworkNodeHandleError, NetworkNodeType,
+};
+use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
+use tracing::{info, instrument};
+
+/// state of each node: the last value direct messaged to it
+type RelayState = u32;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const ROUND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// an in-process address to bind to
+fn localhost() -> Multiaddr {
+    Multiaddr::from_str("/ip4/127.0.0.1/udp/0/quic-v1").unwrap()
+}
+
+/// spin up a single node with `config`
+async fn spin_up(config: NetworkNodeConfig, id: usize) -> Arc<NetworkNodeHandle<RelayState>> {
+    Arc::new(NetworkNodeHandle::new(config, id).await.unwrap())
+}
+
+/// store any direct message in the node's state and ack it
+async fn relay_handle_network_event(
+    event: NetworkEvent,
+    handle: Arc<NetworkNodeHandle<RelayState>>,
+) -> Result<(), NetworkNodeHandleError> {
+    if let NetworkEvent::DirectRequest(m, _, chan) = event {
+        if let Ok(value) = bincode_opts().deserialize::<RelayState>(&m) {
+            handle.modify_state(|s| *s = value).await;
+        }
+        handle.direct_response(chan, &0u32).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_relay_node_type() {
+    assert_eq!(
+        NetworkNodeType::from_str("Relay"),
+        Ok(NetworkNodeType::Relay)
+    );
+}
+
+/// a node that is only reachable through an in-process circuit relay
+/// still receives direct messages, over the relayed connection
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn test_direct_message_through_relay() {
+    setup_logging();
+    setup_backtrace();
+
+    let relay = spin_up(
+        NetworkNodeConfigBuilder::default()
+            .node_type(NetworkNodeType::Relay)
+            .bound_addr(Some(localhost()))
+            .to_connect_addrs(HashSet::default())
+            .build()
+            .unwrap(),
+        0,
+    )
+    .await;
+    let relay_addrs: HashSet<_> = [(relay.peer_id(), relay.listen_addr())].into();
+
+    // reserves a slot with the relay, and only ever advertises itself through it.
+    // hole punching stays off so the connection can't be upgraded to a direct one
+    let private = spin_up(
+        NetworkNodeConfigBuilder::default()
+            .node_type(NetworkNodeType::Regular)
+            .bound_addr(Some(localhost()))
+            .to_connect_addrs(HashSet::default())
+            .relay_client(true)
+            .relay_addrs(relay_addrs)
+            .dcutr(false)
+            .build()
+            .unwrap(),
+        1,
+    )
+    .await;
+
+    // needs the relay client transport to dial circuit addresses
+    let dialer = spin_up(
+        NetworkNodeConfigBuilder::default()
+            .node_type(NetworkNodeType::Regular)
+            .bound_addr(Some(localhost()))
+            .to_connect_addrs(HashSet::default())
+            .relay_client(true)
+            .build()
+            .unwrap(),
+        2,
+    )
+    .await;
+
+    let mut handlers = Vec::new();
+    for handle in [&relay, &private, &dialer] {
+        handlers.push(handle.spawn_handler(relay_handle_network_event).await);
+    }
+
+    let circuit_addr = relay
+        .listen_addr()
+        .with(Protocol::P2p(relay.peer_id()))
+        .with(Protocol::P2pCircuit);
+    info!("dialing {:?} through {:?}", private.peer_id(), circuit_addr);
+    dialer
+        .add_known_peers(vec![(Some(private.peer_id()), circuit_addr)])
+        .await
+        .unwrap();
+
+    // the reservation may not be in place yet, so keep trying until the message makes it
+    let expected: RelayState = 42;
+    let start = std::time::Instant::now();
+    loop {
+        dialer
+            .direct_request(private.peer_id(), &expected)
+            .await
+            .unwrap();
+        if private
+            .state_wait_timeout_until(ROUND_TIMEOUT, |s| *s == expected)
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "direct message through relay never arrived"
+        );
+    }
+
+    let addrs = dialer.connection_addrs(private.peer_id()).await.unwrap();
+    assert!(
+        !addrs.is_empty(),
+        "dialer has no connection to the private node"
+    );
+    assert!(
+        addrs
+            .iter()
+            .all(|addr| addr.iter().any(|p| p == Protocol::P2pCircuit)),
+        "dialer reached the private node without the relay: {addrs:?}"
+    );
+
+    for handle in [&relay, &private, &dialer] {
+        handle.shutdown().await.unwrap();
+    }
+    for fut in handlers {
+        fut.await;
+    }
+}
+
+/// the relay's AutoNAT server only dials back global addresses, so a node that is only
+/// reachable on a local address finds out it is private
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn test_autonat_reports_private() {
+    setup_logging();
+    setup_backtrace();
+
+    let relay = spin_up(
+        NetworkNodeConfigBuilder::default()
+            .node_type(NetworkNodeType::Relay)
+            .bound_addr(Some(localhost()))
+            .to_connect_addrs(HashSet::default())
+            .build()
+            .unwrap(),
+        0,
+    )
+    .await;
+    let relay_addrs: HashSet<_> = [(relay.peer_id(), relay.listen_addr())].into();
+
+    let private = spin_up(
+        NetworkNodeConfigBuilder::default()
+            .node_type(NetworkNodeType::Regular)
+            .bound_addr(Some(localhost()))
+            .to_connect_addrs(HashSet::default())
+            .relay_client(true)
+            .relay_addrs(relay_addrs)
+            .autonat(true)
+            .build()
+            .unwrap(),
+        1,
+    )
+    .await;
+
+    let mut handlers = Vec::new();
+    for handle in [&relay, &private] {
+        handlers.push(handle.spawn_handler(relay_handle_network_event).await);
+    }
+
+    // AutoNAT waits out its boot delay before the first probe
+    let start = std::time::Instant::now();
+    loop {
+        let status = private.nat_status().await.unwrap();
+        if status == NatStatus::Private {
+            break;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "AutoNAT never reported the node as private, last status {status:?}"
+        );
+        async_sleep(Duration::from_secs(1)).await;
+    }
+
+    for handle in [&relay, &private] {
+        handle.shutdown().await.unwrap();
+    }
+    for fut in handlers {
+        fut.await;
+    }
+}
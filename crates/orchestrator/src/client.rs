@@ -1,13 +1,16 @@
 use std::{net::IpAddr, time::Duration};
 
-use crate::config::NetworkConfig;
-use async_compatibility_layer::art::async_sleep;
+use crate::{config::NetworkConfig, results::RunResults};
+use async_compatibility_layer::art::{async_sleep, async_spawn};
 use clap::Parser;
 use futures::{Future, FutureExt};
 
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::traits::{node_implementation::NodeType, signature_key::SignatureKey};
 use surf_disco::{error::ClientError, Client};
 
+/// How often validators post heartbeats to the orchestrator
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Holds the client connection to the orchestrator
 pub struct OrchestratorClient {
     client: surf_disco::Client<ClientError>,
@@ -37,7 +40,10 @@ impl OrchestratorClient {
         let base_url = format!("{0}:{1}", args.host, args.port);
         let base_url = format!("http://{base_url}").parse().unwrap();
         let client = surf_disco::Client::<ClientError>::new(base_url);
-        // TODO ED: Add healthcheck wait here
+        // Wait for the orchestrator to be up, so it doesn't look like it's refusing requests
+        while !client.connect(Some(Duration::from_secs(10))).await {
+            tracing::error!("Orchestrator is not up yet, still waiting");
+        }
         OrchestratorClient { client }
     }
 
@@ -82,14 +88,59 @@ impl OrchestratorClient {
         self.wait_for_fn_from_orchestrator(f).await
     }
 
+    /// Returns the configuration of the run at `run_index` from the orchestrator, or `None` once
+    /// every run of the experiment has finished
+    /// Will block until every node has finished the run before it
+    #[allow(clippy::type_complexity)]
+    pub async fn get_next_run_config<TYPES: NodeType>(
+        &self,
+        node_index: u16,
+        run_index: usize,
+    ) -> Option<NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>> {
+        let f = |client: Client<ClientError>| {
+            async move {
+                let config: Result<
+                    Option<NetworkConfig<TYPES::SignatureKey, TYPES::ElectionConfigType>>,
+                    ClientError,
+                > = client
+                    .post(&format!("api/run/{node_index}/{run_index}"))
+                    .send()
+                    .await;
+                config
+            }
+            .boxed()
+        };
+        self.wait_for_fn_from_orchestrator(f).await
+    }
+
+    /// Posts a heartbeat for the validator with `public_key` every [`HEARTBEAT_INTERVAL`], in the
+    /// background, so the orchestrator can tell if the validator fails
+    pub fn start_heartbeats<KEY: SignatureKey + 'static>(&self, public_key: KEY) {
+        let client = self.client.clone();
+        async_spawn(async move {
+            loop {
+                let result: Result<(), ClientError> = client
+                    .post("api/heartbeat")
+                    .body_json(&public_key)
+                    .unwrap()
+                    .send()
+                    .await;
+                if let Err(e) = result {
+                    tracing::warn!("Failed to post a heartbeat to the orchestrator: {e}");
+                }
+                async_sleep(HEARTBEAT_INTERVAL).await;
+            }
+        });
+    }
+
     /// Tells the orchestrator this validator is ready to start
     /// Blocks until the orchestrator indicates all nodes are ready to start
-    pub async fn wait_for_all_nodes_ready(&self, node_index: u64) -> bool {
+    pub async fn wait_for_all_nodes_ready<KEY: SignatureKey>(&self, public_key: &KEY) -> bool {
         let send_ready_f = |client: Client<ClientError>| {
             async move {
                 let result: Result<_, ClientError> = client
                     .post("api/ready")
-                    .body_json(&node_index)
+                    .body_json(public_key)
                     .unwrap()
                     .send()
                     .await;
@@ -107,6 +158,42 @@ impl OrchestratorClient {
             .await
     }
 
+    /// Tells the orchestrator this validator started running consensus
+    pub async fn post_running<KEY: SignatureKey>(&self, public_key: &KEY) {
+        let f = |client: Client<ClientError>| {
+            async move {
+                let result: Result<(), ClientError> = client
+                    .post("api/running")
+                    .body_json(public_key)
+                    .unwrap()
+                    .send()
+                    .await;
+                result
+            }
+            .boxed()
+        };
+        self.wait_for_fn_from_orchestrator(f).await;
+    }
+
+    /// Sends this validator's results to the orchestrator at the end of the run
+    /// Blocks until the orchestrator has them
+    pub async fn post_run_results(&self, results: RunResults) {
+        let results = &results;
+        let f = |client: Client<ClientError>| {
+            async move {
+                let result: Result<(), ClientError> = client
+                    .post("api/results")
+                    .body_json(results)
+                    .unwrap()
+                    .send()
+                    .await;
+                result
+            }
+            .boxed()
+        };
+        self.wait_for_fn_from_orchestrator(f).await;
+    }
+
     /// Generic function that waits for the orchestrator to return a non-error
     /// Returns whatever type the given function returns
     async fn wait_for_fn_from_orchestrator<F, Fut, GEN>(&self, f: F) -> GEN
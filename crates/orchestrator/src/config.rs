@@ -2,6 +2,7 @@ use hotshot_types::{
     traits::{election::ElectionConfig, signature_key::SignatureKey},
     ExecutionType, HotShotConfig, ValidatorConfig,
 };
+use snafu::Snafu;
 use std::fs;
 use std::{
     env,
@@ -10,6 +11,7 @@ use std::{
     path::PathBuf,
     time::Duration,
 };
+use surf_disco::Url;
 use toml;
 use tracing::error;
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -52,8 +54,9 @@ pub struct Libp2pConfigFile {
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct WebServerConfig {
-    pub host: IpAddr,
-    pub port: u16,
+    /// The instances of the web server cluster. Nodes talk to the first and fail over to the
+    /// next when the one they are using becomes unreachable.
+    pub urls: Vec<Url>,
     pub wait_between_polls: Duration,
 }
 
@@ -76,6 +79,114 @@ pub struct NetworkConfig<KEY: SignatureKey, ELECTIONCONFIG: ElectionConfig> {
     pub config: HotShotConfig<KEY, ELECTIONCONFIG>,
     pub web_server_config: Option<WebServerConfig>,
     pub da_web_server_config: Option<WebServerConfig>,
+    /// If set, each node serves its metrics for Prometheus on this port plus its node index
+    pub metrics_port: Option<u16>,
+    /// If set, the orchestrator writes the run results here once every node has posted its
+    /// own, as CSV if the file ends in `.csv` and as JSON otherwise
+    pub results_file: Option<PathBuf>,
+    /// Index of this run among the runs of the experiment
+    pub run_index: usize,
+    /// The runs of the experiment, each varying some parameters of this config. Without any, the
+    /// experiment is a single run of this config
+    pub runs: Vec<RunParameters>,
+}
+
+/// Parameters of a run of an experiment that differ from the run config. Those not set keep the
+/// value in the run config
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunParameters {
+    /// Rounds each node runs for
+    pub rounds: Option<usize>,
+    /// Transactions submitted per round, over all nodes
+    pub transactions_per_round: Option<usize>,
+    /// Size of each transaction, in bytes
+    pub transaction_size: Option<usize>,
+    /// Base duration for next-view timeout, in milliseconds
+    pub next_view_timeout: Option<u64>,
+    /// The delay a leader inserts before starting pre-commit, in milliseconds
+    pub round_start_delay: Option<u64>,
+    /// Number of DA committee nodes
+    pub committee_nodes: Option<usize>,
+}
+
+/// Why the orchestrator can't run the experiment a run config describes
+#[derive(Debug, Snafu)]
+pub enum NetworkConfigError {
+    /// several runs over web servers, which would serve the proposals and votes of a run to the
+    /// runs after it
+    #[snafu(display(
+        "An experiment of {runs} runs can't use web servers, since they keep earlier runs' data: \
+         use libp2p and leave out `web_server_config` and `da_web_server_config`"
+    ))]
+    RunsOverWebServers { runs: usize },
+}
+
+impl<K: SignatureKey, E: ElectionConfig> NetworkConfig<K, E> {
+    /// Check the orchestrator can run the experiment this config describes
+    ///
+    /// # Errors
+    /// Returns [`NetworkConfigError::RunsOverWebServers`] if the experiment has several runs and
+    /// configures web servers
+    pub fn validate(&self) -> Result<(), NetworkConfigError> {
+        let runs = self.num_runs();
+        if runs > 1 && (self.web_server_config.is_some() || self.da_web_server_config.is_some()) {
+            return RunsOverWebServersSnafu { runs }.fail();
+        }
+        Ok(())
+    }
+
+    /// Number of runs in the experiment
+    pub fn num_runs(&self) -> usize {
+        self.runs.len().max(1)
+    }
+
+    /// The parameters of the run at `run_index` that differ from this config
+    pub fn run_parameters(&self, run_index: usize) -> RunParameters {
+        self.runs.get(run_index).cloned().unwrap_or_default()
+    }
+
+    /// The config of the run at `run_index` of the experiment, or `None` past the last run
+    pub fn for_run(&self, run_index: usize) -> Option<Self> {
+        if run_index >= self.num_runs() {
+            return None;
+        }
+        let RunParameters {
+            rounds,
+            transactions_per_round,
+            transaction_size,
+            next_view_timeout,
+            round_start_delay,
+            committee_nodes,
+        } = self.run_parameters(run_index);
+        let mut config = self.clone();
+        config.run_index = run_index;
+        if let Some(rounds) = rounds {
+            config.rounds = rounds;
+        }
+        if let Some(transactions_per_round) = transactions_per_round {
+            config.transactions_per_round = transactions_per_round;
+            if let Some(libp2p_config) = &mut config.libp2p_config {
+                libp2p_config.num_txn_per_round = transactions_per_round;
+            }
+        }
+        if let Some(transaction_size) = transaction_size {
+            config.transaction_size = transaction_size;
+        }
+        if let Some(next_view_timeout) = next_view_timeout {
+            config.next_view_timeout = next_view_timeout;
+            config.config.next_view_timeout = next_view_timeout;
+            if let Some(libp2p_config) = &mut config.libp2p_config {
+                libp2p_config.next_view_timeout = next_view_timeout;
+            }
+        }
+        if let Some(round_start_delay) = round_start_delay {
+            config.config.round_start_delay = round_start_delay;
+        }
+        if let Some(committee_nodes) = committee_nodes {
+            config.config.da_committee_size = committee_nodes;
+        }
+        Some(config)
+    }
 }
 
 impl<K: SignatureKey, E: ElectionConfig> Default for NetworkConfig<K, E> {
@@ -93,6 +204,10 @@ impl<K: SignatureKey, E: ElectionConfig> Default for NetworkConfig<K, E> {
             election_config_type_name: std::any::type_name::<E>().to_string(),
             web_server_config: None,
             da_web_server_config: None,
+            metrics_port: None,
+            results_file: None,
+            run_index: 0,
+            runs: Vec::new(),
             next_view_timeout: 10,
             num_bootrap: 5,
             propose_min_round_time: Duration::from_secs(0),
@@ -124,6 +239,12 @@ pub struct NetworkConfigFile<KEY: SignatureKey> {
     pub web_server_config: Option<WebServerConfig>,
     #[serde(default = "default_web_server_config")]
     pub da_web_server_config: Option<WebServerConfig>,
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    #[serde(default)]
+    pub results_file: Option<PathBuf>,
+    #[serde(default)]
+    pub runs: Vec<RunParameters>,
 }
 
 fn default_web_server_config() -> Option<WebServerConfig> {
@@ -169,6 +290,10 @@ impl<K: SignatureKey, E: ElectionConfig> From<NetworkConfigFile<K>> for NetworkC
             start_delay_seconds: val.start_delay_seconds,
             web_server_config: val.web_server_config,
             da_web_server_config: val.da_web_server_config,
+            metrics_port: val.metrics_port,
+            results_file: val.results_file,
+            run_index: 0,
+            runs: val.runs,
         }
     }
 }
@@ -342,3 +467,95 @@ impl<KEY: SignatureKey> Default for HotShotConfigFile<KEY> {
 fn default_start_delay_seconds() -> u64 {
     60
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use hotshot_signature_key::bn254::BLSPubKey;
+
+    /// An election config for orchestrators that don't elect anyone
+    #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+    pub(crate) struct TestElectionConfig;
+
+    impl ElectionConfig for TestElectionConfig {}
+
+    #[test]
+    fn a_config_without_runs_is_a_single_run() {
+        let config = NetworkConfig::<BLSPubKey, TestElectionConfig>::default();
+        assert_eq!(config.num_runs(), 1);
+        let run = config.for_run(0).unwrap();
+        assert_eq!(run.run_index, 0);
+        assert_eq!(run.rounds, config.rounds);
+        assert!(config.for_run(1).is_none());
+    }
+
+    #[test]
+    fn runs_override_only_the_parameters_they_set() {
+        let mut config = NetworkConfig::<BLSPubKey, TestElectionConfig>::default();
+        config.runs = vec![
+            RunParameters {
+                rounds: Some(3),
+                transactions_per_round: Some(20),
+                transaction_size: Some(500),
+                next_view_timeout: Some(2000),
+                round_start_delay: Some(4),
+                committee_nodes: Some(2),
+            },
+            RunParameters::default(),
+        ];
+        assert_eq!(config.num_runs(), 2);
+
+        let first = config.for_run(0).unwrap();
+        assert_eq!(first.run_index, 0);
+        assert_eq!(first.rounds, 3);
+        assert_eq!(first.transactions_per_round, 20);
+        assert_eq!(first.transaction_size, 500);
+        assert_eq!(first.next_view_timeout, 2000);
+        assert_eq!(first.config.next_view_timeout, 2000);
+        assert_eq!(first.config.round_start_delay, 4);
+        assert_eq!(first.config.da_committee_size, 2);
+
+        let second = config.for_run(1).unwrap();
+        assert_eq!(second.run_index, 1);
+        assert_eq!(second.rounds, config.rounds);
+        assert_eq!(second.transactions_per_round, config.transactions_per_round);
+        assert_eq!(second.transaction_size, config.transaction_size);
+        assert_eq!(second.next_view_timeout, config.next_view_timeout);
+        assert_eq!(
+            second.config.round_start_delay,
+            config.config.round_start_delay
+        );
+        assert_eq!(
+            second.config.da_committee_size,
+            config.config.da_committee_size
+        );
+        assert_eq!(config.run_parameters(1), RunParameters::default());
+
+        assert!(config.for_run(2).is_none());
+    }
+
+    #[test]
+    fn several_runs_are_refused_over_web_servers() {
+        let web_server_config = WebServerConfig {
+            urls: vec![Url::parse("http://127.0.0.1:9000").unwrap()],
+            wait_between_polls: Duration::from_millis(100),
+        };
+        let mut config = NetworkConfig::<BLSPubKey, TestElectionConfig>::default();
+        config.runs = vec![RunParameters::default(), RunParameters::default()];
+        assert!(config.validate().is_ok());
+
+        config.da_web_server_config = Some(web_server_config.clone());
+        assert!(matches!(
+            config.validate(),
+            Err(NetworkConfigError::RunsOverWebServers { runs: 2 })
+        ));
+
+        config.da_web_server_config = None;
+        config.web_server_config = Some(web_server_config);
+        assert!(config.validate().is_err());
+
+        // a single run over web servers is fine
+        config.runs.truncate(1);
+        assert!(config.validate().is_ok());
+    }
+}
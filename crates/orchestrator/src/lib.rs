@@ -1,12 +1,19 @@
 pub mod client;
 pub mod config;
+pub mod results;
+pub mod status;
 
+use async_compatibility_layer::art::async_spawn;
 use async_lock::RwLock;
 use hotshot_types::traits::{election::ElectionConfig, signature_key::SignatureKey};
 use std::{
+    collections::BTreeMap,
     io,
     io::ErrorKind,
     net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
+    time::{Duration, Instant},
 };
 use tide_disco::{Api, App};
 
@@ -19,7 +26,11 @@ use tide_disco::{
 
 use futures::FutureExt;
 
-use crate::config::NetworkConfig;
+use crate::{
+    config::NetworkConfig,
+    results::{ExperimentReport, RunResults, RunResultsReport},
+    status::{NodePhase, NodeStatus, RunStatus},
+};
 
 use libp2p::identity::{
     ed25519::{Keypair as EdKeypair, SecretKey},
@@ -35,36 +46,133 @@ pub fn libp2p_generate_indexed_identity(seed: [u8; 32], index: u64) -> Keypair {
     <EdKeypair as From<SecretKey>>::from(sk_bytes).into()
 }
 
+/// How long a node can go without a heartbeat before the orchestrator reports it unresponsive
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3 * client::HEARTBEAT_INTERVAL.as_secs());
+
+/// What the orchestrator knows of a registered node
+#[derive(Clone)]
+struct NodeRecord {
+    /// Index of the node
+    node_index: u16,
+    /// The IP address the node identified with
+    identity: IpAddr,
+    /// How far the node got
+    phase: NodePhase,
+    /// When the node last sent a heartbeat or moved to a new phase
+    last_seen: Instant,
+}
+
 #[derive(Default, Clone)]
 struct OrchestratorState<KEY: SignatureKey, ELECTION: ElectionConfig> {
     /// Tracks the latest node index we have generated a configuration for
     latest_index: u16,
     /// The network configuration
     config: NetworkConfig<KEY, ELECTION>,
+    /// Index of the run of the experiment nodes are in
+    current_run: usize,
     /// Whether nodes should start their HotShot instances
     /// Will be set to true once all nodes post they are ready to start
     start: bool,
-    /// The total nodes that have posted they are ready to start
-    pub nodes_connected: u64,
+    /// The nodes that identified with the orchestrator, by public key
+    nodes: BTreeMap<KEY, NodeRecord>,
     /// connection to the web server
     client: Option<surf_disco::Client<ClientError>>,
+    /// The results each node posted at the end of the current run, by node index
+    results: BTreeMap<u64, RunResults>,
+    /// The reports of the runs every node finished
+    reports: Vec<RunResultsReport>,
+    /// Sends the report, once each run finishes, to be written to the results file
+    results_writer: Option<Sender<ExperimentReport>>,
+}
+
+/// Writes each report sent to it to `path`, in order, on a thread of its own so the state isn't
+/// locked while the file is written
+fn spawn_results_writer(path: PathBuf) -> Sender<ExperimentReport> {
+    let (sender, receiver) = mpsc::channel::<ExperimentReport>();
+    std::thread::spawn(move || {
+        for report in receiver {
+            // The results can still be fetched if they can't be written, so don't fail the node
+            match report.write_to_file(&path) {
+                Ok(()) => println!("Run results written to {}", path.display()),
+                Err(e) => tracing::error!("Failed to write run results to {}: {e}", path.display()),
+            }
+        }
+    });
+    sender
 }
 
 impl<KEY: SignatureKey + 'static, ELECTION: ElectionConfig + 'static>
     OrchestratorState<KEY, ELECTION>
 {
     pub fn new(network_config: NetworkConfig<KEY, ELECTION>) -> Self {
-        let mut web_client = None;
-        if network_config.web_server_config.is_some() {
-            let base_url = "http://0.0.0.0/9000".to_string().parse().unwrap();
-            web_client = Some(surf_disco::Client::<ClientError>::new(base_url));
-        }
+        let web_client = network_config
+            .web_server_config
+            .as_ref()
+            .and_then(|config| config.urls.first())
+            .map(|base_url| surf_disco::Client::<ClientError>::new(base_url.clone()));
+        let results_writer = network_config
+            .results_file
+            .clone()
+            .map(spawn_results_writer);
         OrchestratorState {
             latest_index: 0,
             config: network_config,
+            current_run: 0,
             start: false,
-            nodes_connected: 0,
+            nodes: BTreeMap::new(),
             client: web_client,
+            results: BTreeMap::new(),
+            reports: Vec::new(),
+            results_writer,
+        }
+    }
+}
+
+impl<KEY: SignatureKey, ELECTION: ElectionConfig> OrchestratorState<KEY, ELECTION> {
+    /// The public key of the node at `node_index`, which nodes generate from the run's seed
+    fn public_key(&self, node_index: u16) -> KEY {
+        KEY::generated_from_seed_indexed(self.config.seed, node_index.into()).0
+    }
+
+    /// Moves the node registered with `public_key` on to `phase`, unless it's past it already
+    fn advance(&mut self, public_key: &KEY, phase: NodePhase) -> Result<(), ServerError> {
+        let Some(node) = self.nodes.get_mut(public_key) else {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: "No node is registered with this public key".to_string(),
+            });
+        };
+        node.phase = node.phase.max(phase);
+        node.last_seen = Instant::now();
+        Ok(())
+    }
+
+    /// The config of the current run
+    fn current_run_config(&self) -> Result<NetworkConfig<KEY, ELECTION>, ServerError> {
+        self.config
+            .for_run(self.current_run)
+            .ok_or_else(|| ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: "Every run of the experiment has finished".to_string(),
+            })
+    }
+
+    /// Records the report of the current run and moves every node on to the next one
+    fn finish_run(&mut self) {
+        let results = std::mem::take(&mut self.results);
+        self.reports.push(RunResultsReport::new(
+            self.current_run,
+            self.config.run_parameters(self.current_run),
+            self.config.config.total_nodes.get(),
+            results.into_values().collect(),
+        ));
+        println!("Run {} finished", self.current_run);
+        self.current_run += 1;
+        self.start = false;
+        if self.current_run < self.config.num_runs() {
+            for node in self.nodes.values_mut() {
+                node.phase = NodePhase::Identified;
+            }
         }
     }
 }
@@ -75,9 +183,18 @@ pub trait OrchestratorApi<KEY: SignatureKey, ELECTION: ElectionConfig> {
         &mut self,
         node_index: u16,
     ) -> Result<NetworkConfig<KEY, ELECTION>, ServerError>;
+    fn post_getnextrun(
+        &mut self,
+        node_index: u16,
+        run_index: usize,
+    ) -> Result<Option<NetworkConfig<KEY, ELECTION>>, ServerError>;
     fn get_start(&self) -> Result<bool, ServerError>;
-    fn post_ready(&mut self) -> Result<(), ServerError>;
-    fn post_run_results(&mut self) -> Result<(), ServerError>;
+    fn post_ready(&mut self, public_key: &KEY) -> Result<(), ServerError>;
+    fn post_running(&mut self, public_key: &KEY) -> Result<(), ServerError>;
+    fn post_heartbeat(&mut self, public_key: &KEY) -> Result<(), ServerError>;
+    fn get_status(&self) -> Result<RunStatus<KEY>, ServerError>;
+    fn post_run_results(&mut self, results: RunResults) -> Result<(), ServerError>;
+    fn get_run_results(&self) -> Result<ExperimentReport, ServerError>;
 }
 
 impl<KEY, ELECTION> OrchestratorApi<KEY, ELECTION> for OrchestratorState<KEY, ELECTION>
@@ -96,20 +213,28 @@ where
                 message: "Network has reached capacity".to_string(),
             });
         }
+        self.nodes.insert(
+            self.public_key(node_index),
+            NodeRecord {
+                node_index,
+                identity,
+                phase: NodePhase::Identified,
+                last_seen: Instant::now(),
+            },
+        );
 
-        //add new node's key to stake table
-        if self.config.web_server_config.clone().is_some() {
-            let new_key = &self.config.config.my_own_validator_config.public_key;
-            let client_clone = self.client.clone().unwrap();
-            async move {
-                client_clone
-                    .post::<()>("api/staketable")
-                    .body_binary(&new_key)
-                    .unwrap()
-                    .send()
-                    .await
-            }
-            .boxed();
+        // check the new node's key is in the web server's stake table
+        if let Some(client) = self.client.clone() {
+            let new_key = self.public_key(node_index).to_bytes().0;
+            async_spawn(async move {
+                let result = match client.post::<()>("api/staketable").body_binary(&new_key) {
+                    Ok(request) => request.send().await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    tracing::error!("Web server refused node {node_index}'s key: {e}");
+                }
+            });
         }
 
         if self.config.libp2p_config.clone().is_some() {
@@ -138,7 +263,7 @@ where
     // 'identity' endpoint
     fn post_getconfig(
         &mut self,
-        _node_index: u16,
+        node_index: u16,
     ) -> Result<NetworkConfig<KEY, ELECTION>, ServerError> {
         if self.config.libp2p_config.is_some() {
             let libp2p_config = self.config.clone().libp2p_config.unwrap();
@@ -149,7 +274,36 @@ where
                 });
             }
         }
-        Ok(self.config.clone())
+        let config = self.current_run_config()?;
+        let public_key = self.public_key(node_index);
+        self.advance(&public_key, NodePhase::Configured)?;
+        Ok(config)
+    }
+
+    fn post_getnextrun(
+        &mut self,
+        node_index: u16,
+        run_index: usize,
+    ) -> Result<Option<NetworkConfig<KEY, ELECTION>>, ServerError> {
+        // Nodes ask for the next run as soon as they finish theirs, before the others have
+        if run_index > self.current_run {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: format!("Run {} has not finished yet", self.current_run),
+            });
+        }
+        let Some(config) = self.config.for_run(run_index) else {
+            return Ok(None);
+        };
+        if run_index < self.current_run {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: format!("Run {run_index} has already started without this node"),
+            });
+        }
+        let public_key = self.public_key(node_index);
+        self.advance(&public_key, NodePhase::Configured)?;
+        Ok(Some(config))
     }
 
     fn get_start(&self) -> Result<bool, ServerError> {
@@ -163,28 +317,125 @@ where
         Ok(self.start)
     }
 
-    // Assumes nodes do not post 'ready' twice
-    // TODO ED Add a map to verify which nodes have posted they're ready
-    fn post_ready(&mut self) -> Result<(), ServerError> {
-        self.nodes_connected += 1;
-        println!("Nodes connected: {}", self.nodes_connected);
-        if self.nodes_connected
-            >= self
-                .config
-                .config
-                .known_nodes_with_stake
-                .len()
-                .try_into()
-                .unwrap()
-        {
+    fn post_ready(&mut self, public_key: &KEY) -> Result<(), ServerError> {
+        self.advance(public_key, NodePhase::Ready)?;
+        let nodes_ready = self
+            .nodes
+            .values()
+            .filter(|node| node.phase >= NodePhase::Ready)
+            .count();
+        println!("Nodes ready: {nodes_ready}");
+        if nodes_ready >= self.config.config.known_nodes_with_stake.len() {
             self.start = true;
         }
         Ok(())
     }
 
-    fn post_run_results(&mut self) -> Result<(), ServerError> {
+    fn post_running(&mut self, public_key: &KEY) -> Result<(), ServerError> {
+        if !self.start {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: "Network has not started".to_string(),
+            });
+        }
+        self.advance(public_key, NodePhase::Running)
+    }
+
+    fn post_heartbeat(&mut self, public_key: &KEY) -> Result<(), ServerError> {
+        let Some(node) = self.nodes.get_mut(public_key) else {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: "No node is registered with this public key".to_string(),
+            });
+        };
+        node.last_seen = Instant::now();
+        Ok(())
+    }
+
+    fn get_status(&self) -> Result<RunStatus<KEY>, ServerError> {
+        let now = Instant::now();
+        let mut nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|(public_key, node)| {
+                let since_last_seen = now.duration_since(node.last_seen);
+                NodeStatus {
+                    public_key: public_key.clone(),
+                    node_index: node.node_index,
+                    identity: node.identity,
+                    phase: node.phase,
+                    since_last_seen,
+                    unresponsive: node.phase < NodePhase::Finished
+                        && since_last_seen > HEARTBEAT_TIMEOUT,
+                }
+            })
+            .collect();
+        nodes.sort_by_key(|node| node.node_index);
+        let total_nodes = self.config.config.total_nodes.get();
+        Ok(RunStatus {
+            run_index: self.current_run,
+            num_runs: self.config.num_runs(),
+            total_nodes,
+            started: self.start,
+            nodes_missing: total_nodes.saturating_sub(nodes.len()),
+            failed_to_start: nodes
+                .iter()
+                .filter(|node| node.unresponsive && node.phase < NodePhase::Running)
+                .map(|node| node.node_index)
+                .collect(),
+            nodes,
+        })
+    }
+
+    fn post_run_results(&mut self, results: RunResults) -> Result<(), ServerError> {
+        let total_nodes = self.config.config.total_nodes.get();
+        let Some(node_index) = u16::try_from(results.node_index)
+            .ok()
+            .filter(|index| usize::from(*index) < total_nodes)
+        else {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: format!("No node with index {} in the run", results.node_index),
+            });
+        };
+        // Nodes retry until the orchestrator answers, so the results of a finished run may be
+        // posted again
+        if results.run_index < self.current_run {
+            return Ok(());
+        }
+        if results.run_index > self.current_run {
+            return Err(ServerError {
+                status: tide_disco::StatusCode::BadRequest,
+                message: format!("Run {} has not started yet", results.run_index),
+            });
+        }
+        let public_key = self.public_key(node_index);
+        self.advance(&public_key, NodePhase::Finished)?;
+        self.results.insert(results.node_index, results);
+        println!("Nodes reported results: {}", self.results.len());
+        if self.results.len() < total_nodes {
+            return Ok(());
+        }
+        self.finish_run();
+        if let Some(results_writer) = &self.results_writer {
+            // The writer thread only hangs up if it panicked
+            let _ = results_writer.send(self.get_run_results()?);
+        }
         Ok(())
     }
+
+    fn get_run_results(&self) -> Result<ExperimentReport, ServerError> {
+        let mut runs = self.reports.clone();
+        if !self.results.is_empty() {
+            runs.push(RunResultsReport::new(
+                self.current_run,
+                self.config.run_parameters(self.current_run),
+                self.config.config.total_nodes.get(),
+                self.results.values().cloned().collect(),
+            ));
+        }
+        Ok(ExperimentReport { runs })
+    }
 }
 
 /// Sets up all API routes
@@ -193,7 +444,7 @@ fn define_api<KEY: SignatureKey, ELECTION: ElectionConfig, State>(
 where
     State: 'static + Send + Sync + ReadState + WriteState,
     <State as ReadState>::State: Send + Sync + OrchestratorApi<KEY, ELECTION>,
-    KEY: serde::Serialize,
+    KEY: serde::Serialize + 'static,
     ELECTION: serde::Serialize,
 {
     let api_toml = toml::from_str::<toml::Value>(include_str!(concat!(
@@ -222,19 +473,59 @@ where
         }
         .boxed()
     })?
-    .post("postready", |_req, state| {
-        async move { state.post_ready() }.boxed()
+    .post("post_getnextrun", |req, state| {
+        async move {
+            let node_index = req.integer_param("node_index")?;
+            let run_index = req.integer_param("run_index")?;
+            state.post_getnextrun(node_index, run_index)
+        }
+        .boxed()
+    })?
+    .post("postready", |req, state| {
+        async move {
+            let public_key = req.body_auto::<KEY>()?;
+            state.post_ready(&public_key)
+        }
+        .boxed()
+    })?
+    .post("postrunning", |req, state| {
+        async move {
+            let public_key = req.body_auto::<KEY>()?;
+            state.post_running(&public_key)
+        }
+        .boxed()
+    })?
+    .post("postheartbeat", |req, state| {
+        async move {
+            let public_key = req.body_auto::<KEY>()?;
+            state.post_heartbeat(&public_key)
+        }
+        .boxed()
+    })?
+    .get("getstatus", |_req, state| {
+        async move { state.get_status() }.boxed()
     })?
     .get("getstart", |_req, state| {
         async move { state.get_start() }.boxed()
     })?
-    .post("postresults", |_req, state| {
-        async move { state.post_run_results() }.boxed()
+    .post("postresults", |req, state| {
+        async move {
+            let results = req.body_auto::<RunResults>()?;
+            state.post_run_results(results)
+        }
+        .boxed()
+    })?
+    .get("getresults", |_req, state| {
+        async move { state.get_run_results() }.boxed()
     })?;
     Ok(api)
 }
 
 /// Runs the orchestrator
+///
+/// # Errors
+/// Fails if `network_config` describes an experiment the orchestrator can't run, see
+/// [`NetworkConfig::validate`], or if the server does
 pub async fn run_orchestrator<KEY, ELECTION>(
     network_config: NetworkConfig<KEY, ELECTION>,
     host: IpAddr,
@@ -244,6 +535,9 @@ where
     KEY: SignatureKey + 'static + serde::Serialize,
     ELECTION: ElectionConfig + 'static + serde::Serialize,
 {
+    network_config
+        .validate()
+        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
     let api = define_api().map_err(|_e| io::Error::new(ErrorKind::Other, "Failed to define api"));
 
     let state: RwLock<OrchestratorState<KEY, ELECTION>> =
@@ -255,3 +549,121 @@ where
     tracing::error!("lisening on {:?}:{:?}", host, port);
     app.serve(format!("http://{host}:{port}")).await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{test::TestElectionConfig, RunParameters};
+    use hotshot_signature_key::bn254::BLSPubKey;
+    use std::{net::Ipv4Addr, num::NonZeroUsize};
+
+    /// An orchestrator of two nodes through two runs, of 5 and then 7 rounds, which both nodes
+    /// identified with and got the config of the first run from
+    fn configured_state() -> OrchestratorState<BLSPubKey, TestElectionConfig> {
+        let mut config = NetworkConfig::<BLSPubKey, TestElectionConfig>::default();
+        config.config.total_nodes = NonZeroUsize::new(2).unwrap();
+        config.config.known_nodes_with_stake.truncate(2);
+        config.runs = vec![
+            RunParameters {
+                rounds: Some(5),
+                ..RunParameters::default()
+            },
+            RunParameters {
+                rounds: Some(7),
+                ..RunParameters::default()
+            },
+        ];
+        let mut state = OrchestratorState::new(config);
+        for node_index in 0..2 {
+            let identity = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            assert_eq!(state.post_identity(identity).unwrap(), node_index);
+            assert_eq!(state.post_getconfig(node_index).unwrap().rounds, 5);
+        }
+        state
+    }
+
+    /// Run every node through the current run, up to posting its results
+    fn run(state: &mut OrchestratorState<BLSPubKey, TestElectionConfig>) {
+        for node_index in 0..2 {
+            state.post_ready(&state.public_key(node_index)).unwrap();
+        }
+        assert!(state.get_start().unwrap());
+        for node_index in 0..2 {
+            state.post_running(&state.public_key(node_index)).unwrap();
+            state
+                .post_run_results(RunResults {
+                    node_index: node_index.into(),
+                    run_index: state.current_run,
+                    ..RunResults::default()
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn nodes_go_through_every_run_in_order() {
+        let mut state = configured_state();
+        assert!(state.post_getnextrun(0, 1).is_err());
+
+        run(&mut state);
+        assert_eq!(state.current_run, 1);
+        assert!(state.get_start().is_err());
+        assert!(state
+            .nodes
+            .values()
+            .all(|node| node.phase == NodePhase::Identified));
+
+        for node_index in 0..2 {
+            let config = state.post_getnextrun(node_index, 1).unwrap().unwrap();
+            assert_eq!((config.run_index, config.rounds), (1, 7));
+        }
+        // a node can't go back to a run that's over
+        assert!(state.post_getnextrun(0, 0).is_err());
+
+        run(&mut state);
+        assert_eq!(state.current_run, 2);
+        assert!(state.post_getnextrun(0, 2).unwrap().is_none());
+        // the nodes stay finished once the last run is over
+        assert!(state
+            .nodes
+            .values()
+            .all(|node| node.phase == NodePhase::Finished));
+    }
+
+    #[test]
+    fn finished_runs_keep_their_reports() {
+        let mut state = configured_state();
+        run(&mut state);
+        // nodes retrying their post of a finished run's results don't change its report
+        state
+            .post_run_results(RunResults {
+                node_index: 0,
+                run_index: 0,
+                rounds_completed: 3,
+                ..RunResults::default()
+            })
+            .unwrap();
+
+        let report = state.get_run_results().unwrap();
+        assert_eq!(report.runs.len(), 1);
+        assert_eq!(report.runs[0].run_index, 0);
+        assert_eq!(report.runs[0].parameters.rounds, Some(5));
+        assert_eq!(report.runs[0].aggregate.nodes_reported, 2);
+        assert_eq!(report.runs[0].aggregate.min_rounds_completed, 0);
+
+        // the run in progress is reported with the nodes that posted so far
+        state.post_getnextrun(0, 1).unwrap();
+        state.post_getnextrun(1, 1).unwrap();
+        state
+            .post_run_results(RunResults {
+                node_index: 1,
+                run_index: 1,
+                ..RunResults::default()
+            })
+            .unwrap();
+        let report = state.get_run_results().unwrap();
+        assert_eq!(report.runs.len(), 2);
+        assert_eq!(report.runs[1].aggregate.nodes_reported, 1);
+        assert_eq!(report.runs[1].aggregate.total_nodes, 2);
+    }
+}
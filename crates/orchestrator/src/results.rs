@@ -0,0 +1,465 @@
+use std::{fmt::Write, fs, io, path::Path, time::Duration};
+
+use crate::config::RunParameters;
+
+/// Summary statistics of a set of latencies
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of latencies measured
+    pub count: usize,
+    /// Mean latency, if any were measured
+    pub mean: Option<Duration>,
+    /// Median latency, if any were measured
+    pub p50: Option<Duration>,
+    /// 99th percentile latency, if any were measured
+    pub p99: Option<Duration>,
+    /// Longest latency, if any were measured
+    pub max: Option<Duration>,
+}
+
+impl LatencyStats {
+    /// Summarizes `samples`, which can be in any order
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let percentile = |p: usize| {
+            samples
+                .get((samples.len() * p / 100).min(samples.len().saturating_sub(1)))
+                .copied()
+        };
+        Self {
+            count: samples.len(),
+            mean: u32::try_from(samples.len())
+                .ok()
+                .filter(|count| *count > 0)
+                .map(|count| samples.iter().sum::<Duration>() / count),
+            p50: percentile(50),
+            p99: percentile(99),
+            max: samples.last().copied(),
+        }
+    }
+}
+
+/// What a node measured over a run, posted to the orchestrator once the node finishes its rounds
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunResults {
+    /// Index of the node, as given by the orchestrator
+    pub node_index: u64,
+    /// Index of the run among the runs of the experiment
+    pub run_index: usize,
+    /// Leaves the node decided
+    pub rounds_completed: usize,
+    /// Views the node timed out in
+    pub failed_views: usize,
+    /// Transactions the node submitted
+    pub transactions_sent: u64,
+    /// Transactions in the blocks the node decided
+    pub transactions_committed: u64,
+    /// Time from the node starting consensus to finishing its rounds
+    pub elapsed: Duration,
+    /// Time from the node submitting each of its own transactions to deciding it
+    pub latency: LatencyStats,
+}
+
+/// The results of the nodes in a run, aggregated over them
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AggregateResults {
+    /// Nodes that posted their results
+    pub nodes_reported: usize,
+    /// Nodes in the run
+    pub total_nodes: usize,
+    /// Fewest rounds any node completed
+    pub min_rounds_completed: usize,
+    /// Views timed out in, summed over the nodes
+    pub total_failed_views: usize,
+    /// Transactions submitted, summed over the nodes
+    pub total_transactions_sent: u64,
+    /// Most transactions any node decided. Nodes decide the same blocks, so this is how many
+    /// the network committed
+    pub transactions_committed: u64,
+    /// Longest any node took
+    pub max_elapsed: Duration,
+    /// Transactions committed per second of the longest elapsed time
+    pub throughput: f64,
+    /// Transaction latency over all nodes. The count and mean are exact; the percentiles and
+    /// maximum are those of the worst node, since nodes only post their own percentiles
+    pub latency: LatencyStats,
+}
+
+impl AggregateResults {
+    /// Aggregates the results `nodes` posted, out of `total_nodes`
+    pub fn new(total_nodes: usize, nodes: &[RunResults]) -> Self {
+        let transactions_committed = nodes
+            .iter()
+            .map(|node| node.transactions_committed)
+            .max()
+            .unwrap_or(0);
+        let max_elapsed = nodes
+            .iter()
+            .map(|node| node.elapsed)
+            .max()
+            .unwrap_or_default();
+        let throughput = if max_elapsed.is_zero() {
+            0.0
+        } else {
+            transactions_committed as f64 / max_elapsed.as_secs_f64()
+        };
+        let count = nodes.iter().map(|node| node.latency.count).sum();
+        let total_latency: Duration = nodes
+            .iter()
+            .filter_map(|node| {
+                let count = u32::try_from(node.latency.count).ok()?;
+                Some(node.latency.mean? * count)
+            })
+            .sum();
+        let worst = |stat: fn(&LatencyStats) -> Option<Duration>| {
+            nodes.iter().filter_map(|node| stat(&node.latency)).max()
+        };
+        Self {
+            nodes_reported: nodes.len(),
+            total_nodes,
+            min_rounds_completed: nodes
+                .iter()
+                .map(|node| node.rounds_completed)
+                .min()
+                .unwrap_or(0),
+            total_failed_views: nodes.iter().map(|node| node.failed_views).sum(),
+            total_transactions_sent: nodes.iter().map(|node| node.transactions_sent).sum(),
+            transactions_committed,
+            max_elapsed,
+            throughput,
+            latency: LatencyStats {
+                count,
+                mean: u32::try_from(count)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .map(|count| total_latency / count),
+                p50: worst(|latency| latency.p50),
+                p99: worst(|latency| latency.p99),
+                max: worst(|latency| latency.max),
+            },
+        }
+    }
+}
+
+/// The results of a run: each node's, and aggregated over them
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunResultsReport {
+    /// Index of the run among the runs of the experiment
+    pub run_index: usize,
+    /// How the run differed from the run config
+    pub parameters: RunParameters,
+    /// The results aggregated over the nodes that posted theirs
+    pub aggregate: AggregateResults,
+    /// The results each node posted, by node index
+    pub nodes: Vec<RunResults>,
+}
+
+impl RunResultsReport {
+    /// Reports the results `nodes` posted in the run at `run_index`, out of `total_nodes`
+    pub fn new(
+        run_index: usize,
+        parameters: RunParameters,
+        total_nodes: usize,
+        nodes: Vec<RunResults>,
+    ) -> Self {
+        Self {
+            run_index,
+            parameters,
+            aggregate: AggregateResults::new(total_nodes, &nodes),
+            nodes,
+        }
+    }
+}
+
+/// The results of each run of an experiment so far
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ExperimentReport {
+    /// The report of each run, in order, the last of which may still be in progress
+    pub runs: Vec<RunResultsReport>,
+}
+
+impl ExperimentReport {
+    /// The report as CSV, with a row for each node of each run followed by a row, with node
+    /// index `all`, for the aggregate of the run. Durations are in milliseconds
+    pub fn to_csv(&self) -> String {
+        let ms = |duration: Option<Duration>| {
+            duration.map_or(String::new(), |duration| {
+                (duration.as_secs_f64() * 1000.0).to_string()
+            })
+        };
+        let mut csv = "run_index,node_index,rounds_completed,failed_views,transactions_sent,\
+            transactions_committed,elapsed_ms,latency_count,latency_mean_ms,latency_p50_ms,\
+            latency_p99_ms,latency_max_ms\n"
+            .to_string();
+        let mut row = |run: usize,
+                       node: &str,
+                       rounds: usize,
+                       failed: usize,
+                       sent: u64,
+                       committed: u64,
+                       elapsed: Duration,
+                       latency: &LatencyStats| {
+            // writing to a string can't fail
+            let _ = writeln!(
+                csv,
+                "{run},{node},{rounds},{failed},{sent},{committed},{},{},{},{},{},{}",
+                ms(Some(elapsed)),
+                latency.count,
+                ms(latency.mean),
+                ms(latency.p50),
+                ms(latency.p99),
+                ms(latency.max),
+            );
+        };
+        for run in &self.runs {
+            for node in &run.nodes {
+                row(
+                    run.run_index,
+                    &node.node_index.to_string(),
+                    node.rounds_completed,
+                    node.failed_views,
+                    node.transactions_sent,
+                    node.transactions_committed,
+                    node.elapsed,
+                    &node.latency,
+                );
+            }
+            let aggregate = &run.aggregate;
+            row(
+                run.run_index,
+                "all",
+                aggregate.min_rounds_completed,
+                aggregate.total_failed_views,
+                aggregate.total_transactions_sent,
+                aggregate.transactions_committed,
+                aggregate.max_elapsed,
+                &aggregate.latency,
+            );
+        }
+        csv
+    }
+
+    /// Writes the report to `path`, as CSV if it ends in `.csv` and as JSON otherwise
+    ///
+    /// # Errors
+    /// Errors if the file can't be written
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        let contents = if path.extension().is_some_and(|extension| extension == "csv") {
+            self.to_csv()
+        } else {
+            serde_json::to_string_pretty(self).map_err(io::Error::from)?
+        };
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// `ms` milliseconds
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// The results of the node at `node_index` in run 0
+    fn node_results(
+        node_index: u64,
+        rounds_completed: usize,
+        transactions_committed: u64,
+        elapsed: Duration,
+        latency: LatencyStats,
+    ) -> RunResults {
+        RunResults {
+            node_index,
+            run_index: 0,
+            rounds_completed,
+            failed_views: 1,
+            transactions_sent: 5,
+            transactions_committed,
+            elapsed,
+            latency,
+        }
+    }
+
+    #[test]
+    fn latency_stats_of_no_samples() {
+        assert_eq!(
+            LatencyStats::from_samples(Vec::new()),
+            LatencyStats::default()
+        );
+    }
+
+    #[test]
+    fn latency_stats_of_one_sample() {
+        assert_eq!(
+            LatencyStats::from_samples(vec![ms(4)]),
+            LatencyStats {
+                count: 1,
+                mean: Some(ms(4)),
+                p50: Some(ms(4)),
+                p99: Some(ms(4)),
+                max: Some(ms(4)),
+            }
+        );
+    }
+
+    #[test]
+    fn latency_stats_of_unordered_samples() {
+        let samples = (1..=100).rev().map(ms).collect();
+        assert_eq!(
+            LatencyStats::from_samples(samples),
+            LatencyStats {
+                count: 100,
+                mean: Some(Duration::from_micros(50_500)),
+                p50: Some(ms(51)),
+                p99: Some(ms(100)),
+                max: Some(ms(100)),
+            }
+        );
+    }
+
+    #[test]
+    fn aggregate_of_no_nodes() {
+        assert_eq!(
+            AggregateResults::new(4, &[]),
+            AggregateResults {
+                total_nodes: 4,
+                ..AggregateResults::default()
+            }
+        );
+    }
+
+    #[test]
+    fn aggregate_of_one_node() {
+        let latency = LatencyStats::from_samples(vec![ms(2), ms(4)]);
+        let aggregate = AggregateResults::new(
+            1,
+            &[node_results(0, 10, 20, Duration::from_secs(2), latency)],
+        );
+        assert_eq!(
+            aggregate,
+            AggregateResults {
+                nodes_reported: 1,
+                total_nodes: 1,
+                min_rounds_completed: 10,
+                total_failed_views: 1,
+                total_transactions_sent: 5,
+                transactions_committed: 20,
+                max_elapsed: Duration::from_secs(2),
+                throughput: 10.0,
+                latency,
+            }
+        );
+    }
+
+    #[test]
+    fn aggregate_weights_means_by_count_and_takes_the_worst_percentiles() {
+        let nodes = [
+            node_results(
+                0,
+                10,
+                20,
+                Duration::from_secs(2),
+                LatencyStats {
+                    count: 1,
+                    mean: Some(ms(10)),
+                    p50: Some(ms(10)),
+                    p99: Some(ms(10)),
+                    max: Some(ms(10)),
+                },
+            ),
+            node_results(
+                1,
+                8,
+                40,
+                Duration::from_secs(4),
+                LatencyStats {
+                    count: 3,
+                    mean: Some(ms(2)),
+                    p50: Some(ms(2)),
+                    p99: Some(ms(3)),
+                    max: Some(ms(30)),
+                },
+            ),
+            // a node that decided none of its own transactions
+            node_results(2, 9, 40, Duration::from_secs(1), LatencyStats::default()),
+        ];
+        assert_eq!(
+            AggregateResults::new(4, &nodes),
+            AggregateResults {
+                nodes_reported: 3,
+                total_nodes: 4,
+                min_rounds_completed: 8,
+                total_failed_views: 3,
+                total_transactions_sent: 15,
+                transactions_committed: 40,
+                max_elapsed: Duration::from_secs(4),
+                throughput: 10.0,
+                latency: LatencyStats {
+                    count: 4,
+                    mean: Some(ms(4)),
+                    p50: Some(ms(10)),
+                    p99: Some(ms(10)),
+                    max: Some(ms(30)),
+                },
+            }
+        );
+    }
+
+    /// A report of two runs, the second of which no node posted results for yet
+    fn report() -> ExperimentReport {
+        let nodes = vec![
+            node_results(
+                0,
+                10,
+                20,
+                Duration::from_secs(2),
+                LatencyStats::from_samples(vec![ms(2), ms(3)]),
+            ),
+            node_results(1, 9, 20, ms(2500), LatencyStats::default()),
+        ];
+        ExperimentReport {
+            runs: vec![
+                RunResultsReport::new(0, RunParameters::default(), 2, nodes),
+                RunResultsReport::new(1, RunParameters::default(), 2, Vec::new()),
+            ],
+        }
+    }
+
+    #[test]
+    fn csv_has_a_row_per_node_and_per_run() {
+        assert_eq!(
+            report().to_csv(),
+            "run_index,node_index,rounds_completed,failed_views,transactions_sent,\
+            transactions_committed,elapsed_ms,latency_count,latency_mean_ms,latency_p50_ms,\
+            latency_p99_ms,latency_max_ms\n\
+            0,0,10,1,5,20,2000,2,2.5,3,3,3\n\
+            0,1,9,1,5,20,2500,0,,,,\n\
+            0,all,9,2,10,20,2500,2,2.5,3,3,3\n\
+            1,all,0,0,0,0,0,0,,,,\n"
+        );
+    }
+
+    #[test]
+    fn written_as_csv_or_json_by_extension() {
+        let dir = std::env::temp_dir().join(format!(
+            "hotshot-orchestrator-results-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let report = report();
+
+        let csv = dir.join("results.csv");
+        report.write_to_file(&csv).unwrap();
+        assert_eq!(fs::read_to_string(&csv).unwrap(), report.to_csv());
+
+        let json = dir.join("results.json");
+        report.write_to_file(&json).unwrap();
+        let read: ExperimentReport =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(read, report);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{net::IpAddr, time::Duration};
+
+use hotshot_types::traits::signature_key::SignatureKey;
+
+/// How far a node got through the run, in order
+#[derive(
+    serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum NodePhase {
+    /// The node identified itself and was given a node index
+    Identified,
+    /// The node got the run configuration
+    Configured,
+    /// The node posted it is ready to start
+    Ready,
+    /// The node was told to start and is running consensus
+    Running,
+    /// The node finished its rounds and posted its results
+    Finished,
+}
+
+/// A node registered with the orchestrator, as last heard from
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(bound(deserialize = ""))]
+pub struct NodeStatus<KEY: SignatureKey> {
+    /// The node's public key, which it is registered by
+    pub public_key: KEY,
+    /// Index of the node, as given by the orchestrator
+    pub node_index: u16,
+    /// The IP address the node identified with
+    pub identity: IpAddr,
+    /// How far the node got
+    pub phase: NodePhase,
+    /// Time since the node last sent a heartbeat or moved to a new phase
+    pub since_last_seen: Duration,
+    /// Whether the node stopped sending heartbeats before finishing, so likely failed
+    pub unresponsive: bool,
+}
+
+/// The status of every node in the current run, to see why it hasn't started or finished
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(bound(deserialize = ""))]
+pub struct RunStatus<KEY: SignatureKey> {
+    /// Index of the run of the experiment nodes are in, which is the number of runs once
+    /// they've all finished
+    pub run_index: usize,
+    /// Runs in the experiment
+    pub num_runs: usize,
+    /// Nodes in the run
+    pub total_nodes: usize,
+    /// Whether nodes were told to start
+    pub started: bool,
+    /// Nodes yet to identify with the orchestrator
+    pub nodes_missing: usize,
+    /// Indices of the nodes that stopped sending heartbeats before running
+    pub failed_to_start: Vec<u16>,
+    /// Each registered node, by node index
+    pub nodes: Vec<NodeStatus<KEY>>,
+}
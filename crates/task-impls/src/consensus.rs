@@ -1,4 +1,7 @@
-use crate::events::HotShotEvent;
+use crate::{
+    events::HotShotEvent,
+    metrics::{observe_elapsed, ConsensusTaskMetrics, VoteCollectionMetrics},
+};
 use async_compatibility_layer::art::{async_sleep, async_spawn};
 use async_lock::{RwLock, RwLockUpgradableReadGuard};
 #[cfg(async_executor_impl = "async-std")]
@@ -42,6 +45,7 @@ use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
     sync::Arc,
+    time::Instant,
 };
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
@@ -127,6 +131,12 @@ pub struct ConsensusTaskState<
     // ED Should replace this with config information since we need it anyway
     /// The node's id
     pub id: u64,
+
+    /// The view whose quorum votes we collect as the next leader, and since when we wait for them
+    pub votes_awaited: Option<(TYPES::Time, Instant)>,
+
+    /// Metrics of this task
+    pub metrics: ConsensusTaskMetrics,
 }
 
 /// State for the vote collection task.  This handles the building of a QC from a votes received
@@ -157,6 +167,10 @@ pub struct VoteCollectionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     pub event_stream: ChannelStream<HotShotEvent<TYPES>>,
     /// Node id
     pub id: u64,
+    /// When we started waiting for quorum votes for `cur_view`, if we know
+    pub votes_awaited_since: Option<Instant>,
+    /// Metrics of collecting votes
+    pub metrics: VoteCollectionMetrics,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TS for VoteCollectionTaskState<TYPES, I> {}
@@ -195,6 +209,10 @@ async fn vote_handle<TYPES: NodeType, I: NodeImplementation<TYPES>>(
                 }
                 Either::Right(qc) => {
                     debug!("QCFormed! {:?}", qc.view_number);
+                    state.metrics.certificates_formed.add(1);
+                    if let Some(start) = state.votes_awaited_since {
+                        observe_elapsed(&*state.metrics.time_to_certificate, start);
+                    }
                     state
                         .event_stream
                         .publish(HotShotEvent::QCFormed(either::Left(qc.clone())))
@@ -340,7 +358,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     block_header: proposal.block_header.clone(),
                     block_payload: None,
                     rejected: Vec::new(),
-                    timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                    timestamp: hotshot_types::simulation::timestamp_nanos(),
                     proposer_id: self.quorum_membership.get_leader(view).to_bytes(),
                 };
                 let vote = QuorumVote::<TYPES>::create_signed_vote(
@@ -400,7 +418,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     block_header: proposal.block_header.clone(),
                     block_payload: None,
                     rejected: Vec::new(),
-                    timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                    timestamp: hotshot_types::simulation::timestamp_nanos(),
                     proposer_id: self.quorum_membership.get_leader(view).to_bytes(),
                 };
 
@@ -494,6 +512,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
 
             if self.quorum_membership.get_leader(self.cur_view + 1) == self.public_key {
                 debug!("Polling for quorum votes for view {}", *self.cur_view);
+                self.votes_awaited = Some((self.cur_view, Instant::now()));
                 self.quorum_network
                     .inject_consensus_info(ConsensusIntentEvent::PollForVotes(*self.cur_view))
                     .await;
@@ -554,6 +573,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     debug!("Proposal is from an older view {:?}", proposal.data.clone());
                     return;
                 }
+                self.metrics.proposals_received.add(1);
 
                 let view_leader_key = self.quorum_membership.get_leader(view);
                 if view_leader_key != sender {
@@ -593,7 +613,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 // NOTE: We could update our view with a valid TC but invalid QC, but that is not what we do here
                 self.update_view(view).await;
 
-                self.current_proposal = Some(proposal.data.clone());
+                // Only a proposal that passes the checks below may be voted on, even once its DAC
+                // arrives
+                self.current_proposal = None;
 
                 let consensus = self.consensus.upgradable_read().await;
 
@@ -622,7 +644,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         block_header: proposal.data.block_header,
                         block_payload: None,
                         rejected: Vec::new(),
-                        timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                        timestamp: hotshot_types::simulation::timestamp_nanos(),
                         proposer_id: sender.to_bytes(),
                     };
 
@@ -644,10 +666,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     view_number: view,
                     justify_qc: justify_qc.clone(),
                     parent_commitment,
-                    block_header: proposal.data.block_header,
+                    block_header: proposal.data.block_header.clone(),
                     block_payload: None,
                     rejected: Vec::new(),
-                    timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                    timestamp: hotshot_types::simulation::timestamp_nanos(),
                     proposer_id: sender.to_bytes(),
                 };
                 let leaf_commitment = leaf.commit();
@@ -675,18 +697,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         leaf.view_number != consensus.locked_view
                     },
                 );
+                // A proposal that doesn't extend the locked leaf may still be voted for on liveness
                 let safety_check = outcome.is_ok();
-                if let Err(e) = outcome {
-                    self.api.send_view_error(view, Arc::new(e)).await;
-                    return;
-                }
 
                 // Skip if both saftey and liveness checks fail.
                 if !safety_check && !liveness_check {
                     error!("Failed safety check and liveness check");
+                    if let Err(e) = outcome {
+                        self.api.send_view_error(view, Arc::new(e)).await;
+                    }
                     return;
                 }
 
+                self.current_proposal = Some(proposal.data.clone());
+
                 let high_qc = leaf.justify_qc.clone();
                 let mut new_anchor_view = consensus.last_decided_view;
                 let mut new_locked_view = consensus.locked_view;
@@ -877,6 +901,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     return;
                 }
 
+                let votes_awaited_since = self
+                    .votes_awaited
+                    .filter(|(awaited_view, _)| *awaited_view == vote.get_view_number())
+                    .map(|(_, since)| since);
+                if let Some(start) = votes_awaited_since {
+                    observe_elapsed(&*self.metrics.votes.vote_latency, start);
+                }
+
                 let handle_event = HandleEvent(Arc::new(move |event, state| {
                     async move { vote_handle(state, event).await }.boxed()
                 }));
@@ -921,6 +953,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         cur_view: vote.get_view_number(),
                         event_stream: self.event_stream.clone(),
                         id: self.id,
+                        votes_awaited_since,
+                        metrics: self.metrics.votes.clone(),
                     };
                     let name = "Quorum Vote Collection";
                     let filter = FilterEvent(Arc::new(|event| {
@@ -1013,6 +1047,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         cur_view: vote.get_view_number(),
                         event_stream: self.event_stream.clone(),
                         id: self.id,
+                        votes_awaited_since: self
+                            .votes_awaited
+                            .filter(|(awaited_view, _)| *awaited_view == vote.get_view_number())
+                            .map(|(_, since)| since),
+                        metrics: self.metrics.votes.clone(),
                     };
                     let name = "Quorum Vote Collection";
                     let filter = FilterEvent(Arc::new(|event| {
@@ -1247,7 +1286,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 ),
                 block_payload: None,
                 rejected: vec![],
-                timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+                timestamp: hotshot_types::simulation::timestamp_nanos(),
                 proposer_id: self.api.public_key().to_bytes(),
             };
 
@@ -1,4 +1,7 @@
-use crate::events::HotShotEvent;
+use crate::{
+    events::HotShotEvent,
+    metrics::{observe_elapsed, DAMetrics, VoteCollectionMetrics},
+};
 use async_compatibility_layer::art::async_spawn;
 use async_lock::RwLock;
 
@@ -33,7 +36,7 @@ use hotshot_types::{
 };
 
 use snafu::Snafu;
-use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Instant};
 use tracing::{debug, error, instrument, warn};
 
 #[derive(Snafu, Debug)]
@@ -77,6 +80,15 @@ pub struct DATaskState<
 
     /// This state's ID
     pub id: u64,
+
+    /// When `cur_view` started
+    pub view_start: Instant,
+
+    /// The view we lead and are waiting for DA votes for, and since when
+    pub votes_awaited: Option<(TYPES::Time, Instant)>,
+
+    /// Metrics of this task
+    pub metrics: DAMetrics,
 }
 
 /// Struct to maintain DA Vote Collection task state
@@ -101,6 +113,10 @@ pub struct DAVoteCollectionTaskState<TYPES: NodeType, I: NodeImplementation<TYPE
     pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
     /// the id of this task state
     pub id: u64,
+    /// When we started waiting for votes for `cur_view`, if we know
+    pub votes_awaited_since: Option<Instant>,
+    /// Metrics of collecting votes
+    pub metrics: VoteCollectionMetrics,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TS for DAVoteCollectionTaskState<TYPES, I> {}
@@ -133,6 +149,10 @@ async fn vote_handle<TYPES: NodeType, I: NodeImplementation<TYPES>>(
 
                 Right(dac) => {
                     debug!("Sending DAC! {:?}", dac.view_number);
+                    state.metrics.certificates_formed.add(1);
+                    if let Some(start) = state.votes_awaited_since {
+                        observe_elapsed(&*state.metrics.time_to_certificate, start);
+                    }
                     state
                         .event_stream
                         .publish(HotShotEvent::DACSend(dac.clone(), state.public_key.clone()))
@@ -196,10 +216,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     return None;
                 }
 
-                debug!(
-                    "Got a DA block with {} transactions!",
-                    proposal.data.block_payload.transaction_commitments().len()
-                );
+                let num_transactions = proposal.data.block_payload.transaction_commitments().len();
+                debug!("Got a DA block with {} transactions!", num_transactions);
+                self.metrics.proposals_received.add(1);
+                #[allow(clippy::cast_precision_loss)]
+                let num_transactions = num_transactions as f64;
+                self.metrics
+                    .transactions_per_proposal
+                    .add_point(num_transactions);
                 let payload_commitment = proposal.data.block_payload.commit();
 
                 // ED Is this the right leader?
@@ -263,6 +287,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     return None;
                 }
 
+                let votes_awaited_since = self
+                    .votes_awaited
+                    .filter(|(awaited_view, _)| *awaited_view == view)
+                    .map(|(_, since)| since);
+                if let Some(start) = votes_awaited_since {
+                    observe_elapsed(&*self.metrics.votes.vote_latency, start);
+                }
+
                 let handle_event = HandleEvent(Arc::new(move |event, state| {
                     async move { vote_handle(state, event).await }.boxed()
                 }));
@@ -298,6 +330,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         da_network: self.da_network.clone(),
                         public_key: self.public_key.clone(),
                         private_key: self.private_key.clone(),
+                        votes_awaited_since,
+                        metrics: self.metrics.votes.clone(),
                     };
                     let name = "DA Vote Collection";
                     let filter = FilterEvent(Arc::new(|event| {
@@ -333,6 +367,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     error!("View changed by more than 1 going to view {:?}", view);
                 }
                 self.cur_view = view;
+                observe_elapsed(&*self.metrics.view_duration, self.view_start);
+                self.view_start = Instant::now();
 
                 // Inject view info into network
                 let is_da = self
@@ -363,6 +399,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     return None;
                 }
                 debug!("Polling for DA votes for view {}", *self.cur_view + 1);
+                self.votes_awaited = Some((self.cur_view + 1, Instant::now()));
 
                 // Start polling for DA votes for the "next view"
                 self.da_network
@@ -408,6 +445,29 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     .await;
             }
 
+            HotShotEvent::LeafDecided(leaf_chain) => {
+                // every node holding the block reports its transactions as decided, so the web
+                // server hears about it even if the block's leader has gone down. The server
+                // ignores commitments it already knows are decided.
+                for leaf in leaf_chain {
+                    if let Some(payload) = &leaf.block_payload {
+                        let commitments: Vec<Vec<u8>> = payload
+                            .transaction_commitments()
+                            .iter()
+                            .map(|commitment| commitment.as_ref().to_vec())
+                            .collect();
+                        if !commitments.is_empty() {
+                            self.da_network
+                                .inject_consensus_info(ConsensusIntentEvent::TransactionsDecided(
+                                    *leaf.view_number,
+                                    commitments,
+                                ))
+                                .await;
+                        }
+                    }
+                }
+            }
+
             HotShotEvent::Timeout(view) => {
                 self.da_network
                     .inject_consensus_info(ConsensusIntentEvent::CancelPollForVotes(*view))
@@ -435,6 +495,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 | HotShotEvent::BlockReady(_, _, _)
                 | HotShotEvent::Timeout(_)
                 | HotShotEvent::ViewChange(_)
+                | HotShotEvent::LeafDecided(_)
         )
     }
 }
@@ -15,9 +15,16 @@ use hotshot_types::{
     },
     traits::{node_implementation::NodeType, BlockPayload},
 };
+use serde::{Deserialize, Serialize};
 
 /// All of the possible events that can be passed between Sequecning `HotShot` tasks
-#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+///
+/// Events (de)serialize if the block payload's metadata does.
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Serialize",
+    deserialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Deserialize<'de>"
+))]
 pub enum HotShotEvent<TYPES: NodeType> {
     /// Shutdown the task
     Shutdown,
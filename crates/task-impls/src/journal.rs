@@ -0,0 +1,355 @@
+//! A journal of the events a node saw, and replay of a journal into a single task.
+//!
+//! A journal is a file of [`JournalEntry`]s, each encoded with
+//! [`bincode_opts`](hotshot_utils::bincode::bincode_opts) and prefixed by its length as a
+//! little-endian `u64`. Entries are buffered and flushed every [`FLUSH_EVERY`] entries, so a
+//! journal cut off mid-entry, as when the node dies, reads back up to its last whole entry.
+//!
+//! Journals hold [`HotShotEvent`]s, so only nodes whose block payload metadata (de)serializes can
+//! keep one.
+
+use crate::events::HotShotEvent;
+use async_compatibility_layer::art::async_spawn;
+use async_lock::Mutex;
+use bincode::Options;
+use futures::FutureExt;
+use hotshot_task::{
+    event_stream::{ChannelStream, EventStream},
+    task::{FilterEvent, HandleEvent, HotShotTaskCompleted, HotShotTaskTypes, TS},
+    task_impls::{HSTWithEvent, TaskBuilder},
+    task_launcher::TaskRunner,
+};
+use hotshot_types::{
+    data::Leaf,
+    event::{Event, EventType},
+    simple_certificate::QuorumCertificate,
+    simulation::timestamp_nanos,
+    traits::{node_implementation::NodeType, BlockPayload},
+};
+use hotshot_utils::bincode::bincode_opts;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+use tracing::warn;
+
+/// How many entries a [`JournalWriter`] buffers before writing them to the file
+pub const FLUSH_EVERY: usize = 64;
+
+/// Errors writing or reading a journal
+#[derive(Debug, Snafu)]
+pub enum JournalError {
+    /// The journal file couldn't be written or read
+    Io {
+        /// The underlying error
+        source: io::Error,
+    },
+    /// An entry couldn't be encoded or decoded
+    Encoding {
+        /// The underlying error
+        source: bincode::Error,
+    },
+}
+
+/// An event a node emitted to the application, as kept in a journal
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = "", serialize = ""))]
+pub enum ExternalEvent<TYPES: NodeType> {
+    /// A view encountered an error and was interrupted
+    Error {
+        /// The error, as displayed
+        error: String,
+    },
+    /// A new decision event was issued
+    Decide {
+        /// The chain of leaves that were committed, newest first
+        leaf_chain: Vec<Leaf<TYPES>>,
+        /// The QC signing the most recent leaf in `leaf_chain`
+        qc: QuorumCertificate<TYPES>,
+        /// The number of transactions in the block, if known
+        block_size: Option<u64>,
+    },
+    /// A replica task was canceled by a timeout interrupt
+    ReplicaViewTimeout {
+        /// The view that timed out
+        view_number: TYPES::Time,
+    },
+    /// A next leader task was canceled by a timeout interrupt
+    NextLeaderViewTimeout {
+        /// The view that timed out
+        view_number: TYPES::Time,
+    },
+    /// The view has finished
+    ViewFinished {
+        /// The view number that has just finished
+        view_number: TYPES::Time,
+    },
+    /// An event this journal doesn't know the structure of, as debug printed
+    Other(String),
+}
+
+impl<TYPES: NodeType> From<&EventType<TYPES>> for ExternalEvent<TYPES> {
+    fn from(event: &EventType<TYPES>) -> Self {
+        match event {
+            EventType::Error { error } => Self::Error {
+                error: error.to_string(),
+            },
+            EventType::Decide {
+                leaf_chain,
+                qc,
+                block_size,
+            } => Self::Decide {
+                leaf_chain: leaf_chain.as_ref().clone(),
+                qc: qc.as_ref().clone(),
+                block_size: *block_size,
+            },
+            EventType::ReplicaViewTimeout { view_number } => Self::ReplicaViewTimeout {
+                view_number: *view_number,
+            },
+            EventType::NextLeaderViewTimeout { view_number } => Self::NextLeaderViewTimeout {
+                view_number: *view_number,
+            },
+            EventType::ViewFinished { view_number } => Self::ViewFinished {
+                view_number: *view_number,
+            },
+            event => Self::Other(format!("{event:?}")),
+        }
+    }
+}
+
+/// An event kept in a journal
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Serialize",
+    deserialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Deserialize<'de>"
+))]
+pub enum JournalEvent<TYPES: NodeType> {
+    /// An event passed between the tasks
+    Internal(HotShotEvent<TYPES>),
+    /// An event emitted to the application
+    External {
+        /// The view the event originates from
+        view_number: TYPES::Time,
+        /// The event
+        event: ExternalEvent<TYPES>,
+    },
+}
+
+impl<TYPES: NodeType> From<HotShotEvent<TYPES>> for JournalEvent<TYPES> {
+    fn from(event: HotShotEvent<TYPES>) -> Self {
+        Self::Internal(event)
+    }
+}
+
+impl<TYPES: NodeType> From<Event<TYPES>> for JournalEvent<TYPES> {
+    fn from(event: Event<TYPES>) -> Self {
+        Self::External {
+            view_number: event.view_number,
+            event: (&event.event).into(),
+        }
+    }
+}
+
+/// An entry of a journal: an event and when it was seen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Serialize",
+    deserialize = "<TYPES::BlockPayload as BlockPayload>::Metadata: Deserialize<'de>"
+))]
+pub struct JournalEntry<TYPES: NodeType> {
+    /// When the event was seen, since the UNIX epoch
+    pub timestamp: Duration,
+    /// The event
+    pub event: JournalEvent<TYPES>,
+}
+
+/// Writes [`JournalEntry`]s to a journal file. Entries are buffered, and written to the file every
+/// [`FLUSH_EVERY`] entries, on [`flush`](Self::flush) and when the writer is dropped.
+#[derive(Debug)]
+pub struct JournalWriter {
+    /// the journal file
+    writer: BufWriter<File>,
+    /// entries recorded since the last flush
+    unflushed: usize,
+}
+
+impl JournalWriter {
+    /// Start a journal at `path`, replacing any file there
+    ///
+    /// # Errors
+    /// If the file can't be created
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, JournalError> {
+        let file = File::create(path).context(IoSnafu)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            unflushed: 0,
+        })
+    }
+
+    /// Append `event` to the journal, timestamped with the current time
+    ///
+    /// # Errors
+    /// If the event can't be encoded or written
+    pub fn record<TYPES: NodeType>(
+        &mut self,
+        event: impl Into<JournalEvent<TYPES>>,
+    ) -> Result<(), JournalError>
+    where
+        <TYPES::BlockPayload as BlockPayload>::Metadata: Serialize,
+    {
+        let entry = JournalEntry {
+            timestamp: Duration::from_nanos(u64::try_from(timestamp_nanos()).unwrap_or_default()),
+            event: event.into(),
+        };
+        let bytes = bincode_opts().serialize(&entry).context(EncodingSnafu)?;
+        self.writer
+            .write_all(&(bytes.len() as u64).to_le_bytes())
+            .context(IoSnafu)?;
+        self.writer.write_all(&bytes).context(IoSnafu)?;
+        self.unflushed += 1;
+        if self.unflushed >= FLUSH_EVERY {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write every buffered entry to the file
+    ///
+    /// # Errors
+    /// If the entries can't be written
+    pub fn flush(&mut self) -> Result<(), JournalError> {
+        self.unflushed = 0;
+        self.writer.flush().context(IoSnafu)
+    }
+}
+
+/// Read every entry of the journal at `path`, in the order they were written. A journal cut off
+/// mid-entry reads back up to its last whole entry.
+///
+/// # Errors
+/// If the file can't be read, or one of its entries can't be decoded
+pub fn read_journal<TYPES: NodeType>(
+    path: impl AsRef<Path>,
+) -> Result<Vec<JournalEntry<TYPES>>, JournalError>
+where
+    <TYPES::BlockPayload as BlockPayload>::Metadata: DeserializeOwned,
+{
+    let mut bytes = Vec::new();
+    File::open(path)
+        .context(IoSnafu)?
+        .read_to_end(&mut bytes)
+        .context(IoSnafu)?;
+
+    let mut entries = Vec::new();
+    let mut remaining = bytes.as_slice();
+    while !remaining.is_empty() {
+        let mut len = [0u8; 8];
+        if remaining.read_exact(&mut len).is_err() {
+            warn!("Journal ends mid-entry, after {} entries", entries.len());
+            break;
+        }
+        let len = usize::try_from(u64::from_le_bytes(len)).unwrap_or(usize::MAX);
+        if remaining.len() < len {
+            warn!("Journal ends mid-entry, after {} entries", entries.len());
+            break;
+        }
+        let (entry, rest) = remaining.split_at(len);
+        entries.push(bincode_opts().deserialize(entry).context(EncodingSnafu)?);
+        remaining = rest;
+    }
+    Ok(entries)
+}
+
+/// State of the task recording what a replayed task sees
+struct ReplayRecorderState<TYPES: NodeType> {
+    /// the events seen so far
+    events: Arc<Mutex<Vec<HotShotEvent<TYPES>>>>,
+}
+
+impl<TYPES: NodeType> TS for ReplayRecorderState<TYPES> {}
+
+/// Error type for the replay recorder task
+#[derive(Snafu, Debug)]
+pub struct ReplayRecorderTaskError {}
+
+/// Type alias for the replay recorder task
+type ReplayRecorderTaskTypes<TYPES> = HSTWithEvent<
+    ReplayRecorderTaskError,
+    HotShotEvent<TYPES>,
+    ChannelStream<HotShotEvent<TYPES>>,
+    ReplayRecorderState<TYPES>,
+>;
+
+/// Replay the internal events of `journal` into the task built by `build_fn`, one at a time and in
+/// the order they were recorded, then shut it down.
+///
+/// Only the events `input` accepts are replayed. It should leave out the events the task publishes
+/// itself, which it will publish again as it handles the replayed ones.
+///
+/// Returns every event seen on the event stream while replaying, the replayed ones included, for
+/// comparison with the journal.
+pub async fn replay_journal<TYPES, Fut>(
+    journal: Vec<JournalEntry<TYPES>>,
+    input: impl Fn(&HotShotEvent<TYPES>) -> bool,
+    build_fn: impl FnOnce(TaskRunner, ChannelStream<HotShotEvent<TYPES>>) -> Fut,
+) -> Vec<HotShotEvent<TYPES>>
+where
+    TYPES: NodeType,
+    Fut: Future<Output = TaskRunner>,
+{
+    let task_runner = TaskRunner::new();
+    let registry = task_runner.registry.clone();
+    let event_stream = ChannelStream::new();
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let state = ReplayRecorderState {
+        events: events.clone(),
+    };
+    let handler = HandleEvent(Arc::new(move |event, state: ReplayRecorderState<TYPES>| {
+        async move {
+            let shutdown = matches!(event, HotShotEvent::Shutdown);
+            state.events.lock().await.push(event);
+            if shutdown {
+                (Some(HotShotTaskCompleted::ShutDown), state)
+            } else {
+                (None, state)
+            }
+        }
+        .boxed()
+    }));
+    let name = "Replay Recorder";
+    let builder = TaskBuilder::<ReplayRecorderTaskTypes<TYPES>>::new(name.to_string())
+        .register_event_stream(event_stream.clone(), FilterEvent::default())
+        .await
+        .register_registry(&mut registry.clone())
+        .await
+        .register_state(state)
+        .register_event_handler(handler);
+    // impossible for unwrap to fail
+    // we *just* registered
+    let id = builder.get_task_id().unwrap();
+    let task = ReplayRecorderTaskTypes::build(builder).launch();
+
+    let task_runner = task_runner.add_task(id, name.to_string(), task);
+    let task_runner = build_fn(task_runner, event_stream.clone()).await;
+    let runner = async_spawn(async move { task_runner.launch().await });
+
+    for entry in journal {
+        if let JournalEvent::Internal(event) = entry.event {
+            if !matches!(event, HotShotEvent::Shutdown) && input(&event) {
+                event_stream.publish(event).await;
+            }
+        }
+    }
+    event_stream.publish(HotShotEvent::Shutdown).await;
+
+    let _ = runner.await;
+    let events = events.lock().await;
+    events.clone()
+}
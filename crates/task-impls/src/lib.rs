@@ -26,9 +26,15 @@ pub mod events;
 /// The task which implements the network.
 pub mod network;
 
+/// Metrics recorded by the tasks
+pub mod metrics;
+
 /// Defines the types to run unit tests for a task.
 pub mod harness;
 
+/// A journal of the events a node saw, and replay of a journal into a single task
+pub mod journal;
+
 /// The task which implements view synchronization
 pub mod view_sync;
 
@@ -0,0 +1,244 @@
+//! Metrics recorded by the tasks, each created in the task's own subgroup of the consensus metrics
+
+use crate::view_sync::ViewSyncPhase;
+use hotshot_types::{
+    message::MessagePurpose,
+    traits::metrics::{Counter, Gauge, Histogram, Metrics},
+};
+use std::{collections::HashMap, time::Instant};
+
+/// The unit durations are recorded in
+const SECONDS: &str = "seconds";
+
+/// Record the seconds since `start` in `histogram`
+pub(crate) fn observe_elapsed(histogram: &dyn Histogram, start: Instant) {
+    histogram.add_point(start.elapsed().as_secs_f64());
+}
+
+/// Metrics of a task collecting votes into certificates as the leader of a view
+#[derive(Clone, Debug)]
+pub struct VoteCollectionMetrics {
+    /// Seconds from when we started waiting for a view's votes to when each of them arrived
+    pub vote_latency: Box<dyn Histogram>,
+    /// Seconds from when we started waiting for a view's votes to when they formed a certificate
+    pub time_to_certificate: Box<dyn Histogram>,
+    /// Certificates formed from the votes we collected
+    pub certificates_formed: Box<dyn Counter>,
+}
+
+impl VoteCollectionMetrics {
+    /// Create the vote collection metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            vote_latency: metrics
+                .create_histogram(String::from("vote_latency"), Some(SECONDS.to_string())),
+            time_to_certificate: metrics.create_histogram(
+                String::from("time_to_certificate"),
+                Some(SECONDS.to_string()),
+            ),
+            certificates_formed: metrics.create_counter(String::from("certificates_formed"), None),
+        }
+    }
+}
+
+/// Metrics of the consensus task
+#[derive(Clone, Debug)]
+pub struct ConsensusTaskMetrics {
+    /// Quorum proposals received for the current or a later view
+    pub proposals_received: Box<dyn Counter>,
+    /// Collection of quorum votes into QCs
+    pub votes: VoteCollectionMetrics,
+}
+
+impl ConsensusTaskMetrics {
+    /// Create the consensus task metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            proposals_received: metrics.create_counter(String::from("proposals_received"), None),
+            votes: VoteCollectionMetrics::new(metrics),
+        }
+    }
+}
+
+/// Metrics of the DA task
+#[derive(Clone, Debug)]
+pub struct DAMetrics {
+    /// Seconds spent in each view, measured between view changes
+    pub view_duration: Box<dyn Histogram>,
+    /// DA proposals received
+    pub proposals_received: Box<dyn Counter>,
+    /// Transactions in each DA proposal received
+    pub transactions_per_proposal: Box<dyn Histogram>,
+    /// Collection of DA votes into DA certificates
+    pub votes: VoteCollectionMetrics,
+}
+
+impl DAMetrics {
+    /// Create the DA task metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            view_duration: metrics
+                .create_histogram(String::from("view_duration"), Some(SECONDS.to_string())),
+            proposals_received: metrics.create_counter(String::from("proposals_received"), None),
+            transactions_per_proposal: metrics
+                .create_histogram(String::from("transactions_per_proposal"), None),
+            votes: VoteCollectionMetrics::new(metrics),
+        }
+    }
+}
+
+/// Metrics of the VID task
+#[derive(Clone, Debug)]
+pub struct VIDMetrics {
+    /// VID disperse messages received
+    pub disperses_received: Box<dyn Counter>,
+    /// Collection of VID votes into VID certificates
+    pub votes: VoteCollectionMetrics,
+}
+
+impl VIDMetrics {
+    /// Create the VID task metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            disperses_received: metrics.create_counter(String::from("disperses_received"), None),
+            votes: VoteCollectionMetrics::new(metrics),
+        }
+    }
+}
+
+/// Metrics of the view sync task
+#[derive(Clone, Debug)]
+pub struct ViewSyncMetrics {
+    /// Times view sync was triggered by consecutive timeouts
+    pub triggers: Box<dyn Counter>,
+    /// Times a view sync replica reached the pre-commit phase
+    pub precommit_phases: Box<dyn Counter>,
+    /// Times a view sync replica reached the commit phase
+    pub commit_phases: Box<dyn Counter>,
+    /// Times a view sync replica reached the finalize phase
+    pub finalize_phases: Box<dyn Counter>,
+    /// Times a view sync round timed out and moved on to the next relay
+    pub relay_timeouts: Box<dyn Counter>,
+    /// Seconds from a view sync replica starting to it seeing a finalize certificate
+    pub duration: Box<dyn Histogram>,
+}
+
+impl ViewSyncMetrics {
+    /// Create the view sync task metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            triggers: metrics.create_counter(String::from("triggers"), None),
+            precommit_phases: metrics.create_counter(String::from("precommit_phases"), None),
+            commit_phases: metrics.create_counter(String::from("commit_phases"), None),
+            finalize_phases: metrics.create_counter(String::from("finalize_phases"), None),
+            relay_timeouts: metrics.create_counter(String::from("relay_timeouts"), None),
+            duration: metrics.create_histogram(String::from("duration"), Some(SECONDS.to_string())),
+        }
+    }
+
+    /// Count a replica reaching `phase`
+    pub fn phase_reached(&self, phase: &ViewSyncPhase) {
+        match phase {
+            ViewSyncPhase::None => {}
+            ViewSyncPhase::PreCommit => self.precommit_phases.add(1),
+            ViewSyncPhase::Commit => self.commit_phases.add(1),
+            ViewSyncPhase::Finalize => self.finalize_phases.add(1),
+        }
+    }
+}
+
+/// Metrics of the transaction task
+#[derive(Clone, Debug)]
+pub struct TransactionMetrics {
+    /// Transactions in the mempool, waiting to be included in a block
+    pub mempool_size: Box<dyn Gauge>,
+    /// Transactions received that weren't already in the mempool
+    pub transactions_received: Box<dyn Counter>,
+    /// Transactions of the mempool included in a decided block
+    pub transactions_decided: Box<dyn Counter>,
+    /// Transactions in each block we built
+    pub transactions_per_block: Box<dyn Histogram>,
+    /// Seconds spent waiting for transactions to build each block from
+    pub block_wait_duration: Box<dyn Histogram>,
+    /// Seconds spent computing the VID disperse of each block
+    pub vid_disperse_duration: Box<dyn Histogram>,
+}
+
+impl TransactionMetrics {
+    /// Create the transaction task metrics in `metrics`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            mempool_size: metrics.create_gauge(String::from("mempool_size"), None),
+            transactions_received: metrics
+                .create_counter(String::from("transactions_received"), None),
+            transactions_decided: metrics
+                .create_counter(String::from("transactions_decided"), None),
+            transactions_per_block: metrics
+                .create_histogram(String::from("transactions_per_block"), None),
+            block_wait_duration: metrics.create_histogram(
+                String::from("block_wait_duration"),
+                Some(SECONDS.to_string()),
+            ),
+            vid_disperse_duration: metrics.create_histogram(
+                String::from("vid_disperse_duration"),
+                Some(SECONDS.to_string()),
+            ),
+        }
+    }
+}
+
+/// Metrics of a network task: how many messages it handled for each [`MessagePurpose`]. The bytes
+/// behind them are counted by the network itself, which already has them serialized.
+#[derive(Clone, Debug)]
+pub struct NetworkTaskMetrics {
+    /// purpose -> messages
+    purposes: HashMap<MessagePurpose, Box<dyn Counter>>,
+    /// Messages that failed to send
+    pub failed_to_send: Box<dyn Counter>,
+}
+
+impl NetworkTaskMetrics {
+    /// Create the network task metrics in `metrics`, with a subgroup per [`MessagePurpose`]
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        let purposes = [
+            (MessagePurpose::Proposal, "proposal"),
+            (MessagePurpose::CurrentProposal, "current_proposal"),
+            (MessagePurpose::Vote, "vote"),
+            (MessagePurpose::ViewSyncVote, "view_sync_vote"),
+            (MessagePurpose::ViewSyncProposal, "view_sync_proposal"),
+            (MessagePurpose::DAC, "dac"),
+            (MessagePurpose::Internal, "internal"),
+            (MessagePurpose::Data, "data"),
+            (MessagePurpose::VidDisperse, "vid_disperse"),
+            (MessagePurpose::VidVote, "vid_vote"),
+            (MessagePurpose::VidCert, "vid_cert"),
+        ]
+        .into_iter()
+        .map(|(purpose, name)| {
+            let group = metrics.subgroup(name.to_string());
+            (
+                purpose,
+                group.create_counter(String::from("messages"), None),
+            )
+        })
+        .collect();
+        Self {
+            purposes,
+            failed_to_send: metrics.create_counter(String::from("failed_to_send"), None),
+        }
+    }
+
+    /// Count a message for `purpose`
+    pub fn record(&self, purpose: MessagePurpose) {
+        if let Some(messages) = self.purposes.get(&purpose) {
+            messages.add(1);
+        }
+    }
+}
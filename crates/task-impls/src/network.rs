@@ -1,4 +1,4 @@
-use crate::events::HotShotEvent;
+use crate::{events::HotShotEvent, metrics::NetworkTaskMetrics};
 use either::Either::{self, Left, Right};
 use hotshot_task::{
     event_stream::{ChannelStream, EventStream},
@@ -13,7 +13,7 @@ use hotshot_types::{
     },
     traits::{
         election::Membership,
-        network::{CommunicationChannel, TransmitType},
+        network::{CommunicationChannel, TransmitType, ViewMessage},
         node_implementation::NodeType,
     },
     vote::{HasViewNumber, Vote},
@@ -40,6 +40,8 @@ pub enum NetworkTaskKind {
 pub struct NetworkMessageTaskState<TYPES: NodeType> {
     /// event stream (used for publishing)
     pub event_stream: ChannelStream<HotShotEvent<TYPES>>,
+    /// messages received
+    pub metrics: NetworkTaskMetrics,
 }
 
 impl<TYPES: NodeType> TS for NetworkMessageTaskState<TYPES> {}
@@ -50,6 +52,7 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
         // We will send only one event for a vector of transactions.
         let mut transactions = Vec::new();
         for message in messages {
+            self.metrics.record(message.purpose());
             let sender = message.sender;
             match message.kind {
                 MessageKind::Consensus(consensus_message) => {
@@ -140,6 +143,8 @@ pub struct NetworkEventTaskState<TYPES: NodeType, COMMCHANNEL: CommunicationChan
     pub event_stream: ChannelStream<HotShotEvent<TYPES>>,
     /// view number
     pub view: TYPES::Time,
+    /// messages and bytes sent
+    pub metrics: NetworkTaskMetrics,
     // TODO ED Need to add exchange so we can get the recipient key and our own key?
 }
 
@@ -307,6 +312,7 @@ impl<TYPES: NodeType, COMMCHANNEL: CommunicationChannel<TYPES>>
             sender,
             kind: message_kind,
         };
+        let purpose = message.purpose();
         let transmit_result = match transmit_type {
             TransmitType::Direct => {
                 self.channel
@@ -317,8 +323,11 @@ impl<TYPES: NodeType, COMMCHANNEL: CommunicationChannel<TYPES>>
         };
 
         match transmit_result {
-            Ok(()) => {}
-            Err(e) => error!("Failed to send message from network task: {:?}", e),
+            Ok(()) => self.metrics.record(purpose),
+            Err(e) => {
+                self.metrics.failed_to_send.add(1);
+                error!("Failed to send message from network task: {:?}", e);
+            }
         }
 
         None
@@ -1,4 +1,7 @@
-use crate::events::HotShotEvent;
+use crate::{
+    events::HotShotEvent,
+    metrics::{observe_elapsed, TransactionMetrics},
+};
 use async_compatibility_layer::{
     art::async_timeout,
     async_primitives::subscribable_rwlock::{ReadView, SubscribableRwLock},
@@ -80,6 +83,8 @@ pub struct TransactionTaskState<
     pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
     /// This state's ID
     pub id: u64,
+    /// Metrics of this task
+    pub metrics: TransactionMetrics,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> + 'static>
@@ -104,6 +109,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                             if !self.seen_transactions.remove(&transaction.commit())
                                 && txns.insert(transaction.commit(), transaction).is_none()
                             {
+                                self.metrics.transactions_received.add(1);
                                 consensus.metrics.outstanding_transactions.update(1);
                                 consensus
                                     .metrics
@@ -114,6 +120,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                                     }));
                             }
                         }
+                        self.metrics.mempool_size.set(txns.len());
                     })
                     .await;
 
@@ -154,8 +161,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                                 }
                             })
                             .collect();
+                        self.metrics.mempool_size.set(txns.len());
                     })
                     .await;
+                self.metrics
+                    .transactions_decided
+                    .add(usize::try_from(included_txn_count).unwrap_or_default());
 
                 consensus
                     .metrics
@@ -211,7 +222,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
 
                 // TODO (Keyao) Determine whether to allow empty blocks.
                 // <https://github.com/EspressoSystems/HotShot/issues/1822>
+                let wait_start = Instant::now();
                 let txns = self.wait_for_transactions(parent_leaf).await?;
+                observe_elapsed(&*self.metrics.block_wait_duration, wait_start);
+                #[allow(clippy::cast_precision_loss)]
+                let num_txns = txns.len() as f64;
+                self.metrics.transactions_per_block.add_point(num_txns);
                 let (payload, metadata) =
                     match <TYPES::BlockPayload as BlockPayload>::from_transactions(txns) {
                         Ok((payload, metadata)) => (payload, metadata),
@@ -233,9 +249,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 // changes.
                 // TODO <https://github.com/EspressoSystems/HotShot/issues/1693>
                 let vid = VidScheme::new(NUM_CHUNKS, NUM_STORAGE_NODES, &srs).unwrap();
+                let disperse_start = Instant::now();
                 let vid_disperse = vid
                     .disperse(encoded_txns.into_iter().collect::<Vec<u8>>())
                     .unwrap();
+                observe_elapsed(&*self.metrics.vid_disperse_duration, disperse_start);
 
                 // TODO never clone a block
                 // https://github.com/EspressoSystems/HotShot/issues/1858
@@ -284,7 +302,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
         &self,
         _parent_leaf: Leaf<TYPES>,
     ) -> Option<Vec<TYPES::Transaction>> {
-        let task_start_time = Instant::now();
+        // virtual in simulations, like the timeout below
+        let task_start_time = hotshot_types::simulation::Instant::now();
 
         // TODO (Keyao) Investigate the use of transaction hash
         // <https://github.com/EspressoSystems/HotShot/issues/1811>
@@ -333,7 +352,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
         let all_txns = self.transactions.cloned().await;
         // TODO (Keyao) Investigate the use of transaction hash
         // <https://github.com/EspressoSystems/HotShot/issues/1811>
-        let txns: Vec<TYPES::Transaction> = all_txns.values().cloned().collect();
+        // in commitment order, so that the same transactions make the same block on every run
+        let mut txns: Vec<_> = all_txns.into_iter().collect();
+        txns.sort_by(|(a, _), (b, _)| <[u8]>::cmp(a.as_ref(), b.as_ref()));
+        let txns: Vec<TYPES::Transaction> = txns.into_iter().map(|(_, txn)| txn).collect();
         // let txns: Vec<TYPES::Transaction> = all_txns
         //     .iter()
         //     .filter_map(|(txn_hash, txn)| {
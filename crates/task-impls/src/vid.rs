@@ -1,4 +1,7 @@
-use crate::events::HotShotEvent;
+use crate::{
+    events::HotShotEvent,
+    metrics::{observe_elapsed, VIDMetrics, VoteCollectionMetrics},
+};
 use async_compatibility_layer::art::async_spawn;
 use async_lock::RwLock;
 
@@ -32,7 +35,7 @@ use hotshot_types::{
 
 use snafu::Snafu;
 use std::marker::PhantomData;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 use tracing::{debug, error, instrument, warn};
 
 #[derive(Snafu, Debug)]
@@ -71,6 +74,12 @@ pub struct VIDTaskState<
 
     /// This state's ID
     pub id: u64,
+
+    /// The view we lead and are waiting for VID votes for, and since when
+    pub votes_awaited: Option<(TYPES::Time, Instant)>,
+
+    /// Metrics of this task
+    pub metrics: VIDMetrics,
 }
 
 /// Struct to maintain VID Vote Collection task state
@@ -95,6 +104,10 @@ pub struct VIDVoteCollectionTaskState<TYPES: NodeType, I: NodeImplementation<TYP
     pub event_stream: ChannelStream<HotShotEvent<TYPES>>,
     /// the id of this task state
     pub id: u64,
+    /// When we started waiting for votes for `cur_view`, if we know
+    pub votes_awaited_since: Option<Instant>,
+    /// Metrics of collecting votes
+    pub metrics: VoteCollectionMetrics,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TS for VIDVoteCollectionTaskState<TYPES, I> {}
@@ -133,6 +146,10 @@ where
 
                 Right(vid_cert) => {
                     debug!("Sending VID cert! {:?}", vid_cert.view_number);
+                    state.metrics.certificates_formed.add(1);
+                    if let Some(start) = state.votes_awaited_since {
+                        observe_elapsed(&*state.metrics.time_to_certificate, start);
+                    }
                     state
                         .event_stream
                         .publish(HotShotEvent::VidCertSend(
@@ -188,6 +205,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     return None;
                 }
 
+                let votes_awaited_since = self
+                    .votes_awaited
+                    .filter(|(awaited_view, _)| *awaited_view == view)
+                    .map(|(_, since)| since);
+                if let Some(start) = votes_awaited_since {
+                    observe_elapsed(&*self.metrics.votes.vote_latency, start);
+                }
+
                 let handle_event = HandleEvent(Arc::new(move |event, state| {
                     async move { vote_handle(state, event).await }.boxed()
                 }));
@@ -222,6 +247,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                         cur_view: view,
                         event_stream: self.event_stream.clone(),
                         id: self.id,
+                        votes_awaited_since,
+                        metrics: self.metrics.votes.clone(),
                     };
                     let name = "VID Vote Collection";
                     let filter = FilterEvent(Arc::new(|event| {
@@ -274,6 +301,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 }
 
                 debug!("VID disperse data is fresh.");
+                self.metrics.disperses_received.add(1);
                 let payload_commitment = disperse.data.payload_commitment;
 
                 // ED Is this the right leader?
@@ -368,6 +396,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 }
 
                 // Start polling for VID votes for the "next view"
+                self.votes_awaited = Some((self.cur_view + 1, Instant::now()));
                 self.network
                     .inject_consensus_info(ConsensusIntentEvent::PollForVIDVotes(
                         *self.cur_view + 1,
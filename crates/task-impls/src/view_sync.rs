@@ -1,5 +1,8 @@
 #![allow(clippy::module_name_repetitions)]
-use crate::events::HotShotEvent;
+use crate::{
+    events::HotShotEvent,
+    metrics::{observe_elapsed, ViewSyncMetrics},
+};
 use async_compatibility_layer::art::{async_sleep, async_spawn};
 
 use either::Either::{self, Left, Right};
@@ -34,10 +37,16 @@ use hotshot_types::{
         state::ConsensusTime,
     },
 };
+use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::{debug, error, info, instrument};
-#[derive(PartialEq, PartialOrd, Clone, Debug, Eq, Hash)]
+#[derive(PartialEq, PartialOrd, Clone, Debug, Eq, Hash, Serialize, Deserialize)]
 /// Phases of view sync
 pub enum ViewSyncPhase {
     /// No phase; before the protocol has begun
@@ -102,6 +111,9 @@ pub struct ViewSyncTaskState<
 
     /// Last view we garbage collected old tasks
     pub last_garbage_collected_view: TYPES::Time,
+
+    /// Metrics of this task and its replica tasks
+    pub metrics: ViewSyncMetrics,
 }
 
 impl<
@@ -155,6 +167,10 @@ pub struct ViewSyncReplicaTaskState<
     pub api: A,
     /// Event stream to publish events to
     pub event_stream: ChannelStream<HotShotEvent<TYPES>>,
+    /// When this replica task started
+    pub started: Instant,
+    /// Metrics of view sync
+    pub metrics: ViewSyncMetrics,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> + 'static> TS
@@ -264,6 +280,8 @@ impl<
                         event_stream: self.event_stream.clone(),
                         view_sync_timeout: self.view_sync_timeout,
                         id: self.id,
+                        started: Instant::now(),
+                        metrics: self.metrics.clone(),
                     };
 
                 let result = replica_state.handle_event(event.clone()).await;
@@ -634,6 +652,7 @@ impl<
                 }
 
                 if self.num_timeouts_tracked > 2 {
+                    self.metrics.triggers.add(1);
                     // Start polling for view sync certificates
                     self.network
                         .inject_consensus_info(ConsensusIntentEvent::PollForViewSyncCertificate(
@@ -682,6 +701,8 @@ impl<
                         event_stream: self.event_stream.clone(),
                         view_sync_timeout: self.view_sync_timeout,
                         id: self.id,
+                        started: Instant::now(),
+                        metrics: self.metrics.clone(),
                     };
 
                     let result = replica_state
@@ -802,6 +823,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 }
 
                 self.phase = last_seen_certificate;
+                self.metrics.phase_reached(&self.phase);
 
                 if certificate.get_data().relay > self.relay {
                     self.relay = certificate.get_data().relay;
@@ -870,6 +892,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 }
 
                 self.phase = last_seen_certificate;
+                self.metrics.phase_reached(&self.phase);
 
                 if certificate.get_data().relay > self.relay {
                     self.relay = certificate.get_data().relay;
@@ -941,11 +964,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                 }
 
                 self.phase = last_seen_certificate;
+                self.metrics.phase_reached(&self.phase);
 
                 if certificate.get_data().relay > self.relay {
                     self.relay = certificate.get_data().relay;
                 }
 
+                observe_elapsed(&*self.metrics.duration, self.started);
                 self.event_stream
                     .publish(HotShotEvent::ViewChange(self.next_view))
                     .await;
@@ -1000,6 +1025,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, A: ConsensusApi<TYPES, I> +
                     && last_seen_certificate == self.phase
                 {
                     self.relay += 1;
+                    self.metrics.relay_timeouts.add(1);
                     match self.phase {
                         ViewSyncPhase::None => {
                             let vote = ViewSyncPreCommitVote::<TYPES>::create_signed_vote(
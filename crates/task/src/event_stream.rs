@@ -1,7 +1,7 @@
 use async_compatibility_layer::channel::{unbounded, UnboundedSender, UnboundedStream};
 use async_lock::RwLock;
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
@@ -91,8 +91,9 @@ pub struct ChannelStream<EVENT: PassType> {
 
 /// trick to make the event stream clonable
 struct ChannelStreamInner<EVENT: PassType> {
-    /// the subscribers to the channel
-    subscribers: HashMap<StreamId, (FilterEvent<EVENT>, UnboundedSender<EVENT>)>,
+    /// the subscribers to the channel, ordered by id so that events reach them in the same order
+    /// on every run
+    subscribers: BTreeMap<StreamId, (FilterEvent<EVENT>, UnboundedSender<EVENT>)>,
     /// the next unused assignable id
     next_stream_id: StreamId,
 }
@@ -103,7 +104,7 @@ impl<EVENT: PassType> ChannelStream<EVENT> {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(RwLock::new(ChannelStreamInner {
-                subscribers: HashMap::new(),
+                subscribers: BTreeMap::new(),
                 next_stream_id: 0,
             })),
         }
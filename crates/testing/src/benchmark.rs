@@ -0,0 +1,324 @@
+//! Benchmarks of whole test networks
+//!
+//! A [`BenchmarkDescription`] sweeps networks, node counts, transaction payload sizes and DA
+//! committee sizes. Each combination is a test run under a constant load of transactions until
+//! enough views are decided, and gives a [`BenchmarkResult`] with the percentiles of the time
+//! nodes take to decide a view, the throughput, and the serialized size of the events each node
+//! exchanged with the others. The [`BenchmarkReport`] of a sweep serializes to JSON.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    measurement_task::{MeasurementTaskDescription, RunMeasurements},
+    node_types::{Libp2pImpl, MemoryImpl, TestTypes, WebImpl},
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    test_builder::{TestMetadata, TimingData},
+    txn_task::{SizeDistribution, TpsRamp, TxnDistribution, TxnTaskDescription},
+};
+
+/// If set, the file the benchmark tests write their JSON report to
+pub const REPORT_VAR: &str = "HOTSHOT_BENCHMARK_REPORT";
+
+/// The networks nodes can be benchmarked over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkNetwork {
+    /// in memory networking
+    Memory,
+    /// a web server spawned for the run
+    WebServer,
+    /// libp2p over localhost
+    Libp2p,
+}
+
+impl BenchmarkNetwork {
+    /// timing that suits the latency of the network
+    fn timing_data(self) -> TimingData {
+        let next_view_timeout = match self {
+            BenchmarkNetwork::Memory => 1000,
+            BenchmarkNetwork::Libp2p => 2000,
+            // nodes poll the web server, which takes longer to go round
+            BenchmarkNetwork::WebServer => 10000,
+        };
+        TimingData {
+            next_view_timeout,
+            round_start_delay: 25,
+            start_delay: 120_000,
+            ..TimingData::default()
+        }
+    }
+}
+
+/// A combination of parameters in a sweep
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchmarkPoint {
+    /// network the nodes run over
+    pub network: BenchmarkNetwork,
+    /// number of nodes
+    pub nodes: usize,
+    /// bytes each transaction is padded with
+    pub payload_size: u64,
+    /// size of the DA committee
+    pub da_committee_size: usize,
+}
+
+/// Percentiles of a duration, in milliseconds, or `None` without any samples
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    /// median
+    pub p50: Option<f64>,
+    /// 90th percentile
+    pub p90: Option<f64>,
+    /// 99th percentile
+    pub p99: Option<f64>,
+    /// longest
+    pub max: Option<f64>,
+}
+
+/// What a run at one point of a sweep measured
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    /// parameters of the run
+    #[serde(flatten)]
+    pub point: BenchmarkPoint,
+    /// views with a decided leaf
+    pub decided_views: usize,
+    /// transactions in the decided leaves
+    pub decided_transactions: usize,
+    /// seconds from the first view to the last decide
+    pub elapsed_secs: f64,
+    /// time from a node entering a view to it deciding the leaf of that view, past the warmup
+    pub time_to_decide_ms: Percentiles,
+    /// transactions decided per second
+    pub transactions_per_sec: f64,
+    /// views decided per second
+    pub views_per_sec: f64,
+    /// bincode size of the message events each node handed to its networks, by node index. This
+    /// counts each message once however many nodes it went to, and leaves out the wire format and
+    /// the overhead of the network itself, so it isn't the bandwidth the node used
+    pub event_bytes_sent_per_node: Vec<u64>,
+    /// bincode size of the message events each node got from its networks, by node index
+    pub event_bytes_received_per_node: Vec<u64>,
+    /// event bytes sent per second by each node, by node index
+    pub event_bytes_sent_per_sec_per_node: Vec<f64>,
+    /// event bytes received per second by each node, by node index
+    pub event_bytes_received_per_sec_per_node: Vec<f64>,
+}
+
+impl BenchmarkResult {
+    /// summarize what a run at `point` measured
+    #[must_use]
+    pub fn new(point: BenchmarkPoint, measurements: &RunMeasurements) -> Self {
+        let elapsed_secs = measurements
+            .elapsed()
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64());
+        let per_sec = |count: f64| {
+            if elapsed_secs > 0.0 {
+                count / elapsed_secs
+            } else {
+                0.0
+            }
+        };
+        let ms = |p: usize| {
+            measurements
+                .time_to_decide_percentile(p)
+                .map(|duration| duration.as_secs_f64() * 1000.0)
+        };
+        let decided_views = measurements.decided_txns.len();
+        let decided_transactions = measurements.num_decided_txns();
+        let mut event_bytes_sent_per_node = measurements.event_bytes_sent.clone();
+        let mut event_bytes_received_per_node = measurements.event_bytes_received.clone();
+        // nodes that never sent or received anything still get an entry
+        event_bytes_sent_per_node.resize(event_bytes_sent_per_node.len().max(point.nodes), 0);
+        event_bytes_received_per_node
+            .resize(event_bytes_received_per_node.len().max(point.nodes), 0);
+        Self {
+            point,
+            decided_views,
+            decided_transactions,
+            elapsed_secs,
+            time_to_decide_ms: Percentiles {
+                p50: ms(50),
+                p90: ms(90),
+                p99: ms(99),
+                max: ms(100),
+            },
+            transactions_per_sec: per_sec(decided_transactions as f64),
+            views_per_sec: per_sec(decided_views as f64),
+            event_bytes_sent_per_sec_per_node: event_bytes_sent_per_node
+                .iter()
+                .map(|bytes| per_sec(*bytes as f64))
+                .collect(),
+            event_bytes_received_per_sec_per_node: event_bytes_received_per_node
+                .iter()
+                .map(|bytes| per_sec(*bytes as f64))
+                .collect(),
+            event_bytes_sent_per_node,
+            event_bytes_received_per_node,
+        }
+    }
+}
+
+/// The results of a sweep
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// a result for each point of the sweep, in the order they ran
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkReport {
+    /// the report as pretty printed JSON
+    /// # Panics
+    /// Panics if the report can't be serialized, which it always can
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize the benchmark report")
+    }
+
+    /// write the report as JSON to `path`
+    /// # Errors
+    /// Errors if the file can't be written
+    pub fn write_json(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+/// A sweep of benchmarks
+#[derive(Clone, Debug)]
+pub struct BenchmarkDescription {
+    /// networks to run over
+    pub networks: Vec<BenchmarkNetwork>,
+    /// numbers of nodes to run
+    pub node_counts: Vec<usize>,
+    /// bytes to pad each transaction with
+    pub payload_sizes: Vec<u64>,
+    /// DA committee sizes, skipped for node counts smaller than them
+    pub da_committee_sizes: Vec<usize>,
+    /// transactions submitted per second, to random nodes
+    pub tps: f64,
+    /// views to decide in each run
+    pub num_views: usize,
+    /// views before this one don't count towards the time to decide
+    pub warmup_views: u64,
+    /// how long a run may take to decide `num_views` views
+    pub max_duration: Duration,
+}
+
+impl Default for BenchmarkDescription {
+    fn default() -> Self {
+        Self {
+            networks: vec![BenchmarkNetwork::Memory],
+            node_counts: vec![5, 10],
+            payload_sizes: vec![0, 1024],
+            da_committee_sizes: vec![5],
+            tps: 100.0,
+            num_views: 20,
+            warmup_views: 3,
+            max_duration: Duration::from_secs(120),
+        }
+    }
+}
+
+impl BenchmarkDescription {
+    /// the combinations of parameters to run, networks varying slowest
+    #[must_use]
+    pub fn points(&self) -> Vec<BenchmarkPoint> {
+        let mut points = vec![];
+        for network in &self.networks {
+            for nodes in &self.node_counts {
+                for payload_size in &self.payload_sizes {
+                    for da_committee_size in &self.da_committee_sizes {
+                        if da_committee_size > nodes {
+                            continue;
+                        }
+                        points.push(BenchmarkPoint {
+                            network: *network,
+                            nodes: *nodes,
+                            payload_size: *payload_size,
+                            da_committee_size: *da_committee_size,
+                        });
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    /// the test to run at `point`
+    fn metadata(&self, point: &BenchmarkPoint) -> TestMetadata {
+        TestMetadata {
+            total_nodes: point.nodes,
+            start_nodes: point.nodes,
+            num_bootstrap_nodes: point.nodes,
+            da_committee_size: point.da_committee_size,
+            overall_safety_properties: OverallSafetyPropertiesDescription {
+                num_successful_views: self.num_views,
+                check_leaf: true,
+                check_state: true,
+                check_block: true,
+                num_failed_views: self.num_views,
+                transaction_threshold: 0,
+                threshold_calculator: Arc::new(|_active, total| (2 * total / 3 + 1)),
+            },
+            measurement_description: MeasurementTaskDescription {
+                warmup_views: self.warmup_views,
+                measure_event_bytes: true,
+            },
+            txn_description: TxnTaskDescription::DistributionBased(TxnDistribution {
+                sizes: SizeDistribution::Fixed(point.payload_size),
+                tps: TpsRamp::constant(self.tps),
+                ..TxnDistribution::default()
+            }),
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: self.max_duration,
+                },
+            ),
+            timing_data: point.network.timing_data(),
+            ..TestMetadata::default()
+        }
+    }
+
+    /// Run each point of the sweep in turn. A run ends once `num_views` views are decided.
+    /// # Panics
+    /// Panics if a run fails, including if it doesn't decide `num_views` views within
+    /// `max_duration`
+    pub async fn run(&self) -> BenchmarkReport {
+        let mut report = BenchmarkReport::default();
+        for point in self.points() {
+            info!("Benchmarking {point:?}");
+            let metadata = self.metadata(&point);
+            let measurements = match point.network {
+                BenchmarkNetwork::Memory => {
+                    metadata
+                        .gen_launcher::<TestTypes, MemoryImpl>(0)
+                        .launch()
+                        .run_test_with_measurements()
+                        .await
+                }
+                BenchmarkNetwork::WebServer => {
+                    metadata
+                        .gen_launcher::<TestTypes, WebImpl>(0)
+                        .launch()
+                        .run_test_with_measurements()
+                        .await
+                }
+                BenchmarkNetwork::Libp2p => {
+                    metadata
+                        .gen_launcher::<TestTypes, Libp2pImpl>(0)
+                        .launch()
+                        .run_test_with_measurements()
+                        .await
+                }
+            };
+            let result = BenchmarkResult::new(point, &measurements);
+            info!("Benchmarked {result:?}");
+            report.results.push(result);
+        }
+        report
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_compatibility_layer::art::async_spawn;
+use commit::Committable;
+use futures::StreamExt;
+use hotshot::{
+    traits::TestableNodeImplementation, types::SystemContextHandle, HotShotConsensusApi,
+    SystemContext,
+};
+use hotshot_task::{
+    event_stream::{ChannelStream, EventStream},
+    task::FilterEvent,
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    consensus::Consensus,
+    data::{random_commitment, Leaf, QuorumProposal},
+    message::Proposal,
+    simple_vote::{QuorumData, QuorumVote},
+    traits::{
+        block_contents::BlockHeader, consensus_api::ConsensusSharedApi,
+        node_implementation::NodeType, signature_key::SignatureKey, state::ConsensusTime,
+        BlockPayload,
+    },
+    vote::{Certificate, HasViewNumber},
+};
+
+use tracing::warn;
+
+use crate::simulation::SimulationRng;
+
+/// Rewrites the events a node sends to the network, to make it misbehave
+pub trait EventRewriter<TYPES: NodeType>: Send + Sync {
+    /// The events to send in place of `event`, signing as `public_key` with `private_key` if need
+    /// be, given the node's view of `consensus`. Returning `event` alone sends it as is, returning
+    /// nothing withholds it.
+    fn rewrite(
+        &self,
+        event: HotShotEvent<TYPES>,
+        consensus: &Consensus<TYPES>,
+        public_key: &TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Vec<HotShotEvent<TYPES>>;
+}
+
+/// The ways a byzantine node can misbehave
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByzantineBehaviour {
+    /// as leader, propose a second block for the same view, with none of the transactions of the
+    /// first, and broadcast both
+    EquivocatingLeader,
+    /// as replica, vote for a leaf that doesn't exist as well as for the proposed one, sending the
+    /// bogus vote first
+    DoubleVoting,
+    /// as DA leader, send neither the DA proposal nor the VID disperse
+    WithholdDA,
+    /// as leader, justify proposals with a QC whose signatures don't match its data
+    InvalidQC,
+}
+
+impl<TYPES: NodeType> EventRewriter<TYPES> for ByzantineBehaviour {
+    fn rewrite(
+        &self,
+        event: HotShotEvent<TYPES>,
+        consensus: &Consensus<TYPES>,
+        public_key: &TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Vec<HotShotEvent<TYPES>> {
+        match (self, event) {
+            (
+                ByzantineBehaviour::EquivocatingLeader,
+                HotShotEvent::QuorumProposalSend(proposal, sender),
+            ) => match conflicting_proposal(&proposal.data, consensus, private_key) {
+                Some(conflicting) => vec![
+                    HotShotEvent::QuorumProposalSend(proposal, sender.clone()),
+                    HotShotEvent::QuorumProposalSend(conflicting, sender),
+                ],
+                None => vec![HotShotEvent::QuorumProposalSend(proposal, sender)],
+            },
+            (ByzantineBehaviour::DoubleVoting, HotShotEvent::QuorumVoteSend(vote)) => {
+                let bogus = QuorumVote::<TYPES>::create_signed_vote(
+                    QuorumData {
+                        leaf_commit: random_commitment(&mut SimulationRng),
+                    },
+                    vote.get_view_number(),
+                    public_key,
+                    private_key,
+                );
+                vec![
+                    HotShotEvent::QuorumVoteSend(bogus),
+                    HotShotEvent::QuorumVoteSend(vote),
+                ]
+            }
+            (
+                ByzantineBehaviour::WithholdDA,
+                HotShotEvent::DAProposalSend(..) | HotShotEvent::VidDisperseSend(..),
+            ) => vec![],
+            (
+                ByzantineBehaviour::InvalidQC,
+                HotShotEvent::QuorumProposalSend(mut proposal, sender),
+            ) if !proposal.data.justify_qc.is_genesis => {
+                // the QC's data, and so the leaf the proposal is signed over, stay the same
+                proposal.data.justify_qc.vote_commitment = QuorumData::<TYPES> {
+                    leaf_commit: random_commitment(&mut SimulationRng),
+                }
+                .commit();
+                vec![HotShotEvent::QuorumProposalSend(proposal, sender)]
+            }
+            (_, event) => vec![event],
+        }
+    }
+}
+
+/// A validly signed proposal for the same view and parent as `proposal`, for the genesis block
+/// payload instead, or `None` if the parent leaf isn't in `consensus` or `proposal` already is for
+/// the genesis block payload
+fn conflicting_proposal<TYPES: NodeType>(
+    proposal: &QuorumProposal<TYPES>,
+    consensus: &Consensus<TYPES>,
+    private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+) -> Option<Proposal<TYPES, QuorumProposal<TYPES>>> {
+    let Some(parent) = consensus
+        .saved_leaves
+        .get(&proposal.justify_qc.get_data().leaf_commit)
+    else {
+        warn!(
+            "No parent leaf for the proposal in view {}, not equivocating",
+            *proposal.view_number
+        );
+        return None;
+    };
+    let (payload, metadata) = TYPES::BlockPayload::genesis();
+    let block_header = TYPES::BlockHeader::new(payload.commit(), metadata, &parent.block_header);
+    if block_header == proposal.block_header {
+        return None;
+    }
+    let leaf = Leaf {
+        view_number: proposal.view_number,
+        justify_qc: proposal.justify_qc.clone(),
+        parent_commitment: parent.commit(),
+        block_header: block_header.clone(),
+        block_payload: None,
+        rejected: vec![],
+        timestamp: 0,
+        proposer_id: proposal.proposer_id.clone(),
+    };
+    let signature = TYPES::SignatureKey::sign(private_key, leaf.commit().as_ref());
+    Some(Proposal {
+        data: QuorumProposal {
+            block_header,
+            ..proposal.clone()
+        },
+        signature,
+        _pd: PhantomData,
+    })
+}
+
+/// Spawn the tasks of `hotshot` like [`run_tasks`](hotshot::HotShotType::run_tasks) does, with
+/// everything it sends to the network from view `from_view` on rewritten by `rewriter`, counting
+/// the events `rewriter` changed in `rewritten`
+pub async fn run_byzantine_tasks<TYPES: NodeType, I: TestableNodeImplementation<TYPES>>(
+    hotshot: SystemContext<TYPES, I>,
+    rewriter: Arc<dyn EventRewriter<TYPES>>,
+    from_view: TYPES::Time,
+    rewritten: Arc<AtomicUsize>,
+) -> SystemContextHandle<TYPES, I> {
+    let api = HotShotConsensusApi {
+        inner: hotshot.inner.clone(),
+    };
+    let outgoing_event_stream = ChannelStream::new();
+    let mut handle = hotshot
+        .run_tasks_with_outgoing_stream(outgoing_event_stream.clone())
+        .await;
+    let (mut events, _) = handle
+        .get_internal_event_stream_known_impl(FilterEvent::default())
+        .await;
+
+    async_spawn(async move {
+        let mut view = TYPES::Time::genesis();
+        while let Some(event) = events.next().await {
+            match event {
+                // the networking tasks must shut down whatever the rewriter does
+                HotShotEvent::Shutdown => {
+                    outgoing_event_stream.publish(event).await;
+                    break;
+                }
+                HotShotEvent::ViewChange(new_view) if new_view > view => view = new_view,
+                _ => {}
+            }
+            let events = if view >= from_view {
+                let consensus = api.inner.consensus.read().await;
+                let events = rewriter.rewrite(
+                    event.clone(),
+                    &consensus,
+                    api.public_key(),
+                    api.private_key(),
+                );
+                if events != [event] {
+                    rewritten.fetch_add(1, Ordering::Relaxed);
+                }
+                events
+            } else {
+                vec![event]
+            };
+            for event in events {
+                outgoing_event_stream.publish(event).await;
+            }
+        }
+    });
+    handle
+}
+
+/// A node of a test that misbehaves
+#[derive(Clone, Debug)]
+pub struct ByzantineNode {
+    /// index of the node
+    pub idx: usize,
+    /// how it misbehaves
+    pub behaviour: ByzantineBehaviour,
+    /// the view it starts misbehaving in
+    pub from_view: u64,
+}
@@ -0,0 +1,804 @@
+//! Model-based property testing of the consensus task
+//!
+//! A [`Script`] is a random but well-formed run as one replica sees it: leaders proposing on top
+//! of earlier proposals with the DAC and timeout certificate they need, timeouts, view changes,
+//! and votes sent to the wrong leader. [`play`] sends a script to a lone consensus task and, after
+//! each event, compares what the task emits with what a reference model of HotShot's voting,
+//! locking and deciding rules expects. [`ConsensusModelDescription::check`] plays random scripts,
+//! and shrinks the first one the two disagree on to a smaller one they still disagree on.
+//!
+//! Scripts are meant to be played in a [`simulate`](crate::simulation::simulate)d runtime, where
+//! a sleep only ends once every task is idle, so the task is done with an event before the next
+//! one is sent.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use async_compatibility_layer::art::{async_sleep, async_spawn};
+use bitvec::bitvec;
+use commit::{Commitment, Committable};
+use either::Either;
+use futures::{FutureExt, StreamExt};
+use hotshot::{
+    tasks::add_consensus_task,
+    types::{bn254::BLSPubKey, SignatureKey, SystemContextHandle},
+};
+use hotshot_task::{
+    event_stream::{ChannelStream, EventStream},
+    task::FilterEvent,
+    task_launcher::TaskRunner,
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    block_impl::{VIDBlockHeader, VIDBlockPayload},
+    data::{Leaf, QuorumProposal, ViewNumber},
+    message::Proposal,
+    simple_certificate::QuorumCertificate,
+    simple_vote::{DAData, DAVote, QuorumData, QuorumVote, TimeoutData, TimeoutVote},
+    traits::{
+        block_contents::BlockHeader,
+        election::Membership,
+        node_implementation::NodeType,
+        state::{ConsensusTime, TestableBlock},
+    },
+    vote::{Certificate, HasViewNumber, Vote, VoteAccumulator},
+};
+use rand::Rng;
+use snafu::Snafu;
+
+use crate::{
+    node_types::{MemoryImpl, TestTypes},
+    simulation::SimulationRng,
+    task_helpers::{build_system_handle, key_pair_for_id},
+};
+
+/// The node whose consensus task scripts are played to
+pub const REPLICA: u64 = 2;
+
+/// How long to let the consensus task handle an event, in virtual time
+const SETTLE: Duration = Duration::from_millis(1);
+
+/// How many nodes the scripts are written for
+const NODES: u64 = 10;
+
+/// the consensus task and the model disagreeing
+#[derive(Snafu, Debug)]
+pub enum ConsensusModelErr {
+    /// the consensus task emitted other events than the model expected
+    #[snafu(display(
+        "On {input} of step {step}, the model expected {expected:?} but the consensus task emitted {actual:?}"
+    ))]
+    Mismatch {
+        step: usize,
+        input: String,
+        expected: Vec<Observed>,
+        actual: Vec<Observed>,
+    },
+}
+
+/// A step of a script. Views of proposals are unique, and 0 is the genesis leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// the leader of `view` proposes a leaf on top of the one proposed in `parent`. The DAC of the
+    /// proposal is sent first if `dac`, then the proposal itself if `deliver`; an undelivered
+    /// proposal is only known to the replica as the parent of later ones.
+    Propose {
+        view: u64,
+        parent: u64,
+        dac: bool,
+        deliver: bool,
+    },
+    /// the replica's timer for `view` runs out
+    Timeout(u64),
+    /// the replica is told to move to `view`
+    ViewChange(u64),
+    /// node `voter` sends a quorum vote, or a timeout vote if `timeout`, in `view`. It's only sent
+    /// if the replica doesn't lead the next view, so the replica must ignore it.
+    StrayVote {
+        view: u64,
+        voter: u64,
+        timeout: bool,
+    },
+}
+
+impl Step {
+    /// the view of the step
+    fn view(&self) -> u64 {
+        match self {
+            Step::Propose { view, .. }
+            | Step::Timeout(view)
+            | Step::ViewChange(view)
+            | Step::StrayVote { view, .. } => *view,
+        }
+    }
+
+    /// the step with every view above `gap` lowered by one
+    fn close_gap(&self, gap: u64) -> Self {
+        let lower = |view: u64| if view > gap { view - 1 } else { view };
+        match *self {
+            Step::Propose {
+                view,
+                parent,
+                dac,
+                deliver,
+            } => Step::Propose {
+                view: lower(view),
+                parent: lower(parent),
+                dac,
+                deliver,
+            },
+            Step::Timeout(view) => Step::Timeout(lower(view)),
+            Step::ViewChange(view) => Step::ViewChange(lower(view)),
+            Step::StrayVote {
+                view,
+                voter,
+                timeout,
+            } => Step::StrayVote {
+                view: lower(view),
+                voter,
+                timeout,
+            },
+        }
+    }
+}
+
+/// Steps to play to the replica, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Script {
+    /// the steps
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    /// A random script of up to `max_steps` steps. Proposals mostly build on the latest one in the
+    /// next view, but also skip views, fork off older proposals and come in after their view.
+    pub fn generate<R: Rng>(rng: &mut R, max_steps: usize) -> Self {
+        let len = rng.gen_range(1..=max_steps.max(1));
+        let mut proposed = vec![0];
+        let mut top = 0;
+        let mut steps = Vec::with_capacity(len);
+        for _ in 0..len {
+            let step = match rng.gen_range(0..20) {
+                0..=11 => {
+                    let gap = if rng.gen_ratio(7, 10) {
+                        1
+                    } else {
+                        rng.gen_range(2..=3)
+                    };
+                    let mut view = top + gap;
+                    if top > 1 && rng.gen_ratio(1, 10) {
+                        let stale = rng.gen_range(1..top);
+                        if !proposed.contains(&stale) {
+                            view = stale;
+                        }
+                    }
+                    let parents: Vec<u64> =
+                        proposed.iter().copied().filter(|p| *p < view).collect();
+                    let parent = if rng.gen_ratio(7, 10) {
+                        parents.iter().copied().max().unwrap_or(0)
+                    } else {
+                        parents[rng.gen_range(0..parents.len())]
+                    };
+                    proposed.push(view);
+                    top = top.max(view);
+                    Step::Propose {
+                        view,
+                        parent,
+                        dac: rng.gen_ratio(9, 10),
+                        deliver: rng.gen_ratio(9, 10),
+                    }
+                }
+                12..=14 => Step::Timeout(rng.gen_range(top.saturating_sub(1)..=top + 2)),
+                15..=16 => Step::ViewChange(rng.gen_range(top.saturating_sub(1)..=top + 2)),
+                _ => Step::StrayVote {
+                    view: rng.gen_range(top.saturating_sub(1)..=top + 1),
+                    voter: rng.gen_range(0..NODES),
+                    timeout: rng.gen(),
+                },
+            };
+            steps.push(step);
+        }
+        Self { steps }
+    }
+
+    /// The script without the proposals that lost their parent or reuse a view
+    #[must_use]
+    fn normalized(mut self) -> Self {
+        let mut proposed = BTreeSet::from([0]);
+        self.steps.retain(|step| match step {
+            Step::Propose { view, parent, .. } => {
+                parent < view && proposed.contains(parent) && proposed.insert(*view)
+            }
+            _ => true,
+        });
+        self
+    }
+
+    /// Smaller scripts to try in place of this one, with fewer steps first, then lower views
+    fn shrink_candidates(&self) -> Vec<Self> {
+        let mut candidates = Vec::new();
+        let len = self.steps.len();
+        let mut size = len / 2;
+        while size > 0 {
+            for start in (0..len).step_by(size) {
+                let mut steps = self.steps.clone();
+                steps.drain(start..(start + size).min(len));
+                candidates.push(Self { steps }.normalized());
+            }
+            size /= 2;
+        }
+        let views: BTreeSet<u64> = self.steps.iter().map(Step::view).collect();
+        let top = views.iter().copied().max().unwrap_or(0);
+        for gap in (1..top).filter(|view| !views.contains(view)) {
+            let steps = self.steps.iter().map(|step| step.close_gap(gap)).collect();
+            candidates.push(Self { steps }.normalized());
+        }
+        candidates.retain(|candidate| candidate != self);
+        candidates
+    }
+}
+
+/// What the consensus task emits, as far as the model goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Observed {
+    /// moved to a view
+    ViewChange(u64),
+    /// voted for a leaf
+    Vote {
+        view: u64,
+        leaf: Commitment<Leaf<TestTypes>>,
+    },
+    /// voted to time out a view
+    TimeoutVote(u64),
+    /// decided the leaves of these views, newest first
+    Decided(Vec<u64>),
+    /// anything else
+    Other(String),
+}
+
+impl From<HotShotEvent<TestTypes>> for Observed {
+    fn from(event: HotShotEvent<TestTypes>) -> Self {
+        match event {
+            HotShotEvent::ViewChange(view) => Self::ViewChange(*view),
+            HotShotEvent::QuorumVoteSend(vote) => Self::Vote {
+                view: *vote.get_view_number(),
+                leaf: vote.get_data().leaf_commit,
+            },
+            HotShotEvent::TimeoutVoteSend(vote) => Self::TimeoutVote(*vote.get_view_number()),
+            HotShotEvent::LeafDecided(leaves) => {
+                Self::Decided(leaves.iter().map(|leaf| *leaf.view_number).collect())
+            }
+            event => Self::Other(format!("{event:?}")),
+        }
+    }
+}
+
+/// An event sent to the replica, as the model sees it
+#[derive(Clone, Debug)]
+enum Input {
+    /// the DAC of the proposal of a view
+    Dac(u64),
+    /// the proposal of a view, on top of that of another
+    Proposal { view: u64, parent: u64 },
+    /// a view change
+    ViewChange(u64),
+    /// a timeout
+    Timeout(u64),
+    /// something the replica must ignore
+    Ignored,
+}
+
+/// Reference model of a replica's consensus task
+struct Model {
+    /// the view the replica is in
+    cur_view: u64,
+    /// the view of the locked leaf
+    locked_view: u64,
+    /// the view of the last decided leaf
+    last_decided_view: u64,
+    /// the view of the parent of each leaf stored, by view. The genesis leaf has none.
+    leaves: BTreeMap<u64, Option<u64>>,
+    /// the views of the DACs received and not yet discarded
+    da_certs: BTreeSet<u64>,
+    /// the view and parent view of the proposal waiting on its DAC to be voted for
+    current_proposal: Option<(u64, u64)>,
+    /// the commitments of the leaves of the script, by view
+    commitments: HashMap<u64, Commitment<Leaf<TestTypes>>>,
+}
+
+impl Model {
+    /// a replica that only knows the genesis leaf
+    fn new(commitments: HashMap<u64, Commitment<Leaf<TestTypes>>>) -> Self {
+        Self {
+            cur_view: 0,
+            locked_view: 0,
+            last_decided_view: 0,
+            leaves: BTreeMap::from([(0, None)]),
+            da_certs: BTreeSet::new(),
+            current_proposal: None,
+            commitments,
+        }
+    }
+
+    /// What the replica emits on `input`, in order
+    fn handle(&mut self, input: &Input) -> Vec<Observed> {
+        let mut out = Vec::new();
+        match *input {
+            Input::Dac(view) => {
+                self.da_certs.insert(view);
+                if self.vote_if_able(&mut out) {
+                    self.current_proposal = None;
+                }
+            }
+            Input::Proposal { view, parent } => self.handle_proposal(view, parent, &mut out),
+            Input::ViewChange(view) => {
+                self.update_view(view, &mut out);
+            }
+            Input::Timeout(view) => {
+                if view > self.cur_view {
+                    out.push(Observed::TimeoutVote(view));
+                }
+            }
+            Input::Ignored => {}
+        }
+        out
+    }
+
+    /// Move to `view` if it's ahead, discarding the DACs of the views left behind
+    fn update_view(&mut self, view: u64, out: &mut Vec<Observed>) {
+        if view > self.cur_view {
+            for old in self.cur_view..view - 1 {
+                self.da_certs.remove(&old);
+            }
+            self.cur_view = view;
+            out.push(Observed::ViewChange(view));
+        }
+    }
+
+    /// Whether the chain of stored leaves from `view` down reaches `locked`
+    fn extends(&self, mut view: u64, locked: u64) -> bool {
+        loop {
+            let Some(parent) = self.leaves.get(&view) else {
+                return false;
+            };
+            if view == locked {
+                return true;
+            }
+            let Some(parent) = parent else {
+                return false;
+            };
+            view = *parent;
+        }
+    }
+
+    /// Vote for the current proposal if it has its DAC, or extends genesis in view 1
+    fn vote_if_able(&self, out: &mut Vec<Observed>) -> bool {
+        let Some((view, parent)) = self.current_proposal else {
+            return false;
+        };
+        let certified = (view == 1 && parent == 0) || self.da_certs.contains(&view);
+        if !certified || !self.leaves.contains_key(&parent) {
+            return false;
+        }
+        out.push(Observed::Vote {
+            view,
+            leaf: self.commitments[&view],
+        });
+        true
+    }
+
+    /// Handle the proposal of `view` on top of the leaf of `parent`
+    fn handle_proposal(&mut self, view: u64, parent: u64, out: &mut Vec<Observed>) {
+        if view < self.cur_view {
+            return;
+        }
+        self.update_view(view, out);
+        self.current_proposal = None;
+
+        // a proposal with an unknown parent is kept for later proposals to build on, but not
+        // voted for
+        if !self.leaves.contains_key(&parent) {
+            self.leaves.insert(view, Some(parent));
+            return;
+        }
+        // SafeNode: the proposal must extend the locked leaf (safety), or be justified by a QC
+        // newer than the locked leaf (liveness)
+        let safety = self.extends(parent, self.locked_view);
+        let liveness = parent > self.locked_view;
+        if !safety && !liveness {
+            return;
+        }
+        self.current_proposal = Some((view, parent));
+
+        // a chain of leaves in consecutive views locks the leaf two back and decides the one
+        // three back, along with its ancestors down to the last decided leaf
+        let mut new_locked_view = None;
+        let mut new_decided_view = None;
+        let mut decided = Vec::new();
+        if parent + 1 == view {
+            let mut last_visited = view;
+            let mut chain_length = 1;
+            let mut next = Some(parent);
+            while let Some(current) = next {
+                let Some(&grandparent) = self.leaves.get(&current) else {
+                    break;
+                };
+                if current == self.last_decided_view {
+                    break;
+                }
+                next = grandparent;
+                if new_decided_view.is_none() {
+                    if last_visited != current + 1 {
+                        break;
+                    }
+                    last_visited = current;
+                    chain_length += 1;
+                    if chain_length == 2 {
+                        new_locked_view = Some(current);
+                    } else if chain_length == 3 {
+                        new_decided_view = Some(current);
+                    }
+                }
+                if new_decided_view.is_some() {
+                    decided.push(current);
+                }
+            }
+        }
+
+        self.leaves.insert(view, Some(parent));
+        if let Some(locked_view) = new_locked_view {
+            self.locked_view = locked_view;
+        }
+        if let Some(decided_view) = new_decided_view {
+            out.push(Observed::Decided(decided));
+            self.leaves.retain(|view, _| *view >= decided_view);
+            self.last_decided_view = decided_view;
+        }
+
+        if self.vote_if_able(out) {
+            self.current_proposal = None;
+            for old in self.cur_view..=view {
+                self.da_certs.remove(&old);
+            }
+        }
+    }
+}
+
+/// A script turned into events for the replica
+struct Interpreted {
+    /// each event to send, with the step it's for and how the model sees it
+    inputs: Vec<(usize, Input, HotShotEvent<TestTypes>)>,
+    /// the commitments of the leaves proposed, by view
+    commitments: HashMap<u64, Commitment<Leaf<TestTypes>>>,
+}
+
+/// Make a certificate from the votes of every node in `keys` that `membership` counts
+///
+/// # Panics
+/// Panics if the votes of every node aren't enough for a certificate
+fn certify<VOTE, CERT>(
+    keys: &[(<BLSPubKey as SignatureKey>::PrivateKey, BLSPubKey)],
+    membership: &<TestTypes as NodeType>::Membership,
+    vote: impl Fn(&<BLSPubKey as SignatureKey>::PrivateKey, &BLSPubKey) -> VOTE,
+) -> CERT
+where
+    VOTE: Vote<TestTypes>,
+    CERT: Certificate<TestTypes, Voteable = VOTE::Commitment>,
+{
+    let mut accumulator = VoteAccumulator::<TestTypes, VOTE, CERT> {
+        vote_outcomes: HashMap::new(),
+        sig_lists: Vec::new(),
+        signers: bitvec![0; membership.total_nodes()],
+        phantom: PhantomData,
+    };
+    for (private_key, public_key) in keys {
+        match accumulator.accumulate(&vote(private_key, public_key), membership) {
+            Either::Left(rest) => accumulator = rest,
+            Either::Right(cert) => return cert,
+        }
+    }
+    panic!("Every node voting didn't make a certificate");
+}
+
+/// Turn `script` into signed proposals, certificates and votes for the replica of `handle`
+async fn interpret(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl>,
+    script: &Script,
+) -> Interpreted {
+    let memberships = &handle.hotshot.inner.memberships;
+    let quorum = &memberships.quorum_membership;
+    let keys: Vec<_> = (0..quorum.total_nodes() as u64)
+        .map(key_pair_for_id)
+        .collect();
+    let leader = |view: u64| {
+        let leader = quorum.get_leader(ViewNumber::new(view));
+        keys.iter()
+            .find(|(_, public_key)| *public_key == leader)
+            .cloned()
+            .expect("Leaders are test nodes")
+    };
+    let replica = keys[REPLICA as usize].1;
+    let payload_commitment = <VIDBlockPayload as TestableBlock>::genesis().commit();
+
+    let mut leaves = HashMap::from([(0, handle.get_consensus().read().await.get_decided_leaf())]);
+    let mut qcs = HashMap::from([(0, QuorumCertificate::<TestTypes>::genesis())]);
+    let mut interpreted = Interpreted {
+        inputs: Vec::new(),
+        commitments: HashMap::new(),
+    };
+    for (step_idx, step) in script.steps.iter().enumerate() {
+        match *step {
+            Step::Propose {
+                view,
+                parent,
+                dac,
+                deliver,
+            } => {
+                let parent_leaf: Leaf<TestTypes> = leaves[&parent].clone();
+                let justify_qc = qcs
+                    .entry(parent)
+                    .or_insert_with(|| {
+                        certify(&keys, quorum, |private_key, public_key| {
+                            QuorumVote::create_signed_vote(
+                                QuorumData {
+                                    leaf_commit: parent_leaf.commit(),
+                                },
+                                ViewNumber::new(parent),
+                                public_key,
+                                private_key,
+                            )
+                        })
+                    })
+                    .clone();
+                let timeout_certificate = (parent + 1 != view).then(|| {
+                    let timed_out = ViewNumber::new(view - 1);
+                    certify(&keys, quorum, |private_key, public_key| {
+                        TimeoutVote::create_signed_vote(
+                            TimeoutData { view: timed_out },
+                            timed_out,
+                            public_key,
+                            private_key,
+                        )
+                    })
+                });
+                let (leader_private_key, leader_public_key) = leader(view);
+                let block_header =
+                    VIDBlockHeader::new(payload_commitment, (), &parent_leaf.block_header);
+                let leaf = Leaf {
+                    view_number: ViewNumber::new(view),
+                    justify_qc: justify_qc.clone(),
+                    parent_commitment: parent_leaf.commit(),
+                    block_header: block_header.clone(),
+                    block_payload: None,
+                    rejected: vec![],
+                    timestamp: 0,
+                    proposer_id: leader_public_key.to_bytes(),
+                };
+                let signature = BLSPubKey::sign(&leader_private_key, leaf.commit().as_ref());
+                let proposal = Proposal {
+                    data: QuorumProposal {
+                        block_header,
+                        view_number: ViewNumber::new(view),
+                        justify_qc,
+                        timeout_certificate,
+                        proposer_id: leaf.proposer_id.clone(),
+                    },
+                    signature,
+                    _pd: PhantomData,
+                };
+                interpreted.commitments.insert(view, leaf.commit());
+                leaves.insert(view, leaf);
+
+                if dac {
+                    let dac = certify(
+                        &keys,
+                        &memberships.da_membership,
+                        |private_key, public_key| {
+                            DAVote::create_signed_vote(
+                                DAData {
+                                    payload_commit: payload_commitment,
+                                },
+                                ViewNumber::new(view),
+                                public_key,
+                                private_key,
+                            )
+                        },
+                    );
+                    interpreted.inputs.push((
+                        step_idx,
+                        Input::Dac(view),
+                        HotShotEvent::DACRecv(dac),
+                    ));
+                }
+                if deliver {
+                    interpreted.inputs.push((
+                        step_idx,
+                        Input::Proposal { view, parent },
+                        HotShotEvent::QuorumProposalRecv(proposal, leader_public_key),
+                    ));
+                }
+            }
+            Step::Timeout(view) => interpreted.inputs.push((
+                step_idx,
+                Input::Timeout(view),
+                HotShotEvent::Timeout(ViewNumber::new(view)),
+            )),
+            Step::ViewChange(view) => interpreted.inputs.push((
+                step_idx,
+                Input::ViewChange(view),
+                HotShotEvent::ViewChange(ViewNumber::new(view)),
+            )),
+            Step::StrayVote {
+                view,
+                voter,
+                timeout,
+            } => {
+                if quorum.get_leader(ViewNumber::new(view + 1)) == replica {
+                    continue;
+                }
+                let (private_key, public_key) = &keys[(voter as usize) % keys.len()];
+                let view = ViewNumber::new(view);
+                let event = if timeout {
+                    HotShotEvent::TimeoutVoteRecv(TimeoutVote::create_signed_vote(
+                        TimeoutData { view },
+                        view,
+                        public_key,
+                        private_key,
+                    ))
+                } else {
+                    HotShotEvent::QuorumVoteRecv(QuorumVote::create_signed_vote(
+                        QuorumData {
+                            leaf_commit: Commitment::default_commitment_no_preimage(),
+                        },
+                        view,
+                        public_key,
+                        private_key,
+                    ))
+                };
+                interpreted.inputs.push((step_idx, Input::Ignored, event));
+            }
+        }
+    }
+    interpreted
+}
+
+/// Play `script` to a fresh consensus task of [`REPLICA`], checking what it emits on each event
+/// against the model
+///
+/// # Errors
+/// Returns the first event the task and the model disagree on
+pub async fn play(script: &Script) -> Result<(), ConsensusModelErr> {
+    let (handle, _) = build_system_handle(REPLICA).await;
+    let interpreted = interpret(&handle, script).await;
+    let mut model = Model::new(interpreted.commitments);
+
+    let mut result = Ok(());
+    send_each(handle, interpreted.inputs, |step, input, actual| {
+        let expected = model.handle(&input);
+        if actual == expected {
+            return true;
+        }
+        result = Err(ConsensusModelErr::Mismatch {
+            step,
+            input: format!("{input:?}"),
+            expected,
+            actual,
+        });
+        false
+    })
+    .await;
+    result
+}
+
+/// Play `script` to a fresh consensus task of [`REPLICA`], returning what it emits, with the step
+/// it emits it on
+pub async fn observe(script: &Script) -> Vec<(usize, Observed)> {
+    let (handle, _) = build_system_handle(REPLICA).await;
+    let interpreted = interpret(&handle, script).await;
+    let mut observed = Vec::new();
+    send_each(handle, interpreted.inputs, |step, _, emitted| {
+        observed.extend(emitted.into_iter().map(|event| (step, event)));
+        true
+    })
+    .await;
+    observed
+}
+
+/// Send `inputs` one at a time to a fresh consensus task of `handle`'s node, passing `emitted_on`
+/// what the task emits on each until it returns `false`
+async fn send_each(
+    handle: SystemContextHandle<TestTypes, MemoryImpl>,
+    inputs: Vec<(usize, Input, HotShotEvent<TestTypes>)>,
+    mut emitted_on: impl FnMut(usize, Input, Vec<Observed>) -> bool,
+) {
+    let event_stream = ChannelStream::new();
+    let (mut emitted, _) = event_stream.subscribe(FilterEvent::default()).await;
+    let task_runner = add_consensus_task(
+        TaskRunner::new(),
+        event_stream.clone(),
+        ChannelStream::new(),
+        handle,
+    )
+    .await;
+    let runner = async_spawn(async move { task_runner.launch().await });
+
+    for (step, input, event) in inputs {
+        event_stream.publish(event).await;
+        async_sleep(SETTLE).await;
+        // the first event seen is the one just sent
+        let actual: Vec<Observed> = std::iter::from_fn(|| emitted.next().now_or_never().flatten())
+            .skip(1)
+            .map(Observed::from)
+            .collect();
+        if !emitted_on(step, input, actual) {
+            break;
+        }
+    }
+
+    event_stream.publish(HotShotEvent::Shutdown).await;
+    let _ = runner.await;
+}
+
+/// Random scripts to check the consensus task against the model with
+#[derive(Clone, Debug)]
+pub struct ConsensusModelDescription {
+    /// how many scripts to play
+    pub cases: usize,
+    /// the most steps a script has
+    pub max_steps: usize,
+    /// the most smaller scripts to try when shrinking one the model disagrees on
+    pub max_shrinks: usize,
+}
+
+impl Default for ConsensusModelDescription {
+    fn default() -> Self {
+        Self {
+            cases: 32,
+            max_steps: 24,
+            max_shrinks: 256,
+        }
+    }
+}
+
+impl ConsensusModelDescription {
+    /// Play random scripts drawn from [`SimulationRng`], shrinking the first the model disagrees on
+    ///
+    /// # Panics
+    /// Panics with the shrunk script and how the model disagrees on it, if it ever does
+    pub async fn check(self) {
+        for case in 0..self.cases {
+            let script = Script::generate(&mut SimulationRng, self.max_steps);
+            if let Err(e) = play(&script).await {
+                let (script, e) = self.shrink(script, e).await;
+                panic!("Case {case} failed: {e}\nShrunk to {script:#?}");
+            }
+        }
+    }
+
+    /// The smallest script found that the model still disagrees on, starting from `script`
+    async fn shrink(
+        &self,
+        mut script: Script,
+        mut e: ConsensusModelErr,
+    ) -> (Script, ConsensusModelErr) {
+        let mut tries = 0;
+        'shrinking: while tries < self.max_shrinks {
+            for candidate in script.shrink_candidates() {
+                if tries == self.max_shrinks {
+                    break 'shrinking;
+                }
+                tries += 1;
+                if let Err(candidate_e) = play(&candidate).await {
+                    script = candidate;
+                    e = candidate_e;
+                    continue 'shrinking;
+                }
+            }
+            break;
+        }
+        (script, e)
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::VecDeque,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
+
+use crate::{
+    simulation::Instant, test_launcher::TaskGenerator, test_runner::Node, GlobalTestEvent,
+};
+use async_compatibility_layer::art::async_sleep;
+use futures::FutureExt;
+use hotshot::{traits::TestableNodeImplementation, HotShotType, SystemContext};
+use hotshot_task::{
+    boxed_sync,
+    event_stream::ChannelStream,
+    task::{FilterEvent, HandleEvent, HandleMessage, HotShotTaskCompleted, HotShotTaskTypes, TS},
+    task_impls::{HSTWithEventAndMessage, TaskBuilder},
+    GeneratedStream,
+};
+use hotshot_types::traits::{
+    network::LinkFaults, node_implementation::NodeType, signature_key::SignatureKey,
+};
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+pub enum FaultTaskErr {
+    /// the networks of the test can't inject link faults
+    #[snafu(display("The networks of the test can't inject link faults"))]
+    Unsupported,
+    /// no node decided a new view in time after the network healed
+    #[snafu(display("No view decided within {within:?} of healing, still at view {view}"))]
+    NoProgressAfterHeal { within: Duration, view: u64 },
+}
+
+/// Fault task types
+pub type FaultTaskTypes<TYPES, I> = HSTWithEventAndMessage<
+    FaultTaskErr,
+    GlobalTestEvent,
+    ChannelStream<GlobalTestEvent>,
+    (),
+    GeneratedStream<()>,
+    FaultTask<TYPES, I>,
+>;
+
+/// Task that changes the links between the nodes of a test over time
+pub struct FaultTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
+    pub(crate) handles: Vec<Node<TYPES, I>>,
+    /// what to do next, in order
+    pub(crate) actions: VecDeque<FaultAction>,
+    /// the link faults currently applied
+    pub(crate) faults: LinkFaults<TYPES::SignatureKey>,
+    /// the highest view decided by any node when the network last healed
+    pub(crate) decided_at_heal: Option<u64>,
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TS for FaultTask<TYPES, I> {}
+
+/// A change of the links between the nodes of a test. Nodes are referred to by index.
+#[derive(Clone, Debug)]
+pub enum LinkChange {
+    /// split the nodes into groups that can only reach their own group. Nodes in no group can only
+    /// reach each other. Replaces any previous partition.
+    Partition(Vec<Vec<usize>>),
+    /// drop everything `from` sends to `to`, but not what `to` sends to `from`
+    FailLink { from: usize, to: usize },
+    /// deliver what `from` sends to `to` again
+    RestoreLink { from: usize, to: usize },
+    /// delay everything `from` sends to `to` by `latency`
+    SetLatency {
+        from: usize,
+        to: usize,
+        latency: Duration,
+    },
+    /// delay everything node `i` sends to node `j` by `matrix[i][j]`, replacing every latency set
+    /// before
+    LatencyMatrix(Vec<Vec<Duration>>),
+    /// remove every partition, failed link and latency
+    Heal,
+}
+
+/// What the fault task does at a point of a test
+#[derive(Clone, Debug)]
+pub enum FaultAction {
+    /// change the links
+    Change(LinkChange),
+    /// check a view was decided since the network last healed
+    CheckProgress(Duration),
+}
+
+/// Plan of the link faults of a test
+#[derive(Clone, Debug, Default)]
+pub struct FaultPlanDescription {
+    /// the changes to make, with how long after the start of the test they are made
+    pub link_changes: Vec<(Duration, LinkChange)>,
+    /// if set, some node must decide a new view within this long of each `Heal`, for liveness to
+    /// count as resumed. The test must last long enough for the check to run.
+    pub progress_after_heal: Option<Duration>,
+}
+
+impl FaultPlanDescription {
+    /// The actions of the plan, with how long after the start of the test they happen, in order
+    #[must_use]
+    pub fn schedule(&self) -> Vec<(Duration, FaultAction)> {
+        let mut schedule = Vec::new();
+        for (at, change) in &self.link_changes {
+            if let (LinkChange::Heal, Some(within)) = (change, self.progress_after_heal) {
+                schedule.push((*at + within, FaultAction::CheckProgress(within)));
+            }
+            schedule.push((*at, FaultAction::Change(change.clone())));
+        }
+        // stable, so changes made at the same time stay in order
+        schedule.sort_by_key(|(at, _)| *at);
+        schedule
+    }
+
+    pub fn build<TYPES: NodeType, I: TestableNodeImplementation<TYPES>>(
+        self,
+    ) -> TaskGenerator<FaultTask<TYPES, I>>
+    where
+        SystemContext<TYPES, I>: HotShotType<TYPES, I>,
+    {
+        Box::new(move |state, mut registry, test_event_stream| {
+            async move {
+                let event_handler =
+                    HandleEvent::<FaultTaskTypes<TYPES, I>>(Arc::new(move |event, state| {
+                        async move {
+                            match event {
+                                GlobalTestEvent::ShutDown => {
+                                    (Some(HotShotTaskCompleted::ShutDown), state)
+                                }
+                            }
+                        }
+                        .boxed()
+                    }));
+                let start = Instant::now();
+                let atomic_idx = Arc::new(AtomicUsize::new(0));
+                let offsets = Arc::new(
+                    self.schedule()
+                        .into_iter()
+                        .map(|(at, _)| at)
+                        .collect::<Vec<_>>(),
+                );
+                let stream_generator = GeneratedStream::new(Arc::new(move || {
+                    let atomic_idx = atomic_idx.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    offsets.get(atomic_idx).copied().map(|at| {
+                        let fut = async move {
+                            async_sleep((start + at).saturating_duration_since(Instant::now()))
+                                .await;
+                        };
+                        boxed_sync(fut)
+                    })
+                }));
+                let message_handler =
+                    HandleMessage::<FaultTaskTypes<TYPES, I>>(Arc::new(move |_msg, mut state| {
+                        async move {
+                            let result = match state.actions.pop_front() {
+                                Some(FaultAction::Change(change)) => state.apply(change).await,
+                                Some(FaultAction::CheckProgress(within)) => {
+                                    state.check_progress(within).await
+                                }
+                                None => Ok(()),
+                            };
+                            match result {
+                                Ok(()) => (None, state),
+                                Err(e) => (Some(HotShotTaskCompleted::Error(Box::new(e))), state),
+                            }
+                        }
+                        .boxed()
+                    }));
+                let builder =
+                    TaskBuilder::<FaultTaskTypes<TYPES, I>>::new("Test Fault Task".to_string())
+                        .register_event_stream(test_event_stream, FilterEvent::default())
+                        .await
+                        .register_registry(&mut registry)
+                        .await
+                        .register_state(state)
+                        .register_event_handler(event_handler)
+                        .register_message_handler(message_handler)
+                        .register_message_stream(stream_generator);
+                let task_id = builder.get_task_id().unwrap();
+                (task_id, FaultTaskTypes::build(builder).launch())
+            }
+            .boxed()
+        })
+    }
+}
+
+/// The public key of the node at `idx`, as the test runner generates it
+fn node_key<K: SignatureKey>(idx: usize) -> K {
+    K::generated_from_seed_indexed([0u8; 32], idx as u64).0
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> FaultTask<TYPES, I> {
+    /// Make `change` to the link faults, and apply them to the networks of the test
+    async fn apply(&mut self, change: LinkChange) -> Result<(), FaultTaskErr> {
+        tracing::info!("Changing the network links: {change:?}");
+        let faults = &mut self.faults;
+        match change {
+            LinkChange::Partition(groups) => {
+                faults.partitions = groups
+                    .into_iter()
+                    .map(|group| group.into_iter().map(node_key).collect())
+                    .collect();
+            }
+            LinkChange::FailLink { from, to } => {
+                faults.failed_links.insert((node_key(from), node_key(to)));
+            }
+            LinkChange::RestoreLink { from, to } => {
+                faults.failed_links.remove(&(node_key(from), node_key(to)));
+            }
+            LinkChange::SetLatency { from, to, latency } => {
+                faults
+                    .latencies
+                    .insert((node_key(from), node_key(to)), latency);
+            }
+            LinkChange::LatencyMatrix(matrix) => {
+                faults.latencies.clear();
+                for (from, row) in matrix.into_iter().enumerate() {
+                    for (to, latency) in row.into_iter().enumerate() {
+                        faults
+                            .latencies
+                            .insert((node_key(from), node_key(to)), latency);
+                    }
+                }
+            }
+            LinkChange::Heal => {
+                *faults = LinkFaults::default();
+                self.decided_at_heal = Some(self.highest_decided_view().await);
+            }
+        }
+
+        // every node of a test shares the same networks, so any node's channels will do
+        let Some(node) = self.handles.first() else {
+            return Ok(());
+        };
+        let networks = &node.handle.hotshot.inner.networks;
+        if I::set_link_faults(&networks.quorum_network, &networks.da_network, &self.faults) {
+            Ok(())
+        } else {
+            Err(FaultTaskErr::Unsupported)
+        }
+    }
+
+    /// Check some node decided a view since the network last healed
+    async fn check_progress(&self, within: Duration) -> Result<(), FaultTaskErr> {
+        let view = self.highest_decided_view().await;
+        match self.decided_at_heal {
+            Some(healed) if view <= healed => {
+                Err(FaultTaskErr::NoProgressAfterHeal { within, view })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The highest view decided by any node
+    async fn highest_decided_view(&self) -> u64 {
+        let mut highest = 0;
+        for node in &self.handles {
+            let view = *node.handle.get_decided_leaf().await.get_view_number();
+            highest = highest.max(view);
+        }
+        highest
+    }
+}
@@ -15,6 +15,12 @@ pub mod test_runner;
 /// task that's consuming events and asserting safety
 pub mod overall_safety_task;
 
+/// task that's checking the invariants of each node on its own
+pub mod per_node_safety_task;
+
+/// task measuring time to decide and the size of the events nodes exchange
+pub mod measurement_task;
+
 /// task that's submitting transactions to the stream
 pub mod txn_task;
 
@@ -27,6 +33,21 @@ pub mod node_types;
 /// task to spin nodes up and down
 pub mod spinning_task;
 
+/// nodes that misbehave
+pub mod byzantine;
+
+/// task to partition the network and fail, restore and slow down links
+pub mod fault_task;
+
+/// deterministic simulation of tests
+pub mod simulation;
+
+/// property testing of the consensus task against a model of it
+pub mod consensus_model;
+
+/// latency, throughput and event size benchmarks over sweeps of test networks
+pub mod benchmark;
+
 // TODO node changer (spin up and down)
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,304 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_compatibility_layer::channel::UnboundedStream;
+use async_lock::Mutex;
+use bincode::Options;
+use commit::Committable;
+use either::Either;
+use futures::FutureExt;
+use hotshot::traits::TestableNodeImplementation;
+use hotshot_task::{
+    event_stream::ChannelStream,
+    task::{FilterEvent, HandleEvent, HandleMessage, HotShotTaskCompleted, HotShotTaskTypes, TS},
+    task_impls::{HSTWithEventAndMessage, TaskBuilder},
+    Merge, MergeN,
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    event::{Event, EventType},
+    traits::{node_implementation::NodeType, BlockPayload},
+};
+use hotshot_utils::bincode::bincode_opts;
+use snafu::Snafu;
+
+use crate::{
+    overall_safety_task::LoadReport, per_node_safety_task::Misbehaviour, simulation::Instant,
+    test_launcher::TaskGenerator, test_runner::Node, GlobalTestEvent,
+};
+
+/// the measurement task only observes, so it never fails
+#[derive(Snafu, Debug)]
+pub struct MeasurementTaskErr {}
+
+/// What was measured over a test run
+#[derive(Clone, Debug, Default)]
+pub struct RunMeasurements {
+    /// when the first node entered a view
+    pub first_view_at: Option<Instant>,
+    /// when a node last decided a leaf
+    pub last_decide_at: Option<Instant>,
+    /// time from a node entering a view to it deciding the leaf of that view, for each node and
+    /// each view past the warmup
+    pub time_to_decide: Vec<Duration>,
+    /// transactions in the leaf decided in each view
+    pub decided_txns: BTreeMap<u64, usize>,
+    /// commitment of the leaf first decided in each view, to compare runs by
+    pub decided_leaves: BTreeMap<u64, Vec<u8>>,
+    /// bincode size of the message events each node handed to its networks, by node index. This
+    /// is not what went over the wire, which the networks' own metrics count
+    pub event_bytes_sent: Vec<u64>,
+    /// bincode size of the message events each node got from its networks, by node index
+    pub event_bytes_received: Vec<u64>,
+    /// the transactions submitted and how long they took to be decided
+    pub load: LoadReport,
+    /// how many events each byzantine node sent differently than it honestly would have, by id
+    pub misbehaviours_sent: BTreeMap<u64, usize>,
+    /// the misbehaviour each node saw evidence of, by id
+    pub misbehaviours_detected: BTreeMap<u64, BTreeSet<Misbehaviour>>,
+}
+
+impl RunMeasurements {
+    /// time from the first view to the last decide
+    #[must_use]
+    pub fn elapsed(&self) -> Option<Duration> {
+        match (self.first_view_at, self.last_decide_at) {
+            (Some(first), Some(last)) if last > first => Some(last.duration_since(first)),
+            _ => None,
+        }
+    }
+
+    /// the `p`th percentile of the time to decide, for `p` up to 100
+    #[must_use]
+    pub fn time_to_decide_percentile(&self, p: usize) -> Option<Duration> {
+        let mut samples = self.time_to_decide.clone();
+        samples.sort();
+        samples
+            .get((samples.len() * p / 100).min(samples.len().saturating_sub(1)))
+            .copied()
+    }
+
+    /// transactions decided in all views
+    #[must_use]
+    pub fn num_decided_txns(&self) -> usize {
+        self.decided_txns.values().sum()
+    }
+}
+
+/// Task measuring how long nodes take to decide and the size of the events they send, as they run
+pub struct MeasurementTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
+    /// handles
+    pub handles: Vec<Node<TYPES, I>>,
+    /// when each node entered each view it hasn't decided yet, by node index
+    pub view_starts: HashMap<(usize, TYPES::Time), Instant>,
+    /// the views each node decided, by node index
+    pub decided: HashSet<(usize, TYPES::Time)>,
+    /// the measurements, shared with the test runner
+    pub measurements: Arc<Mutex<RunMeasurements>>,
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TS for MeasurementTask<TYPES, I> {}
+
+/// what to measure
+#[derive(Clone, Debug, Default)]
+pub struct MeasurementTaskDescription {
+    /// views before this one don't count towards the time to decide, to leave startup out
+    pub warmup_views: u64,
+    /// whether or not to serialize every message event a node sends or receives to count its bytes
+    pub measure_event_bytes: bool,
+}
+
+/// whether a node sent or received a message
+enum Direction {
+    /// handed to the node's networks
+    Sent,
+    /// got from the node's networks
+    Received,
+}
+
+/// which way `event` went, if it's a message between nodes
+fn direction<TYPES: NodeType>(event: &HotShotEvent<TYPES>) -> Option<Direction> {
+    match event {
+        HotShotEvent::QuorumProposalSend(..)
+        | HotShotEvent::QuorumVoteSend(_)
+        | HotShotEvent::TimeoutVoteSend(_)
+        | HotShotEvent::DAProposalSend(..)
+        | HotShotEvent::DAVoteSend(_)
+        | HotShotEvent::DACSend(..)
+        | HotShotEvent::ViewSyncPreCommitVoteSend(_)
+        | HotShotEvent::ViewSyncCommitVoteSend(_)
+        | HotShotEvent::ViewSyncFinalizeVoteSend(_)
+        | HotShotEvent::ViewSyncPreCommitCertificate2Send(..)
+        | HotShotEvent::ViewSyncCommitCertificate2Send(..)
+        | HotShotEvent::ViewSyncFinalizeCertificate2Send(..)
+        | HotShotEvent::TransactionSend(..)
+        | HotShotEvent::VidDisperseSend(..)
+        | HotShotEvent::VidVoteSend(_)
+        | HotShotEvent::VidCertSend(..) => Some(Direction::Sent),
+        HotShotEvent::QuorumProposalRecv(..)
+        | HotShotEvent::QuorumVoteRecv(_)
+        | HotShotEvent::TimeoutVoteRecv(_)
+        | HotShotEvent::DAProposalRecv(..)
+        | HotShotEvent::DAVoteRecv(_)
+        | HotShotEvent::DACRecv(_)
+        | HotShotEvent::ViewSyncPreCommitVoteRecv(_)
+        | HotShotEvent::ViewSyncCommitVoteRecv(_)
+        | HotShotEvent::ViewSyncFinalizeVoteRecv(_)
+        | HotShotEvent::ViewSyncPreCommitCertificate2Recv(_)
+        | HotShotEvent::ViewSyncCommitCertificate2Recv(_)
+        | HotShotEvent::ViewSyncFinalizeCertificate2Recv(_)
+        | HotShotEvent::TransactionsRecv(_)
+        | HotShotEvent::VidDisperseRecv(..)
+        | HotShotEvent::VidVoteRecv(_)
+        | HotShotEvent::VidCertRecv(_) => Some(Direction::Received),
+        _ => None,
+    }
+}
+
+impl MeasurementTaskDescription {
+    /// build a task
+    pub fn build<TYPES: NodeType, I: TestableNodeImplementation<TYPES>>(
+        self,
+    ) -> TaskGenerator<MeasurementTask<TYPES, I>> {
+        Box::new(move |mut state, mut registry, test_event_stream| {
+            async move {
+                let event_handler =
+                    HandleEvent::<MeasurementTaskTypes<TYPES, I>>(Arc::new(move |event, state| {
+                        async move {
+                            match event {
+                                GlobalTestEvent::ShutDown => {
+                                    (Some(HotShotTaskCompleted::ShutDown), state)
+                                }
+                            }
+                        }
+                        .boxed()
+                    }));
+
+                let message_handler = HandleMessage::<MeasurementTaskTypes<TYPES, I>>(Arc::new(
+                    move |msg, mut state| {
+                        let description = self.clone();
+                        async move {
+                            let (idx, event) = msg;
+                            state.measure(idx, event, &description).await;
+                            (None, state)
+                        }
+                        .boxed()
+                    },
+                ));
+
+                let mut streams = vec![];
+                for node in &mut state.handles {
+                    let external = node
+                        .handle
+                        .get_event_stream_known_impl(FilterEvent::default())
+                        .await
+                        .0;
+                    let internal = node
+                        .handle
+                        .get_internal_event_stream_known_impl(FilterEvent::default())
+                        .await
+                        .0;
+                    streams.push(Merge::new(external, internal));
+                }
+                let builder = TaskBuilder::<MeasurementTaskTypes<TYPES, I>>::new(
+                    "Test Measurement Task".to_string(),
+                )
+                .register_event_stream(test_event_stream, FilterEvent::default())
+                .await
+                .register_registry(&mut registry)
+                .await
+                .register_message_handler(message_handler)
+                .register_message_stream(MergeN::new(streams))
+                .register_event_handler(event_handler)
+                .register_state(state);
+                let task_id = builder.get_task_id().unwrap();
+                (task_id, MeasurementTaskTypes::build(builder).launch())
+            }
+            .boxed()
+        })
+    }
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> MeasurementTask<TYPES, I> {
+    /// Measure `event` of the node at `idx`
+    async fn measure(
+        &mut self,
+        idx: usize,
+        event: Either<Event<TYPES>, HotShotEvent<TYPES>>,
+        description: &MeasurementTaskDescription,
+    ) {
+        let now = Instant::now();
+        match event {
+            Either::Right(HotShotEvent::ViewChange(view)) => {
+                if !self.decided.contains(&(idx, view)) {
+                    self.view_starts.entry((idx, view)).or_insert(now);
+                }
+                let mut measurements = self.measurements.lock().await;
+                measurements.first_view_at.get_or_insert(now);
+            }
+            Either::Left(Event {
+                event: EventType::Decide { leaf_chain, .. },
+                ..
+            }) => {
+                let mut measurements = self.measurements.lock().await;
+                for leaf in leaf_chain.iter() {
+                    let view = leaf.view_number;
+                    if !self.decided.insert((idx, view)) {
+                        continue;
+                    }
+                    // a node that caught up to a view without entering it has nothing to measure
+                    if let Some(start) = self.view_starts.remove(&(idx, view)) {
+                        if *view >= description.warmup_views {
+                            measurements.time_to_decide.push(now.duration_since(start));
+                        }
+                    }
+                    measurements
+                        .decided_leaves
+                        .entry(*view)
+                        .or_insert_with(|| AsRef::<[u8]>::as_ref(&leaf.commit()).to_vec());
+                    if let Some(payload) = &leaf.block_payload {
+                        let txns = payload.transaction_commitments().len();
+                        let decided_txns = measurements.decided_txns.entry(*view).or_default();
+                        *decided_txns = (*decided_txns).max(txns);
+                    }
+                    measurements.last_decide_at = Some(now);
+                }
+            }
+            Either::Right(event) if description.measure_event_bytes => {
+                let Some(direction) = direction(&event) else {
+                    return;
+                };
+                let size = match bincode_opts().serialized_size(&event) {
+                    Ok(size) => size,
+                    Err(e) => {
+                        tracing::error!("Failed to measure the size of {event:?}: {e}");
+                        return;
+                    }
+                };
+                let mut measurements = self.measurements.lock().await;
+                let totals = match direction {
+                    Direction::Sent => &mut measurements.event_bytes_sent,
+                    Direction::Received => &mut measurements.event_bytes_received,
+                };
+                if totals.len() <= idx {
+                    totals.resize(idx + 1, 0);
+                }
+                totals[idx] += size;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// types for the measurement task
+pub type MeasurementTaskTypes<TYPES, I> = HSTWithEventAndMessage<
+    MeasurementTaskErr,
+    GlobalTestEvent,
+    ChannelStream<GlobalTestEvent>,
+    (usize, Either<Event<TYPES>, HotShotEvent<TYPES>>),
+    MergeN<Merge<UnboundedStream<Event<TYPES>>, UnboundedStream<HotShotEvent<TYPES>>>>,
+    MeasurementTask<TYPES, I>,
+>;
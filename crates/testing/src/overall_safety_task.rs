@@ -3,11 +3,13 @@ use either::Either;
 use hotshot_task::{event_stream::EventStream, Merge};
 use hotshot_task_impls::events::HotShotEvent;
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use async_compatibility_layer::channel::UnboundedStream;
+use async_lock::Mutex;
 use futures::FutureExt;
 use hotshot::{traits::TestableNodeImplementation, HotShotError};
 use hotshot_task::{
@@ -21,11 +23,14 @@ use hotshot_types::{
     error::RoundTimedoutState,
     event::{Event, EventType},
     simple_certificate::QuorumCertificate,
-    traits::node_implementation::NodeType,
+    traits::{node_implementation::NodeType, BlockPayload},
 };
 use snafu::Snafu;
+use tracing::info;
 
-use crate::{test_launcher::TaskGenerator, test_runner::Node};
+use crate::{
+    simulation::Instant, test_launcher::TaskGenerator, test_runner::Node, txn_task::SubmittedTxns,
+};
 pub type StateAndBlock<S, B> = (Vec<S>, Vec<B>);
 
 use super::GlobalTestEvent;
@@ -78,10 +83,107 @@ pub struct OverallSafetyTask<TYPES: NodeType, I: TestableNodeImplementation<TYPE
     pub ctx: RoundCtx<TYPES>,
     /// event stream for publishing safety violations
     pub test_event_stream: ChannelStream<GlobalTestEvent>,
+    /// transactions submitted by the transaction task
+    pub submitted: Arc<Mutex<SubmittedTxns<TYPES>>>,
+    /// the load report as of when the task finished
+    pub load: Arc<Mutex<LoadReport>>,
 }
 
 impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TS for OverallSafetyTask<TYPES, I> {}
 
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> OverallSafetyTask<TYPES, I> {
+    /// measure how long the submitted transactions in `leaf_chain` took to be decided, for the
+    /// leaves not seen decided before
+    async fn record_decided(&mut self, leaf_chain: &[Leaf<TYPES>]) {
+        let now = Instant::now();
+        let mut submitted = self.submitted.lock().await;
+        for leaf in leaf_chain {
+            // only count a leaf once its payload is known, whichever node decides it first
+            let Some(payload) = &leaf.block_payload else {
+                continue;
+            };
+            if !self.ctx.decided_leaves.insert(leaf.view_number) {
+                continue;
+            }
+            for txn in payload.transaction_commitments() {
+                if let Some(submitted_at) = submitted.remove(&txn) {
+                    self.ctx
+                        .inclusion_latencies
+                        .push(now.duration_since(submitted_at));
+                    self.ctx.last_inclusion = Some(now);
+                }
+            }
+        }
+    }
+
+    /// throughput and inclusion latency of the transactions submitted so far
+    pub async fn load_report(&self) -> LoadReport {
+        let submitted = self.submitted.lock().await;
+        let mut latencies = self.ctx.inclusion_latencies.clone();
+        latencies.sort();
+        let throughput = match (submitted.first_submission, self.ctx.last_inclusion) {
+            (Some(first), Some(last)) if last > first => {
+                latencies.len() as f64 / last.duration_since(first).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+        let percentile = |p: usize| {
+            latencies
+                .get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+                .copied()
+        };
+        LoadReport {
+            num_submitted: submitted.num_submitted,
+            submitted_per_node: submitted.per_node.clone(),
+            paddings: submitted.paddings.clone(),
+            submissions_per_second: submitted.per_second.clone(),
+            num_decided: latencies.len(),
+            throughput,
+            mean_latency: u32::try_from(latencies.len())
+                .ok()
+                .filter(|len| *len > 0)
+                .map(|len| latencies.iter().sum::<Duration>() / len),
+            median_latency: percentile(50),
+            p99_latency: percentile(99),
+            max_latency: latencies.last().copied(),
+        }
+    }
+
+    /// log the load report and keep it for the test runner, as the task finishes
+    async fn finish_load_report(&self) {
+        let report = self.load_report().await;
+        info!("Transaction load: {:?}", report);
+        *self.load.lock().await = report;
+    }
+}
+
+/// Throughput and inclusion latency of the transactions submitted during a test
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// transactions submitted
+    pub num_submitted: usize,
+    /// node index -> transactions submitted to it
+    pub submitted_per_node: BTreeMap<usize, usize>,
+    /// padding -> transactions submitted with it
+    pub paddings: BTreeMap<u64, usize>,
+    /// transactions submitted in each second since the first submission, the last second being
+    /// cut short by the end of the test
+    pub submissions_per_second: Vec<usize>,
+    /// submitted transactions seen decided
+    pub num_decided: usize,
+    /// transactions decided per second, from the first submission to the last decide including
+    /// a submitted transaction
+    pub throughput: f64,
+    /// mean time from submitting a transaction to it being decided
+    pub mean_latency: Option<Duration>,
+    /// median time from submitting a transaction to it being decided
+    pub median_latency: Option<Duration>,
+    /// 99th percentile of the time from submitting a transaction to it being decided
+    pub p99_latency: Option<Duration>,
+    /// longest time from submitting a transaction to it being decided
+    pub max_latency: Option<Duration>,
+}
+
 /// Result of running a round of consensus
 #[derive(Debug)]
 pub struct RoundResult<TYPES: NodeType> {
@@ -136,6 +238,9 @@ impl<TYPES: NodeType> Default for RoundCtx<TYPES> {
             round_results: Default::default(),
             failed_views: Default::default(),
             successful_views: Default::default(),
+            decided_leaves: Default::default(),
+            inclusion_latencies: Default::default(),
+            last_inclusion: None,
         }
     }
 }
@@ -153,6 +258,12 @@ pub struct RoundCtx<TYPES: NodeType> {
     pub failed_views: HashSet<TYPES::Time>,
     /// successful views
     pub successful_views: HashSet<TYPES::Time>,
+    /// views of the decided leaves whose transactions were counted
+    pub decided_leaves: HashSet<TYPES::Time>,
+    /// time from submission to decide of each submitted transaction seen decided
+    pub inclusion_latencies: Vec<Duration>,
+    /// when a submitted transaction was last seen decided
+    pub last_inclusion: Option<Instant>,
 }
 
 impl<TYPES: NodeType> RoundCtx<TYPES> {
@@ -398,6 +509,7 @@ impl OverallSafetyPropertiesDescription {
                         async move {
                             match event {
                                 GlobalTestEvent::ShutDown => {
+                                    state.finish_load_report().await;
                                     let num_incomplete_views = state.ctx.round_results.len()
                                         - state.ctx.successful_views.len()
                                         - state.ctx.failed_views.len();
@@ -453,6 +565,7 @@ impl OverallSafetyPropertiesDescription {
                                         qc,
                                         block_size: maybe_block_size,
                                     } => {
+                                        state.record_decided(&leaf_chain).await;
                                         let paired_up = (leaf_chain.to_vec(), (*qc).clone());
                                         match state.ctx.round_results.entry(view_number) {
                                             Entry::Occupied(mut o) => o.get_mut().insert_into_result(
@@ -506,6 +619,7 @@ impl OverallSafetyPropertiesDescription {
                                             if state.ctx.successful_views.len()
                                                 >= self.num_successful_views
                                                 {
+                                                    state.finish_load_report().await;
                                                     state
                                                         .test_event_stream
                                                         .publish(GlobalTestEvent::ShutDown)
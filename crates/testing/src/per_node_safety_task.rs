@@ -1,258 +1,470 @@
-// // TODO rename this file to per-node
-//
-// use std::{ops::Deref, sync::Arc};
-//
-// use async_compatibility_layer::channel::UnboundedStream;
-// use either::Either;
-// use futures::{
-//     future::{BoxFuture, LocalBoxFuture},
-//     FutureExt,
-// };
-// use hotshot::traits::TestableNodeImplementation;
-// use hotshot_task::{
-//     event_stream::ChannelStream,
-//     global_registry::{GlobalRegistry, HotShotTaskId},
-//     task::{
-//         FilterEvent, HandleEvent, HandleMessage, HotShotTaskCompleted, HotShotTaskTypes, TaskErr,
-//         HST, TS,
-//     },
-//     task_impls::{HSTWithEvent, HSTWithEventAndMessage, TaskBuilder},
-// };
-// use hotshot_types::{
-//     event::{Event, EventType},
-//     traits::node_implementation::NodeType,
-// };
-// use nll::nll_todo::nll_todo;
-// use snafu::Snafu;
-// use tracing::log::warn;
-//
-// use crate::test_errors::ConsensusTestError;
-//
-// use super::{
-//     completion_task::CompletionTask,
-//     node_ctx::{NodeCtx, ViewFailed, ViewStatus, ViewSuccess},
-//     GlobalTestEvent,
-// };
-//
-// #[derive(Snafu, Debug)]
-// pub enum PerNodeSafetyTaskErr {
-//     // TODO make this more detailed
-//     TooManyFailures,
-//     NotEnoughDecides,
-// }
-// impl TaskErr for PerNodeSafetyTaskErr {}
-//
-// /// Data availability task state
-// ///
-// #[derive(Debug)]
-// pub struct PerNodeSafetyTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>> {
-//     pub(crate) ctx: NodeCtx<TYPES, I>,
-// }
-//
-// impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>> Default
-//     for PerNodeSafetyTask<TYPES, I>
-// {
-//     fn default() -> Self {
-//         Self {
-//             ctx: Default::default(),
-//         }
-//     }
-// }
-//
-// impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>> TS
-//     for PerNodeSafetyTask<TYPES, I>
-// {
-// }
-//
-// /// builder describing custom safety properties
-// #[derive(Clone)]
-// pub enum PerNodeSafetyTaskDescription<
-//     TYPES: NodeType,
-//     I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>,
-// > {
-//     GenProperties(PerNodeSafetyPropertiesDescription),
-//     CustomProperties(PerNodeSafetyFinisher<TYPES, I>),
-// }
-//
-// /// properties used for gen
-// #[derive(Clone, Debug)]
-// pub struct PerNodeSafetyPropertiesDescription {
-//     /// number failed views
-//     pub num_failed_views: Option<usize>,
-//     /// number decide events
-//     pub num_decide_events: Option<usize>,
-// }
-//
-// // basic consistency check for single node
-// /// Exists for easier overriding
-// /// runs at end of all tasks
-// #[derive(Clone)]
-// #[allow(clippy::type_complexity)]
-// pub struct PerNodeSafetyFinisher<
-//     TYPES: NodeType,
-//     I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>,
-// >(
-//     pub  Arc<
-//         dyn for<'a> Fn(&'a mut NodeCtx<TYPES, I>) -> BoxFuture<'a, Result<(), PerNodeSafetyTaskErr>>
-//             + Send
-//             + 'static
-//             + Sync,
-//     >,
-// );
-//
-// impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>> Deref
-//     for PerNodeSafetyFinisher<TYPES, I>
-// {
-//     type Target = dyn for<'a> Fn(&'a mut NodeCtx<TYPES, I>) -> BoxFuture<'a, Result<(), PerNodeSafetyTaskErr>>
-//         + Send
-//         + 'static
-//         + Sync;
-//
-//     fn deref(&self) -> &Self::Target {
-//         &*self.0
-//     }
-// }
-//
-// impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>>
-//     PerNodeSafetyTaskDescription<TYPES, I>
-// {
-//     fn gen_finisher(self) -> PerNodeSafetyFinisher<TYPES, I> {
-//         match self {
-//             PerNodeSafetyTaskDescription::CustomProperties(finisher) => finisher,
-//             PerNodeSafetyTaskDescription::GenProperties(PerNodeSafetyPropertiesDescription {
-//                 num_failed_views,
-//                 num_decide_events,
-//             }) => PerNodeSafetyFinisher(Arc::new(move |ctx: &mut NodeCtx<TYPES, I>| {
-//                 async move {
-//                     let mut num_failed = 0;
-//                     let mut num_decided = 0;
-//                     for (_view_num, view_status) in &ctx.round_results {
-//                         match view_status {
-//                             ViewStatus::InProgress(_) => {}
-//                             ViewStatus::ViewFailed(_) => {
-//                                 num_failed += 1;
-//                             }
-//                             ViewStatus::ViewSuccess(_) => {
-//                                 num_decided += 1;
-//                             }
-//                         }
-//                     }
-//                     if let Some(num_failed_views) = num_failed_views {
-//                         if num_failed >= num_failed_views {
-//                             return Err(PerNodeSafetyTaskErr::TooManyFailures);
-//                         }
-//                     }
-//
-//                     if let Some(num_decide_events) = num_decide_events {
-//                         if num_decided < num_decide_events {
-//                             return Err(PerNodeSafetyTaskErr::NotEnoughDecides);
-//                         }
-//                     }
-//                     Ok(())
-//                 }
-//                 .boxed()
-//             })),
-//         }
-//     }
-//
-//     /// build
-//     pub fn build(
-//         self,
-//         // registry: &mut GlobalRegistry,
-//         // test_event_stream: ChannelStream<GlobalTestEvent>,
-//         // hotshot_event_stream: UnboundedStream<Event<TYPES>>,
-//     ) -> TaskGenerator<
-//         PerNodeSafetyTask<TYPES, I>
-//     > {
-//         Box::new(
-//             move |state, mut registry, test_event_stream, hotshot_event_stream| {
-//                 // TODO this is cursed, there's definitely a better way to do this
-//                 let desc = self.clone();
-//                 async move {
-//                     let test_event_handler = HandleEvent::<PerNodeSafetyTaskTypes<TYPES, I>>(Arc::new(
-//                         move |event, mut state| {
-//                             let finisher = desc.clone().gen_finisher();
-//                             async move {
-//                                 match event {
-//                                     GlobalTestEvent::ShutDown => {
-//                                         let finished = finisher(&mut state.ctx).await;
-//                                         let result = match finished {
-//                                             Ok(()) => HotShotTaskCompleted::ShutDown,
-//                                             Err(err) => HotShotTaskCompleted::Error(Box::new(err)),
-//                                         };
-//                                         return (Some(result), state);
-//                                     }
-//                                     _ => {
-//                                         unimplemented!()
-//                                     }
-//                                 }
-//                             }
-//                             .boxed()
-//                         },
-//                     ));
-//                     let message_handler = HandleMessage::<PerNodeSafetyTaskTypes<TYPES, I>>(Arc::new(
-//                         move |msg, mut state| {
-//                             async move {
-//                                 let Event { view_number, event } = msg;
-//                                 match event {
-//                                     EventType::Error { error } => {
-//                                         // TODO better warn with node idx
-//                                         warn!("View {:?} failed for a replica", view_number);
-//                                         state.ctx.round_results.insert(
-//                                             view_number,
-//                                             ViewStatus::ViewFailed(ViewFailed(error)),
-//                                         );
-//                                     }
-//                                     EventType::Decide { leaf_chain, qc } => {
-//                                         state.ctx.round_results.insert(
-//                                             view_number,
-//                                             ViewStatus::ViewSuccess(ViewSuccess {
-//                                                 agreed_state:
-//
-//                                             }),
-//                                         );
-//                                     }
-//                                     // these aren't failures
-//                                     EventType::ReplicaViewTimeout { view_number }
-//                                     | EventType::NextLeaderViewTimeout { view_number }
-//                                     | EventType::ViewFinished { view_number } => todo!(),
-//                                     _ => todo!(),
-//                                 }
-//                                 (None, state)
-//                             }
-//                             .boxed()
-//                         },
-//                     ));
-//
-//                     let builder = TaskBuilder::<PerNodeSafetyTaskTypes<TYPES, I>>::new(
-//                         "Safety Check Task".to_string(),
-//                     )
-//                     .register_event_stream(test_event_stream, FilterEvent::default())
-//                     .await
-//                     .register_registry(&mut registry)
-//                     .await
-//                     .register_state(state)
-//                     .register_event_handler(test_event_handler)
-//                     .register_message_handler(message_handler)
-//                     .register_message_stream(hotshot_event_stream);
-//                     let task_id = builder.get_task_id().unwrap();
-//                     (task_id, PerNodeSafetyTaskTypes::build(builder).launch())
-//                 }
-//                 .boxed()
-//             },
-//         )
-//     }
-// }
-//
-// // /// Data Availability task types
-// pub type PerNodeSafetyTaskTypes<
-//     TYPES: NodeType,
-//     I: TestableNodeImplementation<TYPES::ConsensusType, TYPES>,
-// > = HSTWithEventAndMessage<
-//     PerNodeSafetyTaskErr,
-//     GlobalTestEvent,
-//     ChannelStream<GlobalTestEvent>,
-//     Event<TYPES>,
-//     UnboundedStream<Event<TYPES>>,
-//     PerNodeSafetyTask<TYPES, I>,
-// >;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use async_compatibility_layer::channel::UnboundedStream;
+use async_lock::Mutex;
+use commit::{Commitment, Committable};
+use either::Either;
+use futures::FutureExt;
+use hotshot::{traits::TestableNodeImplementation, HotShotConsensusApi};
+use hotshot_task::{
+    event_stream::{ChannelStream, EventStream},
+    task::{FilterEvent, HandleEvent, HandleMessage, HotShotTaskCompleted, HotShotTaskTypes, TS},
+    task_impls::{HSTWithEventAndMessage, TaskBuilder},
+    Merge, MergeN,
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    data::{Leaf, QuorumProposal},
+    event::{Event, EventType},
+    traits::{
+        consensus_api::ConsensusSharedApi, election::Membership, node_implementation::NodeType,
+        state::ConsensusTime,
+    },
+    vote::{Certificate, HasViewNumber, Vote},
+};
+use snafu::Snafu;
+
+use crate::{test_launcher::TaskGenerator, test_runner::Node, GlobalTestEvent};
+
+/// violations of a node's own invariants, with the node's events leading up to them, oldest first
+#[derive(Snafu, Debug, Clone)]
+pub enum PerNodeSafetyTaskErr {
+    /// a node voted more than once in a view
+    #[snafu(display("Node {node} voted twice in view {view}, after {events:#?}"))]
+    DoubleVote {
+        node: u64,
+        view: u64,
+        events: Vec<String>,
+    },
+    /// a node decided a leaf that isn't on the chain of the leaves it decided before
+    #[snafu(display(
+        "Node {node} decided a leaf in view {view} off its decided chain, after {events:#?}"
+    ))]
+    ForkedDecide {
+        node: u64,
+        view: u64,
+        events: Vec<String>,
+    },
+    /// a node's locked view went back
+    #[snafu(display("Node {node} went from locked view {from} back to {to}, after {events:#?}"))]
+    LockedViewDecreased {
+        node: u64,
+        from: u64,
+        to: u64,
+        events: Vec<String>,
+    },
+    /// a node's last decided view went back
+    #[snafu(display(
+        "Node {node} went from last decided view {from} back to {to}, after {events:#?}"
+    ))]
+    DecidedViewDecreased {
+        node: u64,
+        from: u64,
+        to: u64,
+        events: Vec<String>,
+    },
+}
+
+/// evidence a node saw of another node misbehaving, which isn't a violation of the node's own
+/// invariants as long as it doesn't act on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Misbehaviour {
+    /// the node got two different proposals for a view
+    ConflictingProposals { view: u64 },
+    /// the node, as leader, got votes for two different leaves from one key in a view
+    ConflictingVotes { view: u64 },
+    /// the node got a proposal justified by an invalid QC
+    InvalidQC { view: u64 },
+    /// the node, as DA committee member, timed out waiting on a view it got no DA proposal for
+    MissingDAProposal { view: u64 },
+}
+
+/// what a node did, as far as its invariants go
+pub struct NodeCtx<TYPES: NodeType> {
+    /// id of the node
+    node: u64,
+    /// the leaf the node voted for in each view
+    votes: HashMap<TYPES::Time, Commitment<Leaf<TYPES>>>,
+    /// the leaves the node decided, by view
+    decided: BTreeMap<TYPES::Time, Commitment<Leaf<TYPES>>>,
+    /// the highest locked view seen
+    locked_view: TYPES::Time,
+    /// the highest last decided view seen
+    last_decided_view: TYPES::Time,
+    /// the node's most recent events, oldest first
+    history: VecDeque<String>,
+    /// the highest view the node moved to
+    view: TYPES::Time,
+    /// the first proposal the node got for each view
+    proposals: HashMap<TYPES::Time, QuorumProposal<TYPES>>,
+    /// the leaf each key voted for in each view, of the votes the node got as leader
+    votes_received: HashMap<(TYPES::Time, TYPES::SignatureKey), Commitment<Leaf<TYPES>>>,
+    /// the views the node got a DA proposal for
+    da_proposals: HashSet<TYPES::Time>,
+}
+
+impl<TYPES: NodeType> NodeCtx<TYPES> {
+    /// context of a node that did nothing yet
+    fn new(node: u64) -> Self {
+        Self {
+            node,
+            votes: HashMap::new(),
+            decided: BTreeMap::new(),
+            locked_view: TYPES::Time::genesis(),
+            last_decided_view: TYPES::Time::genesis(),
+            history: VecDeque::new(),
+            view: TYPES::Time::genesis(),
+            proposals: HashMap::new(),
+            votes_received: HashMap::new(),
+            da_proposals: HashSet::new(),
+        }
+    }
+
+    /// remember `event`, forgetting the oldest event past `history_len`
+    fn record(&mut self, event: String, history_len: usize) {
+        self.history.push_back(event);
+        while self.history.len() > history_len {
+            self.history.pop_front();
+        }
+    }
+
+    /// the events to report a violation with
+    fn events(&self) -> Vec<String> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// check the node's vote for `leaf` in `view` is its first in that view
+    fn check_vote(
+        &mut self,
+        view: TYPES::Time,
+        leaf: Commitment<Leaf<TYPES>>,
+    ) -> Result<(), PerNodeSafetyTaskErr> {
+        if self.votes.insert(view, leaf).is_some() {
+            return Err(PerNodeSafetyTaskErr::DoubleVote {
+                node: self.node,
+                view: *view,
+                events: self.events(),
+            });
+        }
+        Ok(())
+    }
+
+    /// check `leaf` extends the chain of the leaves decided so far
+    fn check_decided(&mut self, leaf: &Leaf<TYPES>) -> Result<(), PerNodeSafetyTaskErr> {
+        let view = leaf.get_view_number();
+        let parent_view = leaf.justify_qc.view_number;
+        let commitment = leaf.commit();
+        // decided chains may have gaps during catchup, so check what we know of: the leaf's view
+        // and parent were decided as this leaf says, and nothing was decided in between
+        let forked = self
+            .decided
+            .get(&view)
+            .is_some_and(|decided| *decided != commitment)
+            || self
+                .decided
+                .get(&parent_view)
+                .is_some_and(|parent| *parent != leaf.parent_commitment)
+            || (parent_view < view
+                && self
+                    .decided
+                    .range(parent_view..view)
+                    .any(|(decided_view, _)| *decided_view != parent_view));
+        if forked {
+            return Err(PerNodeSafetyTaskErr::ForkedDecide {
+                node: self.node,
+                view: *view,
+                events: self.events(),
+            });
+        }
+        self.decided.insert(view, commitment);
+        Ok(())
+    }
+
+    /// check the node's locked view didn't go back
+    fn check_locked_view(&mut self, locked_view: TYPES::Time) -> Result<(), PerNodeSafetyTaskErr> {
+        if locked_view < self.locked_view {
+            return Err(PerNodeSafetyTaskErr::LockedViewDecreased {
+                node: self.node,
+                from: *self.locked_view,
+                to: *locked_view,
+                events: self.events(),
+            });
+        }
+        self.locked_view = locked_view;
+        Ok(())
+    }
+
+    /// check the node's last decided view didn't go back
+    fn check_last_decided_view(
+        &mut self,
+        last_decided_view: TYPES::Time,
+    ) -> Result<(), PerNodeSafetyTaskErr> {
+        if last_decided_view < self.last_decided_view {
+            return Err(PerNodeSafetyTaskErr::DecidedViewDecreased {
+                node: self.node,
+                from: *self.last_decided_view,
+                to: *last_decided_view,
+                events: self.events(),
+            });
+        }
+        self.last_decided_view = last_decided_view;
+        Ok(())
+    }
+}
+
+/// Task checking the invariants of each node on its own, as it runs
+pub struct PerNodeSafetyTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
+    /// handles
+    pub handles: Vec<Node<TYPES, I>>,
+    /// what each node did, by index in `handles`
+    pub ctx: HashMap<usize, NodeCtx<TYPES>>,
+    /// event stream for shutting the test down on violations
+    pub test_event_stream: ChannelStream<GlobalTestEvent>,
+    /// the misbehaviour each node saw evidence of, by node id
+    pub detected: Arc<Mutex<BTreeMap<u64, BTreeSet<Misbehaviour>>>>,
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TS for PerNodeSafetyTask<TYPES, I> {}
+
+/// per node safety properties
+#[derive(Clone, Debug)]
+pub struct PerNodeSafetyPropertiesDescription {
+    /// whether or not to check that nodes vote at most once per view
+    pub check_votes: bool,
+    /// whether or not to check that the leaves each node decides form a single chain
+    pub check_decided_chain: bool,
+    /// whether or not to check that the locked view of each node never decreases
+    pub check_locked_view: bool,
+    /// whether or not to check that the last decided view of each node never decreases
+    pub check_last_decided_view: bool,
+    /// how many of a node's most recent events to report a violation with
+    pub history_len: usize,
+}
+
+impl Default for PerNodeSafetyPropertiesDescription {
+    fn default() -> Self {
+        Self {
+            check_votes: true,
+            check_decided_chain: true,
+            check_locked_view: true,
+            check_last_decided_view: true,
+            history_len: 32,
+        }
+    }
+}
+
+impl PerNodeSafetyPropertiesDescription {
+    /// build a task
+    pub fn build<TYPES: NodeType, I: TestableNodeImplementation<TYPES>>(
+        self,
+    ) -> TaskGenerator<PerNodeSafetyTask<TYPES, I>> {
+        Box::new(move |mut state, mut registry, test_event_stream| {
+            async move {
+                let event_handler = HandleEvent::<PerNodeSafetyTaskTypes<TYPES, I>>(Arc::new(
+                    move |event, state| {
+                        async move {
+                            match event {
+                                GlobalTestEvent::ShutDown => {
+                                    (Some(HotShotTaskCompleted::ShutDown), state)
+                                }
+                            }
+                        }
+                        .boxed()
+                    },
+                ));
+
+                let message_handler = HandleMessage::<PerNodeSafetyTaskTypes<TYPES, I>>(Arc::new(
+                    move |msg, mut state| {
+                        let properties = self.clone();
+                        async move {
+                            let (idx, event) = msg;
+                            match state.check(idx, event, &properties).await {
+                                Ok(()) => (None, state),
+                                Err(e) => {
+                                    state
+                                        .test_event_stream
+                                        .publish(GlobalTestEvent::ShutDown)
+                                        .await;
+                                    (Some(HotShotTaskCompleted::Error(Box::new(e))), state)
+                                }
+                            }
+                        }
+                        .boxed()
+                    },
+                ));
+
+                let mut streams = vec![];
+                for node in &mut state.handles {
+                    let external = node
+                        .handle
+                        .get_event_stream_known_impl(FilterEvent::default())
+                        .await
+                        .0;
+                    let internal = node
+                        .handle
+                        .get_internal_event_stream_known_impl(FilterEvent::default())
+                        .await
+                        .0;
+                    streams.push(Merge::new(external, internal));
+                }
+                let builder = TaskBuilder::<PerNodeSafetyTaskTypes<TYPES, I>>::new(
+                    "Test Per Node Safety Task".to_string(),
+                )
+                .register_event_stream(test_event_stream, FilterEvent::default())
+                .await
+                .register_registry(&mut registry)
+                .await
+                .register_message_handler(message_handler)
+                .register_message_stream(MergeN::new(streams))
+                .register_event_handler(event_handler)
+                .register_state(state);
+                let task_id = builder.get_task_id().unwrap();
+                (task_id, PerNodeSafetyTaskTypes::build(builder).launch())
+            }
+            .boxed()
+        })
+    }
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> PerNodeSafetyTask<TYPES, I> {
+    /// Check `event` of the node at `idx` keeps to its invariants
+    async fn check(
+        &mut self,
+        idx: usize,
+        event: Either<Event<TYPES>, HotShotEvent<TYPES>>,
+        properties: &PerNodeSafetyPropertiesDescription,
+    ) -> Result<(), PerNodeSafetyTaskErr> {
+        let Some(node) = self.handles.get(idx) else {
+            return Ok(());
+        };
+        let ctx = self
+            .ctx
+            .entry(idx)
+            .or_insert_with(|| NodeCtx::new(node.node_id));
+        let history_len = properties.history_len;
+        match event {
+            Either::Right(HotShotEvent::QuorumVoteSend(vote)) => {
+                let view = vote.get_view_number();
+                let leaf = vote.get_data().leaf_commit;
+                ctx.record(format!("voted in view {} for {leaf:?}", *view), history_len);
+                if properties.check_votes {
+                    ctx.check_vote(view, leaf)?;
+                }
+            }
+            Either::Right(HotShotEvent::ViewChange(view)) => {
+                ctx.record(format!("moved to view {}", *view), history_len);
+                ctx.view = ctx.view.max(view);
+            }
+            Either::Right(HotShotEvent::QuorumProposalRecv(proposal, _)) => {
+                let proposal = proposal.data;
+                let view = proposal.view_number;
+                let mut evidence = vec![];
+                let quorum_membership = &node.handle.hotshot.inner.memberships.quorum_membership;
+                if !proposal.justify_qc.is_valid_cert(quorum_membership) {
+                    evidence.push(Misbehaviour::InvalidQC { view: *view });
+                }
+                match ctx.proposals.get(&view) {
+                    Some(first) if *first != proposal => {
+                        evidence.push(Misbehaviour::ConflictingProposals { view: *view });
+                    }
+                    Some(_) => {}
+                    None => {
+                        ctx.proposals.insert(view, proposal);
+                    }
+                }
+                self.detect(node.node_id, evidence).await;
+                return Ok(());
+            }
+            Either::Right(HotShotEvent::QuorumVoteRecv(vote)) => {
+                let view = vote.get_view_number();
+                let leaf = vote.get_data().leaf_commit;
+                let first = *ctx
+                    .votes_received
+                    .entry((view, vote.get_signing_key()))
+                    .or_insert(leaf);
+                if first != leaf {
+                    let evidence = vec![Misbehaviour::ConflictingVotes { view: *view }];
+                    self.detect(node.node_id, evidence).await;
+                }
+                return Ok(());
+            }
+            Either::Right(HotShotEvent::DAProposalRecv(proposal, _)) => {
+                ctx.da_proposals.insert(proposal.data.view_number);
+                return Ok(());
+            }
+            Either::Right(HotShotEvent::Timeout(view)) => {
+                // the timeout only counts if the node is still waiting on the view
+                let waiting = ctx.view < view;
+                let api = HotShotConsensusApi {
+                    inner: node.handle.hotshot.inner.clone(),
+                };
+                let da_member = node
+                    .handle
+                    .hotshot
+                    .inner
+                    .memberships
+                    .da_membership
+                    .get_committee(view)
+                    .contains(api.public_key());
+                if waiting && da_member && !ctx.da_proposals.contains(&view) {
+                    let evidence = vec![Misbehaviour::MissingDAProposal { view: *view }];
+                    self.detect(node.node_id, evidence).await;
+                }
+                return Ok(());
+            }
+            Either::Left(Event {
+                view_number,
+                event: EventType::Decide { leaf_chain, .. },
+            }) => {
+                let views: Vec<_> = leaf_chain.iter().map(|leaf| *leaf.view_number).collect();
+                ctx.record(
+                    format!("decided views {views:?} in view {}", *view_number),
+                    history_len,
+                );
+                if properties.check_decided_chain {
+                    // oldest first, so each leaf's parent is checked before the leaf
+                    for leaf in leaf_chain.iter().rev() {
+                        ctx.check_decided(leaf)?;
+                    }
+                }
+            }
+            _ => return Ok(()),
+        }
+
+        let consensus = node.handle.get_consensus();
+        let consensus = consensus.read().await;
+        if properties.check_locked_view {
+            ctx.check_locked_view(consensus.locked_view)?;
+        }
+        if properties.check_last_decided_view {
+            ctx.check_last_decided_view(consensus.last_decided_view)?;
+        }
+        Ok(())
+    }
+
+    /// Record that the node `node_id` saw `evidence`
+    async fn detect(&self, node_id: u64, evidence: Vec<Misbehaviour>) {
+        if evidence.is_empty() {
+            return;
+        }
+        self.detected
+            .lock()
+            .await
+            .entry(node_id)
+            .or_default()
+            .extend(evidence);
+    }
+}
+
+/// per node types for safety task
+pub type PerNodeSafetyTaskTypes<TYPES, I> = HSTWithEventAndMessage<
+    PerNodeSafetyTaskErr,
+    GlobalTestEvent,
+    ChannelStream<GlobalTestEvent>,
+    (usize, Either<Event<TYPES>, HotShotEvent<TYPES>>),
+    MergeN<Merge<UnboundedStream<Event<TYPES>>, UnboundedStream<HotShotEvent<TYPES>>>>,
+    PerNodeSafetyTask<TYPES, I>,
+>;
@@ -0,0 +1,77 @@
+//! Deterministic simulation of tests
+//!
+//! A simulation runs a whole test on a single thread, with time that only moves forward when
+//! every task is waiting on a timer, and with every random choice of the test harness drawn from a
+//! single seed. Timeouts such as `next_view_timeout` and `round_start_delay` then elapse in
+//! virtual time, and a failing run can be replayed by running it again with the seed it printed.
+//!
+//! Simulations need the tokio executor. Under async-std, tests run as usual and [`SimulationRng`]
+//! falls back to [`thread_rng`]. Leaf timestamps, message ids and network delays drawn by
+//! `HotShot` itself come from [`hotshot_types::simulation`], which a simulation enters as well.
+
+use rand::{thread_rng, RngCore};
+
+pub use hotshot_types::simulation::{Instant, SimulationRng};
+
+/// The environment variable to rerun a simulation with the seed it failed with
+pub const SEED_VAR: &str = "HOTSHOT_SIMULATION_SEED";
+
+/// The seed to simulate with: the one in [`SEED_VAR`] if set, a random one otherwise
+///
+/// # Panics
+/// Panics if [`SEED_VAR`] is set to something other than a `u64`
+#[must_use]
+pub fn simulation_seed() -> u64 {
+    match std::env::var(SEED_VAR) {
+        Ok(seed) => seed
+            .parse()
+            .unwrap_or_else(|_| panic!("{SEED_VAR} must be a u64, not {seed:?}")),
+        Err(_) => thread_rng().next_u64(),
+    }
+}
+
+/// Run `test` to completion on `runtime` in a simulation seeded with `seed`, printing the seed if
+/// it panics
+///
+/// `runtime` must run every task on the current thread, and should start with its clock paused
+/// for timers to elapse in virtual time, which needs tokio's `test-util` feature:
+///
+/// ```ignore
+/// tokio::runtime::Builder::new_current_thread()
+///     .enable_all()
+///     .start_paused(true)
+///     .build()
+/// ```
+///
+/// # Panics
+/// Panics if `test` does, or if `runtime` runs tasks on other threads
+#[cfg(async_executor_impl = "tokio")]
+pub fn simulate<T, F: std::future::Future<Output = T>>(
+    runtime: tokio::runtime::Runtime,
+    seed: u64,
+    test: impl FnOnce() -> F,
+) -> T {
+    assert!(
+        matches!(
+            runtime.handle().runtime_flavor(),
+            tokio::runtime::RuntimeFlavor::CurrentThread
+        ),
+        "Simulations must run on a current thread runtime"
+    );
+    // a single thread polls every task in the order they're woken, and a paused clock jumps to
+    // the next timer whenever they're all waiting
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        runtime.block_on(async {
+            hotshot_types::simulation::enter(seed);
+            test().await
+        })
+    }));
+    hotshot_types::simulation::exit();
+    match result {
+        Ok(output) => output,
+        Err(panic) => {
+            eprintln!("Simulation failed with seed {seed}, rerun it with {SEED_VAR}={seed}");
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
@@ -33,6 +33,17 @@ pub async fn build_system_handle(
 ) -> (
     SystemContextHandle<TestTypes, MemoryImpl>,
     ChannelStream<HotShotEvent<TestTypes>>,
+) {
+    build_system_handle_with_metrics(node_id, ConsensusMetricsValue::new()).await
+}
+
+/// [`build_system_handle`], with the node recording its metrics in `metrics`
+pub async fn build_system_handle_with_metrics(
+    node_id: u64,
+    metrics: ConsensusMetricsValue,
+) -> (
+    SystemContextHandle<TestTypes, MemoryImpl>,
+    ChannelStream<HotShotEvent<TestTypes>>,
 ) {
     let builder = TestMetadata::default_multiple_rounds();
 
@@ -94,7 +105,7 @@ pub async fn build_system_handle(
         memberships,
         networks_bundle,
         initializer,
-        ConsensusMetricsValue::new(),
+        metrics,
     )
     .await
     .expect("Could not init hotshot")
@@ -11,12 +11,16 @@ use hotshot_types::{
 
 use super::completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription};
 use crate::{
+    byzantine::ByzantineNode,
+    fault_task::FaultPlanDescription,
+    measurement_task::MeasurementTaskDescription,
     spinning_task::SpinningTaskDescription,
     test_launcher::{ResourceGenerators, TestLauncher},
 };
 
 use super::{
-    overall_safety_task::OverallSafetyPropertiesDescription, txn_task::TxnTaskDescription,
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    per_node_safety_task::PerNodeSafetyPropertiesDescription, txn_task::TxnTaskDescription,
 };
 use hotshot::{HotShotType, SystemContext};
 /// data describing how a round should be timed.
@@ -49,8 +53,16 @@ pub struct TestMetadata {
     pub da_committee_size: usize,
     // overall safety property description
     pub overall_safety_properties: OverallSafetyPropertiesDescription,
+    /// invariants checked on each node on its own
+    pub per_node_safety_properties: PerNodeSafetyPropertiesDescription,
+    /// what to measure of the run
+    pub measurement_description: MeasurementTaskDescription,
     /// spinning properties
     pub spinning_properties: SpinningTaskDescription,
+    /// nodes that misbehave, among those started with the test
+    pub byzantine_nodes: Vec<ByzantineNode>,
+    /// partitions and link faults over the course of the test
+    pub fault_plan: FaultPlanDescription,
     // txns timing
     pub txn_description: TxnTaskDescription,
     // completion task
@@ -165,7 +177,11 @@ impl Default for TestMetadata {
             spinning_properties: SpinningTaskDescription {
                 node_changes: vec![],
             },
+            byzantine_nodes: vec![],
+            fault_plan: FaultPlanDescription::default(),
             overall_safety_properties: OverallSafetyPropertiesDescription::default(),
+            per_node_safety_properties: PerNodeSafetyPropertiesDescription::default(),
+            measurement_description: MeasurementTaskDescription::default(),
             // arbitrary, haven't done the math on this
             txn_description: TxnTaskDescription::RoundRobinTimeBased(Duration::from_millis(10)),
             completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
@@ -196,7 +212,10 @@ impl TestMetadata {
             txn_description,
             completion_task_description,
             overall_safety_properties,
+            per_node_safety_properties,
+            measurement_description,
             spinning_properties,
+            fault_plan,
             ..
         } = self.clone();
 
@@ -264,7 +283,10 @@ impl TestMetadata {
         let txn_task_generator = txn_description.build();
         let completion_task_generator = completion_task_description.build_and_launch();
         let overall_safety_task_generator = overall_safety_properties.build();
+        let per_node_safety_task_generator = per_node_safety_properties.build();
+        let measurement_task_generator = measurement_description.build();
         let spinning_task_generator = spinning_properties.build();
+        let fault_task_generator = fault_plan.build();
         TestLauncher {
             resource_generator: ResourceGenerators {
                 channel_generator: <I as TestableNodeImplementation<TYPES>>::gen_comm_channels(
@@ -278,10 +300,35 @@ impl TestMetadata {
             metadata: self,
             txn_task_generator,
             overall_safety_task_generator,
+            per_node_safety_task_generator,
+            measurement_task_generator,
             completion_task_generator,
             spinning_task_generator,
+            fault_task_generator,
             hooks: vec![],
         }
         .modify_default_config(mod_config)
     }
+
+    /// Run the test on `runtime` in a deterministic simulation seeded with `seed`, returning what
+    /// was measured of the run. The seed is printed if the test fails. See
+    /// [`simulate`](crate::simulation::simulate) for the runtime to use.
+    #[cfg(async_executor_impl = "tokio")]
+    pub fn simulate<TYPES: NodeType, I>(
+        self,
+        runtime: tokio::runtime::Runtime,
+        seed: u64,
+    ) -> crate::measurement_task::RunMeasurements
+    where
+        I: TestableNodeImplementation<TYPES, CommitteeElectionConfig = TYPES::ElectionConfigType>,
+        SystemContext<TYPES, I>: HotShotType<TYPES, I>,
+        <TYPES::BlockPayload as hotshot_types::traits::BlockPayload>::Metadata: serde::Serialize,
+    {
+        crate::simulation::simulate(runtime, seed, || async move {
+            self.gen_launcher::<TYPES, I>(0)
+                .launch()
+                .run_test_with_measurements()
+                .await
+        })
+    }
 }
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 use futures::future::BoxFuture;
 use hotshot::traits::{NodeImplementation, TestableNodeImplementation};
@@ -10,11 +13,14 @@ use hotshot_task::{
 };
 use hotshot_types::{traits::node_implementation::NodeType, HotShotConfig};
 
-use crate::spinning_task::SpinningTask;
+use crate::{
+    fault_task::FaultTask, measurement_task::MeasurementTask, spinning_task::SpinningTask,
+};
 
 use super::{
     completion_task::CompletionTask, overall_safety_task::OverallSafetyTask,
-    test_builder::TestMetadata, test_runner::TestRunner, txn_task::TxnTask, GlobalTestEvent,
+    per_node_safety_task::PerNodeSafetyTask, test_builder::TestMetadata, test_runner::TestRunner,
+    txn_task::TxnTask, GlobalTestEvent,
 };
 
 pub type Networks<TYPES, I> = (
@@ -71,8 +77,14 @@ pub struct TestLauncher<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
     pub completion_task_generator: TaskGenerator<CompletionTask<TYPES, I>>,
     /// overall safety task generator
     pub overall_safety_task_generator: TaskGenerator<OverallSafetyTask<TYPES, I>>,
+    /// per node safety task generator
+    pub per_node_safety_task_generator: TaskGenerator<PerNodeSafetyTask<TYPES, I>>,
+    /// measurement task generator
+    pub measurement_task_generator: TaskGenerator<MeasurementTask<TYPES, I>>,
 
     pub spinning_task_generator: TaskGenerator<SpinningTask<TYPES, I>>,
+    /// link fault task generator
+    pub fault_task_generator: TaskGenerator<FaultTask<TYPES, I>>,
 
     pub hooks: Vec<Hook>,
 }
@@ -86,6 +98,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestLauncher<TYPES,
             late_start: HashMap::new(),
             next_node_id: 0,
             task_runner: TaskRunner::default(),
+            misbehaviours_sent: BTreeMap::new(),
         }
     }
 
@@ -100,6 +113,28 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestLauncher<TYPES,
         }
     }
 
+    /// override the per node safety task generator
+    pub fn with_per_node_safety_task_generator(
+        self,
+        per_node_safety_task_generator: TaskGenerator<PerNodeSafetyTask<TYPES, I>>,
+    ) -> Self {
+        Self {
+            per_node_safety_task_generator,
+            ..self
+        }
+    }
+
+    /// override the measurement task generator
+    pub fn with_measurement_task_generator(
+        self,
+        measurement_task_generator: TaskGenerator<MeasurementTask<TYPES, I>>,
+    ) -> Self {
+        Self {
+            measurement_task_generator,
+            ..self
+        }
+    }
+
     /// override the safety task generator
     pub fn with_spinning_task_generator(
         self,
@@ -111,6 +146,17 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestLauncher<TYPES,
         }
     }
 
+    /// override the fault task generator
+    pub fn with_fault_task_generator(
+        self,
+        fault_task_generator: TaskGenerator<FaultTask<TYPES, I>>,
+    ) -> Self {
+        Self {
+            fault_task_generator,
+            ..self
+        }
+    }
+
     /// overridde the completion task generator
     pub fn with_completion_task_generator(
         self,
@@ -1,9 +1,13 @@
 use super::{
     completion_task::CompletionTask,
     overall_safety_task::{OverallSafetyTask, RoundCtx},
+    per_node_safety_task::PerNodeSafetyTask,
     txn_task::TxnTask,
 };
 use crate::{
+    byzantine::run_byzantine_tasks,
+    fault_task::FaultTask,
+    measurement_task::{MeasurementTask, RunMeasurements},
     spinning_task::UpDown,
     test_launcher::{Networks, TestLauncher},
 };
@@ -15,17 +19,29 @@ use hotshot_task::{
 };
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
-    traits::{election::Membership, node_implementation::NodeType},
+    traits::{
+        election::Membership, network::LinkFaults, node_implementation::NodeType,
+        state::ConsensusTime, BlockPayload,
+    },
     HotShotConfig, ValidatorConfig,
 };
+use serde::Serialize;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 #[allow(deprecated)]
 use tracing::info;
 
+/// If set, the directory each node started by a test records a journal of its events in, as
+/// `node-<id>.journal`
+pub const JOURNAL_DIR_VAR: &str = "HOTSHOT_JOURNAL_DIR";
+
 #[derive(Clone)]
 pub struct Node<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
     pub node_id: u64,
@@ -40,15 +56,25 @@ pub struct TestRunner<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
     pub(crate) late_start: HashMap<u64, SystemContext<TYPES, I>>,
     pub(crate) next_node_id: u64,
     pub(crate) task_runner: TaskRunner,
+    /// how many events each byzantine node sent differently than it honestly would have, by id
+    pub(crate) misbehaviours_sent: BTreeMap<u64, Arc<AtomicUsize>>,
 }
 
 impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestRunner<TYPES, I>
 where
     SystemContext<TYPES, I>: HotShotType<TYPES, I>,
     I: TestableNodeImplementation<TYPES, CommitteeElectionConfig = TYPES::ElectionConfigType>,
+    <TYPES::BlockPayload as BlockPayload>::Metadata: Serialize,
 {
     /// excecute test
-    pub async fn run_test(mut self) {
+    pub async fn run_test(self) {
+        self.run_test_with_measurements().await;
+    }
+
+    /// excecute test, returning what was measured of the run
+    /// # Panics
+    /// Panics if any task of the test fails
+    pub async fn run_test_with_measurements(mut self) -> RunMeasurements {
         let spinning_changes = self
             .launcher
             .metadata
@@ -79,14 +105,20 @@ where
             late_start,
             next_node_id: _,
             mut task_runner,
+            misbehaviours_sent,
         } = self;
         let registry = GlobalRegistry::default();
         let test_event_stream = ChannelStream::new();
 
+        // transactions submitted, for the safety task to measure how long they take to be decided
+        let submitted = Arc::default();
+        let load = Arc::default();
+
         // add transaction task
         let txn_task_state = TxnTask {
             handles: nodes.clone(),
             next_node_idx: Some(0),
+            submitted: Arc::clone(&submitted),
         };
         let (id, task) = (launcher.txn_task_generator)(
             txn_task_state,
@@ -126,11 +158,34 @@ where
         .await;
         task_runner = task_runner.add_task(id, "Test Spinning Task".to_string(), task);
 
+        // add fault task
+        let fault_task_state = FaultTask {
+            handles: nodes.clone(),
+            actions: launcher
+                .metadata
+                .fault_plan
+                .schedule()
+                .into_iter()
+                .map(|(_, action)| action)
+                .collect(),
+            faults: LinkFaults::default(),
+            decided_at_heal: None,
+        };
+        let (id, task) = (launcher.fault_task_generator)(
+            fault_task_state,
+            registry.clone(),
+            test_event_stream.clone(),
+        )
+        .await;
+        task_runner = task_runner.add_task(id, "Test Fault Task".to_string(), task);
+
         // add safety task
         let overall_safety_task_state = OverallSafetyTask {
             handles: nodes.clone(),
             ctx: RoundCtx::default(),
             test_event_stream: test_event_stream.clone(),
+            submitted,
+            load: Arc::clone(&load),
         };
         let (id, task) = (launcher.overall_safety_task_generator)(
             overall_safety_task_state,
@@ -140,6 +195,38 @@ where
         .await;
         task_runner = task_runner.add_task(id, "Test Overall Safety Task".to_string(), task);
 
+        // add per node safety task
+        let misbehaviours_detected = Arc::default();
+        let per_node_safety_task_state = PerNodeSafetyTask {
+            handles: nodes.clone(),
+            ctx: HashMap::new(),
+            test_event_stream: test_event_stream.clone(),
+            detected: Arc::clone(&misbehaviours_detected),
+        };
+        let (id, task) = (launcher.per_node_safety_task_generator)(
+            per_node_safety_task_state,
+            registry.clone(),
+            test_event_stream.clone(),
+        )
+        .await;
+        task_runner = task_runner.add_task(id, "Test Per Node Safety Task".to_string(), task);
+
+        // add measurement task
+        let measurements = Arc::default();
+        let measurement_task_state = MeasurementTask {
+            handles: nodes.clone(),
+            view_starts: HashMap::new(),
+            decided: HashSet::new(),
+            measurements: Arc::clone(&measurements),
+        };
+        let (id, task) = (launcher.measurement_task_generator)(
+            measurement_task_state,
+            registry.clone(),
+            test_event_stream.clone(),
+        )
+        .await;
+        task_runner = task_runner.add_task(id, "Test Measurement Task".to_string(), task);
+
         // Start hotshot
         for node in nodes {
             if !late_start_nodes.contains(&node.node_id) {
@@ -164,6 +251,15 @@ where
         if !error_list.is_empty() {
             panic!("TEST FAILED! Results: {:?}", error_list);
         }
+
+        let mut measurements = measurements.lock().await.clone();
+        measurements.load = load.lock().await.clone();
+        measurements.misbehaviours_sent = misbehaviours_sent
+            .into_iter()
+            .map(|(node_id, sent)| (node_id, sent.load(Ordering::Relaxed)))
+            .collect();
+        measurements.misbehaviours_detected = misbehaviours_detected.lock().await.clone();
+        measurements
     }
 
     /// add nodes
@@ -185,10 +281,33 @@ where
             if late_start.contains(&node_id) {
                 self.late_start.insert(node_id, hotshot);
             } else {
-                self.nodes.push(Node {
-                    node_id,
-                    handle: hotshot.run_tasks().await,
-                });
+                let byzantine = self
+                    .launcher
+                    .metadata
+                    .byzantine_nodes
+                    .iter()
+                    .find(|node| node.idx as u64 == node_id);
+                let handle = match byzantine {
+                    Some(node) => {
+                        let sent = Arc::clone(self.misbehaviours_sent.entry(node_id).or_default());
+                        run_byzantine_tasks(
+                            hotshot,
+                            Arc::new(node.behaviour),
+                            TYPES::Time::new(node.from_view),
+                            sent,
+                        )
+                        .await
+                    }
+                    None => hotshot.run_tasks().await,
+                };
+                // journal each node's events if asked to, to debug the test with afterwards
+                if let Ok(dir) = std::env::var(JOURNAL_DIR_VAR) {
+                    let path = std::path::Path::new(&dir).join(format!("node-{node_id}.journal"));
+                    if let Err(e) = handle.record_journal(&path).await {
+                        tracing::error!("Failed to start a journal at {}: {e}", path.display());
+                    }
+                }
+                self.nodes.push(Node { node_id, handle });
             }
             results.push(node_id);
         }
@@ -1,5 +1,10 @@
-use crate::test_runner::Node;
+use crate::{
+    simulation::{Instant, SimulationRng},
+    test_runner::Node,
+};
 use async_compatibility_layer::art::async_sleep;
+use async_lock::Mutex;
+use commit::{Commitment, Committable};
 use futures::FutureExt;
 use hotshot::traits::TestableNodeImplementation;
 use hotshot_task::{
@@ -9,19 +14,108 @@ use hotshot_task::{
     task_impls::{HSTWithEventAndMessage, TaskBuilder},
     GeneratedStream,
 };
-use hotshot_types::traits::node_implementation::{NodeImplementation, NodeType};
-use rand::thread_rng;
+use hotshot_types::traits::{
+    node_implementation::{NodeImplementation, NodeType},
+    BlockPayload,
+};
+use rand::{seq::SliceRandom, Rng};
 use snafu::Snafu;
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
 use super::{test_launcher::TaskGenerator, GlobalTestEvent};
 
 // the obvious idea here is to pass in a "stream" that completes every `n` seconds
 // the stream construction can definitely be fancier but that's the baseline idea
 
-/// Data Availability task error
-#[derive(Snafu, Debug)]
-pub struct TxnTaskErr {}
+/// How long to wait before checking the rate again when the target TPS is zero
+const IDLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Transaction task error
+#[derive(Snafu, Debug, Clone)]
+pub enum TxnTaskErr {
+    /// Transactions are to be submitted to a node the test doesn't have
+    #[snafu(display("cannot submit transactions to node {idx}, the test has {num_nodes} nodes"))]
+    NoSuchNode {
+        /// index of the node
+        idx: usize,
+        /// number of nodes in the test
+        num_nodes: usize,
+    },
+    /// Transactions are to be submitted to an empty set of nodes
+    #[snafu(display("no nodes to submit transactions to"))]
+    NoTargets,
+}
+
+/// Transactions submitted by the transaction task, shared with the overall safety task so it can
+/// tell how long they took to be decided
+#[derive(Debug)]
+pub struct SubmittedTxns<TYPES: NodeType> {
+    /// when the first transaction was submitted
+    pub first_submission: Option<Instant>,
+    /// number of transactions submitted
+    pub num_submitted: usize,
+    /// node index -> transactions submitted to it
+    pub per_node: BTreeMap<usize, usize>,
+    /// padding -> transactions submitted with it
+    pub paddings: BTreeMap<u64, usize>,
+    /// transactions submitted in each second since the first submission
+    pub per_second: Vec<usize>,
+    /// transaction -> when each copy of it not yet seen decided was submitted, oldest first
+    pub pending:
+        HashMap<Commitment<<TYPES::BlockPayload as BlockPayload>::Transaction>, VecDeque<Instant>>,
+}
+
+impl<TYPES: NodeType> Default for SubmittedTxns<TYPES> {
+    fn default() -> Self {
+        Self {
+            first_submission: None,
+            num_submitted: 0,
+            per_node: BTreeMap::new(),
+            paddings: BTreeMap::new(),
+            per_second: Vec::new(),
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> SubmittedTxns<TYPES> {
+    /// record `txn`, padded with `padding` bytes, as submitted to node `idx` now
+    pub fn insert(
+        &mut self,
+        txn: Commitment<<TYPES::BlockPayload as BlockPayload>::Transaction>,
+        idx: usize,
+        padding: u64,
+    ) {
+        let now = Instant::now();
+        let first = *self.first_submission.get_or_insert(now);
+        self.num_submitted += 1;
+        *self.per_node.entry(idx).or_default() += 1;
+        *self.paddings.entry(padding).or_default() += 1;
+        let second = usize::try_from(now.duration_since(first).as_secs()).unwrap_or(usize::MAX);
+        if self.per_second.len() <= second {
+            self.per_second.resize(second + 1, 0);
+        }
+        self.per_second[second] += 1;
+        self.pending.entry(txn).or_default().push_back(now);
+    }
+
+    /// remove the oldest pending submission of `txn`, returning when it was submitted
+    pub fn remove(
+        &mut self,
+        txn: &Commitment<<TYPES::BlockPayload as BlockPayload>::Transaction>,
+    ) -> Option<Instant> {
+        let submissions = self.pending.get_mut(txn)?;
+        let submitted = submissions.pop_front();
+        if submissions.is_empty() {
+            self.pending.remove(txn);
+        }
+        submitted
+    }
+}
 
 /// state of task that decides when things are completed
 pub struct TxnTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
@@ -30,6 +124,8 @@ pub struct TxnTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
     pub handles: Vec<Node<TYPES, I>>,
     /// Optional index of the next node.
     pub next_node_idx: Option<usize>,
+    /// Transactions submitted so far
+    pub submitted: Arc<Mutex<SubmittedTxns<TYPES>>>,
 }
 
 impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TS for TxnTask<TYPES, I> {}
@@ -44,14 +140,162 @@ pub type TxnTaskTypes<TYPES, I> = HSTWithEventAndMessage<
     TxnTask<TYPES, I>,
 >;
 
+/// when transactions arrive
+#[derive(Clone, Debug)]
+pub enum ArrivalProcess {
+    /// one transaction at a time, as a Poisson process at the target TPS
+    Poisson,
+    /// `burst_size` transactions at a time, with bursts arriving as a Poisson process at the
+    /// target TPS divided by `burst_size`
+    Bursty {
+        /// transactions in each burst
+        burst_size: usize,
+    },
+}
+
+impl ArrivalProcess {
+    /// transactions submitted on each arrival
+    fn batch_size(&self) -> usize {
+        match self {
+            ArrivalProcess::Poisson => 1,
+            ArrivalProcess::Bursty { burst_size } => (*burst_size).max(1),
+        }
+    }
+}
+
+/// how many bytes each transaction is padded with, on top of its base size
+#[derive(Clone, Debug)]
+pub enum SizeDistribution {
+    /// always the same padding
+    Fixed(u64),
+    /// padding uniform between `min` and `max`, inclusive
+    Uniform {
+        /// smallest padding
+        min: u64,
+        /// largest padding
+        max: u64,
+    },
+    /// padding exponentially distributed with mean `mean`
+    Exponential {
+        /// mean padding
+        mean: u64,
+    },
+}
+
+impl SizeDistribution {
+    /// sample a padding
+    fn sample(&self, rng: &mut impl Rng) -> u64 {
+        match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { min, max } => rng.gen_range(*min..=(*max).max(*min)),
+            SizeDistribution::Exponential { mean } => {
+                (exponential(rng) * *mean as f64).round() as u64
+            }
+        }
+    }
+}
+
+/// which nodes transactions are submitted to
+#[derive(Clone, Debug)]
+pub enum SubmissionTarget {
+    /// a node picked uniformly at random for each transaction
+    Random,
+    /// a node picked uniformly at random among these node indices for each transaction
+    Nodes(Vec<usize>),
+}
+
+/// target transactions per second, ramping linearly from `initial_tps` to `target_tps` over
+/// `ramp_duration` and staying at `target_tps` afterwards
+#[derive(Clone, Debug)]
+pub struct TpsRamp {
+    /// TPS when the task starts
+    pub initial_tps: f64,
+    /// TPS once the ramp is over
+    pub target_tps: f64,
+    /// how long the ramp lasts
+    pub ramp_duration: Duration,
+}
+
+impl TpsRamp {
+    /// a constant `tps`, without a ramp
+    #[must_use]
+    pub fn constant(tps: f64) -> Self {
+        Self {
+            initial_tps: tps,
+            target_tps: tps,
+            ramp_duration: Duration::ZERO,
+        }
+    }
+
+    /// target TPS `elapsed` after the task started
+    fn tps_at(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.ramp_duration {
+            return self.target_tps;
+        }
+        let progress = elapsed.as_secs_f64() / self.ramp_duration.as_secs_f64();
+        self.initial_tps + (self.target_tps - self.initial_tps) * progress
+    }
+}
+
+/// a load of transactions drawn from distributions
+#[derive(Clone, Debug)]
+pub struct TxnDistribution {
+    /// when transactions arrive
+    pub arrivals: ArrivalProcess,
+    /// how large transactions are
+    pub sizes: SizeDistribution,
+    /// which nodes transactions are submitted to
+    pub targets: SubmissionTarget,
+    /// how many transactions arrive per second over time
+    pub tps: TpsRamp,
+}
+
+impl TxnDistribution {
+    /// Check that transactions can be submitted to the targets in a test of `num_nodes` nodes
+    ///
+    /// # Errors
+    /// If a target isn't one of the nodes, or there are no targets
+    pub fn validate(&self, num_nodes: usize) -> Result<(), TxnTaskErr> {
+        match &self.targets {
+            SubmissionTarget::Random if num_nodes == 0 => Err(TxnTaskErr::NoTargets),
+            SubmissionTarget::Random => Ok(()),
+            SubmissionTarget::Nodes(nodes) if nodes.is_empty() => Err(TxnTaskErr::NoTargets),
+            SubmissionTarget::Nodes(nodes) => match nodes.iter().find(|idx| **idx >= num_nodes) {
+                Some(idx) => Err(TxnTaskErr::NoSuchNode {
+                    idx: *idx,
+                    num_nodes,
+                }),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl Default for TxnDistribution {
+    fn default() -> Self {
+        Self {
+            arrivals: ArrivalProcess::Poisson,
+            sizes: SizeDistribution::Fixed(0),
+            targets: SubmissionTarget::Random,
+            tps: TpsRamp::constant(100.0),
+        }
+    }
+}
+
+/// sample an exponential distribution with mean 1
+fn exponential(rng: &mut impl Rng) -> f64 {
+    // `gen` is in [0, 1), so the logarithm is finite
+    -(1.0 - rng.gen::<f64>()).ln()
+}
+
 /// build the transaction task
 #[derive(Clone, Debug)]
 pub enum TxnTaskDescription {
     /// submit transactions in a round robin style using
     /// every `Duration` seconds
     RoundRobinTimeBased(Duration),
-    /// TODO
-    DistributionBased, // others?
+    /// submit transactions as drawn from a distribution
+    DistributionBased(TxnDistribution),
 }
 
 impl TxnTaskDescription {
@@ -63,15 +307,20 @@ impl TxnTaskDescription {
         TYPES: NodeType,
         I: NodeImplementation<TYPES>,
     {
-        Box::new(move |state, mut registry, test_event_stream| {
+        Box::new(move |mut state, mut registry, test_event_stream| {
             async move {
                 // consistency check
-                match self {
+                let validity = match &self {
                     TxnTaskDescription::RoundRobinTimeBased(_) => {
-                        assert!(state.next_node_idx.is_some())
+                        assert!(state.next_node_idx.is_some());
+                        Ok(())
                     }
-                    TxnTaskDescription::DistributionBased => assert!(state.next_node_idx.is_none()),
-                }
+                    // nodes are picked at random
+                    TxnTaskDescription::DistributionBased(distribution) => {
+                        state.next_node_idx = None;
+                        distribution.validate(state.handles.len())
+                    }
+                };
                 // TODO we'll possibly want multiple criterion including:
                 // - certain number of txns committed
                 // - anchor of certain depth
@@ -87,42 +336,78 @@ impl TxnTaskDescription {
                         }
                         .boxed()
                     }));
+                let distribution = match &self {
+                    TxnTaskDescription::RoundRobinTimeBased(_) => None,
+                    TxnTaskDescription::DistributionBased(distribution) => {
+                        Some(distribution.clone())
+                    }
+                };
                 let message_handler =
                     HandleMessage::<TxnTaskTypes<TYPES, I>>(Arc::new(move |_, mut state| {
+                        let distribution = distribution.clone();
+                        let validity = validity.clone();
                         async move {
-                            if let Some(idx) = state.next_node_idx {
+                            // fail on the first submission rather than submit to no node
+                            if let Err(e) = validity {
+                                return (Some(HotShotTaskCompleted::Error(Box::new(e))), state);
+                            }
+                            // node index and padding of each transaction to submit
+                            let txns: Vec<(usize, u64)> = if let Some(idx) = state.next_node_idx {
                                 // submit to idx handle
                                 // increment state
                                 state.next_node_idx = Some((idx + 1) % state.handles.len());
+                                vec![(idx, 0)]
+                            } else if let Some(distribution) = distribution {
+                                let mut rng = SimulationRng;
+                                let all_nodes: Vec<usize> = (0..state.handles.len()).collect();
+                                let targets = match &distribution.targets {
+                                    SubmissionTarget::Random => &all_nodes,
+                                    SubmissionTarget::Nodes(nodes) => nodes,
+                                };
+                                (0..distribution.arrivals.batch_size())
+                                    .filter_map(|_| {
+                                        let idx = *targets.choose(&mut rng)?;
+                                        Some((idx, distribution.sizes.sample(&mut rng)))
+                                    })
+                                    .collect()
+                            } else {
+                                unreachable!("round robin submission always has a next node")
+                            };
+
+                            for (idx, padding) in txns {
                                 match state.handles.get(idx) {
                                     None => {
-                                        // should do error
-                                        unimplemented!()
+                                        let num_nodes = state.handles.len();
+                                        return (
+                                            Some(HotShotTaskCompleted::Error(Box::new(
+                                                TxnTaskErr::NoSuchNode { idx, num_nodes },
+                                            ))),
+                                            state,
+                                        );
                                     }
                                     Some(node) => {
-                                        // use rand::seq::IteratorRandom;
                                         // we're assuming all nodes have the same leaf.
                                         // If they don't match, this is probably fine since
                                         // it should be caught by an assertion (and the txn will be rejected anyway)
                                         let leaf = node.handle.get_decided_leaf().await;
                                         let txn = I::leaf_create_random_transaction(
                                             &leaf,
-                                            &mut thread_rng(),
-                                            0,
+                                            &mut SimulationRng,
+                                            padding,
+                                        );
+                                        state.submitted.lock().await.insert(
+                                            txn.commit(),
+                                            idx,
+                                            padding,
                                         );
                                         node.handle
                                             .submit_transaction(txn.clone())
                                             .await
                                             .expect("Could not send transaction");
-                                        (None, state)
                                     }
                                 }
-                            } else {
-                                // TODO make an issue
-                                // in the case that this is random
-                                // which I haven't implemented yet
-                                unimplemented!()
                             }
+                            (None, state)
                         }
                         .boxed()
                     }));
@@ -135,7 +420,26 @@ impl TxnTaskDescription {
                             Some(boxed_sync(fut))
                         }))
                     }
-                    TxnTaskDescription::DistributionBased => unimplemented!(),
+                    TxnTaskDescription::DistributionBased(distribution) => {
+                        let start = Instant::now();
+                        GeneratedStream::new(Arc::new(move || {
+                            let arrivals_per_txn = 1.0 / distribution.arrivals.batch_size() as f64;
+                            let tps = distribution.tps.clone();
+                            // the gap to the next arrival, in units of the mean gap
+                            let gap = exponential(&mut SimulationRng);
+                            let fut = async move {
+                                loop {
+                                    let rate = tps.tps_at(start.elapsed()) * arrivals_per_txn;
+                                    if rate > 0.0 {
+                                        async_sleep(Duration::from_secs_f64(gap / rate)).await;
+                                        break;
+                                    }
+                                    async_sleep(IDLE_INTERVAL).await;
+                                }
+                            };
+                            Some(boxed_sync(fut))
+                        }))
+                    }
                 };
                 let builder = TaskBuilder::<TxnTaskTypes<TYPES, I>>::new(
                     "Test Transaction Submission Task".to_string(),
@@ -182,3 +182,145 @@ async fn test_with_failures_f() {
         .run_test()
         .await;
 }
+
+/// Test a bursty, ramping load of transactions of varying sizes submitted to some of the nodes.
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_distribution_based_load() {
+    use hotshot_testing::{
+        completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+        node_types::{MemoryImpl, TestTypes},
+        overall_safety_task::OverallSafetyPropertiesDescription,
+        test_builder::TestMetadata,
+        txn_task::{
+            ArrivalProcess, SizeDistribution, SubmissionTarget, TpsRamp, TxnDistribution,
+            TxnTaskDescription,
+        },
+    };
+    use std::time::Duration;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let metadata = TestMetadata {
+        txn_description: TxnTaskDescription::DistributionBased(TxnDistribution {
+            arrivals: ArrivalProcess::Bursty { burst_size: 2 },
+            sizes: SizeDistribution::Uniform { min: 0, max: 64 },
+            targets: SubmissionTarget::Nodes(vec![0, 2, 4]),
+            tps: TpsRamp {
+                initial_tps: 0.0,
+                target_tps: 100.0,
+                ramp_duration: Duration::from_secs(3),
+            },
+        }),
+        // enough views to run well past the ramp
+        overall_safety_properties: OverallSafetyPropertiesDescription {
+            num_successful_views: 100,
+            ..Default::default()
+        },
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestMetadata::default()
+    };
+    let load = metadata
+        .gen_launcher::<TestTypes, MemoryImpl>(0)
+        .launch()
+        .run_test_with_measurements()
+        .await
+        .load;
+
+    // Transactions only went to the targets, spread evenly between them.
+    assert!(load.num_submitted > 0);
+    assert_eq!(
+        load.submitted_per_node.values().sum::<usize>(),
+        load.num_submitted
+    );
+    assert_eq!(
+        load.submitted_per_node.keys().copied().collect::<Vec<_>>(),
+        [0, 2, 4],
+        "{load:?}"
+    );
+    for submitted in load.submitted_per_node.values() {
+        assert!(submitted * 5 >= load.num_submitted, "{load:?}");
+    }
+
+    // Paddings are uniform over 0..=64.
+    assert_eq!(load.paddings.values().sum::<usize>(), load.num_submitted);
+    assert!(
+        load.paddings.keys().all(|padding| *padding <= 64),
+        "{load:?}"
+    );
+    let mean_padding = load
+        .paddings
+        .iter()
+        .map(|(padding, count)| *padding as f64 * *count as f64)
+        .sum::<f64>()
+        / load.num_submitted as f64;
+    assert!((24.0..=40.0).contains(&mean_padding), "{load:?}");
+    assert!(load.paddings.range(..16).next().is_some(), "{load:?}");
+    assert!(load.paddings.range(49..).next().is_some(), "{load:?}");
+
+    // The first second is early in the ramp, well below the 100 TPS it ramps up to. The last
+    // second is cut short by the end of the test, so it's left out.
+    let per_second = &load.submissions_per_second;
+    assert!(per_second.len() >= 6, "Too short to see the ramp: {load:?}");
+    assert!(per_second[0] < 60, "{load:?}");
+    let steady = &per_second[4..per_second.len() - 1];
+    let steady_tps = steady.iter().sum::<usize>() as f64 / steady.len() as f64;
+    assert!((60.0..=140.0).contains(&steady_tps), "{load:?}");
+}
+
+/// Submitting transactions to a node the test doesn't have fails the transaction task.
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[should_panic(expected = "NoSuchNode")]
+async fn test_distribution_based_load_no_such_node() {
+    use hotshot_testing::{
+        node_types::{MemoryImpl, TestTypes},
+        test_builder::TestMetadata,
+        txn_task::{SubmissionTarget, TxnDistribution, TxnTaskDescription, TxnTaskErr},
+    };
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let distribution = TxnDistribution {
+        targets: SubmissionTarget::Nodes(vec![0, 5]),
+        ..TxnDistribution::default()
+    };
+    assert!(matches!(
+        distribution.validate(5),
+        Err(TxnTaskErr::NoSuchNode {
+            idx: 5,
+            num_nodes: 5
+        })
+    ));
+    assert!(matches!(
+        TxnDistribution {
+            targets: SubmissionTarget::Nodes(vec![]),
+            ..TxnDistribution::default()
+        }
+        .validate(5),
+        Err(TxnTaskErr::NoTargets)
+    ));
+    assert!(distribution.validate(6).is_ok());
+
+    let metadata = TestMetadata {
+        txn_description: TxnTaskDescription::DistributionBased(distribution),
+        ..TestMetadata::default()
+    };
+    metadata
+        .gen_launcher::<TestTypes, MemoryImpl>(0)
+        .launch()
+        .run_test()
+        .await;
+}
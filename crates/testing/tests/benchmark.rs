@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use hotshot_testing::benchmark::{
+    BenchmarkDescription, BenchmarkNetwork, BenchmarkReport, REPORT_VAR,
+};
+use tracing::instrument;
+
+/// Write `report` to the file in [`REPORT_VAR`] if set, and log it either way
+fn publish(report: &BenchmarkReport) {
+    let json = report.to_json();
+    tracing::info!("Benchmark report: {json}");
+    if let Ok(path) = std::env::var(REPORT_VAR) {
+        std::fs::write(&path, json)
+            .unwrap_or_else(|e| panic!("Failed to write the benchmark report to {path}: {e}"));
+    }
+}
+
+/// Test a single benchmark run measures what it should
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn test_benchmark_memory_network() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let description = BenchmarkDescription {
+        node_counts: vec![5],
+        payload_sizes: vec![64],
+        num_views: 10,
+        max_duration: Duration::from_secs(60),
+        ..BenchmarkDescription::default()
+    };
+    let report = description.run().await;
+    let [result] = &report.results[..] else {
+        panic!("Expected a single result, got {report:?}");
+    };
+    assert!(result.decided_views >= 10);
+    assert!(result.time_to_decide_ms.p50.is_some());
+    assert!(result.time_to_decide_ms.p50 <= result.time_to_decide_ms.max);
+    assert_eq!(result.event_bytes_sent_per_node.len(), 5);
+    assert!(result
+        .event_bytes_sent_per_node
+        .iter()
+        .all(|bytes| *bytes > 0));
+    assert!(result
+        .event_bytes_received_per_node
+        .iter()
+        .all(|bytes| *bytes > 0));
+}
+
+/// Sweep node counts, payload sizes and DA committee sizes over each network
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+#[ignore]
+async fn benchmark_sweep() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let description = BenchmarkDescription {
+        networks: vec![
+            BenchmarkNetwork::Memory,
+            BenchmarkNetwork::WebServer,
+            BenchmarkNetwork::Libp2p,
+        ],
+        node_counts: vec![5, 10, 20],
+        payload_sizes: vec![0, 1024, 16384],
+        da_committee_sizes: vec![5, 10],
+        ..BenchmarkDescription::default()
+    };
+    publish(&description.run().await);
+}
@@ -0,0 +1,97 @@
+use hotshot_testing::{
+    byzantine::{ByzantineBehaviour, ByzantineNode},
+    node_types::{MemoryImpl, TestTypes},
+    per_node_safety_task::Misbehaviour,
+    test_builder::TestMetadata,
+};
+
+/// Run the multiple rounds test with node 2 misbehaving as `behaviour` from the first view, and
+/// check it did misbehave and an honest node saw evidence of it that `is_evidence` accepts.
+async fn run_with_byzantine_node(
+    behaviour: ByzantineBehaviour,
+    is_evidence: impl Fn(&Misbehaviour) -> bool,
+) {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let metadata = TestMetadata {
+        byzantine_nodes: vec![ByzantineNode {
+            idx: 2,
+            behaviour,
+            from_view: 1,
+        }],
+        ..TestMetadata::default_multiple_rounds()
+    };
+    let measurements = metadata
+        .gen_launcher::<TestTypes, MemoryImpl>(0)
+        .launch()
+        .run_test_with_measurements()
+        .await;
+    assert!(
+        measurements
+            .misbehaviours_sent
+            .get(&2)
+            .is_some_and(|sent| *sent > 0),
+        "Node 2 never misbehaved"
+    );
+    assert!(
+        measurements
+            .misbehaviours_detected
+            .iter()
+            .any(|(node_id, evidence)| *node_id != 2 && evidence.iter().any(&is_evidence)),
+        "No honest node saw evidence of node 2 misbehaving, only {:?}",
+        measurements.misbehaviours_detected
+    );
+}
+
+/// Test a leader proposing a second block for each view it leads, which replicas must not vote
+/// for both of.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_byzantine_equivocating_leader() {
+    run_with_byzantine_node(ByzantineBehaviour::EquivocatingLeader, |evidence| {
+        matches!(evidence, Misbehaviour::ConflictingProposals { .. })
+    })
+    .await;
+}
+
+/// Test a replica voting for a bogus leaf as well as for the proposed one.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_byzantine_double_voting() {
+    run_with_byzantine_node(ByzantineBehaviour::DoubleVoting, |evidence| {
+        matches!(evidence, Misbehaviour::ConflictingVotes { .. })
+    })
+    .await;
+}
+
+/// Test a leader withholding its DA proposal and VID disperse, which fails the views it leads.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_byzantine_withhold_da() {
+    run_with_byzantine_node(ByzantineBehaviour::WithholdDA, |evidence| {
+        matches!(evidence, Misbehaviour::MissingDAProposal { .. })
+    })
+    .await;
+}
+
+/// Test a leader justifying its proposals with invalid QCs, which replicas must not vote for.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_byzantine_invalid_qc() {
+    run_with_byzantine_node(ByzantineBehaviour::InvalidQC, |evidence| {
+        matches!(evidence, Misbehaviour::InvalidQC { .. })
+    })
+    .await;
+}
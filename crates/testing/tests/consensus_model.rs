@@ -0,0 +1,114 @@
+#![cfg(async_executor_impl = "tokio")]
+
+use hotshot_testing::{
+    consensus_model::{observe, play, ConsensusModelDescription, Observed, Script, Step},
+    simulation::{simulate, simulation_seed},
+};
+use tokio::runtime::{Builder, Runtime};
+
+/// A runtime for simulations, whose clock only moves when every task waits on a timer
+fn paused_runtime() -> Runtime {
+    Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .expect("Could not build the simulation runtime")
+}
+
+/// Test the consensus task does what the model expects on a chain of proposals in consecutive
+/// views, which it votes for, locks and decides.
+#[test]
+fn test_consensus_model_chain() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let script = Script {
+        steps: (1..=5)
+            .map(|view| Step::Propose {
+                view,
+                parent: view - 1,
+                dac: true,
+                deliver: true,
+            })
+            .collect(),
+    };
+    simulate(paused_runtime(), simulation_seed(), || async move {
+        if let Err(e) = play(&script).await {
+            panic!("{e}");
+        }
+    });
+}
+
+/// The views the consensus task votes in when played views 1 to 3 in a chain, which locks view 2,
+/// then `steps`
+fn votes_after_lock(steps: &[Step]) -> Vec<u64> {
+    let mut script = Script {
+        steps: (1..=3).map(|view| propose(view, view - 1, true)).collect(),
+    };
+    script.steps.extend_from_slice(steps);
+    simulate(paused_runtime(), simulation_seed(), || async move {
+        observe(&script)
+            .await
+            .into_iter()
+            .filter_map(|(_, observed)| match observed {
+                Observed::Vote { view, .. } => Some(view),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+/// A proposal of `view` on top of that of `parent`, whose DAC and itself reach the replica if
+/// `delivered`
+fn propose(view: u64, parent: u64, delivered: bool) -> Step {
+    Step::Propose {
+        view,
+        parent,
+        dac: delivered,
+        deliver: delivered,
+    }
+}
+
+/// Test the consensus task votes for a proposal that extends its locked leaf, but is justified by
+/// the QC of the locked leaf itself: view 4 builds on the locked view 2 after view 3 times out.
+#[test]
+fn test_consensus_vote_on_safety() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    assert_eq!(votes_after_lock(&[propose(4, 2, true)]), vec![1, 2, 3, 4]);
+}
+
+/// Test the consensus task votes on liveness for a proposal that doesn't extend its locked leaf,
+/// but whose parent is newer than it: view 6 builds on a fork through view 5 down to an
+/// undelivered proposal of view 4 on top of view 1.
+#[test]
+fn test_consensus_vote_on_liveness() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    assert_eq!(
+        votes_after_lock(&[
+            propose(4, 1, false),
+            propose(5, 4, true),
+            propose(6, 5, true),
+        ]),
+        vec![1, 2, 3, 6]
+    );
+}
+
+/// Test the consensus task doesn't vote for a proposal that neither extends its locked leaf nor is
+/// newer than it: view 4 builds on view 1, below the locked view 2.
+#[test]
+fn test_consensus_no_vote_on_safe_node_failure() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    assert_eq!(votes_after_lock(&[propose(4, 1, true)]), vec![1, 2, 3]);
+}
+
+/// Test the consensus task against the model on random scripts, shrinking any it fails.
+#[test]
+fn test_consensus_model() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    simulate(paused_runtime(), simulation_seed(), || {
+        ConsensusModelDescription::default().check()
+    });
+}
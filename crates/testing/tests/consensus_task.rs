@@ -180,3 +180,149 @@ async fn test_consensus_vote() {
 
     run_harness(input, output, None, build_fn).await;
 }
+
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_consensus_vote_metrics() {
+    use hotshot::traits::implementations::PrometheusMetrics;
+    use hotshot_task_impls::harness::run_harness;
+    use hotshot_testing::task_helpers::build_system_handle_with_metrics;
+    use hotshot_types::consensus::ConsensusMetricsValue;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let metrics = PrometheusMetrics::default();
+    let handle = build_system_handle_with_metrics(2, ConsensusMetricsValue::with_metrics(&metrics))
+        .await
+        .0;
+    let (private_key, public_key) = key_pair_for_id(1);
+
+    let mut input = Vec::new();
+    let mut output = HashMap::new();
+
+    // Node 2 leads view 2, so it waits for the votes on the proposal for view 1 from when it
+    // enters view 1, and its own vote is the first of them. One vote isn't enough for a QC.
+    let proposal = build_quorum_proposal(&handle, &private_key, 1).await;
+    input.push(HotShotEvent::QuorumProposalRecv(
+        proposal.clone(),
+        public_key,
+    ));
+    output.insert(
+        HotShotEvent::QuorumProposalRecv(proposal.clone(), public_key),
+        1,
+    );
+    let GeneralConsensusMessage::Vote(vote) = build_vote(&handle, proposal.data).await else {
+        panic!("Expected a vote");
+    };
+    output.insert(HotShotEvent::QuorumVoteSend(vote.clone()), 1);
+    input.push(HotShotEvent::QuorumVoteRecv(vote.clone()));
+    output.insert(HotShotEvent::QuorumVoteRecv(vote), 1);
+    output.insert(HotShotEvent::ViewChange(ViewNumber::new(1)), 1);
+
+    input.push(HotShotEvent::Shutdown);
+    output.insert(HotShotEvent::Shutdown, 1);
+
+    let build_fn = |task_runner, event_stream| {
+        add_consensus_task(task_runner, event_stream, ChannelStream::new(), handle)
+    };
+
+    run_harness(input, output, None, build_fn).await;
+
+    let exported = metrics.export();
+    assert!(
+        exported.contains("quorum_proposals_received_total 1\n"),
+        "{exported}"
+    );
+    assert!(
+        exported.contains("quorum_vote_latency_seconds_count 1\n"),
+        "{exported}"
+    );
+    assert!(
+        exported.contains("quorum_certificates_formed_total 0\n"),
+        "{exported}"
+    );
+    assert!(
+        exported.contains("quorum_time_to_certificate_seconds_count 0\n"),
+        "{exported}"
+    );
+}
+
+/// Test a replica doesn't vote for a proposal that failed its checks once the DAC for its view
+/// arrives, and still votes for the valid proposal of that view.
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_consensus_no_vote_for_invalid_proposal() {
+    use hotshot_task_impls::harness::run_harness;
+    use hotshot_testing::task_helpers::build_system_handle;
+    use hotshot_types::{
+        simple_certificate::DACertificate, simple_vote::DAData, traits::block_contents::BlockHeader,
+    };
+    use std::marker::PhantomData;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let (private_key, public_key) = key_pair_for_id(1);
+
+    let mut input = Vec::new();
+    let mut output = HashMap::new();
+
+    // The leader's proposal with its header tampered with, so its signature doesn't match
+    let proposal = build_quorum_proposal(&handle, &private_key, 1).await;
+    let mut tampered = proposal.clone();
+    tampered.data.block_header.block_number += 1;
+    let data = DAData {
+        payload_commit: proposal.data.block_header.payload_commitment(),
+    };
+    let dac = DACertificate::<TestTypes> {
+        vote_commitment: data.commit(),
+        data,
+        view_number: ViewNumber::new(1),
+        signatures: None,
+        is_genesis: true,
+        _pd: PhantomData,
+    };
+
+    input.push(HotShotEvent::QuorumProposalRecv(
+        tampered.clone(),
+        public_key,
+    ));
+    output.insert(HotShotEvent::QuorumProposalRecv(tampered, public_key), 1);
+    output.insert(HotShotEvent::ViewChange(ViewNumber::new(1)), 1);
+
+    // Voting for the tampered proposal here would be an unexpected event
+    input.push(HotShotEvent::DACRecv(dac.clone()));
+    output.insert(HotShotEvent::DACRecv(dac), 1);
+
+    input.push(HotShotEvent::QuorumProposalRecv(
+        proposal.clone(),
+        public_key,
+    ));
+    output.insert(
+        HotShotEvent::QuorumProposalRecv(proposal.clone(), public_key),
+        1,
+    );
+    let GeneralConsensusMessage::Vote(vote) = build_vote(&handle, proposal.data).await else {
+        panic!("Expected a vote");
+    };
+    output.insert(HotShotEvent::QuorumVoteSend(vote), 1);
+
+    input.push(HotShotEvent::Shutdown);
+    output.insert(HotShotEvent::Shutdown, 1);
+
+    let build_fn = |task_runner, event_stream| {
+        add_consensus_task(task_runner, event_stream, ChannelStream::new(), handle)
+    };
+
+    run_harness(input, output, None, build_fn).await;
+}
@@ -0,0 +1,151 @@
+use commit::Committable;
+use hotshot::{types::SignatureKey, HotShotConsensusApi};
+use hotshot_task_impls::{events::HotShotEvent, journal::JournalEvent};
+use hotshot_testing::node_types::{MemoryImpl, TestTypes};
+use hotshot_types::{
+    block_impl::VIDTransaction,
+    data::{DAProposal, ViewNumber},
+    simple_vote::{DAData, DAVote},
+    traits::{
+        consensus_api::ConsensusSharedApi, node_implementation::NodeType, state::ConsensusTime,
+    },
+};
+use std::marker::PhantomData;
+
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_journal_replay() {
+    use hotshot::tasks::add_da_task;
+    use hotshot_task_impls::journal::{read_journal, replay_journal, JournalWriter};
+    use hotshot_testing::task_helpers::build_system_handle;
+    use hotshot_types::{block_impl::VIDBlockPayload, message::Proposal};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Build the API for node 2.
+    let handle = build_system_handle(2).await.0;
+    let api: HotShotConsensusApi<TestTypes, MemoryImpl> = HotShotConsensusApi {
+        inner: handle.hotshot.inner.clone(),
+    };
+    let pub_key = *api.public_key();
+    let transactions = vec![VIDTransaction(vec![0])];
+    let encoded_txns = VIDTransaction::encode(transactions.clone()).unwrap();
+    let payload_commitment = VIDBlockPayload::vid_commitment(&encoded_txns);
+    let block = VIDBlockPayload {
+        transactions,
+        payload_commitment,
+    };
+
+    let signature =
+        <TestTypes as NodeType>::SignatureKey::sign(api.private_key(), block.commit().as_ref());
+    let message = Proposal {
+        data: DAProposal {
+            block_payload: block.clone(),
+            view_number: ViewNumber::new(2),
+        },
+        signature,
+        _pd: PhantomData,
+    };
+
+    // Record what the DA task of node 2 sees in view 2, including what it publishes itself.
+    let path = std::env::temp_dir().join(format!("hotshot-journal-{}.journal", std::process::id()));
+    let mut journal = JournalWriter::create(&path).unwrap();
+    let recorded = vec![
+        HotShotEvent::ViewChange(ViewNumber::new(1)),
+        HotShotEvent::ViewChange(ViewNumber::new(2)),
+        HotShotEvent::DAProposalRecv(message.clone(), pub_key),
+        HotShotEvent::DAVoteSend(DAVote::create_signed_vote(
+            DAData {
+                payload_commit: block.commit(),
+            },
+            ViewNumber::new(2),
+            api.public_key(),
+            api.private_key(),
+        )),
+    ];
+    for event in recorded.clone() {
+        journal.record(event).unwrap();
+    }
+    drop(journal);
+
+    let journal = read_journal::<TestTypes>(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let read: Vec<_> = journal
+        .iter()
+        .map(|entry| match &entry.event {
+            JournalEvent::Internal(event) => event.clone(),
+            JournalEvent::External { .. } => panic!("Only internal events were recorded"),
+        })
+        .collect();
+    assert_eq!(read, recorded);
+    assert!(journal
+        .windows(2)
+        .all(|entries| entries[0].timestamp <= entries[1].timestamp));
+
+    // Replaying everything but the DA task's own vote makes it send the same vote again.
+    let seen = replay_journal(
+        journal,
+        |event| !matches!(event, HotShotEvent::DAVoteSend(_)),
+        |task_runner, event_stream| add_da_task(task_runner, event_stream, handle),
+    )
+    .await;
+    assert!(seen.contains(&recorded[3]));
+    assert_eq!(seen.last(), Some(&HotShotEvent::Shutdown));
+}
+
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_journal_torn_tail() {
+    use hotshot_task_impls::journal::{read_journal, JournalWriter};
+
+    let path = std::env::temp_dir().join(format!(
+        "hotshot-journal-torn-{}.journal",
+        std::process::id()
+    ));
+    let mut journal = JournalWriter::create(&path).unwrap();
+    for view in 1..=3 {
+        journal
+            .record::<TestTypes>(HotShotEvent::ViewChange(ViewNumber::new(view)))
+            .unwrap();
+    }
+    drop(journal);
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(read_journal::<TestTypes>(&path).unwrap().len(), 3);
+
+    // where the last entry starts, from the length prefixes of the first two
+    let mut last = 0;
+    for _ in 0..2 {
+        let len = u64::from_le_bytes(bytes[last..last + 8].try_into().unwrap());
+        last += 8 + usize::try_from(len).unwrap();
+    }
+
+    // Cut off in the last entry's body, in its length prefix, and right after the second entry,
+    // the journal reads back the two whole entries before it.
+    for cut in [bytes.len() - 1, last + 8, last + 3, last] {
+        std::fs::write(&path, &bytes[..cut]).unwrap();
+        let journal = read_journal::<TestTypes>(&path).unwrap();
+        let views: Vec<_> = journal
+            .into_iter()
+            .map(|entry| match entry.event {
+                JournalEvent::Internal(HotShotEvent::ViewChange(view)) => view,
+                event => panic!("Unexpected event {event:?}"),
+            })
+            .collect();
+        assert_eq!(
+            views,
+            [ViewNumber::new(1), ViewNumber::new(2)],
+            "cut at {cut}"
+        );
+    }
+
+    std::fs::write(&path, b"").unwrap();
+    assert!(read_journal::<TestTypes>(&path).unwrap().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -2,10 +2,12 @@ use std::collections::BTreeSet;
 use std::sync::Arc;
 
 use async_compatibility_layer::logging::setup_logging;
+use bincode::Options;
 use hotshot::demo::DemoState;
 use hotshot::traits::election::static_committee::{GeneralStaticCommittee, StaticElectionConfig};
 use hotshot::traits::implementations::{
     MasterMap, MemoryCommChannel, MemoryNetwork, MemoryStorage, NetworkingMetricsValue,
+    PrometheusMetrics,
 };
 use hotshot::traits::NodeImplementation;
 use hotshot::types::bn254::{BLSPrivKey, BLSPubKey};
@@ -20,6 +22,7 @@ use hotshot_types::{
     message::{DataMessage, MessageKind},
     traits::state::ConsensusTime,
 };
+use hotshot_utils::bincode::bincode_opts;
 use rand::rngs::StdRng;
 use rand::{RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
@@ -353,3 +356,53 @@ async fn memory_network_test_in_flight_message_count() {
     assert_eq!(network1.in_flight_message_count(), Some(0));
     assert_eq!(network2.in_flight_message_count(), Some(0));
 }
+
+// The bytes of messages are counted as they leave the sender and as they reach the recipient
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_byte_metrics() {
+    setup_logging();
+
+    let group: Arc<MasterMap<Message<Test>, <Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let metrics_1 = PrometheusMetrics::default();
+    let metrics_2 = PrometheusMetrics::default();
+    let pub_key_1 = get_pubkey();
+    let network1 = MemoryNetwork::new(
+        pub_key_1,
+        NetworkingMetricsValue::with_metrics(&metrics_1),
+        group.clone(),
+        Option::None,
+    );
+    let pub_key_2 = get_pubkey();
+    let network2 = MemoryNetwork::new(
+        pub_key_2,
+        NetworkingMetricsValue::with_metrics(&metrics_2),
+        group,
+        Option::None,
+    );
+
+    let messages: Vec<Message<Test>> = gen_messages(5, 100, pub_key_1);
+    let mut bytes = 0;
+    for message in messages {
+        bytes += bincode_opts().serialize(&message).unwrap().len();
+        network1.direct_message(message, pub_key_2).await.unwrap();
+        network2.recv_msgs(TransmitType::Direct).await.unwrap();
+    }
+
+    let sent = metrics_1.export();
+    assert!(
+        sent.contains(&format!("outgoing_bytes_total {bytes}\n")),
+        "{sent}"
+    );
+    assert!(sent.contains("incoming_bytes_total 0\n"), "{sent}");
+    let received = metrics_2.export();
+    assert!(
+        received.contains(&format!("incoming_bytes_total {bytes}\n")),
+        "{received}"
+    );
+    assert!(received.contains("outgoing_bytes_total 0\n"), "{received}");
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use hotshot_testing::{
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    fault_task::{FaultPlanDescription, LinkChange},
+    node_types::{MemoryImpl, TestTypes},
+    test_builder::TestMetadata,
+};
+
+/// Run the multiple rounds test for up to a minute with the links changed as planned.
+async fn run_with_fault_plan(fault_plan: FaultPlanDescription) {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let metadata = TestMetadata {
+        fault_plan,
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestMetadata::default_multiple_rounds()
+    };
+    metadata
+        .gen_launcher::<TestTypes, MemoryImpl>(0)
+        .launch()
+        .run_test()
+        .await;
+}
+
+/// Test splitting the network in two halves, neither of which can form a quorum, then healing it.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_partition_heals() {
+    run_with_fault_plan(FaultPlanDescription {
+        link_changes: vec![
+            (
+                Duration::from_secs(1),
+                LinkChange::Partition(vec![(0..5).collect(), (5..10).collect()]),
+            ),
+            (Duration::from_secs(4), LinkChange::Heal),
+        ],
+        progress_after_heal: Some(Duration::from_secs(15)),
+    })
+    .await;
+}
+
+/// Test cutting a minority of the nodes off, which the rest of the network carries on without.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_minority_partition() {
+    run_with_fault_plan(FaultPlanDescription {
+        link_changes: vec![
+            (
+                Duration::from_secs(1),
+                LinkChange::Partition(vec![vec![7, 8, 9]]),
+            ),
+            (Duration::from_secs(5), LinkChange::Heal),
+        ],
+        progress_after_heal: Some(Duration::from_secs(15)),
+    })
+    .await;
+}
+
+/// Test nodes that can receive but not send to some of the others, over links of uneven latency.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_asymmetric_links_and_latency() {
+    // nodes further apart by index are further apart on the network
+    let latencies = (0..10)
+        .map(|from: u64| {
+            (0..10)
+                .map(|to: u64| Duration::from_millis(5 * from.abs_diff(to)))
+                .collect()
+        })
+        .collect();
+    run_with_fault_plan(FaultPlanDescription {
+        link_changes: vec![
+            (Duration::ZERO, LinkChange::LatencyMatrix(latencies)),
+            (
+                Duration::from_secs(1),
+                LinkChange::FailLink { from: 0, to: 1 },
+            ),
+            (
+                Duration::from_secs(1),
+                LinkChange::FailLink { from: 2, to: 3 },
+            ),
+            (
+                Duration::from_secs(1),
+                LinkChange::SetLatency {
+                    from: 4,
+                    to: 5,
+                    latency: Duration::from_millis(300),
+                },
+            ),
+            (
+                Duration::from_secs(5),
+                LinkChange::RestoreLink { from: 0, to: 1 },
+            ),
+            (Duration::from_secs(8), LinkChange::Heal),
+        ],
+        progress_after_heal: Some(Duration::from_secs(15)),
+    })
+    .await;
+}
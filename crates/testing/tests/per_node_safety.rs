@@ -0,0 +1,77 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_compatibility_layer::art::async_timeout;
+use hotshot::HotShotConsensusApi;
+use hotshot_task::{
+    event_stream::{ChannelStream, EventStream},
+    global_registry::GlobalRegistry,
+    task::HotShotTaskCompleted,
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_testing::{
+    node_types::{MemoryImpl, TestTypes},
+    per_node_safety_task::{PerNodeSafetyPropertiesDescription, PerNodeSafetyTask},
+    task_helpers::build_system_handle,
+    test_runner::Node,
+};
+use hotshot_types::{
+    data::{random_commitment, ViewNumber},
+    simple_vote::{QuorumData, QuorumVote},
+    traits::{consensus_api::ConsensusSharedApi, state::ConsensusTime},
+};
+use rand::thread_rng;
+
+/// Test a node voting for two leaves in the same view is reported, along with its votes.
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_per_node_safety_double_vote() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let (handle, event_stream) = build_system_handle(2).await;
+    let api: HotShotConsensusApi<TestTypes, MemoryImpl> = HotShotConsensusApi {
+        inner: handle.hotshot.inner.clone(),
+    };
+    let vote = || {
+        HotShotEvent::QuorumVoteSend(QuorumVote::create_signed_vote(
+            QuorumData {
+                leaf_commit: random_commitment(&mut thread_rng()),
+            },
+            ViewNumber::new(1),
+            api.public_key(),
+            api.private_key(),
+        ))
+    };
+
+    let state = PerNodeSafetyTask {
+        handles: vec![Node { node_id: 2, handle }],
+        ctx: HashMap::new(),
+        test_event_stream: ChannelStream::new(),
+        detected: Arc::default(),
+    };
+    let (_, task) = (PerNodeSafetyPropertiesDescription::default().build())(
+        state,
+        GlobalRegistry::default(),
+        ChannelStream::new(),
+    )
+    .await;
+
+    event_stream
+        .publish(HotShotEvent::ViewChange(ViewNumber::new(1)))
+        .await;
+    event_stream.publish(vote()).await;
+    event_stream.publish(vote()).await;
+
+    match async_timeout(Duration::from_secs(10), task).await {
+        Ok(HotShotTaskCompleted::Error(e)) => {
+            let report = e.to_string();
+            assert!(report.contains("voted twice in view 1"), "{report}");
+            assert!(report.contains("moved to view 1"), "{report}");
+        }
+        Ok(_) => panic!("The double vote wasn't reported"),
+        Err(_) => panic!("The per node safety task didn't see the double vote"),
+    }
+}
@@ -0,0 +1,126 @@
+#![cfg(async_executor_impl = "tokio")]
+
+use std::time::Duration;
+
+use hotshot_testing::{
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    node_types::{MemoryImpl, TestTypes},
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    simulation::{simulate, simulation_seed, Instant, SimulationRng},
+    spinning_task::{ChangeNode, SpinningTaskDescription, UpDown},
+    test_builder::{TestMetadata, TimingData},
+};
+use rand::RngCore;
+use tokio::runtime::{Builder, Runtime};
+
+/// A runtime for simulations, whose clock only moves when every task waits on a timer
+fn paused_runtime() -> Runtime {
+    Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .expect("Could not build the simulation runtime")
+}
+
+/// Test a simulation draws the same numbers for the same seed, and sleeps in virtual time.
+#[test]
+fn test_simulation_is_seeded() {
+    let draw = |seed| {
+        let mut draws = vec![];
+        simulate(paused_runtime(), seed, || async {
+            for _ in 0..8 {
+                draws.push(SimulationRng.next_u64());
+            }
+            let start = Instant::now();
+            async_compatibility_layer::art::async_sleep(Duration::from_secs(3600)).await;
+            assert!(start.elapsed() >= Duration::from_secs(3600));
+        });
+        draws
+    };
+
+    let wall_clock = std::time::Instant::now();
+    assert_eq!(draw(42), draw(42));
+    assert_ne!(draw(42), draw(43));
+    assert!(wall_clock.elapsed() < Duration::from_secs(60));
+}
+
+#[test]
+fn test_success_simulated() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    TestMetadata {
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestMetadata::default()
+    }
+    .simulate::<TestTypes, MemoryImpl>(paused_runtime(), simulation_seed());
+}
+
+/// Test two nodes catching up after starting late, as `test_catchup` does, in a simulation.
+#[test]
+fn test_catchup_simulated() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let catchup_nodes = vec![
+        ChangeNode {
+            idx: 18,
+            updown: UpDown::Up,
+        },
+        ChangeNode {
+            idx: 19,
+            updown: UpDown::Up,
+        },
+    ];
+    TestMetadata {
+        timing_data: TimingData {
+            next_view_timeout: 2000,
+            ..Default::default()
+        },
+        start_nodes: 18,
+        total_nodes: 20,
+        spinning_properties: SpinningTaskDescription {
+            node_changes: vec![(Duration::new(1, 0), catchup_nodes)],
+        },
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_millis(100000),
+            },
+        ),
+        overall_safety_properties: OverallSafetyPropertiesDescription {
+            check_leaf: true,
+            ..Default::default()
+        },
+        ..TestMetadata::default()
+    }
+    .simulate::<TestTypes, MemoryImpl>(paused_runtime(), simulation_seed());
+}
+
+/// Test a run replays the same way for the same seed, deciding the same leaves with the same
+/// transactions.
+#[test]
+fn test_simulation_replays() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let run = |seed| {
+        TestMetadata {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(30),
+                },
+            ),
+            ..TestMetadata::default()
+        }
+        .simulate::<TestTypes, MemoryImpl>(paused_runtime(), seed)
+    };
+
+    let seed = simulation_seed();
+    let first = run(seed);
+    let second = run(seed);
+    assert!(!first.decided_leaves.is_empty(), "Nothing was decided");
+    assert_eq!(first.decided_leaves, second.decided_leaves);
+    assert_eq!(first.decided_txns, second.decided_txns);
+    assert_eq!(first.time_to_decide, second.time_to_decide);
+}
@@ -1,14 +1,75 @@
-use std::time::Duration;
+use std::{collections::BTreeSet, net::TcpListener, time::Duration};
 
-use async_compatibility_layer::logging::shutdown_logging;
+use async_compatibility_layer::{
+    art::{async_sleep, async_spawn, async_timeout},
+    logging::shutdown_logging,
+};
+use commit::Committable;
+use hotshot::{
+    traits::implementations::WebServerNetwork,
+    types::{bn254::BLSPubKey, SignatureKey},
+};
 use hotshot_testing::{
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
     node_types::{TestTypes, WebImpl},
     overall_safety_task::OverallSafetyPropertiesDescription,
     test_builder::{TestMetadata, TimingData},
 };
+use hotshot_types::{
+    block_impl::VIDTransaction,
+    data::ViewNumber,
+    message::{DataMessage, Message, MessageKind, VersionedMessage},
+    traits::{
+        network::{ConnectedNetwork, ConsensusIntentEvent, TransmitType},
+        state::ConsensusTime,
+    },
+};
+use surf_disco::Url;
 use tracing::instrument;
 
+/// A local url nothing is listening on
+fn unreachable_url() -> Url {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    format!("http://127.0.0.1:{port}").parse().unwrap()
+}
+
+/// A transaction submitted by `sender`
+fn transaction(sender: BLSPubKey, payload: Vec<u8>) -> Message<TestTypes> {
+    Message {
+        sender,
+        kind: MessageKind::Data(DataMessage::SubmitTransaction(
+            VIDTransaction(payload),
+            ViewNumber::new(0),
+        )),
+    }
+}
+
+/// The web server computes a posted transaction's commitment the way nodes do, and refuses
+/// anything else
+#[test]
+fn web_server_transaction_commitment() {
+    let sender = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0;
+    let body = VersionedMessage::new(&transaction(sender, vec![1, 2, 3]))
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+    assert_eq!(
+        hotshot_web_server::transaction_commitment::<TestTypes>(&body),
+        Some(hotshot_web_server::config::commitment_param(
+            VIDTransaction(vec![1, 2, 3]).commit().as_ref()
+        ))
+    );
+    let not_a_transaction = VersionedMessage::new(&7u64).unwrap().to_bytes().unwrap();
+    assert_eq!(
+        hotshot_web_server::transaction_commitment::<TestTypes>(&not_a_transaction),
+        None
+    );
+}
+
 /// Web server network test
 #[cfg_attr(
     async_executor_impl = "tokio",
@@ -45,3 +106,93 @@ async fn web_server_network() {
         .await;
     shutdown_logging();
 }
+
+/// Posting to a web server cluster whose instances are all down gives up after trying each of them
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn web_server_cluster_down() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let network = WebServerNetwork::<TestTypes>::create(
+        vec![unreachable_url(), unreachable_url()],
+        Duration::from_millis(100),
+        key,
+        private_key,
+        false,
+    );
+    let result = async_timeout(
+        Duration::from_secs(10),
+        network.broadcast_message(transaction(key, vec![1]), BTreeSet::new()),
+    )
+    .await;
+    assert!(matches!(result, Ok(Err(_))), "{result:?}");
+    network.shut_down().await;
+    shutdown_logging();
+}
+
+/// Nodes whose first web server is down fail over to the next, and a transaction posted there is
+/// delivered exactly once however often it is submitted
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn web_server_failover() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let keys: Vec<_> = (0..2)
+        .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i))
+        .collect();
+    let live = unreachable_url();
+    async_spawn(hotshot_web_server::run_web_server::<TestTypes>(
+        None,
+        live.port().unwrap(),
+        keys.iter().map(|(key, _)| *key).collect(),
+        hotshot_web_server::Options::default(),
+    ));
+    let urls = vec![unreachable_url(), live];
+    let network = |i: usize| {
+        WebServerNetwork::<TestTypes>::create(
+            urls.clone(),
+            Duration::from_millis(100),
+            keys[i].0,
+            keys[i].1.clone(),
+            false,
+        )
+    };
+    let (sender, receiver) = (network(0), network(1));
+    async_timeout(Duration::from_secs(30), async {
+        sender.wait_for_ready().await;
+        receiver.wait_for_ready().await;
+    })
+    .await
+    .expect("Nodes never reached the live web server");
+
+    receiver
+        .inject_consensus_info(ConsensusIntentEvent::PollForTransactions(0))
+        .await;
+    let message = transaction(keys[0].0, vec![1, 2, 3]);
+    for _ in 0..2 {
+        sender
+            .broadcast_message(message.clone(), BTreeSet::new())
+            .await
+            .unwrap();
+    }
+
+    let mut received = Vec::new();
+    for _ in 0..30 {
+        async_sleep(Duration::from_millis(100)).await;
+        received.extend(receiver.recv_msgs(TransmitType::Broadcast).await.unwrap());
+    }
+    assert_eq!(received, vec![message]);
+
+    sender.shut_down().await;
+    receiver.shut_down().await;
+    shutdown_logging();
+}
@@ -86,6 +86,8 @@ pub struct ConsensusMetricsValue {
     pub outstanding_transactions_memory_size: Box<dyn Gauge>,
     /// Number of views that timed out
     pub number_of_timeouts: Box<dyn Counter>,
+    /// The group these metrics are in, for the tasks to create their own subgroups of
+    pub group: Box<dyn Metrics>,
 }
 
 /// The wrapper with a string name for the networking metrics
@@ -207,12 +209,22 @@ impl ConsensusMetricsValue {
     #[must_use]
     pub fn new() -> Self {
         let values = Arc::default();
-        let metrics: Box<dyn Metrics> = Box::new(ConsensusMetrics {
+        let metrics = ConsensusMetrics {
             prefix: String::new(),
             values: Arc::clone(&values),
-        });
+        };
         Self {
             values,
+            ..Self::with_metrics(&metrics)
+        }
+    }
+
+    /// Create the consensus metrics in `metrics`, e.g. to export them. `values` stays empty, since
+    /// the values are kept by `metrics` instead.
+    #[must_use]
+    pub fn with_metrics(metrics: &dyn Metrics) -> Self {
+        Self {
+            values: Arc::default(),
             last_synced_block_height: metrics
                 .create_gauge(String::from("last_synced_block_height"), None),
             last_decided_view: metrics.create_gauge(String::from("last_decided_view"), None),
@@ -227,6 +239,7 @@ impl ConsensusMetricsValue {
             outstanding_transactions_memory_size: metrics
                 .create_gauge(String::from("outstanding_transactions_memory_size"), None),
             number_of_timeouts: metrics.create_counter(String::from("number_of_timeouts"), None),
+            group: dyn_clone::clone_box(metrics),
         }
     }
 }
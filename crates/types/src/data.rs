@@ -333,7 +333,7 @@ impl<TYPES: NodeType> Leaf<TYPES> {
             block_header,
             block_payload: Some(block_payload),
             rejected: Vec::new(),
-            timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+            timestamp: crate::simulation::timestamp_nanos(),
             proposer_id: genesis_proposer_id(),
         }
     }
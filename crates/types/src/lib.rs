@@ -20,6 +20,7 @@ pub mod event;
 pub mod message;
 pub mod simple_certificate;
 pub mod simple_vote;
+pub mod simulation;
 pub mod traits;
 pub mod utils;
 pub mod vote;
@@ -16,16 +16,20 @@ use crate::{
     data::{DAProposal, VidDisperse},
     simple_vote::QuorumVote,
     traits::{
-        network::{NetworkMsg, ViewMessage},
+        network::{FailedToDeserializeSnafu, NetworkError, NetworkMsg, ViewMessage},
         node_implementation::NodeType,
         signature_key::EncodedSignature,
     },
 };
 
+use bincode::Options;
 use derivative::Derivative;
 use either::Either::{self, Left, Right};
+use hotshot_constants::{Version, PROTOCOL_VERSION};
+use hotshot_utils::bincode::bincode_opts;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
 use std::{fmt::Debug, marker::PhantomData};
 
 /// Incoming message
@@ -51,12 +55,73 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for Message<TYPES> {
     }
 }
 
+/// A serialized message tagged with the protocol version of its sender.
+///
+/// This is what actually goes over the wire. The layout of the envelope itself must never change,
+/// so that a node can always read the version of a peer before attempting to decode the payload.
+/// Every transport encodes it with [`Self::to_bytes`] and decodes it with [`Self::from_bytes`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionedMessage {
+    /// the protocol version the payload was serialized under
+    pub version: Version,
+    /// the serialized message
+    pub payload: Vec<u8>,
+}
+
+impl VersionedMessage {
+    /// Serialize `message` under this node's [`PROTOCOL_VERSION`]
+    /// # Errors
+    /// If `message` cannot be serialized
+    pub fn new<M: Serialize>(message: &M) -> Result<Self, bincode::Error> {
+        Ok(Self {
+            version: PROTOCOL_VERSION,
+            payload: bincode_opts().serialize(message)?,
+        })
+    }
+
+    /// Whether this node is able to decode the payload
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        PROTOCOL_VERSION.is_compatible_with(&self.version)
+    }
+
+    /// Decode the payload, refusing messages from incompatible protocol versions
+    /// # Errors
+    /// - [`NetworkError::IncompatibleVersion`] if the sender speaks an incompatible version
+    /// - [`NetworkError::FailedToDeserialize`] if the payload is malformed
+    pub fn open<M: DeserializeOwned>(&self) -> Result<M, NetworkError> {
+        if !self.is_compatible() {
+            return Err(NetworkError::IncompatibleVersion {
+                local: PROTOCOL_VERSION,
+                remote: self.version,
+            });
+        }
+        bincode_opts()
+            .deserialize(&self.payload)
+            .context(FailedToDeserializeSnafu)
+    }
+
+    /// Serialize the envelope itself
+    /// # Errors
+    /// If the envelope cannot be serialized
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::Error> {
+        bincode_opts().serialize(self)
+    }
+
+    /// Deserialize an envelope received from the wire
+    /// # Errors
+    /// If `bytes` is not a valid envelope
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        bincode_opts().deserialize(bytes)
+    }
+}
+
 /// A wrapper type for implementing `PassType` on a vector of `Message`.
 #[derive(Clone, Debug)]
 pub struct Messages<TYPES: NodeType>(pub Vec<Message<TYPES>>);
 
 /// A message type agnostic description of a message's purpose
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub enum MessagePurpose {
     /// Message with a quorum proposal.
     Proposal,
@@ -470,3 +535,53 @@ pub struct Proposal<TYPES: NodeType, PROPOSAL: HasViewNumber<TYPES> + Deserializ
     /// Phantom for TYPES
     pub _pd: PhantomData<TYPES>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_versioned_message_round_trip() {
+        let message = (42u64, "a message".to_string(), vec![1u8, 2, 3]);
+        let versioned = VersionedMessage::new(&message).unwrap();
+        assert_eq!(versioned.version, PROTOCOL_VERSION);
+        let received = VersionedMessage::from_bytes(&versioned.to_bytes().unwrap()).unwrap();
+        assert_eq!(received, versioned);
+        assert_eq!(received.open::<(u64, String, Vec<u8>)>().unwrap(), message);
+    }
+
+    #[test]
+    fn test_versioned_message_minor_mismatch() {
+        // a newer minor version is still decoded
+        let mut versioned = VersionedMessage::new(&7u64).unwrap();
+        versioned.version.minor += 1;
+        assert!(versioned.is_compatible());
+        assert_eq!(versioned.open::<u64>().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_versioned_message_major_mismatch() {
+        let mut versioned = VersionedMessage::new(&7u64).unwrap();
+        versioned.version.major += 1;
+        assert!(!versioned.is_compatible());
+        let remote = versioned.version;
+        assert!(matches!(
+            versioned.open::<u64>(),
+            Err(NetworkError::IncompatibleVersion { local, remote: got })
+                if local == PROTOCOL_VERSION && got == remote
+        ));
+    }
+
+    #[test]
+    fn test_versioned_message_malformed() {
+        assert!(VersionedMessage::from_bytes(&[0xff; 3]).is_err());
+        let versioned = VersionedMessage {
+            version: PROTOCOL_VERSION,
+            payload: vec![],
+        };
+        assert!(matches!(
+            versioned.open::<u64>(),
+            Err(NetworkError::FailedToDeserialize { .. })
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+//! Hooks for deterministic simulations of tests
+//!
+//! Outside of a simulation, [`SimulationRng`] draws from [`thread_rng`] and [`timestamp_nanos`]
+//! reads the system clock. Once a simulation is [`enter`]ed on the thread running it, they draw
+//! from a generator seeded for the simulation and read the virtual clock of its runtime instead,
+//! so the parts of `HotShot` that use them, and [`Instant`] for timing, behave the same way every
+//! time the simulation is run with the same seed.
+//!
+//! The simulation is kept per thread, so a run is only reproducible if every task of it runs on
+//! the thread that entered it: a simulation must run on a single threaded runtime, such as
+//! tokio's current thread runtime. Tasks on any other thread would draw from [`thread_rng`] and
+//! read the system clock.
+
+use std::cell::RefCell;
+
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// Clock to time with, which is virtual in simulations
+#[cfg(async_executor_impl = "tokio")]
+pub use tokio::time::Instant;
+
+/// Clock to time with
+#[cfg(async_executor_impl = "async-std")]
+pub use std::time::Instant;
+
+/// The Unix time every simulation starts at, in nanoseconds
+const SIMULATION_EPOCH_NANOS: i128 = 1_700_000_000_000_000_000;
+
+/// A simulation running on a thread
+struct Simulation {
+    /// generator of every random choice in the simulation
+    rng: ChaCha20Rng,
+    /// when the simulation was entered, on the clock of its runtime
+    #[cfg(async_executor_impl = "tokio")]
+    entered_at: Instant,
+}
+
+thread_local! {
+    /// the simulation running on this thread, if any
+    static SIMULATION: RefCell<Option<Simulation>> = RefCell::new(None);
+}
+
+/// Enter a simulation seeded with `seed` on the current thread, from within the single threaded
+/// runtime the simulation runs on
+///
+/// # Panics
+/// Panics if called from within a tokio runtime that runs tasks on other threads
+pub fn enter(seed: u64) {
+    #[cfg(async_executor_impl = "tokio")]
+    assert!(
+        matches!(
+            tokio::runtime::Handle::current().runtime_flavor(),
+            tokio::runtime::RuntimeFlavor::CurrentThread
+        ),
+        "Simulations must run on a current thread runtime"
+    );
+    let simulation = Simulation {
+        rng: ChaCha20Rng::seed_from_u64(seed),
+        #[cfg(async_executor_impl = "tokio")]
+        entered_at: Instant::now(),
+    };
+    SIMULATION.with(|current| *current.borrow_mut() = Some(simulation));
+}
+
+/// Leave the simulation running on the current thread, if any
+pub fn exit() {
+    SIMULATION.with(|current| *current.borrow_mut() = None);
+}
+
+/// Random number generator of the simulation running on the current thread, or [`thread_rng`]
+/// outside of simulations
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimulationRng;
+
+impl SimulationRng {
+    /// Call `f` with the generator to draw from
+    fn with<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        SIMULATION.with(|current| match current.borrow_mut().as_mut() {
+            Some(simulation) => f(&mut simulation.rng),
+            None => f(&mut thread_rng()),
+        })
+    }
+}
+
+impl RngCore for SimulationRng {
+    fn next_u32(&mut self) -> u32 {
+        Self::with(RngCore::next_u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Self::with(RngCore::next_u64)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Self::with(|rng| rng.fill_bytes(dest));
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        Self::with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+/// The current Unix time in nanoseconds, which in simulations is a fixed epoch plus the virtual
+/// time since the simulation was entered
+#[must_use]
+pub fn timestamp_nanos() -> i128 {
+    #[cfg(async_executor_impl = "tokio")]
+    if let Some(entered_at) = SIMULATION.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map(|simulation| simulation.entered_at)
+    }) {
+        let elapsed = i128::try_from(entered_at.elapsed().as_nanos()).unwrap_or(i128::MAX);
+        return SIMULATION_EPOCH_NANOS.saturating_add(elapsed);
+    }
+    time::OffsetDateTime::now_utc().unix_timestamp_nanos()
+}
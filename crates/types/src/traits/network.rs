@@ -15,16 +15,23 @@ use super::{node_implementation::NodeType, signature_key::SignatureKey};
 use crate::{
     data::ViewNumber,
     message::{Message, MessagePurpose},
+    simulation::SimulationRng,
 };
 use async_compatibility_layer::channel::UnboundedSendError;
 use async_trait::async_trait;
+use hotshot_constants::Version;
 use rand::{
     distributions::{Bernoulli, Uniform},
     prelude::Distribution,
 };
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use std::{collections::BTreeSet, fmt::Debug, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
+};
 
 impl From<NetworkNodeHandleError> for NetworkError {
     fn from(error: NetworkNodeHandleError) -> Self {
@@ -134,6 +141,14 @@ pub enum NetworkError {
     ShutDown,
     /// unable to cancel a request, the request has already been cancelled
     UnableToCancel,
+    /// The peer speaks a protocol version we cannot decode
+    #[snafu(display("Incompatible protocol version: we speak {local}, peer speaks {remote}"))]
+    IncompatibleVersion {
+        /// the version this node speaks
+        local: Version,
+        /// the version the peer speaks
+        remote: Version,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -180,6 +195,8 @@ pub enum ConsensusIntentEvent<K: SignatureKey> {
     CancelPollForVIDDisperse(u64),
     /// Cancel polling for transactions
     CancelPollForTransactions(u64),
+    /// The transactions with these commitments were decided in a particular view
+    TransactionsDecided(u64, Vec<Vec<u8>>),
 }
 
 impl<K: SignatureKey> ConsensusIntentEvent<K> {
@@ -205,7 +222,8 @@ impl<K: SignatureKey> ConsensusIntentEvent<K> {
             | ConsensusIntentEvent::PollForViewSyncCertificate(view_number)
             | ConsensusIntentEvent::PollForTransactions(view_number)
             | ConsensusIntentEvent::CancelPollForTransactions(view_number)
-            | ConsensusIntentEvent::PollFutureLeader(view_number, _) => *view_number,
+            | ConsensusIntentEvent::PollFutureLeader(view_number, _)
+            | ConsensusIntentEvent::TransactionsDecided(view_number, _) => *view_number,
             ConsensusIntentEvent::PollForCurrentProposal => 1,
         }
     }
@@ -373,6 +391,62 @@ pub trait TestableChannelImplementation<TYPES: NodeType>: CommunicationChannel<T
     #[allow(clippy::type_complexity)]
     fn generate_network(
     ) -> Box<dyn Fn(Arc<<Self as CommunicationChannel<TYPES>>::NETWORK>) -> Self + 'static>;
+
+    /// Replace the link faults of every node on the same network as this channel with `faults`.
+    /// Returns `false` if the network can't inject link faults.
+    fn set_link_faults(&self, _faults: LinkFaults<TYPES::SignatureKey>) -> bool {
+        false
+    }
+}
+
+/// Faults of the links between the nodes of a test network. Links are directional: a link from
+/// `a` to `b` carries the messages `a` sends to `b`, and may be down while the link from `b` to `a`
+/// is up.
+#[derive(Clone, Debug)]
+pub struct LinkFaults<K: SignatureKey> {
+    /// groups of nodes that can only reach nodes of the same group. Nodes that are in no group
+    /// can only reach each other. No partition if empty.
+    pub partitions: Vec<BTreeSet<K>>,
+    /// links that drop every message, as `(sender, recipient)`
+    pub failed_links: BTreeSet<(K, K)>,
+    /// how long the messages of a link take to arrive, as `(sender, recipient)`. Links that aren't
+    /// in here deliver right away.
+    pub latencies: BTreeMap<(K, K), Duration>,
+}
+
+impl<K: SignatureKey> Default for LinkFaults<K> {
+    fn default() -> Self {
+        Self {
+            partitions: Vec::new(),
+            failed_links: BTreeSet::new(),
+            latencies: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: SignatureKey> LinkFaults<K> {
+    /// How long a message from `sender` takes to reach `recipient`, or `None` if it never does.
+    /// A node can always reach itself.
+    #[must_use]
+    pub fn link_latency(&self, sender: &K, recipient: &K) -> Option<Duration> {
+        if sender == recipient {
+            return Some(Duration::ZERO);
+        }
+        let group = |key: &K| self.partitions.iter().position(|group| group.contains(key));
+        if group(sender) != group(recipient)
+            || self
+                .failed_links
+                .contains(&(sender.clone(), recipient.clone()))
+        {
+            return None;
+        }
+        Some(
+            self.latencies
+                .get(&(sender.clone(), recipient.clone()))
+                .copied()
+                .unwrap_or(Duration::ZERO),
+        )
+    }
 }
 
 /// Changes that can occur in the network
@@ -468,8 +542,7 @@ impl NetworkReliability for SynchronousNetwork {
     }
     fn sample_delay(&self) -> Duration {
         Duration::from_millis(
-            Uniform::new_inclusive(self.delay_low_ms, self.timeout_ms)
-                .sample(&mut rand::thread_rng()),
+            Uniform::new_inclusive(self.delay_low_ms, self.timeout_ms).sample(&mut SimulationRng),
         )
     }
 }
@@ -495,12 +568,12 @@ impl NetworkReliability for AsynchronousNetwork {
     fn sample_keep(&self) -> bool {
         Bernoulli::from_ratio(self.keep_numerator, self.keep_denominator)
             .unwrap()
-            .sample(&mut rand::thread_rng())
+            .sample(&mut SimulationRng)
     }
     fn sample_delay(&self) -> Duration {
         Duration::from_millis(
             Uniform::new_inclusive(self.delay_low_ms, self.delay_high_ms)
-                .sample(&mut rand::thread_rng()),
+                .sample(&mut SimulationRng),
         )
     }
 }
@@ -6,7 +6,7 @@
 use super::{
     block_contents::{BlockHeader, Transaction},
     election::ElectionConfig,
-    network::{CommunicationChannel, TestableNetworkingImplementation},
+    network::{CommunicationChannel, LinkFaults, TestableNetworkingImplementation},
     state::{ConsensusTime, TestableBlock, TestableState},
     storage::{StorageError, StorageState, TestableStorage},
     State,
@@ -174,6 +174,14 @@ pub trait TestableNodeImplementation<TYPES: NodeType>: NodeImplementation<TYPES>
         num_bootstrap: usize,
         da_committee_size: usize,
     ) -> Box<dyn Fn(u64) -> (Self::QuorumNetwork, Self::CommitteeNetwork)>;
+
+    /// Replace the link faults of the quorum and DA networks of the test with `faults`, through
+    /// the channels of any of its nodes. Returns `false` if either network can't inject them.
+    fn set_link_faults(
+        quorum_network: &Self::QuorumNetwork,
+        da_network: &Self::CommitteeNetwork,
+        faults: &LinkFaults<TYPES::SignatureKey>,
+    ) -> bool;
 }
 
 #[async_trait]
@@ -261,6 +269,16 @@ where
             (quorum_chan, committee_chan)
         })
     }
+
+    fn set_link_faults(
+        quorum_network: &Self::QuorumNetwork,
+        da_network: &Self::CommitteeNetwork,
+        faults: &LinkFaults<TYPES::SignatureKey>,
+    ) -> bool {
+        let quorum_set = quorum_network.set_link_faults(faults.clone());
+        let da_set = da_network.set_link_faults(faults.clone());
+        quorum_set && da_set
+    }
 }
 
 /// Trait with all the type definitions that are used in the current hotshot setup.
@@ -163,7 +163,7 @@ where
             block_header,
             block_payload,
             rejected,
-            timestamp: time::OffsetDateTime::now_utc().unix_timestamp_nanos(),
+            timestamp: crate::simulation::timestamp_nanos(),
             proposer_id,
         }
     }
@@ -1,13 +1,44 @@
+use std::time::Duration;
+
 pub const DEFAULT_WEB_SERVER_PORT: u16 = 9000;
 pub const DEFAULT_WEB_SERVER_DA_PORT: u16 = 9001;
 pub const DEFAULT_WEB_SERVER_VIEW_SYNC_PORT: u16 = 9002;
 
-/// How many views to keep in memory
+/// How many views of each kind of data to keep in memory, unless configured otherwise
 pub const MAX_VIEWS: usize = 25;
-/// How many transactions to keep in memory
+/// How many transactions to keep in memory, unless configured otherwise
 pub const MAX_TXNS: usize = 500;
 /// How many transactions to return at once
 pub const TX_BATCH_SIZE: u64 = 1;
+/// How long the server holds a long-poll (`wait/`) request open before answering with nothing
+pub const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+/// How far a signed request's timestamp may be from the server's clock before it is refused
+pub const MAX_REQUEST_AGE: Duration = Duration::from_secs(60);
+/// The window over which each key's requests are counted for rate limiting
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+/// How many POSTs each key may make per `RATE_LIMIT_WINDOW`
+pub const RATE_LIMIT_REQUESTS: u32 = 1000;
+/// How many write-ahead log entries a persistent web server writes between snapshots of its state
+pub const WAL_SNAPSHOT_INTERVAL: u64 = 1000;
+/// How many write-ahead log entries a persistent web server writes between syncs to disk,
+/// unless told otherwise
+pub const WAL_SYNC_INTERVAL: u64 = 32;
+
+pub fn get_version_route() -> String {
+    "api/version".to_string()
+}
+
+pub fn get_push_route() -> String {
+    "api/push".to_string()
+}
+
+/// The long-poll variant of a GET route, which the server holds open until it has something to return
+pub fn get_wait_route(route: &str) -> String {
+    match route.strip_prefix("api/") {
+        Some(rest) => format!("api/wait/{rest}"),
+        None => route.to_string(),
+    }
+}
 
 pub fn get_proposal_route(view_number: u64) -> String {
     format!("api/proposal/{view_number}")
@@ -65,14 +96,40 @@ pub fn get_transactions_route(index: u64) -> String {
     format!("api/transactions/{index}")
 }
 
-pub fn post_transactions_route() -> String {
-    "api/transactions".to_string()
+/// Transactions are posted under their commitment, see [`commitment_param`]
+pub fn post_transactions_route(commitment: &str) -> String {
+    format!("api/transaction/{commitment}")
+}
+
+pub fn get_transaction_status_route(commitment: &str) -> String {
+    format!("api/transactionstatus/{commitment}")
+}
+
+pub fn post_decided_transactions_route(view_number: u64) -> String {
+    format!("api/decidedtransactions/{view_number}")
+}
+
+/// How a transaction commitment appears in routes: lowercase hex
+pub fn commitment_param(commitment: &[u8]) -> String {
+    commitment
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
 pub fn post_staketable_route() -> String {
     "api/staketable".to_string()
 }
 
+pub fn post_secret_proposal_route(view_number: u64, secret: &str) -> String {
+    format!("api/secret/{view_number}/{secret}")
+}
+
+/// Where an instance forwards a secret proposal to the other instances of its cluster
+pub fn replicate_secret_proposal_route(view_number: u64, secret: &str) -> String {
+    format!("api/secret/{view_number}/{secret}/replica")
+}
+
 pub fn post_view_sync_proposal_route(view_number: u64) -> String {
     format!("api/view_sync_proposal/{view_number}")
 }
@@ -1,28 +1,152 @@
 pub mod config;
-
-use crate::config::{MAX_TXNS, MAX_VIEWS, TX_BATCH_SIZE};
-use async_compatibility_layer::channel::OneShotReceiver;
+pub mod metrics;
+pub mod persistence;
+pub mod replication;
+pub mod retention;
+
+use crate::config::{
+    LONG_POLL_TIMEOUT, MAX_REQUEST_AGE, RATE_LIMIT_REQUESTS, RATE_LIMIT_WINDOW, TX_BATCH_SIZE,
+    WAL_SYNC_INTERVAL,
+};
+use crate::metrics::{Activity, WebServerMetrics};
+use crate::persistence::{v0, Persistence, Restored, WalEntry, FORMAT_VERSION};
+use crate::replication::Replicator;
+use crate::retention::{make_room, RetentionOptions, RetentionStats};
+use async_compatibility_layer::{art::async_timeout, channel::OneShotReceiver};
 use async_lock::RwLock;
 use clap::Args;
-use futures::FutureExt;
+use event_listener::Event;
+use futures::{future, future::BoxFuture, FutureExt};
 use tracing::error;
 
-use hotshot_types::traits::signature_key::{EncodedPublicKey, SignatureKey};
+use commit::Committable;
+use hotshot_constants::PROTOCOL_VERSION;
+use hotshot_types::{
+    message::{DataMessage, Message, MessageKind, VersionedMessage},
+    traits::{
+        node_implementation::NodeType,
+        signature_key::{EncodedPublicKey, EncodedSignature, SignatureKey},
+    },
+};
 use rand::{distributions::Alphanumeric, rngs::StdRng, thread_rng, Rng, SeedableRng};
-use std::{collections::HashMap, io, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use surf_disco::Url;
 use tide_disco::{
     api::ApiError,
     error::ServerError,
     method::{ReadState, WriteState},
-    Api, App, StatusCode,
+    Api, App, RequestParams, StatusCode,
 };
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 type State<KEY> = RwLock<WebServerState<KEY>>;
 type Error = ServerError;
 
+/// The body of every POST to the web server: a message signed by a key in the stake table.
+/// The signature covers the route the request is posted to, so it can't be replayed elsewhere.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedRequest {
+    /// the sender's public key
+    pub key: Vec<u8>,
+    /// seconds since the unix epoch when the request was signed
+    pub timestamp: u64,
+    /// the message being posted
+    pub body: Vec<u8>,
+    /// signature by `key` over the route, timestamp and body
+    pub signature: EncodedSignature,
+}
+
+/// seconds since the unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+impl SignedRequest {
+    /// Sign `body` for posting to `route`
+    pub fn new<KEY: SignatureKey>(
+        key: &KEY,
+        private_key: &KEY::PrivateKey,
+        route: &str,
+        body: Vec<u8>,
+    ) -> Self {
+        let timestamp = unix_now();
+        let signature = KEY::sign(private_key, &Self::signed_bytes(route, timestamp, &body));
+        Self {
+            key: key.to_bytes().0,
+            timestamp,
+            body,
+            signature,
+        }
+    }
+
+    /// Returns the sender if the request was signed for `route` recently enough
+    pub fn verify<KEY: SignatureKey>(&self, route: &str) -> Option<KEY> {
+        if unix_now().abs_diff(self.timestamp) > MAX_REQUEST_AGE.as_secs() {
+            return None;
+        }
+        let key = KEY::from_bytes(&EncodedPublicKey(self.key.clone()))?;
+        key.validate(
+            &self.signature,
+            &Self::signed_bytes(route, self.timestamp, &self.body),
+        )
+        .then_some(key)
+    }
+
+    /// The bytes covered by the signature
+    fn signed_bytes(route: &str, timestamp: u64, body: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(route.len() + body.len() + 24);
+        bytes.extend_from_slice(&(route.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(route.as_bytes());
+        bytes.extend_from_slice(&timestamp.to_le_bytes());
+        bytes.extend_from_slice(body);
+        bytes
+    }
+}
+
+/// What the server reports on its `status` route
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebServerStatus {
+    /// the newest view the server holds any data for
+    pub newest_view: u64,
+    /// the view of the most recent proposal
+    pub recent_proposal: u64,
+    /// how many transactions the server has received in total
+    pub transactions_received: u64,
+    /// how many transactions are waiting to be included
+    pub pending_transactions: usize,
+    /// how many decided transactions the server remembers
+    pub decided_transactions: usize,
+    /// how many long-poll requests are being held open
+    pub active_pollers: usize,
+    /// how much of each kind of data the server currently keeps
+    pub retention: Vec<RetentionStats>,
+}
+
+/// Whether a transaction, looked up by its commitment, has made it into a decided block
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// waiting to be included, at this index of the server's transactions
+    Pending { index: u64 },
+    /// included in the block decided in this view
+    Decided { view: u64 },
+    /// never received, or dropped to make room for newer transactions
+    Unknown,
+}
+
 /// State that tracks proposals and votes the server receives
 /// Data is stored as a `Vec<u8>` to not incur overhead from deserializing
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 struct WebServerState<KEY> {
     /// view number -> (secret, proposal)
     proposals: HashMap<u64, (String, Vec<u8>)>,
@@ -65,37 +189,107 @@ struct WebServerState<KEY> {
     // recent_vid_certificate: u64,
     vid_vote_index: HashMap<u64, u64>,
 
-    /// index -> transaction
-    // TODO ED Make indexable by hash of tx
-    transactions: HashMap<u64, Vec<u8>>,
-    txn_lookup: HashMap<Vec<u8>, u64>,
+    /// index -> (commitment, transaction)
+    transactions: HashMap<u64, (String, Vec<u8>)>,
+    /// commitment -> index of the pending transaction
+    txn_lookup: HashMap<String, u64>,
+    /// commitment -> view the transaction was decided in, for the most recently decided
+    /// transactions
+    decided_txns: HashMap<String, u64>,
+    /// commitments in `decided_txns`, oldest first
+    decided_txn_order: VecDeque<String>,
     /// highest transaction index
     num_txns: u64,
+    /// index of the oldest transaction in memory
+    oldest_transaction: u64,
+
+    /// bytes of payload kept for each kind of data
+    proposal_bytes: usize,
+    vid_disperse_bytes: usize,
+    vote_bytes: usize,
+    vid_vote_bytes: usize,
+    view_sync_proposal_bytes: usize,
+    view_sync_vote_bytes: usize,
+    da_certificate_bytes: usize,
+    vid_certificate_bytes: usize,
+    transaction_bytes: usize,
+    /// how much of each kind of data to keep
+    #[serde(skip)]
+    retention: RetentionOptions,
 
     /// shutdown signal
+    #[serde(skip)]
     shutdown: Option<OneShotReceiver<()>>,
-    /// stake table with leader keys
+    /// stake table with leader keys, in leader order. Only these keys may post
+    #[serde(skip)]
     stake_table: Vec<KEY>,
+    /// key -> (start of its current rate limit window, requests made in it)
+    #[serde(skip)]
+    rate_limits: HashMap<KEY, (Instant, u32)>,
+    /// write-ahead log every change is recorded in, if the server is persistent
+    #[serde(skip)]
+    persistence: Option<Persistence>,
+    /// forwards accepted posts to the other instances, if the server is part of a cluster
+    #[serde(skip)]
+    replicator: Option<Replicator>,
+    /// signatures of the posts accepted within `MAX_REQUEST_AGE`, so each is only applied once
+    /// however many times it is delivered
+    #[serde(skip)]
+    seen_requests: HashSet<EncodedSignature>,
+    /// (timestamp, signature) of the posts in `seen_requests`, oldest first
+    #[serde(skip)]
+    seen_request_order: VecDeque<(u64, EncodedSignature)>,
+    /// requests served and long-polls held, for the `metrics` route
+    #[serde(skip)]
+    activity: Arc<Activity>,
+    /// notified whenever something is posted, to wake the held long-polls
+    #[serde(skip)]
+    posted: Arc<Event>,
+    /// computes a posted transaction's commitment, if the server knows the node types
+    #[serde(skip)]
+    transaction_commitment: Option<fn(&[u8]) -> Option<String>>,
     /// prng for generating endpoint
+    #[serde(skip, default = "StdRng::from_entropy")]
     _prng: StdRng,
 }
 
-impl<KEY: SignatureKey + 'static> WebServerState<KEY> {
+impl<KEY: SignatureKey> WebServerState<KEY> {
     fn new() -> Self {
         Self {
             proposals: HashMap::new(),
             da_certificates: HashMap::new(),
             votes: HashMap::new(),
             num_txns: 0,
+            oldest_transaction: 0,
+            proposal_bytes: 0,
+            vid_disperse_bytes: 0,
+            vote_bytes: 0,
+            vid_vote_bytes: 0,
+            view_sync_proposal_bytes: 0,
+            view_sync_vote_bytes: 0,
+            da_certificate_bytes: 0,
+            vid_certificate_bytes: 0,
+            transaction_bytes: 0,
+            retention: RetentionOptions::default(),
             oldest_vote: 0,
             oldest_proposal: 0,
             recent_proposal: 0,
             oldest_certificate: 0,
             shutdown: None,
             stake_table: Vec::new(),
+            rate_limits: HashMap::new(),
+            persistence: None,
+            replicator: None,
+            seen_requests: HashSet::new(),
+            seen_request_order: VecDeque::new(),
             vote_index: HashMap::new(),
             transactions: HashMap::new(),
             txn_lookup: HashMap::new(),
+            decided_txns: HashMap::new(),
+            decided_txn_order: VecDeque::new(),
+            activity: Arc::default(),
+            posted: Arc::default(),
+            transaction_commitment: None,
             _prng: StdRng::from_entropy(),
             view_sync_proposals: HashMap::new(),
             view_sync_votes: HashMap::new(),
@@ -125,6 +319,406 @@ impl<KEY: SignatureKey + 'static> WebServerState<KEY> {
         self.shutdown = shutdown_listener;
         self
     }
+    /// Only accept posts from the keys in `stake_table`, where the leader of view `v`
+    /// is `stake_table[v % stake_table.len()]`
+    pub fn with_stake_table(mut self, stake_table: Vec<KEY>) -> Self {
+        // generate a secret for each leader's first submission endpoint
+        for node_index in 0..stake_table.len() as u64 {
+            let secret = thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(30)
+                .map(char::from)
+                .collect();
+            self.proposals.insert(node_index, (secret, Vec::new()));
+        }
+        self.stake_table = stake_table;
+        self
+    }
+    /// Persist the state in `dir`, first restoring whatever an earlier run left there, syncing
+    /// the log every `sync_every` changes.
+    /// Must be called after `with_stake_table`, since replaying proposals depends on it.
+    ///
+    /// # Errors
+    /// If the persisted state could not be opened or its snapshot is corrupt
+    pub fn with_persistence(mut self, dir: &Path, sync_every: u64) -> io::Result<Self> {
+        let (mut persistence, restored) = Persistence::open(dir, sync_every)?;
+        let replayed = match restored {
+            Restored::Current { snapshot, entries } => {
+                if let Some(snapshot) = snapshot {
+                    let restored: Self = bincode::deserialize(&snapshot)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    self = Self {
+                        shutdown: self.shutdown,
+                        stake_table: self.stake_table,
+                        replicator: self.replicator,
+                        retention: self.retention,
+                        transaction_commitment: self.transaction_commitment,
+                        ..restored
+                    };
+                }
+                let replayed = entries.len();
+                for entry in entries {
+                    // entries are logged before they are applied, so some may have been
+                    // refused at the time
+                    let _ = self.apply(entry);
+                }
+                replayed
+            }
+            Restored::V0 { snapshot, entries } => {
+                if let Some(snapshot) = snapshot {
+                    let restored: v0::State = bincode::deserialize(&snapshot)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    self.migrate_v0_state(restored);
+                }
+                let replayed = entries.len();
+                for entry in entries {
+                    let _ = self.apply_v0(entry);
+                }
+                let migrated = bincode::serialize(&self)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                persistence.snapshot(&migrated)?;
+                info!(
+                    "Migrated web server state in {} to format version {FORMAT_VERSION}",
+                    dir.display()
+                );
+                replayed
+            }
+        };
+        info!(
+            "Restored web server state from {}: {} views of proposals, {} transactions, replayed {replayed} log entries",
+            dir.display(),
+            self.proposals.len(),
+            self.num_txns
+        );
+        self.persistence = Some(persistence);
+        Ok(self)
+    }
+
+    /// Check transactions posted to the server against the commitment they are posted under,
+    /// using `commitment` to compute it from a transaction's bytes. This is also how
+    /// transactions persisted before they were indexed by commitment are migrated, so it must
+    /// be called before `with_persistence`.
+    pub fn with_transaction_commitment(mut self, commitment: fn(&[u8]) -> Option<String>) -> Self {
+        self.transaction_commitment = Some(commitment);
+        self
+    }
+
+    /// The commitment of the transaction `txn`, if the server knows how to compute it and
+    /// `txn` is one
+    fn commitment_of(&self, txn: &[u8]) -> Option<String> {
+        self.transaction_commitment
+            .and_then(|commitment| commitment(txn))
+    }
+
+    /// Take on the state snapshotted before the format was versioned. Pending transactions
+    /// whose commitment can't be computed are dropped.
+    fn migrate_v0_state(&mut self, state: v0::State) {
+        /// total bytes of the payloads in `entries`
+        fn bytes<'a>(entries: impl Iterator<Item = &'a Vec<u8>>) -> usize {
+            entries.map(Vec::len).sum()
+        }
+        self.proposal_bytes = bytes(state.proposals.values().map(|(_, entry)| entry));
+        self.vid_disperse_bytes = bytes(state.vid_disperses.values().map(|(_, entry)| entry));
+        self.da_certificate_bytes = bytes(state.da_certificates.values().map(|(_, entry)| entry));
+        self.vid_certificate_bytes = bytes(state.vid_certificates.values().map(|(_, entry)| entry));
+        self.vote_bytes = bytes(state.votes.values().flatten().map(|(_, entry)| entry));
+        self.vid_vote_bytes = bytes(state.vid_votes.values().flatten().map(|(_, entry)| entry));
+        self.view_sync_vote_bytes = bytes(
+            state
+                .view_sync_votes
+                .values()
+                .flatten()
+                .map(|(_, entry)| entry),
+        );
+        self.view_sync_proposal_bytes = bytes(
+            state
+                .view_sync_proposals
+                .values()
+                .flatten()
+                .map(|(_, entry)| entry),
+        );
+
+        self.proposals = state.proposals;
+        self.view_sync_proposals = state.view_sync_proposals;
+        self.view_sync_proposal_index = state.view_sync_proposal_index;
+        self.da_certificates = state.da_certificates;
+        self.oldest_proposal = state.oldest_proposal;
+        self.recent_proposal = state.recent_proposal;
+        self.oldest_certificate = state.oldest_certificate;
+        self.oldest_view_sync_proposal = state.oldest_view_sync_proposal;
+        self.votes = state.votes;
+        self.view_sync_votes = state.view_sync_votes;
+        self.vote_index = state.vote_index;
+        self.view_sync_vote_index = state.view_sync_vote_index;
+        self.oldest_vote = state.oldest_vote;
+        self.oldest_view_sync_vote = state.oldest_view_sync_vote;
+        self.vid_disperses = state.vid_disperses;
+        self.oldest_vid_disperse = state.oldest_vid_disperse;
+        self.recent_vid_disperse = state.recent_vid_disperse;
+        self.vid_votes = state.vid_votes;
+        self.oldest_vid_vote = state.oldest_vid_vote;
+        self.vid_certificates = state.vid_certificates;
+        self.oldest_vid_certificate = state.oldest_vid_certificate;
+        self.vid_vote_index = state.vid_vote_index;
+
+        self.num_txns = state.num_txns;
+        self.oldest_transaction = state.num_txns;
+        for (index, txn) in state.transactions {
+            let Some(commitment) = self.commitment_of(&txn) else {
+                warn!("Dropping persisted transaction {index} with no commitment");
+                continue;
+            };
+            self.oldest_transaction = self.oldest_transaction.min(index);
+            self.transaction_bytes += txn.len();
+            self.txn_lookup.insert(commitment.clone(), index);
+            self.transactions.insert(index, (commitment, txn));
+        }
+    }
+
+    /// Apply a change logged before the format was versioned
+    fn apply_v0(&mut self, entry: v0::WalEntry<'_>) -> Result<(), Error> {
+        let entry = match entry {
+            v0::WalEntry::Vote(view, vote) => WalEntry::Vote(view, vote),
+            v0::WalEntry::VidVote(view, vote) => WalEntry::VidVote(view, vote),
+            v0::WalEntry::ViewSyncVote(view, vote) => WalEntry::ViewSyncVote(view, vote),
+            v0::WalEntry::Proposal(view, proposal) => WalEntry::Proposal(view, proposal),
+            v0::WalEntry::SecretProposal(view, proposal) => {
+                WalEntry::SecretProposal(view, proposal)
+            }
+            v0::WalEntry::VidDisperse(view, disperse) => WalEntry::VidDisperse(view, disperse),
+            v0::WalEntry::ViewSyncProposal(view, proposal) => {
+                WalEntry::ViewSyncProposal(view, proposal)
+            }
+            v0::WalEntry::DaCertificate(view, cert) => WalEntry::DaCertificate(view, cert),
+            v0::WalEntry::VidCertificate(view, cert) => WalEntry::VidCertificate(view, cert),
+            v0::WalEntry::Transaction(txn) => {
+                let Some(commitment) = self.commitment_of(&txn) else {
+                    warn!("Dropping persisted transaction with no commitment");
+                    return Ok(());
+                };
+                WalEntry::Transaction(Cow::Owned(commitment), txn)
+            }
+            v0::WalEntry::CompletedTransaction(txn) => {
+                // which view it was decided in wasn't recorded, so it is just no longer pending
+                let index = self
+                    .commitment_of(&txn)
+                    .and_then(|commitment| self.txn_lookup.remove(&commitment));
+                if let Some((_, txn)) = index.and_then(|index| self.transactions.remove(&index)) {
+                    self.transaction_bytes -= txn.len();
+                }
+                return Ok(());
+            }
+        };
+        self.apply(entry)
+    }
+
+    /// Keep as much of each kind of data as `retention` allows
+    pub fn with_retention(mut self, retention: RetentionOptions) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Forward every post the server accepts to the other instances of its cluster at `peers`
+    pub fn with_peers(mut self, peers: Vec<Url>) -> Self {
+        if !peers.is_empty() {
+            self.replicator = Some(Replicator::new(peers));
+        }
+        self
+    }
+
+    /// Whether a post with `signature` was already accepted, forgetting posts too old to be
+    /// accepted again
+    fn already_seen(&mut self, signature: &EncodedSignature) -> bool {
+        let now = unix_now();
+        while let Some((timestamp, _)) = self.seen_request_order.front() {
+            if timestamp.saturating_add(MAX_REQUEST_AGE.as_secs()) >= now {
+                break;
+            }
+            if let Some((_, old)) = self.seen_request_order.pop_front() {
+                self.seen_requests.remove(&old);
+            }
+        }
+        self.seen_requests.contains(signature)
+    }
+
+    /// Apply a logged change to the state
+    fn apply(&mut self, entry: WalEntry<'_>) -> Result<(), Error> {
+        match entry {
+            WalEntry::Vote(view, vote) => self.post_vote(view, vote.into_owned()),
+            WalEntry::VidVote(view, vote) => self.post_vid_vote(view, vote.into_owned()),
+            WalEntry::ViewSyncVote(view, vote) => self.post_view_sync_vote(view, vote.into_owned()),
+            WalEntry::Proposal(view, proposal) => self.post_proposal(view, proposal.into_owned()),
+            WalEntry::SecretProposal(view, proposal) => {
+                self.post_secret_proposal(view, proposal.into_owned())
+            }
+            WalEntry::VidDisperse(view, disperse) => {
+                self.post_vid_disperse(view, disperse.into_owned())
+            }
+            WalEntry::ViewSyncProposal(view, proposal) => {
+                self.post_view_sync_proposal(view, proposal.into_owned())
+            }
+            WalEntry::DaCertificate(view, cert) => {
+                self.post_da_certificate(view, cert.into_owned())
+            }
+            WalEntry::VidCertificate(view, cert) => {
+                self.post_vid_certificate(view, cert.into_owned())
+            }
+            WalEntry::Transaction(commitment, txn) => {
+                self.post_transaction(commitment.into_owned(), txn.into_owned())
+            }
+            WalEntry::DecidedTransactions(view, commitments) => {
+                self.post_decided_transactions(view, commitments.into_owned())
+            }
+        }
+    }
+
+    /// Record a change in the write-ahead log before it is applied, snapshotting first if
+    /// the log has grown long enough. Failures are logged rather than refusing the request.
+    ///
+    /// Also wakes the held long-polls: they can only look again once the write lock the
+    /// change is made under is released, by when it has been applied.
+    fn log(&mut self, entry: &WalEntry<'_>) {
+        self.posted.notify(usize::MAX);
+        if self
+            .persistence
+            .as_ref()
+            .is_some_and(Persistence::needs_snapshot)
+        {
+            let snapshot = bincode::serialize(&*self);
+            if let Some(persistence) = self.persistence.as_mut() {
+                if let Err(e) = snapshot
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                    .and_then(|snapshot| persistence.snapshot(&snapshot))
+                {
+                    error!("Failed to snapshot web server state: {e}");
+                }
+            }
+        }
+        if let Some(persistence) = self.persistence.as_mut() {
+            if let Err(e) = persistence.append(entry) {
+                error!("Failed to write web server log entry: {e}");
+            }
+        }
+    }
+
+    /// Count a request against `sender`'s rate limit, refusing it if the limit is reached
+    fn check_rate_limit(&mut self, sender: &KEY) -> Result<(), Error> {
+        let now = Instant::now();
+        let (window_start, requests) = self.rate_limits.entry(sender.clone()).or_insert((now, 0));
+        if now.duration_since(*window_start) >= RATE_LIMIT_WINDOW {
+            *window_start = now;
+            *requests = 0;
+        }
+        if *requests >= RATE_LIMIT_REQUESTS {
+            return Err(ServerError {
+                status: StatusCode::TooManyRequests,
+                message: format!("Rate limit of {RATE_LIMIT_REQUESTS} requests reached"),
+            });
+        }
+        *requests += 1;
+        Ok(())
+    }
+
+    /// What is kept of each kind of data, against the retention limits
+    fn data_stats(&self) -> Vec<RetentionStats> {
+        /// (views kept, newest view kept, items kept) of data stored one item per view
+        fn single(data: &HashMap<u64, (String, Vec<u8>)>) -> (usize, u64, usize) {
+            let items = data.values().filter(|(_, item)| !item.is_empty()).count();
+            (
+                data.len(),
+                data.keys().copied().max().unwrap_or_default(),
+                items,
+            )
+        }
+        /// (views kept, newest view kept, items kept) of data stored as many items per view
+        fn many(data: &HashMap<u64, Vec<(u64, Vec<u8>)>>) -> (usize, u64, usize) {
+            let items = data.values().map(Vec::len).sum();
+            (
+                data.len(),
+                data.keys().copied().max().unwrap_or_default(),
+                items,
+            )
+        }
+        let stats = |data: &str, limit, (retained, newest, items), bytes, oldest| RetentionStats {
+            data: data.to_string(),
+            limit,
+            retained,
+            items,
+            bytes,
+            oldest,
+            newest,
+        };
+        let retention = &self.retention;
+        vec![
+            stats(
+                "proposals",
+                retention.proposals,
+                single(&self.proposals),
+                self.proposal_bytes,
+                self.oldest_proposal,
+            ),
+            stats(
+                "vid_disperses",
+                retention.vid_disperses,
+                single(&self.vid_disperses),
+                self.vid_disperse_bytes,
+                self.oldest_vid_disperse,
+            ),
+            stats(
+                "votes",
+                retention.votes,
+                many(&self.votes),
+                self.vote_bytes,
+                self.oldest_vote,
+            ),
+            stats(
+                "vid_votes",
+                retention.vid_votes,
+                many(&self.vid_votes),
+                self.vid_vote_bytes,
+                self.oldest_vid_vote,
+            ),
+            stats(
+                "view_sync_proposals",
+                retention.view_sync_proposals,
+                many(&self.view_sync_proposals),
+                self.view_sync_proposal_bytes,
+                self.oldest_view_sync_proposal,
+            ),
+            stats(
+                "view_sync_votes",
+                retention.view_sync_votes,
+                many(&self.view_sync_votes),
+                self.view_sync_vote_bytes,
+                self.oldest_view_sync_vote,
+            ),
+            stats(
+                "da_certificates",
+                retention.da_certificates,
+                single(&self.da_certificates),
+                self.da_certificate_bytes,
+                self.oldest_certificate,
+            ),
+            stats(
+                "vid_certificates",
+                retention.vid_certificates,
+                single(&self.vid_certificates),
+                self.vid_certificate_bytes,
+                self.oldest_vid_certificate,
+            ),
+            stats(
+                "transactions",
+                retention.transactions,
+                (
+                    self.transactions.len(),
+                    self.transactions.keys().copied().max().unwrap_or_default(),
+                    self.transactions.len(),
+                ),
+                self.transaction_bytes,
+                self.oldest_transaction,
+            ),
+        ]
+    }
 }
 
 /// Trait defining methods needed for the `WebServerState`
@@ -155,9 +749,22 @@ pub trait WebServerDataSource<KEY> {
         -> Result<(), Error>;
 
     fn post_da_certificate(&mut self, view_number: u64, cert: Vec<u8>) -> Result<(), Error>;
-    fn post_transaction(&mut self, txn: Vec<u8>) -> Result<(), Error>;
+    fn post_transaction(&mut self, commitment: String, txn: Vec<u8>) -> Result<(), Error>;
+    fn post_decided_transactions(
+        &mut self,
+        view_number: u64,
+        commitments: Vec<String>,
+    ) -> Result<(), Error>;
+    fn get_transaction_status(&self, commitment: &str) -> Result<TransactionStatus, Error>;
+    fn check_transaction(&self, commitment: &str, txn: &[u8]) -> Result<(), Error>;
     fn post_staketable(&mut self, key: Vec<u8>) -> Result<(), Error>;
-    fn post_completed_transaction(&mut self, block: Vec<u8>) -> Result<(), Error>;
+    fn admit(&mut self, sender: &KEY, request: &SignedRequest) -> Result<bool, Error>;
+    fn replicate(&mut self, route: &str, request: &SignedRequest);
+    fn get_status(&self) -> Result<WebServerStatus, Error>;
+    fn get_metrics(&self) -> Result<WebServerMetrics, Error>;
+    fn activity(&self) -> &Arc<Activity>;
+    fn posted(&self) -> &Arc<Event>;
+    fn check_leader(&self, key: &KEY, view_number: u64) -> Result<(), Error>;
     fn post_secret_proposal(&mut self, _view_number: u64, _proposal: Vec<u8>) -> Result<(), Error>;
     fn proposal(&self, view_number: u64) -> Option<(String, Vec<u8>)>;
 
@@ -296,11 +903,7 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
     fn get_transactions(&self, index: u64) -> Result<Option<(u64, Vec<Vec<u8>>)>, Error> {
         let mut txns_to_return = vec![];
 
-        let lowest_in_memory_txs = if self.num_txns < MAX_TXNS.try_into().unwrap() {
-            0
-        } else {
-            self.num_txns as usize - MAX_TXNS
-        };
+        let lowest_in_memory_txs = self.oldest_transaction as usize;
 
         let starting_index = if (index as usize) < lowest_in_memory_txs {
             lowest_in_memory_txs
@@ -309,7 +912,7 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         };
 
         for idx in starting_index..=self.num_txns.try_into().unwrap() {
-            if let Some(txn) = self.transactions.get(&(idx as u64)) {
+            if let Some((_, txn)) = self.transactions.get(&(idx as u64)) {
                 txns_to_return.push(txn.clone())
             }
             if txns_to_return.len() >= TX_BATCH_SIZE as usize {
@@ -372,13 +975,20 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
 
     /// Stores a received vote in the `WebServerState`
     fn post_vote(&mut self, view_number: u64, vote: Vec<u8>) -> Result<(), Error> {
-        // Only keep vote history for MAX_VIEWS number of views
-        if self.votes.len() >= MAX_VIEWS {
-            self.votes.remove(&self.oldest_vote);
-            while !self.votes.contains_key(&self.oldest_vote) {
-                self.oldest_vote += 1;
-            }
+        self.log(&WalEntry::Vote(view_number, Cow::Borrowed(&vote)));
+        // Only keep as much history as the retention allows
+        for (view, _) in make_room(
+            &mut self.votes,
+            &mut self.oldest_vote,
+            &mut self.vote_bytes,
+            self.retention.votes,
+            view_number,
+            vote.len(),
+            |entries| entries.iter().map(|(_, entry)| entry.len()).sum(),
+        ) {
+            self.vote_index.remove(&view);
         }
+        self.vote_bytes += vote.len();
         let next_index = self.vote_index.entry(view_number).or_insert(0);
         self.votes
             .entry(view_number)
@@ -392,13 +1002,20 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
 
     /// Stores a received VID vote in the `WebServerState`
     fn post_vid_vote(&mut self, view_number: u64, vote: Vec<u8>) -> Result<(), Error> {
-        // Only keep vote history for MAX_VIEWS number of views
-        if self.vid_votes.len() >= MAX_VIEWS {
-            self.vid_votes.remove(&self.oldest_vote);
-            while !self.vid_votes.contains_key(&self.oldest_vid_vote) {
-                self.oldest_vid_vote += 1;
-            }
+        self.log(&WalEntry::VidVote(view_number, Cow::Borrowed(&vote)));
+        // Only keep as much history as the retention allows
+        for (view, _) in make_room(
+            &mut self.vid_votes,
+            &mut self.oldest_vid_vote,
+            &mut self.vid_vote_bytes,
+            self.retention.vid_votes,
+            view_number,
+            vote.len(),
+            |entries| entries.iter().map(|(_, entry)| entry.len()).sum(),
+        ) {
+            self.vid_vote_index.remove(&view);
         }
+        self.vid_vote_bytes += vote.len();
         let next_index = self.vid_vote_index.entry(view_number).or_insert(0);
         self.vid_votes
             .entry(view_number)
@@ -411,16 +1028,20 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
     }
 
     fn post_view_sync_vote(&mut self, view_number: u64, vote: Vec<u8>) -> Result<(), Error> {
-        // Only keep vote history for MAX_VIEWS number of views
-        if self.view_sync_votes.len() >= MAX_VIEWS {
-            self.view_sync_votes.remove(&self.oldest_view_sync_vote);
-            while !self
-                .view_sync_votes
-                .contains_key(&self.oldest_view_sync_vote)
-            {
-                self.oldest_view_sync_vote += 1;
-            }
+        self.log(&WalEntry::ViewSyncVote(view_number, Cow::Borrowed(&vote)));
+        // Only keep as much history as the retention allows
+        for (view, _) in make_room(
+            &mut self.view_sync_votes,
+            &mut self.oldest_view_sync_vote,
+            &mut self.view_sync_vote_bytes,
+            self.retention.view_sync_votes,
+            view_number,
+            vote.len(),
+            |entries| entries.iter().map(|(_, entry)| entry.len()).sum(),
+        ) {
+            self.view_sync_vote_index.remove(&view);
         }
+        self.view_sync_vote_bytes += vote.len();
         let next_index = self.view_sync_vote_index.entry(view_number).or_insert(0);
         self.view_sync_votes
             .entry(view_number)
@@ -433,19 +1054,24 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
     }
     /// Stores a received proposal in the `WebServerState`
     fn post_proposal(&mut self, view_number: u64, mut proposal: Vec<u8>) -> Result<(), Error> {
+        self.log(&WalEntry::Proposal(view_number, Cow::Borrowed(&proposal)));
         error!("Received proposal for view {}", view_number);
 
         if view_number > self.recent_proposal {
             self.recent_proposal = view_number;
         }
 
-        // Only keep proposal history for MAX_VIEWS number of view
-        if self.proposals.len() >= MAX_VIEWS {
-            self.proposals.remove(&self.oldest_proposal);
-            while !self.proposals.contains_key(&self.oldest_proposal) {
-                self.oldest_proposal += 1;
-            }
-        }
+        // Only keep as much history as the retention allows
+        make_room(
+            &mut self.proposals,
+            &mut self.oldest_proposal,
+            &mut self.proposal_bytes,
+            self.retention.proposals,
+            view_number,
+            proposal.len(),
+            |(_, entry)| entry.len(),
+        );
+        self.proposal_bytes += proposal.len();
         self.proposals
             .entry(view_number)
             .and_modify(|(_, empty_proposal)| empty_proposal.append(&mut proposal))
@@ -454,18 +1080,26 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
     }
 
     fn post_vid_disperse(&mut self, view_number: u64, mut disperse: Vec<u8>) -> Result<(), Error> {
+        self.log(&WalEntry::VidDisperse(
+            view_number,
+            Cow::Borrowed(&disperse),
+        ));
         error!("Received VID disperse for view {}", view_number);
         if view_number > self.recent_vid_disperse {
             self.recent_vid_disperse = view_number;
         }
 
-        // Only keep proposal history for MAX_VIEWS number of view
-        if self.vid_disperses.len() >= MAX_VIEWS {
-            self.vid_disperses.remove(&self.oldest_vid_disperse);
-            while !self.vid_disperses.contains_key(&self.oldest_vid_disperse) {
-                self.oldest_vid_disperse += 1;
-            }
-        }
+        // Only keep as much history as the retention allows
+        make_room(
+            &mut self.vid_disperses,
+            &mut self.oldest_vid_disperse,
+            &mut self.vid_disperse_bytes,
+            self.retention.vid_disperses,
+            view_number,
+            disperse.len(),
+            |(_, entry)| entry.len(),
+        );
+        self.vid_disperse_bytes += disperse.len();
         self.vid_disperses
             .entry(view_number)
             .and_modify(|(_, empty_proposal)| empty_proposal.append(&mut disperse))
@@ -478,17 +1112,23 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         view_number: u64,
         proposal: Vec<u8>,
     ) -> Result<(), Error> {
-        // Only keep proposal history for MAX_VIEWS number of view
-        if self.view_sync_proposals.len() >= MAX_VIEWS {
-            self.view_sync_proposals
-                .remove(&self.oldest_view_sync_proposal);
-            while !self
-                .view_sync_proposals
-                .contains_key(&self.oldest_view_sync_proposal)
-            {
-                self.oldest_view_sync_proposal += 1;
-            }
+        self.log(&WalEntry::ViewSyncProposal(
+            view_number,
+            Cow::Borrowed(&proposal),
+        ));
+        // Only keep as much history as the retention allows
+        for (view, _) in make_room(
+            &mut self.view_sync_proposals,
+            &mut self.oldest_view_sync_proposal,
+            &mut self.view_sync_proposal_bytes,
+            self.retention.view_sync_proposals,
+            view_number,
+            proposal.len(),
+            |entries| entries.iter().map(|(_, entry)| entry.len()).sum(),
+        ) {
+            self.view_sync_proposal_index.remove(&view);
         }
+        self.view_sync_proposal_bytes += proposal.len();
         let next_index = self
             .view_sync_proposal_index
             .entry(view_number)
@@ -505,15 +1145,20 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
 
     /// Stores a received DA certificate in the `WebServerState`
     fn post_da_certificate(&mut self, view_number: u64, mut cert: Vec<u8>) -> Result<(), Error> {
+        self.log(&WalEntry::DaCertificate(view_number, Cow::Borrowed(&cert)));
         debug!("Received DA Certificate for view {}", view_number);
 
-        // Only keep proposal history for MAX_VIEWS number of view
-        if self.da_certificates.len() >= MAX_VIEWS {
-            self.da_certificates.remove(&self.oldest_certificate);
-            while !self.da_certificates.contains_key(&self.oldest_certificate) {
-                self.oldest_certificate += 1;
-            }
-        }
+        // Only keep as much history as the retention allows
+        make_room(
+            &mut self.da_certificates,
+            &mut self.oldest_certificate,
+            &mut self.da_certificate_bytes,
+            self.retention.da_certificates,
+            view_number,
+            cert.len(),
+            |(_, entry)| entry.len(),
+        );
+        self.da_certificate_bytes += cert.len();
         self.da_certificates
             .entry(view_number)
             .and_modify(|(_, empty_cert)| empty_cert.append(&mut cert))
@@ -526,18 +1171,23 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         view_number: u64,
         mut certificate: Vec<u8>,
     ) -> Result<(), Error> {
+        self.log(&WalEntry::VidCertificate(
+            view_number,
+            Cow::Borrowed(&certificate),
+        ));
         error!("Received VID Certificate for view {}", view_number);
 
-        // Only keep proposal history for MAX_VIEWS number of view
-        if self.vid_certificates.len() >= MAX_VIEWS {
-            self.vid_certificates.remove(&self.oldest_vid_certificate);
-            while !self
-                .vid_certificates
-                .contains_key(&self.oldest_vid_certificate)
-            {
-                self.oldest_vid_certificate += 1;
-            }
-        }
+        // Only keep as much history as the retention allows
+        make_room(
+            &mut self.vid_certificates,
+            &mut self.oldest_vid_certificate,
+            &mut self.vid_certificate_bytes,
+            self.retention.vid_certificates,
+            view_number,
+            certificate.len(),
+            |(_, entry)| entry.len(),
+        );
+        self.vid_certificate_bytes += certificate.len();
         self.vid_certificates
             .entry(view_number)
             .and_modify(|(_, empty_cert)| empty_cert.append(&mut certificate))
@@ -545,16 +1195,32 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         Ok(())
     }
 
-    /// Stores a received group of transactions in the `WebServerState`
-    fn post_transaction(&mut self, txn: Vec<u8>) -> Result<(), Error> {
-        if self.transactions.len() >= MAX_TXNS {
-            let old_txn = self.transactions.remove(&(self.num_txns - MAX_TXNS as u64));
-            if let Some(old_txn) = old_txn {
-                self.txn_lookup.remove(&old_txn);
-            }
+    /// Stores a received transaction in the `WebServerState` under its commitment, unless it
+    /// is already pending or decided
+    fn post_transaction(&mut self, commitment: String, txn: Vec<u8>) -> Result<(), Error> {
+        if self.txn_lookup.contains_key(&commitment) || self.decided_txns.contains_key(&commitment)
+        {
+            debug!("Ignoring transaction {commitment} the server already has");
+            return Ok(());
+        }
+        self.log(&WalEntry::Transaction(
+            Cow::Borrowed(&commitment),
+            Cow::Borrowed(&txn),
+        ));
+        for (_, (old_commitment, _)) in make_room(
+            &mut self.transactions,
+            &mut self.oldest_transaction,
+            &mut self.transaction_bytes,
+            self.retention.transactions,
+            self.num_txns,
+            txn.len(),
+            |(_, txn)| txn.len(),
+        ) {
+            self.txn_lookup.remove(&old_commitment);
         }
-        self.txn_lookup.insert(txn.clone(), self.num_txns);
-        self.transactions.insert(self.num_txns, txn);
+        self.transaction_bytes += txn.len();
+        self.txn_lookup.insert(commitment.clone(), self.num_txns);
+        self.transactions.insert(self.num_txns, (commitment, txn));
         self.num_txns += 1;
 
         debug!(
@@ -565,40 +1231,171 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         Ok(())
     }
 
+    /// The stake table is fixed when the server starts, so this only confirms `key` is in it.
+    /// That changes nothing, so unlike every other post it needn't be signed.
     fn post_staketable(&mut self, key: Vec<u8>) -> Result<(), Error> {
-        // KALEY TODO: need security checks here
-        let new_key = KEY::from_bytes(&(EncodedPublicKey(key)));
-        if let Some(new_key) = new_key {
-            let node_index = self.stake_table.len() as u64;
-            //generate secret for leader's first submission endpoint when key is added
-            let secret = thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(30)
-                .map(char::from)
-                .collect();
-            self.proposals.insert(node_index, (secret, Vec::new()));
-            self.stake_table.push(new_key);
-            Ok(())
-        } else {
-            Err(ServerError {
+        match KEY::from_bytes(&(EncodedPublicKey(key))) {
+            Some(key) if self.stake_table.contains(&key) => Ok(()),
+            Some(_) => Err(ServerError {
+                status: StatusCode::Forbidden,
+                message: "Key is not in the stake table".to_string(),
+            }),
+            None => Err(ServerError {
                 status: StatusCode::BadRequest,
                 message: "Only signature keys can be added to stake table".to_string(),
-            })
+            }),
+        }
+    }
+
+    /// Checks the sender of a verified request is a stake table member within their rate limit.
+    /// Returns `false` for a request that was already accepted, e.g. when it is delivered again
+    /// by another instance of the cluster.
+    fn admit(&mut self, sender: &KEY, request: &SignedRequest) -> Result<bool, Error> {
+        if !self.stake_table.contains(sender) {
+            return Err(ServerError {
+                status: StatusCode::Forbidden,
+                message: "Sender is not in the stake table".to_string(),
+            });
+        }
+        if self.already_seen(&request.signature) {
+            return Ok(false);
+        }
+        self.check_rate_limit(sender)?;
+        self.seen_requests.insert(request.signature.clone());
+        self.seen_request_order
+            .push_back((request.timestamp, request.signature.clone()));
+        Ok(true)
+    }
+
+    /// Reports the views, transactions and pollers the server currently has, and how much of
+    /// each kind of data it keeps against the retention limits
+    fn get_status(&self) -> Result<WebServerStatus, Error> {
+        let retention = self.data_stats();
+        Ok(WebServerStatus {
+            newest_view: retention
+                .iter()
+                .filter(|stats| stats.data != "transactions")
+                .map(|stats| stats.newest)
+                .max()
+                .unwrap_or_default(),
+            recent_proposal: self.recent_proposal,
+            transactions_received: self.num_txns,
+            pending_transactions: self.transactions.len(),
+            decided_transactions: self.decided_txns.len(),
+            active_pollers: self.activity.pollers().values().sum(),
+            retention,
+        })
+    }
+
+    /// Reports requests served per route, what is kept of each kind of data and the long-polls
+    /// held open per view
+    fn get_metrics(&self) -> Result<WebServerMetrics, Error> {
+        Ok(WebServerMetrics {
+            requests: self.activity.requests(),
+            pollers: self.activity.pollers(),
+            data: self.data_stats(),
+        })
+    }
+
+    fn activity(&self) -> &Arc<Activity> {
+        &self.activity
+    }
+
+    fn posted(&self) -> &Arc<Event> {
+        &self.posted
+    }
+
+    /// Forwards an accepted request to the other instances of the cluster, if there are any
+    fn replicate(&mut self, route: &str, request: &SignedRequest) {
+        if let Some(replicator) = &mut self.replicator {
+            replicator.replicate(route, request);
         }
     }
 
-    fn post_completed_transaction(&mut self, txn: Vec<u8>) -> Result<(), Error> {
-        if let Some(idx) = self.txn_lookup.remove(&txn) {
-            self.transactions.remove(&idx);
+    /// Refuses `key` unless it is the leader for `view_number`
+    fn check_leader(&self, key: &KEY, view_number: u64) -> Result<(), Error> {
+        let leader = match self.stake_table.len() as u64 {
+            0 => None,
+            len => self.stake_table.get((view_number % len) as usize),
+        };
+        if leader == Some(key) {
             Ok(())
         } else {
             Err(ServerError {
-                status: StatusCode::BadRequest,
-                message: "Transaction Not Found".to_string(),
+                status: StatusCode::Forbidden,
+                message: format!("Sender is not the leader for view {view_number}"),
             })
         }
     }
 
+    /// Drops the transactions decided in `view_number` from the pending transactions and
+    /// remembers them as decided, for as many transactions as are retained
+    fn post_decided_transactions(
+        &mut self,
+        view_number: u64,
+        mut commitments: Vec<String>,
+    ) -> Result<(), Error> {
+        // every node that decides the block reports it, so most reports are repeats
+        commitments.retain(|commitment| !self.decided_txns.contains_key(commitment));
+        if commitments.is_empty() {
+            return Ok(());
+        }
+        self.log(&WalEntry::DecidedTransactions(
+            view_number,
+            Cow::Borrowed(&commitments),
+        ));
+        for commitment in commitments {
+            if let Some(idx) = self.txn_lookup.remove(&commitment) {
+                if let Some((_, txn)) = self.transactions.remove(&idx) {
+                    self.transaction_bytes -= txn.len();
+                }
+            }
+            if self
+                .decided_txns
+                .insert(commitment.clone(), view_number)
+                .is_none()
+            {
+                self.decided_txn_order.push_back(commitment);
+            }
+        }
+        while self.decided_txn_order.len() > self.retention.transactions.count {
+            if let Some(old) = self.decided_txn_order.pop_front() {
+                self.decided_txns.remove(&old);
+            }
+        }
+        Ok(())
+    }
+
+    /// Return whether the transaction with `commitment` is pending, decided, or unknown
+    fn get_transaction_status(&self, commitment: &str) -> Result<TransactionStatus, Error> {
+        Ok(if let Some(&index) = self.txn_lookup.get(commitment) {
+            TransactionStatus::Pending { index }
+        } else if let Some(&view) = self.decided_txns.get(commitment) {
+            TransactionStatus::Decided { view }
+        } else {
+            TransactionStatus::Unknown
+        })
+    }
+
+    /// Checks `txn` is a transaction whose commitment is `commitment`, if the server knows how
+    /// to compute it, so nobody can post a transaction under another's commitment
+    fn check_transaction(&self, commitment: &str, txn: &[u8]) -> Result<(), Error> {
+        if self.transaction_commitment.is_none() {
+            return Ok(());
+        }
+        match self.commitment_of(txn) {
+            Some(computed) if computed == commitment => Ok(()),
+            Some(_) => Err(ServerError {
+                status: StatusCode::BadRequest,
+                message: "Transaction does not match its commitment".to_string(),
+            }),
+            None => Err(ServerError {
+                status: StatusCode::BadRequest,
+                message: "Expected a transaction".to_string(),
+            }),
+        }
+    }
+
     //KALEY TODO: this will be merged with post_proposal once it is fully working,
     //but keeping it separate to not break things in the meantime
     fn post_secret_proposal(
@@ -606,18 +1403,29 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
         view_number: u64,
         mut proposal: Vec<u8>,
     ) -> Result<(), Error> {
+        self.log(&WalEntry::SecretProposal(
+            view_number,
+            Cow::Borrowed(&proposal),
+        ));
         debug!("Received proposal for view {}", view_number);
 
-        // Only keep proposal history for MAX_VIEWS number of views
-        if self.proposals.len() >= MAX_VIEWS {
-            self.proposals.remove(&self.oldest_proposal);
-            while !self.proposals.contains_key(&self.oldest_proposal) {
-                self.oldest_proposal += 1;
-            }
-        }
+        // Only keep as much history as the retention allows
+        make_room(
+            &mut self.proposals,
+            &mut self.oldest_proposal,
+            &mut self.proposal_bytes,
+            self.retention.proposals,
+            view_number,
+            proposal.len(),
+            |(_, entry)| entry.len(),
+        );
+        self.proposal_bytes += proposal.len();
+        // an instance that didn't hand out the secret may not have an endpoint for the view
         self.proposals
             .entry(view_number)
-            .and_modify(|(_, empty_proposal)| empty_proposal.append(&mut proposal));
+            .or_default()
+            .1
+            .append(&mut proposal);
 
         //generate new secret for the next time this node is leader
         let secret = thread_rng()
@@ -632,13 +1440,255 @@ impl<KEY: SignatureKey> WebServerDataSource<KEY> for WebServerState<KEY> {
     }
 }
 
-#[derive(Args, Default)]
+/// Refuse message bodies that were not serialized under a protocol version compatible with ours
+fn check_version(body: &[u8]) -> Result<(), Error> {
+    match VersionedMessage::from_bytes(body) {
+        Ok(message) if message.is_compatible() => Ok(()),
+        Ok(message) => Err(ServerError {
+            status: StatusCode::BadRequest,
+            message: format!(
+                "Incompatible protocol version {}, server speaks {}",
+                message.version, PROTOCOL_VERSION
+            ),
+        }),
+        Err(_) => Err(ServerError {
+            status: StatusCode::BadRequest,
+            message: "Message is not a versioned message".to_string(),
+        }),
+    }
+}
+
+/// Checks a POST body is a request for `route` with a valid, recent signature over a message
+/// of a compatible protocol version, returning the sender and the request. This needs nothing
+/// from the server's state, so is done before its lock is taken.
+fn verify_request<KEY: SignatureKey>(
+    route: &str,
+    body: &[u8],
+) -> Result<(KEY, SignedRequest), Error> {
+    let request: SignedRequest = bincode::deserialize(body).map_err(|_| ServerError {
+        status: StatusCode::BadRequest,
+        message: "Request is not signed".to_string(),
+    })?;
+    let Some(sender) = request.verify::<KEY>(route) else {
+        return Err(ServerError {
+            status: StatusCode::Unauthorized,
+            message: "Invalid or expired request signature".to_string(),
+        });
+    };
+    check_version(&request.body)?;
+    Ok((sender, request))
+}
+
+/// Serve a signed POST to `route`: the signature is checked before the state lock is taken,
+/// then `post` runs under it if the sender is admitted. A request that was already accepted is
+/// ignored.
+async fn post_signed<State, KEY, F>(
+    state: &State,
+    route: &str,
+    body: &[u8],
+    post: F,
+) -> Result<(), Error>
+where
+    State: ReadState + WriteState + Send + Sync,
+    <State as ReadState>::State: WebServerDataSource<KEY>,
+    KEY: SignatureKey,
+    F: FnOnce(&mut <State as ReadState>::State, KEY, SignedRequest) -> Result<(), Error> + Send,
+{
+    let (sender, request) = verify_request::<KEY>(route, body)?;
+    state
+        .write(|state| {
+            future::ready(match state.admit(&sender, &request) {
+                Ok(true) => post(state, sender, request),
+                Ok(false) => Ok(()),
+                Err(e) => Err(e),
+            })
+            .boxed()
+        })
+        .await
+}
+
+/// Hold a long-poll request open until `get` has something to return, answering with nothing
+/// once `LONG_POLL_TIMEOUT` passes. `get` is checked again each time something is posted; the
+/// state lock is only held while checking, never while waiting. The request counts as a poller
+/// for `view`, if it is for one, while it is held.
+async fn long_poll<State, KEY, T>(
+    state: &State,
+    view: Option<u64>,
+    get: impl Fn(&<State as ReadState>::State) -> Result<Option<T>, Error> + Send + Sync,
+) -> Result<Option<T>, Error>
+where
+    State: ReadState + Send + Sync,
+    <State as ReadState>::State: WebServerDataSource<KEY>,
+    T: Send,
+{
+    let (activity, posted) = state
+        .read(|state| {
+            future::ready((Arc::clone(state.activity()), Arc::clone(state.posted()))).boxed()
+        })
+        .await;
+    let _poller = view.map(|view| Activity::start_poll(&activity, view));
+    let deadline = Instant::now() + LONG_POLL_TIMEOUT;
+    loop {
+        // listen before checking, so a post landing in between still wakes us
+        let listener = posted.listen();
+        // the getters report missing data as an error, which just means nothing has arrived yet
+        if let Ok(Some(found)) = state.read(|state| future::ready(get(state)).boxed()).await {
+            return Ok(Some(found));
+        }
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            return Ok(None);
+        };
+        if async_timeout(remaining, listener).await.is_err() {
+            return Ok(None);
+        }
+    }
+}
+
+#[derive(Args, Clone, Debug, Default)]
 pub struct Options {
     #[arg(long = "web-server-api-path", env = "WEB_SERVER_API_PATH")]
     pub api_path: Option<PathBuf>,
+    /// Directory to persist the server's state in, so a restarted server picks up where it left off.
+    /// State is only kept in memory if this is not set.
+    #[arg(
+        long = "web-server-persistence-path",
+        env = "WEB_SERVER_PERSISTENCE_PATH"
+    )]
+    pub persistence_path: Option<PathBuf>,
+    /// How many changes a persistent server logs between syncs to disk, 32 by default. Set to 1
+    /// to sync every change: otherwise those since the last sync are lost if the machine goes down.
+    #[arg(
+        long = "web-server-sync-every",
+        env = "WEB_SERVER_SYNC_EVERY",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub sync_every: Option<u64>,
+    /// The other instances of this server's cluster, as a comma separated list of URLs.
+    /// Every post this instance accepts is replicated to them, and theirs to it.
+    #[arg(
+        long = "web-server-peers",
+        env = "WEB_SERVER_PEERS",
+        value_delimiter = ','
+    )]
+    pub peers: Vec<Url>,
+    #[command(flatten)]
+    pub retention: RetentionOptions,
 }
 
 /// Sets up all API routes
+/// Route registration that counts every request to a route, under its name, in the server's
+/// [`Activity`] before handing it to the route's handler
+trait CountedRoutes<State: ReadState, KEY>: Sized {
+    fn counted_get<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(RequestParams, &<State as ReadState>::State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize;
+
+    fn counted_post<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(RequestParams, &<State as ReadState>::State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize;
+
+    fn counted_at<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static + Send + Sync + Fn(RequestParams, &State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize;
+
+    fn counted_metrics<F, T>(
+        &mut self,
+        name: &'static str,
+        handler: F,
+    ) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(
+                RequestParams,
+                &<State as ReadState>::State,
+            ) -> BoxFuture<'_, Result<Cow<'_, T>, Error>>,
+        T: 'static + Clone + tide_disco::metrics::Metrics;
+}
+
+impl<State, KEY> CountedRoutes<State, KEY> for Api<State, Error>
+where
+    State: 'static + Send + Sync + ReadState + WriteState,
+    <State as ReadState>::State: Send + Sync + WebServerDataSource<KEY>,
+{
+    fn counted_get<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(RequestParams, &<State as ReadState>::State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize,
+    {
+        self.get(name, move |req, state| {
+            state.activity().record_request(name);
+            handler(req, state)
+        })
+    }
+
+    fn counted_post<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(RequestParams, &<State as ReadState>::State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize,
+    {
+        self.post(name, move |req, state| {
+            state.activity().record_request(name);
+            handler(req, state)
+        })
+    }
+
+    fn counted_at<F, T>(&mut self, name: &'static str, handler: F) -> Result<&mut Self, ApiError>
+    where
+        F: 'static + Send + Sync + Fn(RequestParams, &State) -> BoxFuture<'_, Result<T, Error>>,
+        T: Serialize,
+    {
+        self.at(name, move |req, state| {
+            // the handler's future does nothing until awaited, so this is counted first
+            let handled = handler(req, state);
+            async move {
+                state
+                    .read(|state| future::ready(state.activity().record_request(name)).boxed())
+                    .await;
+                handled.await
+            }
+            .boxed()
+        })
+    }
+
+    fn counted_metrics<F, T>(
+        &mut self,
+        name: &'static str,
+        handler: F,
+    ) -> Result<&mut Self, ApiError>
+    where
+        F: 'static
+            + Send
+            + Sync
+            + Fn(
+                RequestParams,
+                &<State as ReadState>::State,
+            ) -> BoxFuture<'_, Result<Cow<'_, T>, Error>>,
+        T: 'static + Clone + tide_disco::metrics::Metrics,
+    {
+        self.metrics(name, move |req, state| {
+            state.activity().record_request(name);
+            handler(req, state)
+        })
+    }
+}
+
 fn define_api<State, KEY>(options: &Options) -> Result<Api<State, Error>, ApiError>
 where
     State: 'static + Send + Sync + ReadState + WriteState,
@@ -656,24 +1706,127 @@ where
             Api::<State, Error>::new(toml)?
         }
     };
-    api.get("getproposal", |req, state| {
+    api.counted_get("version", |_req, _state| {
+        async move { Ok(PROTOCOL_VERSION) }.boxed()
+    })?
+    .counted_get("push", |_req, _state| {
+        async move { Ok(LONG_POLL_TIMEOUT.as_millis() as u64) }.boxed()
+    })?
+    .counted_get("status", |_req, state| {
+        async move { state.get_status() }.boxed()
+    })?
+    .counted_metrics("metrics", |_req, state| {
+        async move { Ok(Cow::Owned(state.get_metrics()?)) }.boxed()
+    })?
+    .counted_at("waitproposal", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_proposal(view_number)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitviddisperse", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_vid_disperse(view_number)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitcertificate", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_da_certificate(view_number)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitvidcertificate", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_vid_certificate(view_number)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitvotes", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            let index: u64 = req.integer_param("index")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_votes(view_number, index)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitvidvotes", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            let index: u64 = req.integer_param("index")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_vid_votes(view_number, index)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitviewsyncvotes", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            let index: u64 = req.integer_param("index")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_view_sync_votes(view_number, index)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waitviewsyncproposal", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            let index: u64 = req.integer_param("index")?;
+            long_poll(state, Some(view_number), |state| {
+                state.get_view_sync_proposal(view_number, index)
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("waittransactions", |req, state| {
+        async move {
+            let index: u64 = req.integer_param("index")?;
+            long_poll(state, None, |state| state.get_transactions(index)).await
+        }
+        .boxed()
+    })?
+    .counted_get("getproposal", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             state.get_proposal(view_number)
         }
         .boxed()
     })?
-    .get("getviddisperse", |req, state| {
+    .counted_get("getviddisperse", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             state.get_vid_disperse(view_number)
         }
         .boxed()
     })?
-    .get("getrecentproposal", |_req, state| {
+    .counted_get("getrecentproposal", |_req, state| {
         async move { state.get_recent_proposal() }.boxed()
     })?
-    .get("getviewsyncproposal", |req, state| {
+    .counted_get("getviewsyncproposal", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             let index: u64 = req.integer_param("index")?;
@@ -681,21 +1834,21 @@ where
         }
         .boxed()
     })?
-    .get("getcertificate", |req, state| {
+    .counted_get("getcertificate", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             state.get_da_certificate(view_number)
         }
         .boxed()
     })?
-    .get("getvidcertificate", |req, state| {
+    .counted_get("getvidcertificate", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             state.get_vid_certificate(view_number)
         }
         .boxed()
     })?
-    .get("getvotes", |req, state| {
+    .counted_get("getvotes", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             let index: u64 = req.integer_param("index")?;
@@ -703,7 +1856,7 @@ where
         }
         .boxed()
     })?
-    .get("getvidvotes", |req, state| {
+    .counted_get("getvidvotes", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             let index: u64 = req.integer_param("index")?;
@@ -711,7 +1864,7 @@ where
         }
         .boxed()
     })?
-    .get("getviewsyncvotes", |req, state| {
+    .counted_get("getviewsyncvotes", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
             let index: u64 = req.integer_param("index")?;
@@ -719,149 +1872,313 @@ where
         }
         .boxed()
     })?
-    .get("gettransactions", |req, state| {
+    .counted_get("gettransactions", |req, state| {
         async move {
             let index: u64 = req.integer_param("index")?;
             state.get_transactions(index)
         }
         .boxed()
     })?
-    .post("postvote", |req, state| {
+    .counted_at("postvote", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            // Using body_bytes because we don't want to deserialize; body_auto or body_json deserializes automatically
-            let vote = req.body_bytes();
-            state.post_vote(view_number, vote)
+            let route = config::post_vote_route(view_number);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                state.post_vote(view_number, request.body.clone())?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("postvidvote", |req, state| {
+    .counted_at("postvidvote", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            // Using body_bytes because we don't want to deserialize; body_auto or body_json deserializes automatically
-            let vote = req.body_bytes();
-            state.post_vid_vote(view_number, vote)
+            let route = config::post_vid_vote_route(view_number);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                state.post_vid_vote(view_number, request.body.clone())?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("postviewsyncvote", |req, state| {
+    .counted_at("postviewsyncvote", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            // Using body_bytes because we don't want to deserialize; body_auto or body_json deserializes automatically
-            let vote = req.body_bytes();
-            state.post_view_sync_vote(view_number, vote)
+            let route = config::post_view_sync_vote_route(view_number);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                state.post_view_sync_vote(view_number, request.body.clone())?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("postproposal", |req, state| {
+    .counted_at("postproposal", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let proposal = req.body_bytes();
-            state.post_proposal(view_number, proposal)
+            let route = config::post_proposal_route(view_number);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    state.check_leader(&sender, view_number)?;
+                    state.post_proposal(view_number, request.body.clone())?;
+                    state.replicate(&route, &request);
+                    Ok(())
+                },
+            )
+            .await
         }
         .boxed()
     })?
-    .post("postviddisperse", |req, state| {
+    .counted_at("postviddisperse", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let disperse = req.body_bytes();
-            state.post_vid_disperse(view_number, disperse)
+            let route = config::post_vid_disperse_route(view_number);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    state.check_leader(&sender, view_number)?;
+                    state.post_vid_disperse(view_number, request.body.clone())?;
+                    state.replicate(&route, &request);
+                    Ok(())
+                },
+            )
+            .await
         }
         .boxed()
     })?
-    .post("postviewsyncproposal", |req, state| {
+    .counted_at("postviewsyncproposal", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let proposal = req.body_bytes();
-            state.post_view_sync_proposal(view_number, proposal)
+            let route = config::post_view_sync_proposal_route(view_number);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                state.post_view_sync_proposal(view_number, request.body.clone())?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("postcertificate", |req, state| {
+    .counted_at("postcertificate", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let cert = req.body_bytes();
-            state.post_da_certificate(view_number, cert)
+            let route = config::post_da_certificate_route(view_number);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    state.check_leader(&sender, view_number)?;
+                    state.post_da_certificate(view_number, request.body.clone())?;
+                    state.replicate(&route, &request);
+                    Ok(())
+                },
+            )
+            .await
         }
         .boxed()
     })?
-    .post("postvidcertificate", |req, state| {
+    .counted_at("postvidcertificate", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let cert = req.body_bytes();
-            state.post_vid_certificate(view_number, cert)
+            let route = config::post_vid_certificate_route(view_number);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    state.check_leader(&sender, view_number)?;
+                    state.post_vid_certificate(view_number, request.body.clone())?;
+                    state.replicate(&route, &request);
+                    Ok(())
+                },
+            )
+            .await
         }
         .boxed()
     })?
-    .post("posttransaction", |req, state| {
+    .counted_get("gettransactionstatus", |req, state| {
         async move {
-            let txns = req.body_bytes();
-            state.post_transaction(txns)
+            let commitment = req.string_param("commitment")?;
+            state.get_transaction_status(commitment)
         }
         .boxed()
     })?
-    .post("poststaketable", |req, state| {
+    .counted_at("posttransaction", |req, state| {
         async move {
-            //works one key at a time for now
-            let key = req.body_bytes();
-            state.post_staketable(key)
+            let commitment = req.string_param("commitment")?.to_string();
+            let route = config::post_transactions_route(&commitment);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                state.check_transaction(&commitment, &request.body)?;
+                state.post_transaction(commitment, request.body.clone())?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("postcompletedtransaction", |req, state| {
+    .counted_at("postdecidedtransactions", |req, state| {
         async move {
-            //works one txn at a time for now
-            let txn = req.body_bytes();
-            state.post_completed_transaction(txn)
+            let view_number: u64 = req.integer_param("view_number")?;
+            let route = config::post_decided_transactions_route(view_number);
+            post_signed::<_, KEY, _>(state, &route, &req.body_bytes(), |state, _, request| {
+                let commitments = VersionedMessage::from_bytes(&request.body)
+                    .ok()
+                    .and_then(|message| message.open::<Vec<String>>().ok())
+                    .ok_or_else(|| ServerError {
+                        status: StatusCode::BadRequest,
+                        message: "Expected a list of transaction commitments".to_string(),
+                    })?;
+                state.post_decided_transactions(view_number, commitments)?;
+                state.replicate(&route, &request);
+                Ok(())
+            })
+            .await
         }
         .boxed()
     })?
-    .post("secret", |req, state| {
+    .counted_post("poststaketable", |req, state| {
+        async move {
+            //works one key at a time for now
+            let key: Vec<u8> =
+                bincode::deserialize(&req.body_bytes()).map_err(|_| ServerError {
+                    status: StatusCode::BadRequest,
+                    message: "Expected a serialized public key".to_string(),
+                })?;
+            state.post_staketable(key)
+        }
+        .boxed()
+    })?
+    .counted_at("secret", |req, state| {
         async move {
             let view_number: u64 = req.integer_param("view_number")?;
-            let secret: &str = req.string_param("secret")?;
-            //if secret is correct and view_number->proposal is empty, proposal is valid
-            if let Some(prop) = state.proposal(view_number) {
-                if prop.1.is_empty() {
-                    if prop.0 == secret {
-                        let proposal = req.body_bytes();
-                        state.post_secret_proposal(view_number, proposal)
-                    } else {
-                        Err(ServerError {
+            let secret = req.string_param("secret")?.to_string();
+            let route = config::post_secret_proposal_route(view_number, &secret);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    //if secret is correct and view_number->proposal is empty, proposal is valid
+                    let Some(prop) = state.proposal(view_number) else {
+                        return Err(ServerError {
+                            status: StatusCode::BadRequest,
+                            message: format!("No endpoint for view number {} yet", view_number),
+                        });
+                    };
+                    if !prop.1.is_empty() {
+                        return Err(ServerError {
+                            status: StatusCode::BadRequest,
+                            message: format!(
+                                "Proposal already submitted for view {:?}",
+                                view_number
+                            ),
+                        });
+                    }
+                    if prop.0 != secret {
+                        return Err(ServerError {
                             status: StatusCode::BadRequest,
                             message: format!(
                                 "Wrong secret value for proposal for view {:?}",
                                 view_number
                             ),
-                        })
+                        });
                     }
-                } else {
-                    Err(ServerError {
-                        status: StatusCode::BadRequest,
-                        message: format!("Proposal already submitted for view {:?}", view_number),
-                    })
-                }
-            } else {
-                Err(ServerError {
-                    status: StatusCode::BadRequest,
-                    message: format!("No endpoint for view number {} yet", view_number),
-                })
-            }
+                    state.check_leader(&sender, view_number)?;
+                    // each instance hands out its own secrets, so the others are sent the
+                    // proposal on a route that only checks it came from the leader
+                    state.post_secret_proposal(view_number, request.body.clone())?;
+                    state.replicate(
+                        &config::replicate_secret_proposal_route(view_number, &secret),
+                        &request,
+                    );
+                    Ok(())
+                },
+            )
+            .await
+        }
+        .boxed()
+    })?
+    .counted_at("replicatesecret", |req, state| {
+        async move {
+            let view_number: u64 = req.integer_param("view_number")?;
+            let secret = req.string_param("secret")?.to_string();
+            // signed for the instance the leader posted it to
+            let route = config::post_secret_proposal_route(view_number, &secret);
+            post_signed::<_, KEY, _>(
+                state,
+                &route,
+                &req.body_bytes(),
+                |state, sender, request| {
+                    state.check_leader(&sender, view_number)?;
+                    if state
+                        .proposal(view_number)
+                        .is_some_and(|(_, proposal)| !proposal.is_empty())
+                    {
+                        return Ok(());
+                    }
+                    state.post_secret_proposal(view_number, request.body.clone())?;
+                    state.replicate(
+                        &config::replicate_secret_proposal_route(view_number, &secret),
+                        &request,
+                    );
+                    Ok(())
+                },
+            )
+            .await
         }
         .boxed()
     })?;
     Ok(api)
 }
 
-pub async fn run_web_server<KEY: SignatureKey + 'static>(
+/// The commitment of the transaction submitted in `body`, a versioned message posted by a node
+/// of type `TYPES`, as it appears in routes. `None` if `body` doesn't submit a transaction.
+pub fn transaction_commitment<TYPES: NodeType>(body: &[u8]) -> Option<String> {
+    let message: Message<TYPES> = VersionedMessage::from_bytes(body).ok()?.open().ok()?;
+    match message.kind {
+        MessageKind::Data(DataMessage::SubmitTransaction(txn, _)) => {
+            Some(config::commitment_param(txn.commit().as_ref()))
+        }
+        MessageKind::Consensus(_) => None,
+    }
+}
+
+/// Runs the web server for nodes of type `TYPES` on `port`. Only keys in `stake_table` may post
+/// to it, and the leader of view `v` is taken to be `stake_table[v % stake_table.len()]`.
+///
+/// # Errors
+/// If the persisted state in `options.persistence_path` can not be restored, or serving fails
+pub async fn run_web_server<TYPES: NodeType>(
     shutdown_listener: Option<OneShotReceiver<()>>,
     port: u16,
+    stake_table: Vec<TYPES::SignatureKey>,
+    options: Options,
 ) -> io::Result<()> {
-    let options = Options::default();
-
     let api = define_api(&options).unwrap();
-    let state = State::new(WebServerState::new().with_shutdown_signal(shutdown_listener));
-    let mut app = App::<State<KEY>, Error>::with_state(state);
+    let mut web_server_state = WebServerState::new()
+        .with_shutdown_signal(shutdown_listener)
+        .with_stake_table(stake_table)
+        .with_retention(options.retention)
+        .with_peers(options.peers.clone())
+        .with_transaction_commitment(transaction_commitment::<TYPES>);
+    if let Some(dir) = &options.persistence_path {
+        web_server_state = web_server_state
+            .with_persistence(dir, options.sync_every.unwrap_or(WAL_SYNC_INTERVAL))?;
+    }
+    let state = State::new(web_server_state);
+    let mut app = App::<State<TYPES::SignatureKey>, Error>::with_state(state);
 
     app.register_module("api", api).unwrap();
 
@@ -871,3 +2188,378 @@ pub async fn run_web_server<KEY: SignatureKey + 'static>(
 
     app_future.await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::retention::Retention;
+    use async_compatibility_layer::art::{async_sleep, async_spawn};
+    use hotshot_signature_key::bn254::BLSPubKey;
+    use std::time::Duration;
+
+    /// a versioned message, as nodes post
+    fn message(payload: u64) -> Vec<u8> {
+        VersionedMessage::new(&payload).unwrap().to_bytes().unwrap()
+    }
+
+    /// a server whose stake table is the first `n` keys from the default seed
+    fn stake_table_state(n: u64) -> WebServerState<BLSPubKey> {
+        WebServerState::new().with_stake_table(
+            (0..n)
+                .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0)
+                .collect(),
+        )
+    }
+
+    /// `body` signed for `route` by the key with index `i` from the default seed
+    fn signed(i: u64, route: &str, body: Vec<u8>) -> SignedRequest {
+        let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], i);
+        SignedRequest::new(&key, &private_key, route, body)
+    }
+
+    /// a request signed by a stake table member is accepted, once
+    #[test]
+    fn test_signed_request_accepted() {
+        let mut state = stake_table_state(2);
+        let route = config::post_vote_route(1);
+        let request = signed(1, &route, message(7));
+        let body = bincode::serialize(&request).unwrap();
+
+        let (sender, verified) = verify_request::<BLSPubKey>(&route, &body).unwrap();
+        assert_eq!(
+            sender,
+            BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0
+        );
+        assert_eq!(verified, request);
+        assert!(state.admit(&sender, &verified).unwrap());
+        // delivered again, e.g. by another instance of the cluster
+        assert!(!state.admit(&sender, &verified).unwrap());
+    }
+
+    /// requests that aren't signed, signed for another route, stale, or from outside the
+    /// stake table are refused
+    #[test]
+    fn test_signed_request_rejected() {
+        let mut state = stake_table_state(2);
+        let route = config::post_vote_route(1);
+        let status = |result: Result<(BLSPubKey, SignedRequest), Error>| result.unwrap_err().status;
+
+        assert_eq!(
+            status(verify_request(&route, &[1, 2, 3])),
+            StatusCode::BadRequest
+        );
+
+        let other_route =
+            bincode::serialize(&signed(1, &config::post_vote_route(2), message(7))).unwrap();
+        assert_eq!(
+            status(verify_request(&route, &other_route)),
+            StatusCode::Unauthorized
+        );
+
+        let mut tampered = signed(1, &route, message(7));
+        tampered.body = message(8);
+        assert_eq!(
+            status(verify_request(
+                &route,
+                &bincode::serialize(&tampered).unwrap()
+            )),
+            StatusCode::Unauthorized
+        );
+
+        let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1);
+        let timestamp = unix_now() - 2 * MAX_REQUEST_AGE.as_secs();
+        let body = message(7);
+        let stale = SignedRequest {
+            key: key.to_bytes().0,
+            timestamp,
+            signature: BLSPubKey::sign(
+                &private_key,
+                &SignedRequest::signed_bytes(&route, timestamp, &body),
+            ),
+            body,
+        };
+        assert_eq!(
+            status(verify_request(&route, &bincode::serialize(&stale).unwrap())),
+            StatusCode::Unauthorized
+        );
+
+        let unversioned = bincode::serialize(&signed(1, &route, vec![])).unwrap();
+        assert_eq!(
+            status(verify_request(&route, &unversioned)),
+            StatusCode::BadRequest
+        );
+
+        let outsider = signed(5, &route, message(7));
+        let body = bincode::serialize(&outsider).unwrap();
+        let (sender, request) = verify_request::<BLSPubKey>(&route, &body).unwrap();
+        assert_eq!(
+            state.admit(&sender, &request).unwrap_err().status,
+            StatusCode::Forbidden
+        );
+    }
+
+    /// the unsigned stake table route only confirms membership
+    #[test]
+    fn test_post_staketable() {
+        let mut state = stake_table_state(2);
+        let member = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+        let outsider = BLSPubKey::generated_from_seed_indexed([0u8; 32], 5).0;
+        assert!(state.post_staketable(member.to_bytes().0).is_ok());
+        assert_eq!(
+            state
+                .post_staketable(outsider.to_bytes().0)
+                .unwrap_err()
+                .status,
+            StatusCode::Forbidden
+        );
+        assert_eq!(
+            state.post_staketable(vec![1, 2, 3]).unwrap_err().status,
+            StatusCode::BadRequest
+        );
+    }
+
+    /// a restarted persistent server carries on with the transaction index where it left off,
+    /// whether its state comes from the log, a snapshot or both
+    #[test]
+    fn test_restore_num_txns() {
+        let dir =
+            std::env::temp_dir().join(format!("hotshot-web-server-restore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let open = || stake_table_state(2).with_persistence(&dir, 1).unwrap();
+
+        let mut state = open();
+        for i in 0..3 {
+            state
+                .post_transaction(format!("txn{i}"), vec![i as u8])
+                .unwrap();
+        }
+        drop(state);
+
+        let mut state = open();
+        assert_eq!(state.num_txns, 3);
+        // already pending, so not logged again
+        state.post_transaction("txn0".to_string(), vec![0]).unwrap();
+        assert_eq!(state.num_txns, 3);
+        for i in 3..config::WAL_SNAPSHOT_INTERVAL + 5 {
+            state
+                .post_transaction(format!("txn{i}"), vec![i as u8])
+                .unwrap();
+        }
+        drop(state);
+
+        let state = open();
+        assert_eq!(state.num_txns, config::WAL_SNAPSHOT_INTERVAL + 5);
+        assert_eq!(
+            state
+                .get_transaction_status(&format!("txn{}", config::WAL_SNAPSHOT_INTERVAL + 4))
+                .unwrap(),
+            TransactionStatus::Pending {
+                index: config::WAL_SNAPSHOT_INTERVAL + 4
+            }
+        );
+        drop(state);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// stands in for a node type's transaction commitment: the transaction's bytes, in hex
+    fn commitment(txn: &[u8]) -> Option<String> {
+        (!txn.is_empty()).then(|| config::commitment_param(txn))
+    }
+
+    /// a transaction is stored once, however many times it is posted, and not again once
+    /// decided
+    #[test]
+    fn test_post_transaction_dedup() {
+        let mut state = stake_table_state(2);
+        state.post_transaction("a".to_string(), vec![1]).unwrap();
+        state.post_transaction("a".to_string(), vec![1]).unwrap();
+        assert_eq!(state.num_txns, 1);
+        assert_eq!(state.transaction_bytes, 1);
+
+        state
+            .post_decided_transactions(3, vec!["a".to_string()])
+            .unwrap();
+        state.post_transaction("a".to_string(), vec![1]).unwrap();
+        assert_eq!(state.num_txns, 1);
+        assert!(state.transactions.is_empty());
+    }
+
+    /// decided transactions stop being pending and report the view they were decided in, even
+    /// when the other nodes that decided them report them again
+    #[test]
+    fn test_decided_transactions() {
+        let mut state = stake_table_state(2).with_retention(RetentionOptions {
+            transactions: Retention::count(3),
+            ..RetentionOptions::default()
+        });
+        for (i, commitment) in ["a", "b", "c"].iter().enumerate() {
+            state
+                .post_transaction(commitment.to_string(), vec![i as u8; 2])
+                .unwrap();
+        }
+        state
+            .post_decided_transactions(4, vec!["a".to_string(), "b".to_string()])
+            .unwrap();
+        // a repeat from another node changes nothing
+        state
+            .post_decided_transactions(5, vec!["b".to_string()])
+            .unwrap();
+        assert_eq!(
+            state.get_transaction_status("b").unwrap(),
+            TransactionStatus::Decided { view: 4 }
+        );
+        assert_eq!(
+            state.get_transaction_status("c").unwrap(),
+            TransactionStatus::Pending { index: 2 }
+        );
+        assert_eq!(state.transaction_bytes, 2);
+        assert_eq!(
+            state.get_transactions(0).unwrap(),
+            Some((0, vec![vec![2, 2]]))
+        );
+
+        // only the most recently decided are remembered
+        state
+            .post_decided_transactions(6, vec!["c".to_string(), "d".to_string()])
+            .unwrap();
+        assert_eq!(
+            state.get_transaction_status("a").unwrap(),
+            TransactionStatus::Unknown
+        );
+        assert_eq!(
+            state.get_transaction_status("c").unwrap(),
+            TransactionStatus::Decided { view: 6 }
+        );
+        assert_eq!(
+            state.get_transaction_status("e").unwrap(),
+            TransactionStatus::Unknown
+        );
+    }
+
+    /// a transaction is only accepted under its own commitment, once the server can compute it
+    #[test]
+    fn test_check_transaction() {
+        let state = stake_table_state(2);
+        assert!(state.check_transaction("anything", &[1]).is_ok());
+
+        let state = state.with_transaction_commitment(commitment);
+        assert!(state.check_transaction("0102", &[1, 2]).is_ok());
+        for (claimed, txn) in [("0102", &[1, 3][..]), ("", &[])] {
+            assert_eq!(
+                state.check_transaction(claimed, txn).unwrap_err().status,
+                StatusCode::BadRequest
+            );
+        }
+    }
+
+    /// state persisted before the format was versioned is migrated, indexing its transactions
+    /// by commitment, and persisted again in the current format
+    #[test]
+    fn test_migrate_v0() {
+        let dir =
+            std::env::temp_dir().join(format!("hotshot-web-server-v0-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let snapshot = v0::State {
+            proposals: HashMap::from([(1, ("secret".to_string(), vec![1, 2, 3]))]),
+            recent_proposal: 1,
+            transactions: HashMap::from([(0, vec![10]), (1, vec![11])]),
+            txn_lookup: HashMap::from([(vec![10], 0), (vec![11], 1)]),
+            num_txns: 2,
+            ..v0::State::default()
+        };
+        std::fs::write(
+            dir.join(persistence::SNAPSHOT_FILE),
+            bincode::serialize(&snapshot).unwrap(),
+        )
+        .unwrap();
+        let mut log = Vec::new();
+        for entry in [
+            v0::WalEntry::Transaction(Cow::Owned(vec![12])),
+            v0::WalEntry::CompletedTransaction(Cow::Owned(vec![10])),
+        ] {
+            let encoded = bincode::serialize(&entry).unwrap();
+            log.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+            log.extend_from_slice(&encoded);
+        }
+        std::fs::write(dir.join(persistence::WAL_FILE), log).unwrap();
+
+        let open = || {
+            stake_table_state(2)
+                .with_transaction_commitment(commitment)
+                .with_persistence(&dir, 1)
+                .unwrap()
+        };
+        for _ in 0..2 {
+            // the second time round, from the migrated files
+            let state = open();
+            assert_eq!(
+                state.proposal(1),
+                Some(("secret".to_string(), vec![1, 2, 3]))
+            );
+            assert_eq!(state.proposal_bytes, 3);
+            assert_eq!(state.num_txns, 3);
+            assert_eq!(
+                state.get_transaction_status("0a").unwrap(),
+                TransactionStatus::Unknown
+            );
+            assert_eq!(
+                state.get_transaction_status("0b").unwrap(),
+                TransactionStatus::Pending { index: 1 }
+            );
+            assert_eq!(
+                state.get_transaction_status("0c").unwrap(),
+                TransactionStatus::Pending { index: 2 }
+            );
+            assert_eq!(state.transaction_bytes, 2);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// a held long-poll answers as soon as what it waits for is posted
+    #[cfg_attr(
+        async_executor_impl = "tokio",
+        tokio::test(flavor = "multi_thread", worker_threads = 2)
+    )]
+    #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+    async fn test_long_poll_wakes_on_post() {
+        let state = Arc::new(State::new(WebServerState::<BLSPubKey>::new()));
+        let poster = Arc::clone(&state);
+        async_spawn(async move {
+            async_sleep(Duration::from_millis(100)).await;
+            // something unrelated first, which shouldn't end the poll
+            poster.write().await.post_vote(2, vec![2]).unwrap();
+            async_sleep(Duration::from_millis(100)).await;
+            poster.write().await.post_vote(1, vec![1]).unwrap();
+        });
+
+        let start = Instant::now();
+        let votes = long_poll::<_, BLSPubKey, _>(&*state, "waitvotes", Some(1), |state| {
+            state.get_votes(1, 0)
+        })
+        .await
+        .unwrap();
+        assert_eq!(votes, Some(vec![vec![1]]));
+        assert!(start.elapsed() < LONG_POLL_TIMEOUT / 2);
+        // no longer counted as a poller
+        assert!(state.read().await.activity().pollers().is_empty());
+    }
+
+    /// a held long-poll answers with nothing once it times out
+    #[cfg_attr(
+        async_executor_impl = "tokio",
+        tokio::test(flavor = "multi_thread", worker_threads = 2)
+    )]
+    #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+    async fn test_long_poll_times_out() {
+        let state = State::new(WebServerState::<BLSPubKey>::new());
+        let start = Instant::now();
+        let votes = long_poll::<_, BLSPubKey, _>(&state, "waitvotes", Some(1), |state| {
+            state.get_votes(1, 0)
+        })
+        .await
+        .unwrap();
+        assert_eq!(votes, None);
+        assert!(start.elapsed() >= LONG_POLL_TIMEOUT);
+    }
+}
@@ -0,0 +1,223 @@
+//! Operational metrics of the web server, served on its `metrics` route in the Prometheus text
+//! exposition format.
+
+use crate::retention::RetentionStats;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// What the server has been asked to do: requests served per route and long-polls held open per
+/// view. Kept behind its own locks so routes that only read the state can record themselves.
+#[derive(Debug, Default)]
+pub struct Activity {
+    /// route -> requests served
+    requests: Mutex<BTreeMap<String, u64>>,
+    /// view -> long-poll requests currently held open for it
+    pollers: Mutex<BTreeMap<u64, usize>>,
+}
+
+impl Activity {
+    /// Count a request to `route`
+    pub fn record_request(&self, route: &str) {
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        *requests.entry(route.to_string()).or_default() += 1;
+    }
+
+    /// Count a long-poll held open for `view` until the returned guard is dropped
+    pub fn start_poll(activity: &Arc<Self>, view: u64) -> Poller {
+        let mut pollers = activity.pollers.lock().unwrap_or_else(|e| e.into_inner());
+        *pollers.entry(view).or_default() += 1;
+        Poller {
+            activity: Arc::clone(activity),
+            view,
+        }
+    }
+
+    /// requests served so far, by route
+    pub fn requests(&self) -> BTreeMap<String, u64> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// long-polls currently held open, by view
+    pub fn pollers(&self) -> BTreeMap<u64, usize> {
+        self.pollers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// A long-poll being held open, counted in [`Activity`] for as long as it lives
+#[derive(Debug)]
+pub struct Poller {
+    activity: Arc<Activity>,
+    view: u64,
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        let mut pollers = self
+            .activity
+            .pollers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = pollers.get_mut(&self.view) {
+            *count -= 1;
+            if *count == 0 {
+                pollers.remove(&self.view);
+            }
+        }
+    }
+}
+
+/// A snapshot of the web server's metrics
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebServerMetrics {
+    /// route -> requests served
+    pub requests: BTreeMap<String, u64>,
+    /// view -> long-poll requests currently held open for it
+    pub pollers: BTreeMap<u64, usize>,
+    /// what is kept of each kind of data
+    pub data: Vec<RetentionStats>,
+}
+
+impl fmt::Display for WebServerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# HELP web_server_requests_total Requests served, by route"
+        )?;
+        writeln!(f, "# TYPE web_server_requests_total counter")?;
+        for (route, count) in &self.requests {
+            writeln!(f, "web_server_requests_total{{route=\"{route}\"}} {count}")?;
+        }
+
+        // per kind of data, one gauge for each of these
+        let gauges: [(&str, &str, fn(&RetentionStats) -> u64); 5] = [
+            (
+                "web_server_stored_bytes",
+                "Bytes of payload kept",
+                |stats| stats.bytes as u64,
+            ),
+            ("web_server_stored_items", "Items kept", |stats| {
+                stats.items as u64
+            }),
+            (
+                "web_server_stored_views",
+                "Views kept (for transactions, transactions kept)",
+                |stats| stats.retained as u64,
+            ),
+            (
+                "web_server_oldest_view",
+                "Oldest view kept (for transactions, oldest index)",
+                |stats| stats.oldest,
+            ),
+            (
+                "web_server_newest_view",
+                "Newest view kept (for transactions, newest index)",
+                |stats| stats.newest,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            writeln!(f, "# HELP {name} {help}, by kind of data")?;
+            writeln!(f, "# TYPE {name} gauge")?;
+            for stats in &self.data {
+                writeln!(f, "{name}{{data=\"{}\"}} {}", stats.data, value(stats))?;
+            }
+        }
+
+        writeln!(
+            f,
+            "# HELP web_server_active_pollers Long-poll requests held open, by view"
+        )?;
+        writeln!(f, "# TYPE web_server_active_pollers gauge")?;
+        for (view, count) in &self.pollers {
+            writeln!(f, "web_server_active_pollers{{view=\"{view}\"}} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+impl tide_disco::metrics::Metrics for WebServerMetrics {
+    type Error = Infallible;
+
+    fn export(&self) -> Result<String, Self::Error> {
+        Ok(self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::retention::Retention;
+
+    /// requests and pollers are counted, and pollers stop counting once their guard drops
+    #[test]
+    fn test_activity() {
+        let activity = Arc::new(Activity::default());
+        activity.record_request("getvotes");
+        activity.record_request("getvotes");
+        activity.record_request("postvote");
+        let poller = Activity::start_poll(&activity, 3);
+        let _other = Activity::start_poll(&activity, 3);
+        assert_eq!(
+            activity.requests(),
+            BTreeMap::from([("getvotes".to_string(), 2), ("postvote".to_string(), 1)])
+        );
+        assert_eq!(activity.pollers(), BTreeMap::from([(3, 2)]));
+        drop(poller);
+        assert_eq!(activity.pollers(), BTreeMap::from([(3, 1)]));
+    }
+
+    /// the metrics are exposed as one family per metric, labelled by route, kind of data or view
+    #[test]
+    fn test_text_exposition() {
+        let metrics = WebServerMetrics {
+            requests: BTreeMap::from([("getproposal".to_string(), 4), ("postvote".to_string(), 2)]),
+            pollers: BTreeMap::from([(7, 1)]),
+            data: vec![RetentionStats {
+                data: "votes".to_string(),
+                limit: Retention::count(100),
+                retained: 2,
+                items: 5,
+                bytes: 640,
+                oldest: 6,
+                newest: 7,
+            }],
+        };
+        let expected = "\
+# HELP web_server_requests_total Requests served, by route
+# TYPE web_server_requests_total counter
+web_server_requests_total{route=\"getproposal\"} 4
+web_server_requests_total{route=\"postvote\"} 2
+# HELP web_server_stored_bytes Bytes of payload kept, by kind of data
+# TYPE web_server_stored_bytes gauge
+web_server_stored_bytes{data=\"votes\"} 640
+# HELP web_server_stored_items Items kept, by kind of data
+# TYPE web_server_stored_items gauge
+web_server_stored_items{data=\"votes\"} 5
+# HELP web_server_stored_views Views kept (for transactions, transactions kept), by kind of data
+# TYPE web_server_stored_views gauge
+web_server_stored_views{data=\"votes\"} 2
+# HELP web_server_oldest_view Oldest view kept (for transactions, oldest index), by kind of data
+# TYPE web_server_oldest_view gauge
+web_server_oldest_view{data=\"votes\"} 6
+# HELP web_server_newest_view Newest view kept (for transactions, newest index), by kind of data
+# TYPE web_server_newest_view gauge
+web_server_newest_view{data=\"votes\"} 7
+# HELP web_server_active_pollers Long-poll requests held open, by view
+# TYPE web_server_active_pollers gauge
+web_server_active_pollers{view=\"7\"} 1
+";
+        assert_eq!(
+            tide_disco::metrics::Metrics::export(&metrics).unwrap(),
+            expected
+        );
+    }
+}
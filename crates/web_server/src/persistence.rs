@@ -0,0 +1,508 @@
+//! Optional durable storage for the web server's state.
+//!
+//! Every change to the state is appended to a write-ahead log before it is applied. Every
+//! `WAL_SNAPSHOT_INTERVAL` entries the whole state is snapshotted and the log is truncated, so
+//! a restarted server loads the latest snapshot and replays the log on top of it.
+//!
+//! The log is synced to disk every `sync_every` entries rather than on every one. Entries
+//! written since the last sync survive the server going down, but not the machine.
+//!
+//! Both files start with a header naming the version of the format they are written in. Files
+//! from before the format was versioned have no header; they are read as version 0, for the
+//! server to migrate and snapshot in the current format before logging anything new.
+
+use crate::config::WAL_SNAPSHOT_INTERVAL;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Name of the write-ahead log inside the persistence directory
+pub(crate) const WAL_FILE: &str = "wal";
+/// Name of the latest snapshot inside the persistence directory
+pub(crate) const SNAPSHOT_FILE: &str = "snapshot";
+/// Start of the header of both files, followed by the little endian format version
+const FORMAT_MAGIC: [u8; 6] = *b"hswsrv";
+/// Length of the header of both files
+const HEADER_LEN: usize = FORMAT_MAGIC.len() + 2;
+/// Version of the format the log and snapshot are currently written in
+pub const FORMAT_VERSION: u16 = 1;
+
+/// A change to the web server's state, as recorded in the write-ahead log.
+/// Payloads are borrowed when writing so logging doesn't copy them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WalEntry<'a> {
+    Vote(u64, Cow<'a, [u8]>),
+    VidVote(u64, Cow<'a, [u8]>),
+    ViewSyncVote(u64, Cow<'a, [u8]>),
+    Proposal(u64, Cow<'a, [u8]>),
+    SecretProposal(u64, Cow<'a, [u8]>),
+    VidDisperse(u64, Cow<'a, [u8]>),
+    ViewSyncProposal(u64, Cow<'a, [u8]>),
+    DaCertificate(u64, Cow<'a, [u8]>),
+    VidCertificate(u64, Cow<'a, [u8]>),
+    Transaction(Cow<'a, str>, Cow<'a, [u8]>),
+    DecidedTransactions(u64, Cow<'a, [String]>),
+}
+
+/// The format written before it was versioned
+pub mod v0 {
+    use serde::Deserialize;
+    use std::{borrow::Cow, collections::HashMap};
+
+    /// A change to the web server's state. Transactions were keyed by their bytes rather than
+    /// their commitment, and dropped when completed without recording where.
+    #[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+    #[cfg_attr(test, derive(serde::Serialize))]
+    pub enum WalEntry<'a> {
+        Vote(u64, Cow<'a, [u8]>),
+        VidVote(u64, Cow<'a, [u8]>),
+        ViewSyncVote(u64, Cow<'a, [u8]>),
+        Proposal(u64, Cow<'a, [u8]>),
+        SecretProposal(u64, Cow<'a, [u8]>),
+        VidDisperse(u64, Cow<'a, [u8]>),
+        ViewSyncProposal(u64, Cow<'a, [u8]>),
+        DaCertificate(u64, Cow<'a, [u8]>),
+        VidCertificate(u64, Cow<'a, [u8]>),
+        Transaction(Cow<'a, [u8]>),
+        CompletedTransaction(Cow<'a, [u8]>),
+    }
+
+    /// The web server's state as it was snapshotted
+    #[derive(Debug, Default, Deserialize)]
+    #[cfg_attr(test, derive(serde::Serialize))]
+    pub struct State {
+        pub proposals: HashMap<u64, (String, Vec<u8>)>,
+        pub view_sync_proposals: HashMap<u64, Vec<(u64, Vec<u8>)>>,
+        pub view_sync_proposal_index: HashMap<u64, u64>,
+        pub da_certificates: HashMap<u64, (String, Vec<u8>)>,
+        pub oldest_proposal: u64,
+        pub recent_proposal: u64,
+        pub oldest_certificate: u64,
+        pub oldest_view_sync_proposal: u64,
+        pub votes: HashMap<u64, Vec<(u64, Vec<u8>)>>,
+        pub view_sync_votes: HashMap<u64, Vec<(u64, Vec<u8>)>>,
+        pub vote_index: HashMap<u64, u64>,
+        pub view_sync_vote_index: HashMap<u64, u64>,
+        pub oldest_vote: u64,
+        pub oldest_view_sync_vote: u64,
+        pub vid_disperses: HashMap<u64, (String, Vec<u8>)>,
+        pub oldest_vid_disperse: u64,
+        pub recent_vid_disperse: u64,
+        pub vid_votes: HashMap<u64, Vec<(u64, Vec<u8>)>>,
+        pub oldest_vid_vote: u64,
+        pub vid_certificates: HashMap<u64, (String, Vec<u8>)>,
+        pub oldest_vid_certificate: u64,
+        pub vid_vote_index: HashMap<u64, u64>,
+        pub transactions: HashMap<u64, Vec<u8>>,
+        pub txn_lookup: HashMap<Vec<u8>, u64>,
+        pub num_txns: u64,
+    }
+}
+
+/// What an earlier run left in the persistence directory: the latest snapshot, if any, and the
+/// log entries to replay on top of it
+pub enum Restored {
+    /// written in the current format
+    Current {
+        snapshot: Option<Vec<u8>>,
+        entries: Vec<WalEntry<'static>>,
+    },
+    /// written before the format was versioned, to be migrated
+    V0 {
+        snapshot: Option<Vec<u8>>,
+        entries: Vec<v0::WalEntry<'static>>,
+    },
+}
+
+/// The write-ahead log and snapshot of the web server's state, kept in one directory
+pub struct Persistence {
+    /// the directory the log and snapshot live in
+    dir: PathBuf,
+    /// the open write-ahead log
+    wal: File,
+    /// entries appended since the last snapshot
+    entries_since_snapshot: u64,
+    /// how many entries to append between syncs
+    sync_every: u64,
+    /// entries appended since the last sync
+    unsynced: u64,
+}
+
+impl Persistence {
+    /// Open (creating if need be) the persisted state in `dir`, returning what an earlier run
+    /// left there. The log is synced every `sync_every` appends, or on every one if that is 0.
+    ///
+    /// A torn write at the end of the log, e.g. from a crash mid-append, is dropped.
+    /// If the files predate versioning, the state must be migrated and passed to `snapshot`
+    /// before anything is appended.
+    ///
+    /// # Errors
+    /// If the directory or its files could not be read or created, or were written in a newer
+    /// format
+    pub fn open(dir: &Path, sync_every: u64) -> io::Result<(Self, Restored)> {
+        fs::create_dir_all(dir)?;
+
+        let snapshot = match fs::read(dir.join(SNAPSHOT_FILE)) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut wal = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(WAL_FILE))?;
+        let mut log = Vec::new();
+        wal.read_to_end(&mut log)?;
+
+        let (snapshot_version, snapshot) = match snapshot {
+            Some(snapshot) => {
+                let (version, body) = Self::split_header(&snapshot)?;
+                (Some(version), Some(body.to_vec()))
+            }
+            None => (None, None),
+        };
+        let wal_version = if log.is_empty() {
+            None
+        } else {
+            Some(Self::split_header(&log)?.0)
+        };
+
+        let mut persistence = Self {
+            dir: dir.to_path_buf(),
+            wal,
+            entries_since_snapshot: 0,
+            sync_every: sync_every.max(1),
+            unsynced: 0,
+        };
+        let restored = match (snapshot_version, wal_version) {
+            (Some(0), None | Some(0)) | (None, Some(0)) => {
+                let entries = persistence.read_entries(&log)?;
+                Restored::V0 { snapshot, entries }
+            }
+            (_, Some(0)) => {
+                // a crash between migrating the snapshot and truncating the log it covers
+                warn!("Dropping write-ahead log already migrated into the snapshot");
+                persistence.truncate()?;
+                Restored::Current {
+                    snapshot,
+                    entries: Vec::new(),
+                }
+            }
+            (Some(0), Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "write-ahead log is newer than the snapshot it follows",
+                ))
+            }
+            (_, None) => {
+                persistence.truncate()?;
+                Restored::Current {
+                    snapshot,
+                    entries: Vec::new(),
+                }
+            }
+            (_, Some(_)) => {
+                let entries = persistence.read_entries(&log[HEADER_LEN..])?;
+                Restored::Current { snapshot, entries }
+            }
+        };
+        Ok((persistence, restored))
+    }
+
+    /// Split a file into the version it is written in and what follows its header. A file with
+    /// no header predates versioning: it is version 0, and all of it follows.
+    ///
+    /// # Errors
+    /// If the file is written in a newer format than this server knows
+    fn split_header(bytes: &[u8]) -> io::Result<(u16, &[u8])> {
+        let Some(rest) = bytes.strip_prefix(&FORMAT_MAGIC[..]) else {
+            return Ok((0, bytes));
+        };
+        let version = rest
+            .get(..2)
+            .and_then(|version| version.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated header"))?;
+        if version > FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("written in format version {version}, newer than {FORMAT_VERSION}"),
+            ));
+        }
+        Ok((version, &rest[2..]))
+    }
+
+    /// The header every file starts with
+    fn header() -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..FORMAT_MAGIC.len()].copy_from_slice(&FORMAT_MAGIC);
+        header[FORMAT_MAGIC.len()..].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header
+    }
+
+    /// Decode the entries of `log`, which is the log's tail from its first entry on, dropping
+    /// any torn entry at its end
+    fn read_entries<E: DeserializeOwned>(&mut self, log: &[u8]) -> io::Result<Vec<E>> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while let Some((entry, len)) = Self::read_entry(&log[offset..]) {
+            entries.push(entry);
+            offset += len;
+        }
+        if offset < log.len() {
+            warn!(
+                "Dropping {} bytes of incomplete write-ahead log",
+                log.len() - offset
+            );
+            let len = self.wal.metadata()?.len();
+            self.wal.set_len(len - (log.len() - offset) as u64)?;
+        }
+        self.entries_since_snapshot = entries.len() as u64;
+        Ok(entries)
+    }
+
+    /// Decode one length prefixed entry from the start of `bytes`, returning it and how many
+    /// bytes it took up
+    fn read_entry<E: DeserializeOwned>(bytes: &[u8]) -> Option<(E, usize)> {
+        let len = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+        let end = 8usize.checked_add(usize::try_from(len).ok()?)?;
+        let entry = bincode::deserialize(bytes.get(8..end)?).ok()?;
+        Some((entry, end))
+    }
+
+    /// Empty the log, leaving just its header
+    fn truncate(&mut self) -> io::Result<()> {
+        self.wal.set_len(0)?;
+        self.wal.write_all(&Self::header())?;
+        self.wal.sync_all()?;
+        self.entries_since_snapshot = 0;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Append `entry` to the log, syncing it to disk if `sync_every` entries are unsynced
+    ///
+    /// # Errors
+    /// If the entry could not be written
+    pub fn append(&mut self, entry: &WalEntry<'_>) -> io::Result<()> {
+        let encoded =
+            bincode::serialize(entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut record = Vec::with_capacity(encoded.len() + 8);
+        record.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+        record.extend_from_slice(&encoded);
+        self.wal.write_all(&record)?;
+        self.entries_since_snapshot += 1;
+        self.unsynced += 1;
+        if self.unsynced >= self.sync_every {
+            self.wal.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+
+    /// Whether enough has been logged that the state should be snapshotted
+    pub fn needs_snapshot(&self) -> bool {
+        self.entries_since_snapshot >= WAL_SNAPSHOT_INTERVAL
+    }
+
+    /// Replace the snapshot with `state` and truncate the log it covers
+    ///
+    /// # Errors
+    /// If the snapshot could not be written or the log truncated
+    pub fn snapshot(&mut self, state: &[u8]) -> io::Result<()> {
+        // write to the side and rename, so a crash never leaves a half written snapshot
+        let tmp = self.dir.join(format!("{SNAPSHOT_FILE}.tmp"));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&Self::header())?;
+        file.write_all(state)?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE))?;
+
+        self.truncate()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// a fresh directory for `name` under the system's temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("hotshot-web-server-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// open the persisted state in `dir`, which must be in the current format
+    fn open(dir: &Path, sync_every: u64) -> (Persistence, Option<Vec<u8>>, Vec<WalEntry<'static>>) {
+        match Persistence::open(dir, sync_every).unwrap() {
+            (persistence, Restored::Current { snapshot, entries }) => {
+                (persistence, snapshot, entries)
+            }
+            (_, Restored::V0 { .. }) => panic!("expected the current format"),
+        }
+    }
+
+    /// write `entries` to `dir` as a log from before the format was versioned
+    fn write_v0_log(dir: &Path, entries: &[v0::WalEntry<'_>]) {
+        fs::create_dir_all(dir).unwrap();
+        let mut log = Vec::new();
+        for entry in entries {
+            let encoded = bincode::serialize(entry).unwrap();
+            log.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+            log.extend_from_slice(&encoded);
+        }
+        fs::write(dir.join(WAL_FILE), log).unwrap();
+    }
+
+    fn vote(view: u64) -> WalEntry<'static> {
+        WalEntry::Vote(view, Cow::Owned(vec![view as u8; 3]))
+    }
+
+    /// a new directory opens empty, and appended entries are there to replay on reopening
+    #[test]
+    fn test_open_and_append() {
+        let dir = temp_dir("append");
+        let (mut persistence, snapshot, entries) = open(&dir, 2).unwrap();
+        assert_eq!(snapshot, None);
+        assert!(entries.is_empty());
+
+        let txn = WalEntry::Transaction(Cow::Borrowed("commitment"), Cow::Borrowed(&[1, 2]));
+        for entry in [vote(1), vote(2), txn.clone()] {
+            persistence.append(&entry).unwrap();
+        }
+        // the last entry is written but not yet synced
+        assert_eq!(persistence.unsynced, 1);
+        drop(persistence);
+
+        let (persistence, snapshot, entries) = open(&dir, 2).unwrap();
+        assert_eq!(snapshot, None);
+        assert_eq!(entries, vec![vote(1), vote(2), txn]);
+        assert_eq!(persistence.entries_since_snapshot, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// snapshotting replaces the snapshot and truncates the log it covers
+    #[test]
+    fn test_snapshot_truncates() {
+        let dir = temp_dir("snapshot");
+        let (mut persistence, _, _) = open(&dir, 1).unwrap();
+        for view in 0..WAL_SNAPSHOT_INTERVAL {
+            assert!(!persistence.needs_snapshot());
+            persistence.append(&vote(view)).unwrap();
+        }
+        assert!(persistence.needs_snapshot());
+        persistence.snapshot(b"first").unwrap();
+        persistence.snapshot(b"state").unwrap();
+        assert!(!persistence.needs_snapshot());
+        persistence.append(&vote(7)).unwrap();
+        drop(persistence);
+
+        let (_, snapshot, entries) = open(&dir, 1).unwrap();
+        assert_eq!(snapshot.as_deref(), Some(&b"state"[..]));
+        assert_eq!(entries, vec![vote(7)]);
+        assert!(!dir.join(format!("{SNAPSHOT_FILE}.tmp")).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// an entry torn off mid-write is dropped, keeping everything before it
+    #[test]
+    fn test_torn_tail_recovery() {
+        let dir = temp_dir("torn");
+        let (mut persistence, _, _) = open(&dir, 1).unwrap();
+        persistence.append(&vote(1)).unwrap();
+        persistence.append(&vote(2)).unwrap();
+        drop(persistence);
+        let wal = dir.join(WAL_FILE);
+        let len = fs::metadata(&wal).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&wal)
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+
+        let (mut persistence, _, entries) = open(&dir, 1).unwrap();
+        assert_eq!(entries, vec![vote(1)]);
+        // the torn bytes are gone, so new entries follow on from the good ones
+        persistence.append(&vote(3)).unwrap();
+        drop(persistence);
+        let (_, _, entries) = open(&dir, 1).unwrap();
+        assert_eq!(entries, vec![vote(1), vote(3)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// files written before the format was versioned are read as version 0, and snapshotting
+    /// the migrated state moves them to the current format
+    #[test]
+    fn test_v0_files_migrate() {
+        let dir = temp_dir("v0");
+        let entries = vec![
+            v0::WalEntry::Vote(1, Cow::Owned(vec![1])),
+            v0::WalEntry::Transaction(Cow::Owned(vec![2, 3])),
+        ];
+        write_v0_log(&dir, &entries);
+        fs::write(dir.join(SNAPSHOT_FILE), b"old state").unwrap();
+
+        let (mut persistence, restored) = Persistence::open(&dir, 1).unwrap();
+        let Restored::V0 {
+            snapshot,
+            entries: restored,
+        } = restored
+        else {
+            panic!("expected version 0");
+        };
+        assert_eq!(snapshot.as_deref(), Some(&b"old state"[..]));
+        assert_eq!(restored, entries);
+
+        persistence.snapshot(b"migrated").unwrap();
+        persistence.append(&vote(2)).unwrap();
+        drop(persistence);
+        let (_, snapshot, entries) = open(&dir, 1);
+        assert_eq!(snapshot.as_deref(), Some(&b"migrated"[..]));
+        assert_eq!(entries, vec![vote(2)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// a version 0 log left behind by a crash mid-migration was already covered by the
+    /// migrated snapshot, so is dropped rather than replayed
+    #[test]
+    fn test_interrupted_migration() {
+        let dir = temp_dir("interrupted");
+        let (mut persistence, _, _) = open(&dir, 1);
+        persistence.snapshot(b"migrated").unwrap();
+        drop(persistence);
+        write_v0_log(&dir, &[v0::WalEntry::Vote(1, Cow::Owned(vec![1]))]);
+
+        let (mut persistence, snapshot, entries) = open(&dir, 1);
+        assert_eq!(snapshot.as_deref(), Some(&b"migrated"[..]));
+        assert!(entries.is_empty());
+        persistence.append(&vote(3)).unwrap();
+        drop(persistence);
+        assert_eq!(open(&dir, 1).2, vec![vote(3)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// files from a newer format are refused rather than misread
+    #[test]
+    fn test_newer_format_refused() {
+        let dir = temp_dir("newer");
+        fs::create_dir_all(&dir).unwrap();
+        let mut header = Persistence::header();
+        header[FORMAT_MAGIC.len()..].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(dir.join(WAL_FILE), header).unwrap();
+        assert_eq!(
+            Persistence::open(&dir, 1).err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
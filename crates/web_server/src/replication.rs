@@ -0,0 +1,98 @@
+//! Replication between the instances of a web server cluster.
+//!
+//! Every post an instance accepts is forwarded, exactly as the node signed it, to every peer
+//! instance. Peers authenticate forwarded posts like any other, so instances don't need to trust
+//! each other, and recognise posts they have already seen by their signature so forwarding stops.
+
+use crate::SignedRequest;
+use async_compatibility_layer::art::{async_sleep, async_spawn};
+use futures::{
+    channel::mpsc::{channel, Sender},
+    StreamExt,
+};
+use std::time::Duration;
+use surf_disco::{error::ClientError, Url};
+use tide_disco::Error as _;
+use tracing::{debug, warn};
+
+/// How many posts may wait to be forwarded to a peer before new ones are dropped
+const QUEUE_CAPACITY: usize = 1024;
+/// How many times forwarding a post is attempted before it is given up on
+const MAX_ATTEMPTS: u32 = 5;
+/// How long to wait before the first retry, doubled for each one after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Forwards accepted posts to the other instances of the cluster
+#[derive(Debug)]
+pub struct Replicator {
+    /// one queue per peer, so a slow or unreachable peer doesn't hold up the others
+    peers: Vec<(Url, Sender<(String, SignedRequest)>)>,
+}
+
+impl Replicator {
+    /// Start forwarding to `peers`, each in its own task
+    pub fn new(peers: Vec<Url>) -> Self {
+        let peers = peers
+            .into_iter()
+            .map(|url| {
+                let (sender, mut receiver) = channel::<(String, SignedRequest)>(QUEUE_CAPACITY);
+                let client = surf_disco::Client::<ClientError>::new(url.clone());
+                let peer = url.clone();
+                async_spawn(async move {
+                    // posts are forwarded in the order they were accepted
+                    while let Some((route, request)) = receiver.next().await {
+                        forward(&client, &peer, &route, &request).await;
+                    }
+                });
+                (url, sender)
+            })
+            .collect();
+        Self { peers }
+    }
+
+    /// Queue a post the server accepted at `route` to be forwarded to every peer. A peer that
+    /// has fallen [`QUEUE_CAPACITY`] posts behind misses it.
+    pub fn replicate(&mut self, route: &str, request: &SignedRequest) {
+        for (url, peer) in &mut self.peers {
+            // the receiving task only stops when the server does, so the queue can only be full
+            if peer.try_send((route.to_string(), request.clone())).is_err() {
+                warn!("Replication queue to {url} is full, not forwarding {route}");
+            }
+        }
+    }
+}
+
+/// Post `request` to `route` on the peer at `url`, retrying with exponential backoff while the
+/// peer is unreachable or fails. A post the peer refuses is not retried: it would be refused again.
+async fn forward(
+    client: &surf_disco::Client<ClientError>,
+    url: &Url,
+    route: &str,
+    request: &SignedRequest,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result: Result<(), ClientError> = match client.post(route).body_binary(request) {
+            Ok(post) => post.send().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                debug!("Replicated {route} to {url}");
+                return;
+            }
+            Err(e) if e.status().is_client_error() => {
+                warn!("{url} refused replicated {route}: {e:?}");
+                return;
+            }
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                debug!("Failed to replicate {route} to {url}, retrying in {backoff:?}: {e:?}");
+                async_sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(e) => {
+                warn!("Failed to replicate {route} to {url} after {MAX_ATTEMPTS} attempts: {e:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! How much of each kind of data the web server keeps in memory.
+//!
+//! Each kind of data is kept for a number of views (for transactions, a number of transactions)
+//! and optionally up to a number of bytes, whichever runs out first. The oldest views are dropped
+//! to make room for new ones.
+
+use crate::config::{MAX_TXNS, MAX_VIEWS};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// How much of one kind of data to keep. Parsed from `COUNT` or `COUNT:BYTES`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    /// how many views (for transactions, how many transactions) to keep
+    pub count: usize,
+    /// how many bytes of payload to keep at most, if limited
+    pub bytes: Option<usize>,
+}
+
+impl Retention {
+    /// Keep `count` views, however large they are
+    pub const fn count(count: usize) -> Self {
+        Self { count, bytes: None }
+    }
+}
+
+impl fmt::Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bytes {
+            Some(bytes) => write!(f, "{}:{bytes}", self.count),
+            None => write!(f, "{}", self.count),
+        }
+    }
+}
+
+impl FromStr for Retention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, bytes) = match s.split_once(':') {
+            Some((count, bytes)) => (count, Some(bytes)),
+            None => (s, None),
+        };
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid retention count {count:?}: {e}"))?;
+        if count == 0 {
+            return Err("retention count must be at least 1".to_string());
+        }
+        let bytes = bytes
+            .map(|bytes| {
+                bytes
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid retention size {bytes:?}: {e}"))
+            })
+            .transpose()?;
+        Ok(Self { count, bytes })
+    }
+}
+
+/// How much of each kind of data the web server keeps, each given as `COUNT` or `COUNT:BYTES`.
+/// More views help late joiners catch up, at the cost of memory.
+#[derive(Args, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionOptions {
+    #[arg(
+        long = "web-server-retain-proposals",
+        env = "WEB_SERVER_RETAIN_PROPOSALS",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub proposals: Retention,
+    #[arg(
+        long = "web-server-retain-vid-disperses",
+        env = "WEB_SERVER_RETAIN_VID_DISPERSES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub vid_disperses: Retention,
+    #[arg(
+        long = "web-server-retain-votes",
+        env = "WEB_SERVER_RETAIN_VOTES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub votes: Retention,
+    #[arg(
+        long = "web-server-retain-vid-votes",
+        env = "WEB_SERVER_RETAIN_VID_VOTES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub vid_votes: Retention,
+    #[arg(
+        long = "web-server-retain-view-sync-proposals",
+        env = "WEB_SERVER_RETAIN_VIEW_SYNC_PROPOSALS",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub view_sync_proposals: Retention,
+    #[arg(
+        long = "web-server-retain-view-sync-votes",
+        env = "WEB_SERVER_RETAIN_VIEW_SYNC_VOTES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub view_sync_votes: Retention,
+    #[arg(
+        long = "web-server-retain-da-certificates",
+        env = "WEB_SERVER_RETAIN_DA_CERTIFICATES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub da_certificates: Retention,
+    #[arg(
+        long = "web-server-retain-vid-certificates",
+        env = "WEB_SERVER_RETAIN_VID_CERTIFICATES",
+        default_value_t = Retention::count(MAX_VIEWS)
+    )]
+    pub vid_certificates: Retention,
+    /// Counted in transactions rather than views
+    #[arg(
+        long = "web-server-retain-transactions",
+        env = "WEB_SERVER_RETAIN_TRANSACTIONS",
+        default_value_t = Retention::count(MAX_TXNS)
+    )]
+    pub transactions: Retention,
+}
+
+impl Default for RetentionOptions {
+    fn default() -> Self {
+        Self {
+            proposals: Retention::count(MAX_VIEWS),
+            vid_disperses: Retention::count(MAX_VIEWS),
+            votes: Retention::count(MAX_VIEWS),
+            vid_votes: Retention::count(MAX_VIEWS),
+            view_sync_proposals: Retention::count(MAX_VIEWS),
+            view_sync_votes: Retention::count(MAX_VIEWS),
+            da_certificates: Retention::count(MAX_VIEWS),
+            vid_certificates: Retention::count(MAX_VIEWS),
+            transactions: Retention::count(MAX_TXNS),
+        }
+    }
+}
+
+/// What the web server currently keeps of one kind of data
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionStats {
+    /// the kind of data, e.g. "votes"
+    pub data: String,
+    /// how much of it may be kept
+    pub limit: Retention,
+    /// how many views (for transactions, how many transactions) are kept
+    pub retained: usize,
+    /// how many items, e.g. individual votes, are kept across those views
+    pub items: usize,
+    /// how many bytes of payload are kept
+    pub bytes: usize,
+    /// the oldest view (for transactions, the oldest index) kept
+    pub oldest: u64,
+    /// the newest view (for transactions, the newest index) kept
+    pub newest: u64,
+}
+
+/// Drop the oldest views of `data` until `incoming` more bytes for `view` fit within `retention`,
+/// keeping `bytes` up to date and setting `oldest` to the oldest view kept once `view` is added.
+/// Never drops `view` itself. Returns what was dropped.
+pub(crate) fn make_room<V>(
+    data: &mut HashMap<u64, V>,
+    oldest: &mut u64,
+    bytes: &mut usize,
+    retention: Retention,
+    view: u64,
+    incoming: usize,
+    size: impl Fn(&V) -> usize,
+) -> Vec<(u64, V)> {
+    let mut dropped = Vec::new();
+    loop {
+        let too_many = !data.contains_key(&view) && data.len() >= retention.count;
+        let too_large = retention
+            .bytes
+            .is_some_and(|max| bytes.saturating_add(incoming) > max);
+        if !(too_many || too_large) {
+            break;
+        }
+        // views may arrive out of order, so find the oldest rather than counting up to it
+        match data.keys().min() {
+            Some(&old) if old != view => {
+                if let Some(value) = data.remove(&old) {
+                    *bytes = bytes.saturating_sub(size(&value));
+                    dropped.push((old, value));
+                }
+            }
+            _ => break,
+        }
+    }
+    *oldest = data.keys().copied().fold(view, u64::min);
+    dropped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// views of `len` bytes each, and their total size
+    fn views(views: &[(u64, usize)]) -> (HashMap<u64, Vec<u8>>, usize) {
+        let data = views
+            .iter()
+            .map(|&(view, len)| (view, vec![0; len]))
+            .collect();
+        (data, views.iter().map(|(_, len)| len).sum())
+    }
+
+    /// `make_room` for `view`, returning the views dropped
+    fn room_for(
+        data: &mut HashMap<u64, Vec<u8>>,
+        oldest: &mut u64,
+        bytes: &mut usize,
+        retention: Retention,
+        view: u64,
+        incoming: usize,
+    ) -> Vec<u64> {
+        make_room(data, oldest, bytes, retention, view, incoming, Vec::len)
+            .into_iter()
+            .map(|(view, _)| view)
+            .collect()
+    }
+
+    #[test]
+    fn test_retention_from_str() {
+        assert_eq!("5".parse::<Retention>(), Ok(Retention::count(5)));
+        assert_eq!(
+            " 5 : 1024 ".parse::<Retention>(),
+            Ok(Retention {
+                count: 5,
+                bytes: Some(1024)
+            })
+        );
+        for retention in [Retention::count(7), "7:100".parse::<Retention>().unwrap()] {
+            assert_eq!(retention.to_string().parse::<Retention>(), Ok(retention));
+        }
+
+        let error = |s: &str| s.parse::<Retention>().unwrap_err();
+        assert_eq!(error("0"), "retention count must be at least 1");
+        assert_eq!(error("0:100"), "retention count must be at least 1");
+        assert!(error("").starts_with("invalid retention count"));
+        assert!(error("-1").starts_with("invalid retention count"));
+        assert!(error("many:100").starts_with("invalid retention count"));
+        assert!(error("5:").starts_with("invalid retention size"));
+        assert!(error("5:lots").starts_with("invalid retention size"));
+        assert!(error("5:1:2").starts_with("invalid retention size"));
+    }
+
+    /// the oldest views are dropped first, whatever order they arrived in
+    #[test]
+    fn test_make_room_count() {
+        let (mut data, mut bytes) = views(&[(5, 1), (2, 1), (9, 1)]);
+        let mut oldest = 0;
+        let retention = Retention::count(3);
+
+        assert_eq!(
+            room_for(&mut data, &mut oldest, &mut bytes, retention, 10, 1),
+            vec![2]
+        );
+        assert_eq!(oldest, 5);
+        assert_eq!(bytes, 2);
+        data.insert(10, vec![0]);
+        bytes += 1;
+
+        // more for a view already kept doesn't take up another view
+        assert!(room_for(&mut data, &mut oldest, &mut bytes, retention, 9, 1).is_empty());
+        assert_eq!(oldest, 5);
+
+        // a smaller limit drops as many as it takes
+        assert_eq!(
+            room_for(
+                &mut data,
+                &mut oldest,
+                &mut bytes,
+                Retention::count(1),
+                11,
+                1
+            ),
+            vec![5, 9, 10]
+        );
+        assert!(data.is_empty());
+        assert_eq!(oldest, 11);
+        assert_eq!(bytes, 0);
+    }
+
+    /// with a size limit, views are dropped until the incoming bytes fit, but never the view
+    /// they are for
+    #[test]
+    fn test_make_room_bytes() {
+        let (mut data, mut bytes) = views(&[(1, 4), (2, 4)]);
+        let mut oldest = 1;
+        let retention = Retention {
+            count: 10,
+            bytes: Some(10),
+        };
+
+        assert_eq!(
+            room_for(&mut data, &mut oldest, &mut bytes, retention, 3, 5),
+            vec![1]
+        );
+        assert_eq!(oldest, 2);
+        assert_eq!(bytes, 4);
+
+        // fits already
+        assert!(room_for(&mut data, &mut oldest, &mut bytes, retention, 3, 6).is_empty());
+
+        // doesn't fit even alone, so everything else goes and it's kept regardless
+        assert_eq!(
+            room_for(&mut data, &mut oldest, &mut bytes, retention, 2, 20),
+            Vec::<u64>::new()
+        );
+        assert_eq!(data.len(), 1);
+        assert_eq!(
+            room_for(&mut data, &mut oldest, &mut bytes, retention, 3, 20),
+            vec![2]
+        );
+        assert!(data.is_empty());
+        assert_eq!(bytes, 0);
+        assert_eq!(oldest, 3);
+    }
+}